@@ -0,0 +1,474 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+use syn::{Attribute, Expr, Fields, Item, ItemEnum, Lit, Type};
+
+fn main() {
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let sha = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|sha| !sha.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=BRB_GIT_SHA={sha}");
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible.
+    let seconds = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        });
+    println!("cargo:rustc-env=BRB_BUILD_DATE={}", civil_date(seconds));
+
+    println!(
+        "cargo:rustc-env=BRB_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
+    let mut features = env::vars()
+        .filter_map(|(name, _)| {
+            let feature = name.strip_prefix("CARGO_FEATURE_")?;
+            Some(feature.to_ascii_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+    println!("cargo:rustc-env=BRB_FEATURES={}", features.join(","));
+
+    write_channel_reference();
+}
+
+/// `YYYY-MM-DD` for seconds since the Unix epoch (UTC).
+fn civil_date(seconds: u64) -> String {
+    // Howard Hinnant's days-to-civil algorithm.
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Writes the field tables `src/reference.rs` includes, read from the channel
+/// types in `src/config.rs` so `brb channels docs` cannot drift from them.
+///
+/// A type's summary and example come from its `ChannelConfig` variant: the
+/// first doc paragraph and a `yaml` code block. A field's description is its
+/// first doc paragraph; its kind, whether it is required and its default come
+/// from the Rust type and serde attributes, or a "Defaults to `VALUE`." doc
+/// paragraph for defaults applied when sending.
+fn write_channel_reference() {
+    println!("cargo:rerun-if-changed=src/config.rs");
+
+    let source = fs::read_to_string("src/config.rs").expect("read src/config.rs");
+    let file = syn::parse_file(&source).expect("parse src/config.rs");
+    let config = ConfigTypes { items: &file.items };
+
+    let mut output = String::from("/// Options every channel type takes next to its own fields.\n");
+    output.push_str("pub const COMMON_FIELDS: &[FieldReference] = &[\n");
+    for field in config.fields("Channel") {
+        writeln!(output, "    {field},").unwrap();
+    }
+    output.push_str("];\n\n");
+
+    output.push_str("/// Every channel type, in the order the docs list them.\n");
+    output.push_str("pub const CHANNEL_REFERENCES: &[ChannelReference] = &[\n");
+    let channels = config.enumeration("ChannelConfig");
+    let rename_all = serde_values(&channels.attrs, "rename_all").pop();
+    for variant in &channels.variants {
+        let Fields::Unnamed(fields) = &variant.fields else {
+            panic!(
+                "ChannelConfig::{} should wrap its config struct",
+                variant.ident
+            );
+        };
+        let struct_name = type_name(&fields.unnamed[0].ty);
+        let docs = doc_lines(&variant.attrs);
+        let example = yaml_block(&docs)
+            .unwrap_or_else(|| panic!("ChannelConfig::{} has no yaml example", variant.ident));
+
+        writeln!(output, "    ChannelReference {{").unwrap();
+        writeln!(
+            output,
+            "        type_name: {:?},",
+            renamed(&variant.ident.to_string(), rename_all.as_deref())
+        )
+        .unwrap();
+        writeln!(output, "        summary: {:?},", first_paragraph(&docs)).unwrap();
+        writeln!(output, "        fields: &[").unwrap();
+        for field in config.fields(&struct_name) {
+            writeln!(output, "            {field},").unwrap();
+        }
+        writeln!(output, "        ],").unwrap();
+        writeln!(output, "        example: {example:?},").unwrap();
+        writeln!(output, "    }},").unwrap();
+    }
+    output.push_str("];\n");
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set for build scripts");
+    fs::write(Path::new(&out_dir).join("channel_reference.rs"), output)
+        .expect("write channel_reference.rs");
+}
+
+/// The items of `src/config.rs`, looked up by name.
+struct ConfigTypes<'a> {
+    items: &'a [Item],
+}
+
+impl ConfigTypes<'_> {
+    fn enumeration(&self, name: &str) -> &ItemEnum {
+        self.items
+            .iter()
+            .find_map(|item| match item {
+                Item::Enum(item) if item.ident == name => Some(item),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("src/config.rs has no enum `{name}`"))
+    }
+
+    /// `FieldReference` expressions for the fields of struct `name`.
+    fn fields(&self, name: &str) -> Vec<String> {
+        let fields = self
+            .items
+            .iter()
+            .find_map(|item| match item {
+                Item::Struct(item) if item.ident == name => Some(&item.fields),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("src/config.rs has no struct `{name}`"));
+
+        let mut references = Vec::new();
+        for field in fields {
+            let flags = serde_flags(&field.attrs);
+            if flags.iter().any(|flag| flag == "flatten" || flag == "skip") {
+                continue;
+            }
+            let ident = field
+                .ident
+                .as_ref()
+                .expect("config structs have named fields");
+            let field_name = serde_values(&field.attrs, "rename")
+                .pop()
+                .unwrap_or_else(|| ident.to_string());
+            let context = format!("{name}.{field_name}");
+
+            let deserialize_with = serde_values(&field.attrs, "deserialize_with").pop();
+            let kind = match deserialize_with {
+                Some(function) => deserializer_kind(&function, &context),
+                None => self.kind(&field.ty, &context),
+            };
+
+            let docs = doc_lines(&field.attrs);
+            let mut description = first_paragraph(&docs);
+            if let Some(choices) = self.choices(&field.ty) {
+                let lead = if choices.len() == 2 {
+                    "Either"
+                } else {
+                    "One of"
+                };
+                description.push_str(&format!(" {lead} {}.", listed(&choices)));
+            }
+
+            let has_default = flags.iter().any(|flag| flag == "default");
+            let default_fn = serde_values(&field.attrs, "default").pop();
+            let required = !has_default && default_fn.is_none() && !is_option(&field.ty);
+            let default = documented_default(&docs)
+                .or_else(|| default_fn.map(|function| self.default_fn_value(&function)))
+                .or_else(|| has_default.then(|| self.type_default(&field.ty)).flatten());
+
+            references.push(format!(
+                "FieldReference {{ name: {field_name:?}, kind: {kind:?}, required: {required}, \
+                 default: {default:?}, description: {description:?} }}"
+            ));
+        }
+        references
+    }
+
+    /// YAML shape of a value of type `ty`.
+    fn kind(&self, ty: &Type, context: &str) -> String {
+        let name = type_name(ty);
+        match name.as_str() {
+            "Option" => self.kind(type_argument(ty, context), context),
+            "Vec" => format!("list of {}", self.kind(type_argument(ty, context), context)),
+            "BTreeMap" | "HashMap" => "mapping".to_string(),
+            "String" => "string".to_string(),
+            "bool" => "bool".to_string(),
+            "u8" | "u16" | "u32" | "u64" | "usize" | "i32" | "i64" => "integer".to_string(),
+            "Duration" => "duration".to_string(),
+            _ => {
+                if self.unit_variants(&name).is_some() {
+                    "string".to_string()
+                } else if self.items.iter().any(|item| match item {
+                    Item::Struct(item) => item.ident == name,
+                    Item::Enum(item) => item.ident == name,
+                    _ => false,
+                }) {
+                    "mapping".to_string()
+                } else {
+                    panic!("{context}: no YAML kind for type `{name}`")
+                }
+            }
+        }
+    }
+
+    /// The serde names of enum `name`'s variants, if it has only unit ones.
+    fn unit_variants(&self, name: &str) -> Option<Vec<(String, bool)>> {
+        let item = self.items.iter().find_map(|item| match item {
+            Item::Enum(item) if item.ident == name => Some(item),
+            _ => None,
+        })?;
+        let rename_all = serde_values(&item.attrs, "rename_all").pop();
+        item.variants
+            .iter()
+            .map(|variant| {
+                let is_default = variant
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("default"));
+                matches!(variant.fields, Fields::Unit).then(|| {
+                    let name = serde_values(&variant.attrs, "rename")
+                        .pop()
+                        .unwrap_or_else(|| {
+                            renamed(&variant.ident.to_string(), rename_all.as_deref())
+                        });
+                    (name, is_default)
+                })
+            })
+            .collect()
+    }
+
+    /// The values a field of type `ty` takes, when it is a unit enum.
+    fn choices(&self, ty: &Type) -> Option<Vec<String>> {
+        let ty = if is_option(ty) {
+            type_argument(ty, "")
+        } else {
+            ty
+        };
+        let variants = self.unit_variants(&type_name(ty))?;
+        Some(variants.into_iter().map(|(name, _)| name).collect())
+    }
+
+    /// What `#[serde(default)]` gives a field of type `ty`, if worth naming.
+    fn type_default(&self, ty: &Type) -> Option<String> {
+        match type_name(ty).as_str() {
+            "bool" => Some("false".to_string()),
+            "u8" | "u16" | "u32" | "u64" | "usize" | "i32" | "i64" => Some("0".to_string()),
+            name => self
+                .unit_variants(name)?
+                .into_iter()
+                .find_map(|(name, is_default)| is_default.then_some(name)),
+        }
+    }
+
+    /// The literal a `default = "function"` function returns.
+    fn default_fn_value(&self, function: &str) -> String {
+        let body = self
+            .items
+            .iter()
+            .find_map(|item| match item {
+                Item::Fn(item) if item.sig.ident == function => Some(&item.block),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("src/config.rs has no function `{function}`"));
+        let value = match body.stmts.as_slice() {
+            [syn::Stmt::Expr(expr, None)] => literal(expr),
+            _ => None,
+        };
+        value.unwrap_or_else(|| panic!("`{function}` should return a literal"))
+    }
+}
+
+/// YAML shape of a field read by `deserialize_with = "function"`.
+fn deserializer_kind(function: &str, context: &str) -> String {
+    match function {
+        "deserialize_duration" | "deserialize_optional_duration" => "duration",
+        "deserialize_optional_size" => "size",
+        "deserialize_urls" => "string or list of string",
+        "deserialize_chat_id" => "string or integer",
+        _ => panic!("{context}: no YAML kind for `deserialize_with = \"{function}\"`"),
+    }
+    .to_string()
+}
+
+/// The value of a literal, possibly converted with `.to_string()`.
+fn literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(expr) => match &expr.lit {
+            Lit::Str(lit) => Some(lit.value()),
+            Lit::Int(lit) => Some(lit.base10_digits().to_string()),
+            Lit::Bool(lit) => Some(lit.value.to_string()),
+            _ => None,
+        },
+        Expr::MethodCall(call) if call.args.is_empty() => literal(&call.receiver),
+        _ => None,
+    }
+}
+
+/// The last path segment of `ty`, e.g. `Option` for `Option<String>`.
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// The first generic argument of `ty`, e.g. `String` for `Vec<String>`.
+fn type_argument<'a>(ty: &'a Type, context: &str) -> &'a Type {
+    if let Type::Path(path) = ty
+        && let Some(segment) = path.path.segments.last()
+        && let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments
+        && let Some(syn::GenericArgument::Type(argument)) = arguments.args.first()
+    {
+        return argument;
+    }
+    panic!("{context}: expected a generic type")
+}
+
+fn is_option(ty: &Type) -> bool {
+    type_name(ty) == "Option"
+}
+
+/// Bare flags in `#[serde(...)]`, such as `default` or `flatten`.
+fn serde_flags(attrs: &[Attribute]) -> Vec<String> {
+    serde_metas(attrs)
+        .into_iter()
+        .filter(|(_, value)| value.is_none())
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Values of `name = "..."` in `#[serde(...)]`.
+fn serde_values(attrs: &[Attribute], name: &str) -> Vec<String> {
+    serde_metas(attrs)
+        .into_iter()
+        .filter(|(meta, _)| meta == name)
+        .filter_map(|(_, value)| value)
+        .collect()
+}
+
+fn serde_metas(attrs: &[Attribute]) -> Vec<(String, Option<String>)> {
+    let mut metas = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            let name = meta
+                .path
+                .get_ident()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let value = if meta.input.peek(syn::Token![=]) {
+                Some(meta.value()?.parse::<syn::LitStr>()?.value())
+            } else {
+                None
+            };
+            metas.push((name, value));
+            Ok(())
+        })
+        .expect("parse #[serde(...)]");
+    }
+    metas
+}
+
+/// Doc comment lines, without the space after `///`.
+fn doc_lines(attrs: &[Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(lit), ..
+            }) => {
+                let line = lit.value();
+                Some(line.strip_prefix(' ').unwrap_or(&line).to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn first_paragraph(docs: &[String]) -> String {
+    docs.iter()
+        .map(|line| line.trim())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A "Defaults to `VALUE`." paragraph's value.
+fn documented_default(docs: &[String]) -> Option<String> {
+    docs.iter().find_map(|line| {
+        let value = line
+            .trim()
+            .strip_prefix("Defaults to `")?
+            .strip_suffix("`.")?;
+        Some(value.to_string())
+    })
+}
+
+/// The body of the first ```` ```yaml ```` block, indented as it sits under
+/// a channel ID.
+fn yaml_block(docs: &[String]) -> Option<String> {
+    let start = docs.iter().position(|line| line.trim() == "```yaml")? + 1;
+    let length = docs[start..].iter().position(|line| line.trim() == "```")?;
+    Some(
+        docs[start..start + length]
+            .iter()
+            .map(|line| format!("    {line}\n"))
+            .collect(),
+    )
+}
+
+/// `name` under a serde `rename_all` rule.
+fn renamed(name: &str, rename_all: Option<&str>) -> String {
+    match rename_all {
+        None => name.to_string(),
+        Some("lowercase") => name.to_lowercase(),
+        Some("snake_case") => {
+            let mut renamed = String::new();
+            for (index, character) in name.chars().enumerate() {
+                if character.is_uppercase() && index > 0 {
+                    renamed.push('_');
+                }
+                renamed.push(character.to_ascii_lowercase());
+            }
+            renamed
+        }
+        Some(rule) => panic!("unsupported rename_all = \"{rule}\""),
+    }
+}
+
+/// "`a`, `b` or `c`".
+fn listed(names: &[String]) -> String {
+    let names = names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} or {last}", rest.join(", ")),
+        _ => names.join(""),
+    }
+}
@@ -0,0 +1,57 @@
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// How often the ntfy acknowledgement topic is polled.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// One line of ntfy's JSON message stream.
+#[derive(Debug, Deserialize)]
+struct NtfyMessage {
+    event: String,
+
+    #[serde(default)]
+    message: String,
+}
+
+/// Topic URL an ntfy channel's acknowledgements are published to: its own
+/// topic with `-ack` appended, without any query string.
+pub fn ntfy_ack_url(url: &str) -> String {
+    let base = url.split_once('?').map_or(url, |(base, _)| base);
+    format!("{}-ack", base.trim_end_matches('/'))
+}
+
+/// `Actions` header adding an "Acknowledge" button to an ntfy message, which
+/// publishes the run ID to the acknowledgement topic.
+pub fn ntfy_ack_action(label: &str, ack_url: &str, run_id: &str) -> String {
+    format!("http, {label}, {ack_url}, method=POST, body={run_id}, clear=true")
+}
+
+/// Whether a poll of the acknowledgement topic, one JSON message per line,
+/// holds an acknowledgement of `run_id`.
+pub fn acknowledges_run(poll: &str, run_id: &str) -> bool {
+    poll.lines()
+        .filter_map(|line| serde_json::from_str::<NtfyMessage>(line).ok())
+        .any(|message| message.event == "message" && message.message.trim() == run_id)
+}
+
+/// Polls the acknowledgement topic at `ack_url` until `run_id` shows up or
+/// `timeout` runs out. `poll` fetches messages published since a Unix time.
+pub fn await_ntfy_ack(
+    run_id: &str,
+    since: i64,
+    timeout: Duration,
+    mut poll: impl FnMut(i64) -> Option<String>,
+) -> bool {
+    let started = Instant::now();
+    loop {
+        if poll(since).is_some_and(|messages| acknowledges_run(&messages, run_id)) {
+            return true;
+        }
+
+        let left = timeout.saturating_sub(started.elapsed());
+        if left.is_zero() {
+            return false;
+        }
+        std::thread::sleep(left.min(POLL_INTERVAL));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::config::{ConfigError, private_data_dir};
+use crate::event::new_run_id;
+use crate::lock::{LockError, RunLock, acquire_lock_in};
+use crate::perms::write_private;
+use crate::runner::LineWatcher;
+use chrono::{SecondsFormat, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A wrapped command in progress, as listed by `brb status`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunningCommand {
+    /// Process ID of the `brb` running the command.
+    pub pid: u32,
+
+    /// Command argv, already redacted.
+    pub command: Vec<String>,
+
+    /// UTC start timestamp (RFC3339).
+    pub started_at: String,
+
+    /// Latest progress `progress_pattern` found in the output, e.g. `72%` or
+    /// `12/40`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<String>,
+}
+
+/// This process's entry in the active runs directory; removed when dropped.
+///
+/// The entry's lock is held for as long as the run lasts, so entries left
+/// behind by a `brb` that was killed are told apart by their free lock.
+#[derive(Debug)]
+pub struct ActiveRun {
+    dir: PathBuf,
+    id: String,
+    lock: Option<RunLock>,
+}
+
+/// Directory holding one entry per `brb` run in progress.
+pub fn active_runs_dir() -> Result<PathBuf, ConfigError> {
+    Ok(private_data_dir()?.join("active"))
+}
+
+/// Registers a running command in `dir`.
+pub fn register_run(dir: &Path, command: &[String]) -> Result<ActiveRun, String> {
+    let id = new_run_id();
+    let lock = acquire_lock_in(dir, &id, false).map_err(|error| error.to_string())?;
+    let running = RunningCommand {
+        pid: std::process::id(),
+        command: command.to_vec(),
+        started_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        progress: None,
+    };
+
+    let path = entry_path(dir, &id);
+    let json = serde_json::to_string_pretty(&running)
+        .map_err(|error| format!("failed to encode active run: {error}"))?;
+    write_private(&path, json)
+        .map_err(|error| format!("failed to write {}: {error}", path.display()))?;
+
+    Ok(ActiveRun {
+        dir: dir.to_path_buf(),
+        id,
+        lock: Some(lock),
+    })
+}
+
+/// Commands in progress, oldest first. Entries whose `brb` has exited are
+/// cleaned up along the way.
+pub fn running_commands(dir: &Path) -> Vec<RunningCommand> {
+    running_entries(dir)
+        .into_iter()
+        .map(|(_, running)| running)
+        .collect()
+}
+
+impl ActiveRun {
+    /// Commands in progress besides this one, oldest first.
+    pub fn others(&self) -> Vec<RunningCommand> {
+        running_entries(&self.dir)
+            .into_iter()
+            .filter(|(id, _)| *id != self.id)
+            .map(|(_, running)| running)
+            .collect()
+    }
+
+    /// Records the run's latest progress for `brb status`.
+    pub fn set_progress(&self, progress: &str) -> Result<(), String> {
+        let path = entry_path(&self.dir, &self.id);
+        let mut running = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<RunningCommand>(&raw).ok())
+            .ok_or_else(|| format!("failed to read {}", path.display()))?;
+        running.progress = Some(progress.to_string());
+        let json = serde_json::to_string_pretty(&running)
+            .map_err(|error| format!("failed to encode active run: {error}"))?;
+
+        // written aside and renamed so `brb status` never reads half an entry.
+        let partial = path.with_extension("json.partial");
+        write_private(&partial, json)
+            .and_then(|()| fs::rename(&partial, &path))
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))
+    }
+}
+
+/// The progress `pattern` finds in a line of output: `done/total` from the
+/// named groups `done` and `total`, otherwise a percentage from the first
+/// group or the whole match. The last match wins when a line has several.
+pub fn extract_progress(pattern: &Regex, line: &str) -> Option<String> {
+    let captures = pattern.captures_iter(line).last()?;
+    if let (Some(done), Some(total)) = (captures.name("done"), captures.name("total")) {
+        return Some(format!("{}/{}", done.as_str(), total.as_str()));
+    }
+
+    let value = captures
+        .get(1)
+        .or_else(|| captures.get(0))?
+        .as_str()
+        .trim()
+        .trim_end_matches('%')
+        .trim();
+    let percent = value.parse::<f64>().ok()?;
+    (0.0..=100.0)
+        .contains(&percent)
+        .then(|| format!("{value}%"))
+}
+
+/// Watches output for `pattern`, recording on `run` each change in the
+/// progress it finds.
+pub fn progress_watcher(pattern: Regex, run: Arc<ActiveRun>) -> LineWatcher {
+    let written = Mutex::new(String::new());
+    LineWatcher::new(move |_, line| {
+        let Some(progress) = extract_progress(&pattern, line) else {
+            return;
+        };
+        let Ok(mut written) = written.lock() else {
+            return;
+        };
+        if *written != progress && run.set_progress(&progress).is_ok() {
+            *written = progress;
+        }
+    })
+}
+
+impl Drop for ActiveRun {
+    fn drop(&mut self) {
+        // the entry goes before the lock so no one sees it unlocked and live.
+        let _ = fs::remove_file(entry_path(&self.dir, &self.id));
+        drop(self.lock.take());
+        let _ = fs::remove_file(lock_path(&self.dir, &self.id));
+    }
+}
+
+fn running_entries(dir: &Path) -> Vec<(String, RunningCommand)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut running = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+
+        match acquire_lock_in(dir, id, false) {
+            Err(LockError::Busy { .. }) => {}
+            Ok(lock) => {
+                let _ = fs::remove_file(&path);
+                drop(lock);
+                let _ = fs::remove_file(lock_path(dir, id));
+                continue;
+            }
+            Err(_) => continue,
+        }
+
+        if let Some(command) = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<RunningCommand>(&raw).ok())
+        {
+            running.push((id.to_string(), command));
+        }
+    }
+
+    running.sort_by(|(_, a), (_, b)| a.started_at.cmp(&b.started_at));
+    running
+}
+
+fn entry_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.json"))
+}
+
+fn lock_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.lock"))
+}
@@ -0,0 +1,32 @@
+use crate::cli::RunArgs;
+use crate::config::Config;
+
+/// Subcommand names, which an alias could never be reached by.
+pub const RESERVED_NAMES: &[&str] = &[
+    "run", "init", "channels", "config", "template", "secret", "aliases", "doctor", "history",
+    "annotate", "status", "bench", "import", "session",
+];
+
+/// Replaces the wrapped command with the alias its first word names, adding
+/// any arguments after it. Alias presets only fill options the command line
+/// and environment left unset. Returns the alias name, or `None` when the
+/// command is not an alias or came after `--`.
+pub fn expand_alias(config: &Config, args: &mut RunArgs) -> Option<String> {
+    if args.separated {
+        return None;
+    }
+    let name = args.command.first()?;
+    let alias = config.aliases.get(name)?;
+    let name = name.clone();
+
+    let mut command = alias.command.clone();
+    command.extend(args.command.drain(1..));
+    args.command = command;
+
+    if args.channels.is_empty() && !args.all_channels {
+        args.channels = alias.channels.clone();
+    }
+    args.notify_on = args.notify_on.or(alias.notify_on);
+    args.min_duration = args.min_duration.or(alias.min_duration);
+    Some(name)
+}
@@ -0,0 +1,68 @@
+use crate::runner::RunResult;
+use serde::{Deserialize, Serialize};
+
+/// Duration statistics for the runs of a `brb bench`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BenchSummary {
+    /// Number of runs.
+    pub runs: usize,
+
+    /// Runs that exited non-zero or failed to start.
+    pub failures: usize,
+
+    /// Shortest run in milliseconds.
+    pub min_ms: u128,
+
+    /// Mean run duration in milliseconds.
+    pub mean_ms: u128,
+
+    /// 95th percentile (nearest rank) in milliseconds.
+    pub p95_ms: u128,
+
+    /// Longest run in milliseconds.
+    pub max_ms: u128,
+}
+
+impl BenchSummary {
+    /// Summarises finished runs; `None` when there are none.
+    pub fn from_runs(runs: &[RunResult]) -> Option<Self> {
+        let mut durations = runs
+            .iter()
+            .map(|run| run.duration.as_millis())
+            .collect::<Vec<_>>();
+        durations.sort_unstable();
+        let (&min_ms, &max_ms) = (durations.first()?, durations.last()?);
+
+        // nearest rank: the smallest duration at least 95% of runs fit in.
+        let rank = (durations.len() * 95).div_ceil(100).max(1);
+        Some(Self {
+            runs: runs.len(),
+            failures: runs.iter().filter(|run| run.exit_code != 0).count(),
+            min_ms,
+            mean_ms: durations.iter().sum::<u128>() / durations.len() as u128,
+            p95_ms: durations[rank - 1],
+            max_ms,
+        })
+    }
+}
+
+/// A single result standing for every run of a bench: the first run's start,
+/// the last run's finish, the first failing exit code (or 0) and the output
+/// of the first failing run (or the last run).
+pub fn combined_run(runs: &[RunResult]) -> Option<RunResult> {
+    let (first, last) = (runs.first()?, runs.last()?);
+    let failed = runs.iter().find(|run| run.exit_code != 0);
+    let shown = failed.unwrap_or(last);
+    Some(RunResult {
+        command: first.command.clone(),
+        started_at: first.started_at,
+        finished_at: last.finished_at,
+        duration: (last.finished_at - first.started_at)
+            .to_std()
+            .unwrap_or_default(),
+        exit_code: shown.exit_code,
+        spawn_error: failed.and_then(|run| run.spawn_error.clone()),
+        output_tail: shown.output_tail.clone(),
+        cwd: first.cwd.clone(),
+    })
+}
@@ -0,0 +1,66 @@
+use brb_cli::cargo::{CARGO_MIN_DURATION, brb_args, find_manifest, project_name};
+use brb_cli::cli::MIN_DURATION_ENV;
+use brb_cli::config::{LoadOptions, load_config_or_fallback};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+    // cargo runs `cargo-brb brb <args>`; running `cargo-brb <args>` works too.
+    if args.first().map(String::as_str) == Some("brb") {
+        args.remove(0);
+    }
+    if args.is_empty() {
+        eprintln!("usage: cargo brb <cargo-command> [args...]");
+        std::process::exit(1);
+    }
+
+    let dir = env::current_dir().unwrap_or_default();
+    let project = find_manifest(&args, &dir).and_then(|manifest| project_name(&manifest));
+    // config and the environment win over the default; a broken config is
+    // left for brb to report.
+    let min_duration = (env::var(MIN_DURATION_ENV)
+        .unwrap_or_default()
+        .trim()
+        .is_empty()
+        && load_config_or_fallback(LoadOptions::default())
+            .is_ok_and(|loaded| loaded.config.min_duration.is_none()))
+    .then_some(CARGO_MIN_DURATION);
+
+    let mut command = Command::new(brb_program());
+    command.args(brb_args(&args, project.as_deref(), min_duration));
+    std::process::exit(run(command));
+}
+
+/// The `brb` installed next to this binary, or else the one on `PATH`.
+fn brb_program() -> PathBuf {
+    let name = format!("brb{}", env::consts::EXE_SUFFIX);
+    env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(&name)))
+        .filter(|program| program.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+/// Replaces this process with `brb`, so signals and the terminal go straight
+/// to it.
+#[cfg(unix)]
+fn run(mut command: Command) -> i32 {
+    use std::os::unix::process::CommandExt;
+
+    let error = command.exec();
+    eprintln!("cargo-brb: failed to run brb: {error}");
+    1
+}
+
+#[cfg(not(unix))]
+fn run(mut command: Command) -> i32 {
+    match command.status() {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(error) => {
+            eprintln!("cargo-brb: failed to run brb: {error}");
+            1
+        }
+    }
+}
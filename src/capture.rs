@@ -0,0 +1,163 @@
+use crate::state::StateStore;
+use crate::template::render;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Commands (and runs) whose captured values are kept per channel; the
+/// oldest are dropped first.
+const MAX_CAPTURED_COMMANDS: usize = 100;
+
+/// Placeholder prefix that marks an `extra` value as using captured values.
+const CAPTURED_PREFIX: &str = "captured.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CapturedEntry {
+    /// RFC3339 timestamp of the response the values came from.
+    captured_at: String,
+    values: BTreeMap<String, String>,
+}
+
+/// Converts a capture path to a JSON pointer. Paths are either JSON pointers
+/// (`/message/id`) or `$`-rooted paths (`$.message.id`, `$.items[0].id`).
+pub fn capture_pointer(path: &str) -> Option<String> {
+    if path.is_empty() || path.starts_with('/') {
+        return Some(path.to_string());
+    }
+
+    let mut rest = path.strip_prefix('$')?;
+    let mut pointer = String::new();
+    while !rest.is_empty() {
+        let segment;
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            (segment, rest) = tail.split_at(end);
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']')?;
+            segment = tail[..end].trim_matches(['"', '\'']);
+            rest = &tail[end + 1..];
+        } else {
+            return None;
+        }
+        if segment.is_empty() {
+            return None;
+        }
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    }
+    Some(pointer)
+}
+
+/// Pulls the values named in a channel's `capture` out of a response body.
+/// Paths that match nothing, or `null`, are left out; strings are kept as
+/// is and anything else as JSON.
+pub fn extract_captures(
+    capture: &BTreeMap<String, String>,
+    response: &str,
+) -> BTreeMap<String, String> {
+    let Ok(body) = serde_json::from_str::<Value>(response) else {
+        return BTreeMap::new();
+    };
+
+    capture
+        .iter()
+        .filter_map(|(name, path)| {
+            let value = body.pointer(&capture_pointer(path)?)?;
+            let value = match value {
+                Value::Null => return None,
+                Value::String(value) => value.clone(),
+                other => other.to_string(),
+            };
+            Some((name.clone(), value))
+        })
+        .collect()
+}
+
+/// Key values are kept under for one run rather than for its command, such
+/// as the ID of the "running" message its completion edits and its
+/// follow-ups reply to.
+pub fn run_key(run_id: &str) -> String {
+    format!("run:{run_id}")
+}
+
+/// Values captured from the last response a channel got for `key`: a
+/// command's fingerprint, or a [`run_key`].
+pub fn load_captures(store: &StateStore, channel_id: &str, key: &str) -> BTreeMap<String, String> {
+    let state: BTreeMap<String, CapturedEntry> = store.load(&state_name(channel_id));
+    state
+        .get(key)
+        .map(|entry| entry.values.clone())
+        .unwrap_or_default()
+}
+
+/// Records values captured from a response, replacing what was kept for the
+/// same key.
+pub fn save_captures(
+    store: &StateStore,
+    channel_id: &str,
+    key: &str,
+    values: BTreeMap<String, String>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let name = state_name(channel_id);
+    let _lock = store.lock(&name).map_err(|error| error.to_string())?;
+    let mut state: BTreeMap<String, CapturedEntry> = store.load(&name);
+    state.insert(
+        key.to_string(),
+        CapturedEntry {
+            captured_at: now.to_rfc3339(),
+            values,
+        },
+    );
+
+    while state.len() > MAX_CAPTURED_COMMANDS {
+        let Some(oldest) = state
+            .iter()
+            .min_by(|(_, a), (_, b)| a.captured_at.cmp(&b.captured_at))
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        state.remove(&oldest);
+    }
+    store.save(&name, &state)
+}
+
+/// Fills `{captured.NAME}` placeholders in the `extra` fields of a JSON
+/// body. Fields naming a value that was not captured are left out, so the
+/// first message for a command is sent without them.
+pub fn apply_captures(
+    body: &mut Value,
+    extra: &BTreeMap<String, String>,
+    captured: &BTreeMap<String, String>,
+) {
+    let Value::Object(object) = body else {
+        return;
+    };
+
+    let values = captured
+        .iter()
+        .map(|(name, value)| (format!("{CAPTURED_PREFIX}{name}"), value.clone()))
+        .collect::<BTreeMap<_, _>>();
+    let values = values
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect::<BTreeMap<_, _>>();
+
+    for (key, source) in extra {
+        if !source.contains(&format!("{{{CAPTURED_PREFIX}")) {
+            continue;
+        }
+        let rendered = render(&BTreeMap::new(), source, &values);
+        if rendered.contains(&format!("{{{CAPTURED_PREFIX}")) {
+            object.remove(key);
+        } else {
+            object.insert(key.clone(), Value::String(rendered));
+        }
+    }
+}
+
+fn state_name(channel_id: &str) -> String {
+    format!("captures-{channel_id}")
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Minimum run duration `cargo brb` notifies about when neither config nor
+/// `BRB_MIN_DURATION` sets one, so quick checks stay quiet.
+pub const CARGO_MIN_DURATION: Duration = Duration::from_secs(30);
+
+/// The manifest cargo would use: the file given with `--manifest-path`, or
+/// the nearest `Cargo.toml` in `dir` or its parents.
+pub fn find_manifest(cargo_args: &[String], dir: &Path) -> Option<PathBuf> {
+    let mut args = cargo_args.iter().take_while(|arg| *arg != "--");
+    while let Some(arg) = args.next() {
+        if arg == "--manifest-path" {
+            return args.next().map(|path| dir.join(path));
+        }
+        if let Some(path) = arg.strip_prefix("--manifest-path=") {
+            return Some(dir.join(path));
+        }
+    }
+
+    dir.ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+}
+
+/// The `[package]` name in a `Cargo.toml`, if it has one.
+pub fn package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if key.trim() != "name" {
+            continue;
+        }
+        let value = value.trim();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|quote| matches!(quote, '"' | '\''))?;
+        let name = value[1..].split(quote).next()?;
+        return Some(name.to_string()).filter(|name| !name.is_empty());
+    }
+    None
+}
+
+/// Project name for a manifest: its package name, or for a virtual
+/// workspace, the name of the directory it is in.
+pub fn project_name(manifest: &Path) -> Option<String> {
+    let package = fs::read_to_string(manifest)
+        .ok()
+        .and_then(|raw| package_name(&raw));
+    package.or_else(|| {
+        manifest
+            .parent()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    })
+}
+
+/// `brb` arguments that run `cargo` with `cargo_args`, named after `project`
+/// and only notifying for runs of at least `min_duration` when given.
+pub fn brb_args(
+    cargo_args: &[String],
+    project: Option<&str>,
+    min_duration: Option<Duration>,
+) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(project) = project {
+        args.extend(["--project".to_string(), project.to_string()]);
+    }
+    if let Some(min_duration) = min_duration {
+        args.extend([
+            "--min-duration".to_string(),
+            format!("{}s", min_duration.as_secs()),
+        ]);
+    }
+    args.extend(["--".to_string(), "cargo".to_string()]);
+    args.extend_from_slice(cargo_args);
+    args
+}
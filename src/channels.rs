@@ -1,11 +1,59 @@
-use crate::config::{ChannelConfig, Config, CustomChannel, WebhookChannel};
+#[cfg(feature = "webhook")]
+use crate::ack::{await_ntfy_ack, ntfy_ack_action, ntfy_ack_url};
+#[cfg(feature = "webhook")]
+use crate::capture::{apply_captures, extract_captures, load_captures, run_key, save_captures};
+use crate::config::{
+    AckConfig, Channel, ChannelConfig, Config, CustomChannel, DesktopChannel, Interpreter,
+    Priority, WhenDnd,
+};
+#[cfg(feature = "webhook")]
+use crate::config::{
+    GoogleChatChannel, HealthchecksChannel, HttpConfig, MqttChannel, NtfyChannel, Oversize,
+    PushoverChannel, SlackChannel, TelegramChannel, WebhookChannel, WebhookFormat, ZulipChannel,
+};
+use crate::dbus::{Notification, Signal};
+use crate::diagnostic::closest_match;
+use crate::digest::{DigestDecision, apply_digest};
+use crate::dnd::{dnd_active, queue_for_dnd, release_dnd_queue};
 use crate::event::CompletionEvent;
+#[cfg(feature = "desktop")]
+use crate::format::{MessageText, desktop_text};
+#[cfg(feature = "webhook")]
+use crate::format::{
+    WebhookPayload, fitted_payload, googlechat_message, healthchecks_body, idempotency_key,
+    mqtt_payload, ntfy_message, progress_payload, pushover_message, seal_payload, slack_blocks,
+    telegram_message, telegram_progress_message, zulip_message,
+};
+use crate::format::{custom_payload, messages, status_appearance};
+#[cfg(feature = "webhook")]
+use crate::mqtt::{Broker, Message, MqttError, Session};
+use crate::perms::private_file_options;
+use crate::pipeline::{prepare, run_pipeline, selection_steps};
+#[cfg(feature = "webhook")]
+use crate::progress::{
+    apply_progress, apply_thread_reply, capture_message, capture_telegram_message, is_follow_up,
+    message_id, progress_edit_target, progress_post_url,
+};
+#[cfg(feature = "webhook")]
+use crate::session::{SessionThread, load_thread, save_thread};
+use crate::state::StateStore;
+#[cfg(feature = "webhook")]
+use crate::trigger::TRIGGERED_STATUS;
+use chrono::{DateTime, Utc};
 use regex::Regex;
+#[cfg(feature = "webhook")]
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 
 /// Notification delivery status for a single channel.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DeliveryResult {
     /// Channel id from config.
     pub channel_id: String,
@@ -13,138 +61,2284 @@ pub struct DeliveryResult {
     /// Whether delivery succeeded.
     pub success: bool,
 
-    /// Optional failure reason.
-    pub error: Option<String>,
+    /// Why delivery failed.
+    pub error: Option<DeliveryError>,
+
+    /// Time spent delivering, including retries; unset when nothing was sent.
+    pub latency_ms: Option<u128>,
+
+    /// Delivery attempts made (more than one when webhook retries kicked in).
+    pub attempts: u32,
+
+    /// Whether the event was held to be sent later (digest or do-not-disturb).
+    pub queued: bool,
+
+    /// Why the channel was deliberately not notified, if it was not.
+    pub skipped: Option<String>,
+
+    /// What the user did with a desktop notification sent with `wait`, or
+    /// with a failure on a channel with `ack`.
+    pub interaction: Option<Interaction>,
+
+    /// Whether someone acknowledged a failure on a channel with `ack`; unset
+    /// when there was nothing to acknowledge or no way to find out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub acknowledged: Option<bool>,
+
+    /// Per-URL outcomes for webhooks that fan out to several URLs.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TargetResult>,
+
+    /// Values a webhook's `capture` pulled from the response.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub captured: BTreeMap<String, String>,
+}
+
+/// Delivery status for one URL of a fanned-out webhook channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetResult {
+    /// Host of the URL; the rest may carry a token, so it is not kept.
+    pub host: String,
+
+    /// Whether this URL accepted the payload.
+    pub success: bool,
+
+    /// Why this URL failed.
+    pub error: Option<DeliveryError>,
+
+    /// Attempts made against this URL.
+    pub attempts: u32,
+}
+
+/// How someone responded to a notification while `brb` waited: a desktop
+/// notification sent with `wait`, or a failure on a channel with `ack`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interaction {
+    /// The notification was clicked.
+    Clicked,
+
+    /// The "Re-run" action was picked.
+    Rerun,
+
+    /// The failure was acknowledged with the "Acknowledge" action.
+    Acknowledged,
+
+    /// The user dismissed it.
+    Dismissed,
+
+    /// It expired unread.
+    Expired,
+
+    /// It was closed some other way.
+    Closed,
+
+    /// Nothing happened before the wait ran out.
+    Unanswered,
+}
+
+impl Interaction {
+    /// Maps a notification server signal to an interaction.
+    pub fn from_signal(signal: Option<&Signal>) -> Self {
+        match signal {
+            None => Self::Unanswered,
+            Some(Signal::ActionInvoked { action, .. }) if action == "rerun" => Self::Rerun,
+            Some(Signal::ActionInvoked { action, .. }) if action == "ack" => Self::Acknowledged,
+            Some(Signal::ActionInvoked { .. }) => Self::Clicked,
+            Some(Signal::Closed { reason: 1, .. }) => Self::Expired,
+            Some(Signal::Closed { reason: 2, .. }) => Self::Dismissed,
+            Some(Signal::Closed { .. }) => Self::Closed,
+        }
+    }
+
+    /// Whether this response shows someone saw a failure: a click or the
+    /// "Acknowledge" action. Dismissing it does not count.
+    pub fn acknowledges(self) -> bool {
+        matches!(self, Self::Clicked | Self::Acknowledged)
+    }
+}
+
+/// A failed delivery: what kind of failure it was, plus a readable message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeliveryError {
+    /// Failure category.
+    #[serde(flatten)]
+    pub kind: DeliveryErrorKind,
+
+    /// Human-readable reason, with token-like values redacted.
+    pub message: String,
+}
+
+/// Category of a delivery failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum DeliveryErrorKind {
+    /// The receiver did not answer in time.
+    Timeout,
+
+    /// The receiver answered with a non-2xx status.
+    Http { status: u16 },
+
+    /// A notifier process could not be started or talked to.
+    Spawn,
+
+    /// A notifier process exited unsuccessfully.
+    Exit { code: Option<i32> },
+
+    /// The channel or its settings cannot be used as configured.
+    Config,
+
+    /// The request could not reach the receiver.
+    Network,
+}
+
+impl DeliveryError {
+    fn new(kind: DeliveryErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    fn config(message: impl Into<String>) -> Self {
+        Self::new(DeliveryErrorKind::Config, message)
+    }
+
+    fn spawn(message: impl Into<String>) -> Self {
+        Self::new(DeliveryErrorKind::Spawn, message)
+    }
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Filters selected channel IDs down to those a run's completion passes the
+/// [`selection_steps`] of: runs shorter than `min_duration` notify nothing,
+/// and each channel's `notify_on` policy must accept the event. Unknown IDs
+/// are kept so delivery can report them.
+pub fn channels_for_event(
+    config: &Config,
+    selected_channel_ids: &[String],
+    event: &CompletionEvent,
+) -> Vec<String> {
+    selected_channel_ids
+        .iter()
+        .filter(|channel_id| {
+            config.channels.get(*channel_id).is_none_or(|channel| {
+                run_pipeline(&selection_steps(config, channel), channel, event).is_ok()
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Channels for a run selected by default: those of the `tiers` entry with
+/// the longest `min_duration` the run reached, otherwise `default_channels`.
+pub fn tiered_channels<'a>(config: &'a Config, event: &CompletionEvent) -> &'a [String] {
+    config
+        .tiers
+        .iter()
+        .filter(|tier| tier.min_duration.as_millis() <= event.duration_ms)
+        .max_by_key(|tier| tier.min_duration)
+        .map_or(&config.default_channels, |tier| &tier.channels)
+}
+
+/// `selected` plus any channels the event's `exit_codes` rule adds.
+pub fn with_exit_code_channels(
+    config: &Config,
+    selected: &[String],
+    event: &CompletionEvent,
+) -> Vec<String> {
+    let mut channels = selected.to_vec();
+    if let Some(rule) = config.exit_codes.get(&event.exit_code) {
+        for channel_id in &rule.channels {
+            if !channels.contains(channel_id) {
+                channels.push(channel_id.clone());
+            }
+        }
+    }
+    channels
 }
 
-/// Sends one event to all selected channel IDs.
+/// Sends one event to all selected channel IDs. Responses to wait for, such
+/// as acknowledgements, are awaited together once every channel has been
+/// sent to, so one slow `ack` does not hold up the others.
 pub fn notify_selected(
     config: &Config,
     selected_channel_ids: &[String],
     event: &CompletionEvent,
 ) -> Vec<DeliveryResult> {
-    selected_channel_ids
+    // only probed when a desktop channel cares about do-not-disturb.
+    let mut dnd = None;
+    let mut deliveries = Deliveries::default();
+
+    for channel_id in delivery_order(config, selected_channel_ids) {
+        if let Some(delivered) = config.channels.get(channel_id).and_then(|channel| {
+            channel.suppress_if_delivered.iter().find(|other| {
+                deliveries
+                    .results
+                    .iter()
+                    .any(|result| &result.channel_id == *other && result.delivered())
+            })
+        }) {
+            deliveries.push(DeliveryResult::skipped(
+                channel_id,
+                &format!("delivered on {delivered}"),
+            ));
+            continue;
+        }
+
+        let desktop = match config.channels.get(channel_id).map(|channel| &channel.kind) {
+            Some(ChannelConfig::Desktop(desktop)) if desktop.when_dnd != WhenDnd::Notify => desktop,
+            _ => {
+                deliveries.send(config, channel_id, event);
+                continue;
+            }
+        };
+
+        if !*dnd.get_or_insert_with(dnd_active) {
+            let released = release_dnd(channel_id, desktop, event);
+            deliveries.send(config, channel_id, &released);
+            continue;
+        }
+
+        match desktop.when_dnd {
+            WhenDnd::Notify => deliveries.send(config, channel_id, event),
+            WhenDnd::Skip => deliveries.push(DeliveryResult::skipped(channel_id, "do not disturb")),
+            WhenDnd::Queue => match StateStore::open()
+                .map_err(|error| error.to_string())
+                .and_then(|store| queue_for_dnd(&store, channel_id, event))
+            {
+                Ok(()) => deliveries.push(DeliveryResult {
+                    queued: true,
+                    ..DeliveryResult::skipped(channel_id, "do not disturb")
+                }),
+                Err(error) => {
+                    eprintln!(
+                        "brb: dnd queue unavailable for `{channel_id}`, sending now: {error}"
+                    );
+                    deliveries.send(config, channel_id, event);
+                }
+            },
+            WhenDnd::Escalate => {
+                deliveries.push(DeliveryResult::skipped(
+                    channel_id,
+                    &format!(
+                        "do not disturb, escalated to {}",
+                        desktop.escalate_to.join(", ")
+                    ),
+                ));
+                for target in &desktop.escalate_to {
+                    if !selected_channel_ids.contains(target) {
+                        deliveries.send(config, target, event);
+                    }
+                }
+            }
+        }
+    }
+
+    deliveries.await_responses();
+    escalate_unacknowledged(config, selected_channel_ids, event, &mut deliveries);
+    follow_escalation(config, event, &mut deliveries);
+    deliveries.results
+}
+
+/// Waits for someone to respond to a notification that has been sent.
+type Response = Box<dyn FnOnce() -> Interaction + Send>;
+
+/// Outcomes of the channels sent to so far, and the responses still to be
+/// waited for.
+#[derive(Default)]
+struct Deliveries {
+    results: Vec<DeliveryResult>,
+
+    /// Index into `results`, whether the response acknowledges a failure,
+    /// and the wait itself.
+    pending: Vec<(usize, bool, Response)>,
+}
+
+impl Deliveries {
+    fn push(&mut self, result: DeliveryResult) {
+        self.results.push(result);
+    }
+
+    /// Delivers to one channel, leaving any response to wait for pending.
+    fn send(&mut self, config: &Config, channel_id: &str, event: &CompletionEvent) {
+        let (result, response) = deliver(config, channel_id, event);
+        if let Some((acknowledges, response)) = response {
+            self.pending
+                .push((self.results.len(), acknowledges, response));
+        }
+        self.results.push(result);
+    }
+
+    /// Waits for every pending response at once.
+    fn await_responses(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        let responses = std::thread::scope(|scope| {
+            pending
+                .into_iter()
+                .map(|(index, acknowledges, response)| (index, acknowledges, scope.spawn(response)))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|(index, acknowledges, waiting)| {
+                    let interaction = waiting.join().unwrap_or(Interaction::Unanswered);
+                    (index, acknowledges, interaction)
+                })
+                .collect::<Vec<_>>()
+        });
+        for (index, acknowledges, interaction) in responses {
+            let result = &mut self.results[index];
+            result.interaction = Some(interaction);
+            if acknowledges {
+                result.acknowledged = Some(interaction.acknowledges());
+            }
+        }
+    }
+}
+
+/// Works down the top-level `escalation` chain for a failure, one step at a
+/// time, until a step's channels have it in hand.
+fn follow_escalation(config: &Config, event: &CompletionEvent, deliveries: &mut Deliveries) {
+    if event.exit_code == 0 {
+        return;
+    }
+
+    let mut round = 0;
+    for step in &config.escalation {
+        if handled(&deliveries.results[round..]) {
+            return;
+        }
+        round = deliveries.results.len();
+
+        let channels = step
+            .channels
+            .iter()
+            .filter(|channel_id| {
+                !deliveries
+                    .results
+                    .iter()
+                    .any(|result| &result.channel_id == *channel_id)
+            })
+            .collect::<Vec<_>>();
+        if channels.is_empty() {
+            continue;
+        }
+        eprintln!(
+            "brb: failure not acknowledged, escalating to {}",
+            channels
+                .iter()
+                .map(|channel_id| channel_id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        for channel_id in channels {
+            deliveries.send(config, channel_id, event);
+        }
+        deliveries.await_responses();
+    }
+}
+
+/// Whether someone has a failure in hand after one round of deliveries: it
+/// was acknowledged, or it reached a channel that cannot report an
+/// acknowledgement at all.
+fn handled(round: &[DeliveryResult]) -> bool {
+    round.iter().any(|result| {
+        result.acknowledged == Some(true) || (result.delivered() && result.acknowledged.is_none())
+    })
+}
+
+/// Sends failures nobody acknowledged on to the channels' `ack.escalate_to`
+/// targets, skipping channels already notified.
+fn escalate_unacknowledged(
+    config: &Config,
+    selected_channel_ids: &[String],
+    event: &CompletionEvent,
+    deliveries: &mut Deliveries,
+) {
+    let targets = deliveries
+        .results
         .iter()
-        .map(|channel_id| {
-            let Some(channel) = config.channels.get(channel_id) else {
-                return DeliveryResult {
-                    channel_id: channel_id.clone(),
-                    success: false,
-                    error: Some("channel not found in config".to_string()),
+        .filter(|result| result.acknowledged == Some(false))
+        .filter_map(|result| config.channels.get(&result.channel_id)?.ack.as_ref())
+        .flat_map(|ack| ack.escalate_to.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for target in targets {
+        if !selected_channel_ids.contains(&target)
+            && !deliveries
+                .results
+                .iter()
+                .any(|result| result.channel_id == target)
+        {
+            deliveries.send(config, &target, event);
+        }
+    }
+    deliveries.await_responses();
+}
+
+/// Orders channels so each comes after the channels named in its
+/// `suppress_if_delivered`, keeping the selection order otherwise.
+fn delivery_order<'a>(config: &Config, selected_channel_ids: &'a [String]) -> Vec<&'a String> {
+    let mut pending = selected_channel_ids.iter().collect::<Vec<_>>();
+    let mut ordered = Vec::with_capacity(pending.len());
+
+    while !pending.is_empty() {
+        let ready = pending
+            .iter()
+            .position(|channel_id| {
+                config.channels.get(*channel_id).is_none_or(|channel| {
+                    channel
+                        .suppress_if_delivered
+                        .iter()
+                        .all(|other| !pending.contains(&other))
+                })
+            })
+            // cycles are rejected at config load; fall back to selection order.
+            .unwrap_or(0);
+        ordered.push(pending.remove(ready));
+    }
+
+    ordered
+}
+
+impl DeliveryResult {
+    /// Whether the channel actually received the notification just now.
+    pub fn delivered(&self) -> bool {
+        self.success && !self.queued && self.skipped.is_none()
+    }
+
+    /// A successful outcome for `channel_id` that has not sent anything yet.
+    fn new(channel_id: &str) -> Self {
+        Self {
+            channel_id: channel_id.to_string(),
+            success: true,
+            error: None,
+            latency_ms: None,
+            attempts: 0,
+            queued: false,
+            skipped: None,
+            interaction: None,
+            acknowledged: None,
+            targets: Vec::new(),
+            captured: BTreeMap::new(),
+        }
+    }
+
+    /// A channel that was deliberately not notified.
+    fn skipped(channel_id: &str, reason: &str) -> Self {
+        Self {
+            skipped: Some(reason.to_string()),
+            ..Self::new(channel_id)
+        }
+    }
+}
+
+/// Delivers to one channel, applying its pipeline and digest window; disabled
+/// channels are skipped however they were selected. Returns
+/// the response to wait for, if any, and whether it acknowledges a failure.
+fn deliver(
+    config: &Config,
+    channel_id: &str,
+    event: &CompletionEvent,
+) -> (DeliveryResult, Option<(bool, Response)>) {
+    let Some(channel) = config.channels.get(channel_id) else {
+        let message = match closest_match(channel_id, config.channels.keys().map(String::as_str)) {
+            Some(suggestion) => {
+                format!("channel not found in config; did you mean `{suggestion}`?")
+            }
+            None => "channel not found in config".to_string(),
+        };
+        let result = DeliveryResult {
+            success: false,
+            error: Some(DeliveryError::config(message)),
+            ..DeliveryResult::new(channel_id)
+        };
+        return (result, None);
+    };
+    if !channel.enabled {
+        return (DeliveryResult::skipped(channel_id, "disabled"), None);
+    }
+    if let Some(note) = &event.posted_note {
+        return (deliver_note(config, channel_id, channel, event, note), None);
+    }
+
+    let prepared = match prepare(config, channel, event) {
+        Ok(prepared) => prepared,
+        Err(reason) => return (DeliveryResult::skipped(channel_id, &reason), None),
+    };
+    let (channel, event) = (&*prepared.channel, prepared.event);
+
+    let digested;
+    let event = match channel.digest {
+        Some(window) => match digest_event(channel_id, window, &event) {
+            DigestDecision::Send(event) => {
+                digested = event;
+                &*digested
+            }
+            DigestDecision::Hold => {
+                let result = DeliveryResult {
+                    queued: true,
+                    ..DeliveryResult::new(channel_id)
                 };
+                return (result, None);
+            }
+        },
+        None => &*event,
+    };
+
+    let started = Instant::now();
+    let mut result = DeliveryResult {
+        attempts: 1,
+        ..DeliveryResult::new(channel_id)
+    };
+    let mut response = None;
+    let outcome = send_one(
+        config,
+        channel_id,
+        channel,
+        event,
+        &mut result,
+        &mut response,
+    );
+    result.latency_ms = Some(started.elapsed().as_millis());
+    match outcome {
+        Ok(()) => {
+            let acknowledges = failure_ack(channel, event).is_some();
+            (result, response.map(|response| (acknowledges, response)))
+        }
+        Err(error) => {
+            let result = DeliveryResult {
+                success: false,
+                error: Some(DeliveryError {
+                    message: redact_sensitive(&error.message),
+                    ..error
+                }),
+                ..result
             };
+            (result, None)
+        }
+    }
+}
 
-            match send_one(channel, event) {
-                Ok(()) => DeliveryResult {
-                    channel_id: channel_id.clone(),
-                    success: true,
-                    error: None,
-                },
-                Err(error) => DeliveryResult {
-                    channel_id: channel_id.clone(),
-                    success: false,
-                    error: Some(redact_sensitive(&error)),
-                },
+/// Folds completions queued during do-not-disturb into `event` for a `queue`
+/// channel. If the store is unavailable the event is sent as is.
+fn release_dnd(
+    channel_id: &str,
+    desktop: &DesktopChannel,
+    event: &CompletionEvent,
+) -> CompletionEvent {
+    if desktop.when_dnd != WhenDnd::Queue {
+        return event.clone();
+    }
+
+    StateStore::open()
+        .map_err(|error| error.to_string())
+        .and_then(|store| release_dnd_queue(&store, channel_id, event))
+        .unwrap_or_else(|error| {
+            eprintln!("brb: dnd queue unavailable for `{channel_id}`: {error}");
+            event.clone()
+        })
+}
+
+/// Problems that depend on the machine rather than the config file, such as a
+/// custom notifier that is not installed. Checked by `brb channels validate`.
+pub fn preflight_problems(config: &Config) -> Vec<String> {
+    config
+        .channels
+        .iter()
+        .filter_map(|(channel_id, channel)| {
+            let ChannelConfig::Custom(custom) = &channel.kind else {
+                return None;
+            };
+            if let Some(interpreter) = custom.interpreter {
+                let program = interpreter.program();
+                if find_executable(program).is_none() {
+                    return Some(format!(
+                        "channel `{channel_id}`: interpreter `{program}` was not found"
+                    ));
+                }
+                return find_script(&custom.exec).is_none().then(|| {
+                    format!(
+                        "channel `{channel_id}`: custom notifier script `{}` was not found",
+                        custom.exec
+                    )
+                });
             }
+            find_executable(&custom.exec).is_none().then(|| {
+                format!(
+                    "channel `{channel_id}`: custom notifier `{}` was not found or is not executable",
+                    custom.exec
+                )
+            })
         })
         .collect()
 }
 
-fn send_one(channel: &ChannelConfig, event: &CompletionEvent) -> Result<(), String> {
-    match channel {
-        ChannelConfig::Desktop(_) => send_desktop(event),
-        ChannelConfig::Webhook(webhook) => send_webhook(webhook, event),
-        ChannelConfig::Custom(custom) => send_custom(custom, event),
-    }
+/// Resolves a program the way process spawning does: paths are checked
+/// directly, bare names are searched for on `PATH`. On Windows, names without
+/// an extension also match `PATHEXT` extensions and `.ps1`.
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    search(program, resolve_executable)
 }
 
-fn send_desktop(event: &CompletionEvent) -> Result<(), String> {
-    let title = if event.exit_code == 0 {
-        "brb: success".to_string()
+/// Program and arguments that run a custom notifier: `exec` itself, or its
+/// interpreter with the script as the first argument.
+pub fn custom_invocation(custom: &CustomChannel) -> (String, Vec<String>) {
+    let exec = if cfg!(windows) {
+        // the resolved path carries the extension the interpreter is picked from.
+        find_executable(&custom.exec)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| custom.exec.clone())
     } else {
-        format!("brb: failed (exit {})", event.exit_code)
+        custom.exec.clone()
+    };
+    let interpreter = custom.interpreter.or_else(|| {
+        cfg!(windows)
+            .then(|| Interpreter::for_script(&exec))
+            .flatten()
+    });
+
+    let Some(interpreter) = interpreter else {
+        return (exec, custom.args.clone());
+    };
+    let mut args = match interpreter {
+        Interpreter::Cmd => vec!["/C".to_string()],
+        Interpreter::Powershell | Interpreter::Pwsh => [
+            "-NoProfile",
+            "-NonInteractive",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-File",
+        ]
+        .map(String::from)
+        .to_vec(),
+        Interpreter::Sh => Vec::new(),
+    };
+    args.push(exec);
+    args.extend(custom.args.iter().cloned());
+    (interpreter.program().to_string(), args)
+}
+
+/// Finds a script passed to an interpreter, which only needs to be a file.
+fn find_script(script: &str) -> Option<PathBuf> {
+    search(script, |path| path.is_file().then(|| path.to_path_buf()))
+}
+
+fn search(program: &str, resolve: impl Fn(&Path) -> Option<PathBuf>) -> Option<PathBuf> {
+    let candidate = Path::new(program);
+    if candidate.components().count() > 1 {
+        return resolve(candidate);
+    }
+
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path).find_map(|dir| resolve(&dir.join(program)))
+}
+
+#[cfg(unix)]
+fn resolve_executable(path: &Path) -> Option<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .then(|| path.to_path_buf())
+}
+
+#[cfg(not(unix))]
+fn resolve_executable(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    if path.extension().is_some() {
+        return None;
+    }
+
+    let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    extensions
+        .split(';')
+        .chain([".PS1"])
+        .filter_map(|extension| extension.strip_prefix('.'))
+        .map(|extension| path.with_extension(extension.to_ascii_lowercase()))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Applies a channel's digest window using the default state store. If the
+/// store is unavailable the event is sent immediately rather than dropped.
+fn digest_event(channel_id: &str, window: Duration, event: &CompletionEvent) -> DigestDecision {
+    let decision = StateStore::open()
+        .map_err(|error| error.to_string())
+        .and_then(|store| apply_digest(&store, channel_id, window, event, Utc::now()));
+
+    match decision {
+        Ok(decision) => decision,
+        Err(error) => {
+            eprintln!("brb: digest state unavailable for `{channel_id}`, sending now: {error}");
+            DigestDecision::Send(Box::new(event.clone()))
+        }
+    }
+}
+
+/// Shows `event` on a desktop channel straight away, skipping its digest and
+/// do-not-disturb handling. Nothing is shown when the pipeline drops it.
+#[cfg_attr(not(feature = "desktop"), allow(unused_variables))]
+pub fn show_desktop(
+    config: &Config,
+    channel: &Channel,
+    event: &CompletionEvent,
+) -> Result<(), DeliveryError> {
+    let Ok(prepared) = prepare(config, channel, event) else {
+        return Ok(());
+    };
+    let (channel, event) = (&*prepared.channel, &*prepared.event);
+    match &channel.kind {
+        #[cfg(feature = "desktop")]
+        ChannelConfig::Desktop(desktop) => send_desktop(
+            config,
+            desktop,
+            channel.template.as_deref(),
+            event,
+            None,
+            // a preview is not waited on.
+            &mut None,
+        ),
+        #[cfg(not(feature = "desktop"))]
+        ChannelConfig::Desktop(_) => Err(DeliveryError::config(missing_feature("desktop"))),
+        _ => Err(DeliveryError::config("not a desktop channel")),
+    }
+}
+
+#[cfg_attr(
+    not(all(feature = "desktop", feature = "webhook")),
+    allow(unused_variables, clippy::ptr_arg)
+)]
+fn send_one(
+    config: &Config,
+    channel_id: &str,
+    channel: &Channel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+    response: &mut Option<Response>,
+) -> Result<(), DeliveryError> {
+    let ack = failure_ack(channel, event);
+    match &channel.kind {
+        #[cfg(feature = "desktop")]
+        ChannelConfig::Desktop(desktop) => send_desktop(
+            config,
+            desktop,
+            channel.template.as_deref(),
+            event,
+            ack,
+            response,
+        ),
+        #[cfg(not(feature = "desktop"))]
+        ChannelConfig::Desktop(_) => Err(DeliveryError::config(missing_feature("desktop"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Webhook(webhook) => {
+            let since = Utc::now().timestamp();
+            send_webhook(config, channel_id, channel, webhook, event, delivery)?;
+            if let Some(ack) = ack
+                && webhook.format == WebhookFormat::Ntfy
+            {
+                *response = Some(await_webhook_ack(config, webhook, ack, event, since));
+            }
+            Ok(())
+        }
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Webhook(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Slack(slack) => send_slack(config, channel, slack, event, delivery),
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Slack(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::GoogleChat(chat) => send_googlechat(config, channel, chat, event, delivery),
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::GoogleChat(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Telegram(telegram) => {
+            send_telegram(config, channel_id, channel, telegram, event, delivery)
+        }
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Telegram(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Ntfy(ntfy) => {
+            let since = Utc::now().timestamp();
+            send_ntfy(config, channel, ntfy, event, delivery)?;
+            if let Some(ack) = ack {
+                *response = Some(await_webhook_ack(
+                    config,
+                    &ntfy.as_webhook(),
+                    ack,
+                    event,
+                    since,
+                ));
+            }
+            Ok(())
+        }
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Ntfy(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Pushover(pushover) => {
+            send_pushover(config, channel, pushover, event, delivery)
+        }
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Pushover(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Mqtt(mqtt) => send_mqtt(config, channel, mqtt, event, delivery),
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Mqtt(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Zulip(zulip) => send_zulip(config, channel, zulip, event, delivery),
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Zulip(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        #[cfg(feature = "webhook")]
+        ChannelConfig::Healthchecks(healthchecks) => {
+            send_healthchecks(config, channel, healthchecks, event, delivery)
+        }
+        #[cfg(not(feature = "webhook"))]
+        ChannelConfig::Healthchecks(_) => Err(DeliveryError::config(missing_feature("webhook"))),
+        // replaced by the channel its target describes when config loads.
+        ChannelConfig::Url(_) => Err(DeliveryError::config("url channel target was not resolved")),
+        ChannelConfig::Custom(custom) => send_custom(channel, custom, event),
+    }
+}
+
+/// The channel's `ack` settings when `event` is a failure worth acknowledging.
+fn failure_ack<'a>(channel: &'a Channel, event: &CompletionEvent) -> Option<&'a AckConfig> {
+    channel.ack.as_ref().filter(|_| event.exit_code != 0)
+}
+
+/// How long one poll of an ntfy acknowledgement topic may take.
+#[cfg(feature = "webhook")]
+const ACK_POLL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Waits for the "Acknowledge" action of an ntfy message to publish the run
+/// ID to the channel's acknowledgement topic.
+#[cfg(feature = "webhook")]
+fn await_webhook_ack(
+    config: &Config,
+    webhook: &WebhookChannel,
+    ack: &AckConfig,
+    event: &CompletionEvent,
+    since: i64,
+) -> Response {
+    let Some(url) = webhook.url.first() else {
+        return Box::new(|| Interaction::Unanswered);
+    };
+    let ack_url = ntfy_ack_url(url);
+    let http = config.http.for_webhook(webhook);
+    let mut client = reqwest::blocking::Client::builder()
+        .timeout(ACK_POLL_TIMEOUT)
+        .user_agent(
+            http.user_agent
+                .unwrap_or_else(|| format!("brb/{}", env!("CARGO_PKG_VERSION"))),
+        );
+    if let Some(proxy) = http
+        .proxy
+        .as_deref()
+        .and_then(|proxy| reqwest::Proxy::all(proxy).ok())
+    {
+        client = client.proxy(proxy);
+    }
+    let Ok(client) = client.build() else {
+        return Box::new(|| Interaction::Unanswered);
     };
 
-    let duration_s = event.duration_ms as f64 / 1000.0;
-    let body = format!("{} ({:.2}s)", event.command.join(" "), duration_s);
+    let run_id = event.run_id.clone();
+    let timeout = ack.timeout;
+    Box::new(move || {
+        let acknowledged = await_ntfy_ack(&run_id, since, timeout, |since| {
+            let mut request = client.get(format!("{ack_url}/json?poll=1&since={since}"));
+            for (name, value) in &http.headers {
+                request = request.header(name, value);
+            }
+            request
+                .send()
+                .ok()
+                .filter(|response| response.status().is_success())
+                .and_then(|response| response.text().ok())
+        });
+        if acknowledged {
+            Interaction::Acknowledged
+        } else {
+            Interaction::Unanswered
+        }
+    })
+}
+
+/// Delivery error for a channel type compiled out of this build.
+#[cfg(not(all(feature = "desktop", feature = "webhook")))]
+fn missing_feature(feature: &str) -> String {
+    format!("brb was built without the `{feature}` feature")
+}
+
+#[cfg(feature = "desktop")]
+fn send_desktop(
+    config: &Config,
+    desktop: &DesktopChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+    ack: Option<&AckConfig>,
+    response: &mut Option<Response>,
+) -> Result<(), DeliveryError> {
+    let MessageText { title, body } = desktop_text(config, desktop, template, event);
 
     #[cfg(target_os = "macos")]
     {
-        let script = format!(
-            "display notification \"{}\" with title \"{}\"",
-            escape_applescript(&body),
-            escape_applescript(&title)
-        );
+        let _ = (ack, response);
+        // `display notification` cannot replace earlier notifications, but
+        // terminal-notifier can when it is installed.
+        let (mut command, bundle, app) = match find_executable("terminal-notifier") {
+            Some(notifier) if desktop.group => {
+                let mut command = Command::new(notifier);
+                command
+                    .arg("-title")
+                    .arg(&title)
+                    .arg("-message")
+                    .arg(&body)
+                    .arg("-group")
+                    .arg(group_key(event));
+                if let Some(sound) = desktop_sound(desktop, event) {
+                    command.arg("-sound").arg(sound);
+                }
+                (
+                    command,
+                    crate::ncprefs::TERMINAL_NOTIFIER,
+                    "terminal-notifier",
+                )
+            }
+            _ => {
+                let mut script = format!(
+                    "display notification \"{}\" with title \"{}\"",
+                    escape_applescript(&body),
+                    escape_applescript(&title)
+                );
+                if let Some(sound) = desktop_sound(desktop, event) {
+                    script.push_str(&format!(" sound name \"{}\"", escape_applescript(sound)));
+                }
+                let mut command = Command::new("osascript");
+                command.arg("-e").arg(script);
+                (command, crate::ncprefs::SCRIPT_EDITOR, "Script Editor")
+            }
+        };
 
-        let status = Command::new("osascript")
-            .arg("-e")
-            .arg(script)
-            .status()
-            .map_err(|error| format!("failed to run osascript: {error}"))?;
+        // both notifiers exit 0 whatever Notification Center then does with
+        // the notification, so its settings are checked first.
+        if let Some(problem) = crate::ncprefs::notifier_problem(bundle, app) {
+            return Err(DeliveryError::config(problem));
+        }
+
+        let output = command.output().map_err(|error| {
+            DeliveryError::spawn(format!("failed to run desktop notifier: {error}"))
+        })?;
 
-        if status.success() {
+        if output.status.success() {
             Ok(())
         } else {
-            Err("desktop notifier command returned non-zero status".to_string())
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let message = match stderr.trim() {
+                "" => "desktop notifier command returned non-zero status".to_string(),
+                stderr => format!("desktop notifier command failed: {stderr}"),
+            };
+            Err(DeliveryError::new(
+                DeliveryErrorKind::Exit {
+                    code: output.status.code(),
+                },
+                message,
+            ))
         }
     }
 
     #[cfg(target_os = "linux")]
     {
-        let status = Command::new("notify-send")
-            .arg(title)
-            .arg(body)
-            .status()
-            .map_err(|error| format!("failed to run notify-send: {error}"))?;
+        // the notification server's reply confirms delivery, and waiting for a
+        // response needs its signals; notify-send only stands in when there
+        // is no session bus and nothing needs waiting for.
+        let wait = ack.map(|ack| ack.timeout).or(desktop.wait);
+        let bus = match crate::dbus::session_bus() {
+            Ok(bus) => bus,
+            Err(_) if wait.is_none() => return notify_send(config, desktop, event, title, body),
+            Err(error) => return Err(DeliveryError::spawn(error)),
+        };
 
-        if status.success() {
-            Ok(())
-        } else {
-            Err("desktop notifier command returned non-zero status".to_string())
+        let mut notification = desktop_notification(config, desktop, event, title, body);
+        let Some(wait) = wait else {
+            notification.actions.clear();
+            bus.notify(&notification).map_err(DeliveryError::spawn)?;
+            return Ok(());
+        };
+        if ack.is_some() {
+            notification
+                .actions
+                .push(("ack".to_string(), messages(config).acknowledge.to_string()));
         }
+        let watch = bus
+            .notify_and_watch(&notification)
+            .map_err(DeliveryError::spawn)?;
+        *response = Some(Box::new(move || {
+            Interaction::from_signal(watch.wait(wait).as_ref())
+        }));
+        Ok(())
     }
 
     #[cfg(target_os = "windows")]
     {
-        let _ = title;
-        let _ = body;
-        Err("desktop channel is not implemented on Windows yet".to_string())
+        let _ = (desktop, title, body, ack, response);
+        Err(DeliveryError::config(
+            "desktop channel is not implemented on Windows yet",
+        ))
     }
 
     #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
     {
-        let _ = title;
-        let _ = body;
-        Err("desktop channel is not supported on this platform".to_string())
+        let _ = (desktop, title, body, ack, response);
+        Err(DeliveryError::config(
+            "desktop channel is not supported on this platform",
+        ))
+    }
+}
+
+/// Shows a desktop notification with `notify-send`, for when there is no
+/// session bus to reach the notification server over.
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+fn notify_send(
+    config: &Config,
+    desktop: &DesktopChannel,
+    event: &CompletionEvent,
+    title: String,
+    body: String,
+) -> Result<(), DeliveryError> {
+    let status = Command::new("notify-send")
+        .args(notify_send_args(config, desktop, event))
+        .arg(title)
+        .arg(body)
+        .status()
+        .map_err(|error| DeliveryError::spawn(format!("failed to run notify-send: {error}")))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(DeliveryError::new(
+            DeliveryErrorKind::Exit {
+                code: status.code(),
+            },
+            "desktop notifier command returned non-zero status",
+        ))
+    }
+}
+
+/// `notify-send` options (before the title and body) for a desktop channel:
+/// urgency, the replace hints used by `group`, the sound picked by `sounds`,
+/// and expiry for `dismiss_success_after`.
+pub fn notify_send_args(
+    config: &Config,
+    desktop: &DesktopChannel,
+    event: &CompletionEvent,
+) -> Vec<String> {
+    let mut args = vec![format!("--urgency={}", desktop_urgency(config, event))];
+
+    if desktop.group {
+        // servers differ in which hint they honour for replacement.
+        let key = group_key(event);
+        args.push(format!(
+            "--hint=string:x-canonical-private-synchronous:{key}"
+        ));
+        args.push(format!("--hint=string:x-dunst-stack-tag:{key}"));
+    }
+
+    if let Some(sound) = desktop_sound(desktop, event) {
+        args.push(format!("--hint=string:sound-name:{sound}"));
+    }
+
+    if let Some(after) = desktop.dismiss_success_after {
+        if event.exit_code == 0 {
+            args.push(format!("--expire-time={}", after.as_millis()));
+            args.push("--transient".to_string());
+        } else {
+            args.push("--expire-time=0".to_string());
+        }
+    }
+
+    args
+}
+
+/// Desktop urgency (`low`, `normal` or `critical`) for the event's priority.
+/// Only a priority set in `appearance` changes it from `normal`, since
+/// `critical` notifications never expire on their own.
+pub fn desktop_urgency(config: &Config, event: &CompletionEvent) -> &'static str {
+    match desktop_priority(config, event) {
+        Priority::Min | Priority::Low => "low",
+        Priority::Default => "normal",
+        Priority::High | Priority::Urgent => "critical",
+    }
+}
+
+fn desktop_priority(config: &Config, event: &CompletionEvent) -> Priority {
+    status_appearance(config, event)
+        .priority
+        .unwrap_or(Priority::Default)
+}
+
+/// The D-Bus notification for a desktop channel, matching what
+/// [`notify_send_args`] asks for plus the click and "Re-run" actions.
+pub fn desktop_notification(
+    config: &Config,
+    desktop: &DesktopChannel,
+    event: &CompletionEvent,
+    title: String,
+    body: String,
+) -> Notification {
+    let mut notification = Notification {
+        summary: title,
+        body,
+        actions: vec![("default".to_string(), "brb".to_string())],
+        urgency: match desktop_priority(config, event) {
+            Priority::Min | Priority::Low => 0,
+            Priority::Default => 1,
+            Priority::High | Priority::Urgent => 2,
+        },
+        expire_timeout: -1,
+        ..Notification::default()
+    };
+
+    if desktop.rerun {
+        notification
+            .actions
+            .push(("rerun".to_string(), messages(config).rerun.to_string()));
+    }
+
+    if desktop.group {
+        let key = group_key(event);
+        notification.hints = vec![
+            ("x-canonical-private-synchronous".to_string(), key.clone()),
+            ("x-dunst-stack-tag".to_string(), key),
+        ];
+    }
+
+    if let Some(sound) = desktop_sound(desktop, event) {
+        notification
+            .hints
+            .push(("sound-name".to_string(), sound.to_string()));
+    }
+
+    if let Some(after) = desktop.dismiss_success_after {
+        if event.exit_code == 0 {
+            notification.expire_timeout = i32::try_from(after.as_millis()).unwrap_or(i32::MAX);
+            notification.transient = true;
+        } else {
+            notification.expire_timeout = 0;
+        }
     }
+
+    notification
 }
 
-fn send_webhook(webhook: &WebhookChannel, event: &CompletionEvent) -> Result<(), String> {
+/// The sound of the first `sounds` rule matching the event's status and
+/// duration, if any.
+pub fn desktop_sound<'a>(desktop: &'a DesktopChannel, event: &CompletionEvent) -> Option<&'a str> {
+    desktop
+        .sounds
+        .iter()
+        .find(|rule| {
+            rule.status
+                .as_ref()
+                .is_none_or(|status| *status == event.status)
+                && rule
+                    .min_duration
+                    .is_none_or(|min| event.duration_ms >= min.as_millis())
+        })
+        .map(|rule| rule.sound.as_str())
+}
+
+/// Identifies notifications for the same command, so grouped ones replace
+/// each other.
+fn group_key(event: &CompletionEvent) -> String {
+    format!("brb:{}", event.fingerprint)
+}
+
+#[cfg(feature = "webhook")]
+fn send_webhook(
+    config: &Config,
+    channel_id: &str,
+    channel: &Channel,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
     let method = reqwest::Method::from_bytes(webhook.method.as_bytes())
-        .map_err(|_| "invalid HTTP method in webhook config".to_string())?;
-    let headers = build_headers(&webhook.headers)?;
+        .map_err(|_| DeliveryError::config("invalid HTTP method in webhook config"))?;
+
+    let (mut payload, _) =
+        fitted_payload(config, channel, webhook, event).map_err(DeliveryError::config)?;
+    if let WebhookPayload::Json(body) = &mut payload
+        && webhook
+            .extra
+            .values()
+            .any(|value| value.contains("{captured."))
+    {
+        let captured = StateStore::open()
+            .map(|store| load_captures(&store, channel_id, &event.fingerprint))
+            .unwrap_or_default();
+        apply_captures(body, &webhook.extra, &captured);
+    }
+    // the run's "running" message is edited into the final status, and its
+    // follow-ups reply to it.
+    let progress = webhook
+        .progress
+        .then(|| run_message(channel_id, event))
+        .flatten();
+    let follow_up = is_follow_up(event);
+    if let (Some(message), WebhookPayload::Json(body)) = (&progress, &mut payload) {
+        if follow_up {
+            apply_thread_reply(body, message);
+        } else {
+            apply_progress(body, message);
+        }
+    }
+    // Slack's `chat.postMessage` answers with the message's `ts`, so runs in a
+    // session can reply to the first one; a running message is edited instead.
+    let threaded = webhook.format == WebhookFormat::Slack
+        && webhook.url.len() == 1
+        && progress.is_none()
+        && event.session.is_some();
+    let thread = threaded
+        .then(|| session_thread(channel_id, event))
+        .flatten();
+    if let (Some(thread), WebhookPayload::Json(body)) = (&thread, &mut payload)
+        && let Some(object) = body.as_object_mut()
+    {
+        object.insert(
+            "thread_ts".to_string(),
+            serde_json::Value::String(thread.message_id.clone()),
+        );
+    }
+    if !webhook.encrypt_to.is_empty() {
+        payload = seal_payload(config, webhook, event, payload).map_err(DeliveryError::spawn)?;
+    }
+    // structured-mode CloudEvents are recognized by their content type.
+    let content_type =
+        if webhook.format == WebhookFormat::CloudEvents && webhook.encrypt_to.is_empty() {
+            "application/cloudevents+json"
+        } else {
+            "application/json"
+        };
+    let (mut headers, body) = match payload {
+        WebhookPayload::Json(body) => (
+            BTreeMap::from([("Content-Type".to_string(), content_type.to_string())]),
+            serde_json::to_vec(&body)
+                .map_err(|_| DeliveryError::config("failed to encode webhook body"))?,
+        ),
+        WebhookPayload::Text { body, headers } => (ntfy_headers(headers), body.into_bytes()),
+    };
+    let body = if webhook.oversize == Oversize::Gzip
+        && webhook
+            .max_payload
+            .is_some_and(|max| body.len() as u64 > max)
+    {
+        headers.insert("Content-Encoding".to_string(), "gzip".to_string());
+        gzip(&body).map_err(|error| {
+            DeliveryError::config(format!("failed to compress webhook body: {error}"))
+        })?
+    } else {
+        body
+    };
+    if !webhook.idempotency_header.is_empty() {
+        headers.insert(
+            webhook.idempotency_header.clone(),
+            idempotency_key(event, channel_id),
+        );
+    }
+    let http = config.http.for_webhook(webhook);
+    headers.extend(http.headers.clone());
+    if failure_ack(channel, event).is_some()
+        && webhook.format == WebhookFormat::Ntfy
+        && let Some(url) = webhook.url.first()
+    {
+        headers.insert(
+            "Actions".to_string(),
+            ntfy_ack_action(
+                messages(config).acknowledge,
+                &ntfy_ack_url(url),
+                &event.run_id,
+            ),
+        );
+    }
+
+    let client = webhook_client(&http)?;
+    let headers = build_headers(&headers).map_err(DeliveryError::config)?;
+    if let [url] = webhook.url.as_slice() {
+        let mut edit_attempts = 0;
+        if let Some(message_id) = progress
+            .as_ref()
+            .filter(|_| !follow_up)
+            .and_then(|message| message_id(webhook.format, message))
+        {
+            let (edit_method, edit_url) = progress_edit_target(webhook.format, url, message_id);
+            let edit_method = reqwest::Method::from_bytes(edit_method.as_bytes())
+                .map_err(|_| DeliveryError::config("invalid HTTP method for message edits"))?;
+            let request = client
+                .request(edit_method, edit_url)
+                .headers(headers.clone())
+                .body(body.clone());
+            match send_with_retries(&request, webhook.retries, &mut edit_attempts)
+                .map_err(|error| error.message)
+                .and_then(|response| capture_message(webhook.format, &response).map(|_| response))
+            {
+                Ok(response) => {
+                    delivery.attempts = edit_attempts;
+                    record_captures(channel_id, webhook, event, &response, delivery);
+                    return Ok(());
+                }
+                Err(error) => eprintln!(
+                    "brb: could not edit the running message on `{channel_id}`, posting instead: {error}"
+                ),
+            }
+        }
+
+        let request = client.request(method, url).headers(headers).body(body);
+        let outcome = send_with_retries(&request, webhook.retries, &mut delivery.attempts);
+        delivery.attempts += edit_attempts;
+        let response = outcome?;
+        if threaded && thread.is_none() {
+            let message_id = capture_message(webhook.format, &response)
+                .ok()
+                .and_then(|message| message_id(webhook.format, &message).map(str::to_string));
+            record_session_thread(channel_id, event, message_id);
+        }
+        // Slack Web API messages can be replied to by notes posted later.
+        if webhook.format == WebhookFormat::Slack
+            && progress.is_none()
+            && !follow_up
+            && let Ok(message) = capture_message(webhook.format, &response)
+        {
+            record_run_message(channel_id, event, message);
+        }
+        record_captures(channel_id, webhook, event, &response, delivery);
+        return Ok(());
+    }
+
+    // every URL gets the same body and idempotency key; the channel only
+    // counts as delivered when all of them accept it.
+    delivery.attempts = 0;
+    let mut accepted = None;
+    for url in &webhook.url {
+        let request = client
+            .request(method.clone(), url)
+            .headers(headers.clone())
+            .body(body.clone());
+        let mut tries = 0;
+        let outcome = send_with_retries(&request, webhook.retries, &mut tries);
+        delivery.attempts += tries;
+        let (success, error) = match outcome {
+            Ok(response) => {
+                accepted.get_or_insert(response);
+                (true, None)
+            }
+            Err(error) => (false, Some(error)),
+        };
+        delivery.targets.push(TargetResult {
+            host: reqwest::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default(),
+            success,
+            error,
+            attempts: tries,
+        });
+    }
+    if let Some(response) = accepted {
+        record_captures(channel_id, webhook, event, &response, delivery);
+    }
+
+    let failed = delivery
+        .targets
+        .iter()
+        .filter_map(|target| target.error.as_ref())
+        .collect::<Vec<_>>();
+    match failed.first() {
+        None => Ok(()),
+        Some(first) => Err(DeliveryError {
+            kind: first.kind,
+            message: format!(
+                "{} of {} webhook urls failed: {}",
+                failed.len(),
+                delivery.targets.len(),
+                first.message
+            ),
+        }),
+    }
+}
+
+/// Posts a Block Kit message to a Slack incoming webhook.
+#[cfg(feature = "webhook")]
+fn send_slack(
+    config: &Config,
+    channel: &Channel,
+    slack: &SlackChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let body = slack_blocks(config, slack, channel.template.as_deref(), event);
+    let mut headers = config.http.headers.clone();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    let request = webhook_client(&config.http)?
+        .post(&slack.url)
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .body(body.to_string());
+    send_with_retries(&request, slack.retries, &mut delivery.attempts).map(|_| ())
+}
+
+/// Posts a card to a Google Chat incoming webhook. Runs in a session reply
+/// in the thread their key names, which Chat starts on first use.
+#[cfg(feature = "webhook")]
+fn send_googlechat(
+    config: &Config,
+    channel: &Channel,
+    chat: &GoogleChatChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let body = googlechat_message(config, chat, channel.template.as_deref(), event);
+    let mut url = reqwest::Url::parse(&chat.url)
+        .map_err(|error| DeliveryError::config(format!("invalid url: {error}")))?;
+    if event.session.is_some() {
+        url.query_pairs_mut()
+            .append_pair("messageReplyOption", "REPLY_MESSAGE_FALLBACK_TO_NEW_THREAD");
+    }
+    let mut headers = config.http.headers.clone();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    let request = webhook_client(&config.http)?
+        .post(url)
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .body(body.to_string());
+    send_with_retries(&request, chat.retries, &mut delivery.attempts).map(|_| ())
+}
+
+/// Sends a message to a Telegram chat through the Bot API's `sendMessage`.
+#[cfg(feature = "webhook")]
+fn send_telegram(
+    config: &Config,
+    channel_id: &str,
+    channel: &Channel,
+    telegram: &TelegramChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let mut body = telegram_message(config, telegram, channel.template.as_deref(), event);
+    // like webhooks, the run's "running" message is edited or replied to; a
+    // session's first message is replied to otherwise.
+    let progress = telegram
+        .progress
+        .then(|| run_message(channel_id, event))
+        .flatten()
+        .and_then(|message| message.get("message_id")?.parse::<i64>().ok());
+    let follow_up = is_follow_up(event);
+    let thread = progress
+        .is_none()
+        .then(|| session_thread(channel_id, event))
+        .flatten();
+    let reply_to = match progress {
+        Some(message_id) => follow_up.then_some(message_id),
+        None => thread
+            .as_ref()
+            .and_then(|thread| thread.message_id.parse::<i64>().ok()),
+    };
+    if let Some(message_id) = reply_to {
+        body["reply_parameters"] = serde_json::json!({
+            "message_id": message_id,
+            "allow_sending_without_reply": true,
+        });
+    }
+    let mut headers = config.http.headers.clone();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    let client = webhook_client(&config.http)?;
+    let headers = build_headers(&headers).map_err(DeliveryError::config)?;
+
+    let mut edit_attempts = 0;
+    if let Some(message_id) = progress.filter(|_| !follow_up) {
+        let mut edit = body.clone();
+        edit["message_id"] = serde_json::Value::from(message_id);
+        let request = client
+            .post(telegram_url(telegram, "editMessageText"))
+            .headers(headers.clone())
+            .body(edit.to_string());
+        match send_with_retries(&request, telegram.retries, &mut edit_attempts) {
+            Ok(_) => {
+                delivery.attempts = edit_attempts;
+                return Ok(());
+            }
+            Err(error) => eprintln!(
+                "brb: could not edit the running message on `{channel_id}`, posting instead: {}",
+                error.message
+            ),
+        }
+    }
 
-    let client = reqwest::blocking::Client::new();
-    let response = client
-        .request(method, &webhook.url)
+    let request = client
+        .post(telegram_url(telegram, "sendMessage"))
         .headers(headers)
-        .json(event)
-        .send()
-        .map_err(|_| "webhook request failed".to_string())?;
+        .body(body.to_string());
+    let outcome = send_with_retries(&request, telegram.retries, &mut delivery.attempts);
+    delivery.attempts += edit_attempts;
+    let response = outcome?;
+    if progress.is_none() && thread.is_none() {
+        let message_id = serde_json::from_str::<serde_json::Value>(&response)
+            .ok()
+            .and_then(|response| response["result"]["message_id"].as_i64())
+            .map(|message_id| message_id.to_string());
+        record_session_thread(channel_id, event, message_id);
+    }
+    if progress.is_none()
+        && !follow_up
+        && let Ok(message) = capture_telegram_message(&response)
+    {
+        record_run_message(channel_id, event, message);
+    }
+    Ok(())
+}
 
-    if response.status().is_success() {
-        Ok(())
+/// The "running" message `post_progress` posted for the event's run on a
+/// channel, as captured from the response.
+#[cfg(feature = "webhook")]
+fn run_message(channel_id: &str, event: &CompletionEvent) -> Option<BTreeMap<String, String>> {
+    let store = StateStore::open()
+        .inspect_err(|error| {
+            eprintln!("brb: running message state unavailable for `{channel_id}`: {error}");
+        })
+        .ok()?;
+    Some(load_captures(&store, channel_id, &run_key(&event.run_id)))
+        .filter(|message| !message.is_empty())
+}
+
+/// Keeps the message a run's completion posted on a channel, so notes posted
+/// later can reply to it.
+#[cfg(feature = "webhook")]
+fn record_run_message(
+    channel_id: &str,
+    event: &CompletionEvent,
+    message: BTreeMap<String, String>,
+) {
+    if let Err(error) = StateStore::open()
+        .map_err(|error| error.to_string())
+        .and_then(|store| {
+            save_captures(
+                &store,
+                channel_id,
+                &run_key(&event.run_id),
+                message,
+                Utc::now(),
+            )
+        })
+    {
+        eprintln!("brb: could not keep the run's message on `{channel_id}`: {error}");
+    }
+}
+
+/// Sends a note posted with `brb annotate --post` on its own, as a reply to
+/// the message the run left on the channel: its Slack Web API message or
+/// Telegram message. Channels that cannot reply, or kept no message for the
+/// run, are skipped.
+#[cfg(feature = "webhook")]
+fn deliver_note(
+    config: &Config,
+    channel_id: &str,
+    channel: &Channel,
+    event: &CompletionEvent,
+    note: &str,
+) -> DeliveryResult {
+    let no_message = || DeliveryResult::skipped(channel_id, "no message of the run to reply to");
+    let text = format!("{}: {note}", messages(config).note);
+    let started = Instant::now();
+    let mut result = DeliveryResult::new(channel_id);
+    let outcome = match &channel.kind {
+        ChannelConfig::Webhook(webhook) if webhook.format == WebhookFormat::Slack => {
+            let Some(message) = run_message(channel_id, event) else {
+                return no_message();
+            };
+            reply_on_slack(
+                config,
+                channel_id,
+                webhook,
+                event,
+                &message,
+                &text,
+                &mut result.attempts,
+            )
+        }
+        ChannelConfig::Telegram(telegram) => {
+            let Some(message) = run_message(channel_id, event) else {
+                return no_message();
+            };
+            reply_on_telegram(config, telegram, &message, &text, &mut result.attempts)
+        }
+        _ => return DeliveryResult::skipped(channel_id, "cannot reply to the run's message"),
+    };
+    result.latency_ms = Some(started.elapsed().as_millis());
+    match outcome {
+        Ok(()) => result,
+        Err(error) => DeliveryResult {
+            success: false,
+            error: Some(DeliveryError {
+                message: redact_sensitive(&error.message),
+                ..error
+            }),
+            ..result
+        },
+    }
+}
+
+#[cfg(not(feature = "webhook"))]
+fn deliver_note(
+    _config: &Config,
+    channel_id: &str,
+    _channel: &Channel,
+    _event: &CompletionEvent,
+    _note: &str,
+) -> DeliveryResult {
+    DeliveryResult::skipped(channel_id, "cannot reply to the run's message")
+}
+
+/// Posts `text` in the thread of the run's Slack Web API message.
+#[cfg(feature = "webhook")]
+fn reply_on_slack(
+    config: &Config,
+    channel_id: &str,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+    message: &BTreeMap<String, String>,
+    text: &str,
+    attempts: &mut u32,
+) -> Result<(), DeliveryError> {
+    let Some(url) = webhook.url.first() else {
+        return Err(DeliveryError::config("webhook has no url"));
+    };
+    let mut body = serde_json::json!({ "text": text });
+    apply_thread_reply(&mut body, message);
+
+    let mut headers =
+        BTreeMap::from([("Content-Type".to_string(), "application/json".to_string())]);
+    if !webhook.idempotency_header.is_empty() {
+        headers.insert(
+            webhook.idempotency_header.clone(),
+            idempotency_key(event, channel_id),
+        );
+    }
+    let http = config.http.for_webhook(webhook);
+    headers.extend(http.headers.clone());
+    let request = webhook_client(&http)?
+        .post(url)
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .body(body.to_string());
+    let response = send_with_retries(&request, webhook.retries, attempts)?;
+    // Slack answers a failed post with `200` and an `error`.
+    capture_message(webhook.format, &response)
+        .map(|_| ())
+        .map_err(DeliveryError::config)
+}
+
+/// Sends `text` as a reply to the run's Telegram message.
+#[cfg(feature = "webhook")]
+fn reply_on_telegram(
+    config: &Config,
+    telegram: &TelegramChannel,
+    message: &BTreeMap<String, String>,
+    text: &str,
+    attempts: &mut u32,
+) -> Result<(), DeliveryError> {
+    let message_id = message
+        .get("message_id")
+        .and_then(|message_id| message_id.parse::<i64>().ok())
+        .ok_or_else(|| DeliveryError::config("the run's message has no message id"))?;
+    let body = serde_json::json!({
+        "chat_id": telegram.chat_id,
+        "text": text,
+        "reply_parameters": {
+            "message_id": message_id,
+            "allow_sending_without_reply": true,
+        },
+    });
+
+    let mut headers = config.http.headers.clone();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    let request = webhook_client(&config.http)?
+        .post(telegram_url(telegram, "sendMessage"))
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .body(body.to_string());
+    send_with_retries(&request, telegram.retries, attempts).map(|_| ())
+}
+
+/// The first message the run's session posted on a channel, to reply to.
+#[cfg(feature = "webhook")]
+fn session_thread(channel_id: &str, event: &CompletionEvent) -> Option<SessionThread> {
+    let session = event.session.as_deref()?;
+    let store = StateStore::open().ok()?;
+    load_thread(&store, channel_id, session, Utc::now())
+}
+
+/// Keeps a session's first message on a channel so later runs in the session
+/// reply to it. Runs outside a session keep nothing.
+#[cfg(feature = "webhook")]
+fn record_session_thread(channel_id: &str, event: &CompletionEvent, message_id: Option<String>) {
+    let (Some(session), Some(message_id)) = (event.session.as_deref(), message_id) else {
+        return;
+    };
+    if let Err(error) = StateStore::open()
+        .map_err(|error| error.to_string())
+        .and_then(|store| save_thread(&store, channel_id, session, message_id, Utc::now()))
+    {
+        eprintln!("brb: could not keep the session thread for `{channel_id}`: {error}");
+    }
+}
+
+/// Sends a message through Pushover's Messages API.
+#[cfg(feature = "webhook")]
+fn send_pushover(
+    config: &Config,
+    channel: &Channel,
+    pushover: &PushoverChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let mut body = pushover_message(config, pushover, channel.template.as_deref(), event);
+    body["token"] = serde_json::Value::from(pushover.api_token.trim());
+    body["user"] = serde_json::Value::from(pushover.user_key.trim());
+    let mut headers = config.http.headers.clone();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    let request = webhook_client(&config.http)?
+        .post(pushover_url(pushover, "messages.json"))
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .body(body.to_string());
+    send_with_retries(&request, pushover.retries, &mut delivery.attempts).map(|_| ())
+}
+
+/// Posts a message to a Zulip stream through the REST API, as the bot.
+#[cfg(feature = "webhook")]
+fn send_zulip(
+    config: &Config,
+    channel: &Channel,
+    zulip: &ZulipChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let message = zulip_message(config, zulip, channel.template.as_deref(), event);
+    let form = message
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| (name.as_str(), value.as_str().unwrap_or_default()))
+        .collect::<Vec<_>>();
+    let mut headers = config.http.headers.clone();
+    headers.insert("Authorization".to_string(), zulip.authorization());
+    let request = webhook_client(&config.http)?
+        .post(zulip.api_url("messages"))
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .form(&form);
+    send_with_retries(&request, zulip.retries, &mut delivery.attempts).map(|_| ())
+}
+
+/// Pings a Healthchecks check with how the run went: its success endpoint,
+/// `/fail`, or `/log` for a trigger, which leaves the check's state alone.
+/// The run ID pairs the ping with the run's `/start` one.
+#[cfg(feature = "webhook")]
+fn send_healthchecks(
+    config: &Config,
+    channel: &Channel,
+    healthchecks: &HealthchecksChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let endpoint = if event.status == TRIGGERED_STATUS {
+        "/log"
+    } else if event.exit_code == 0 {
+        ""
     } else {
-        Err(format!(
-            "webhook returned HTTP {}",
-            response.status().as_u16()
-        ))
+        "/fail"
+    };
+    let body = healthchecks_body(config, healthchecks, channel.template.as_deref(), event);
+    let mut headers = config.http.headers.clone();
+    headers.insert(
+        "Content-Type".to_string(),
+        "text/plain; charset=utf-8".to_string(),
+    );
+    let request = webhook_client(&config.http)?
+        .post(healthchecks_url(healthchecks, endpoint, &event.run_id))
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .body(body);
+    send_with_retries(&request, healthchecks.retries, &mut delivery.attempts).map(|_| ())
+}
+
+/// Pings the `/start` endpoint of each selected Healthchecks channel, so the
+/// check times the run and flags one that never finishes. Returns a problem
+/// for each ping that failed.
+#[cfg(feature = "webhook")]
+pub fn ping_start(config: &Config, selected_channel_ids: &[String], run_id: &str) -> Vec<String> {
+    let mut problems = Vec::new();
+    for channel_id in selected_channel_ids {
+        let Some(ChannelConfig::Healthchecks(healthchecks)) = config
+            .channels
+            .get(channel_id)
+            .filter(|channel| channel.enabled)
+            .map(|channel| &channel.kind)
+        else {
+            continue;
+        };
+
+        let pinged = webhook_client(&config.http).and_then(|client| {
+            let request = client
+                .post(healthchecks_url(healthchecks, "/start", run_id))
+                .headers(build_headers(&config.http.headers).map_err(DeliveryError::config)?);
+            send_with_retries(&request, healthchecks.retries, &mut 0)
+        });
+        if let Err(error) = pinged {
+            problems.push(format!(
+                "could not ping the start of the run on `{channel_id}`: {}",
+                error.message
+            ));
+        }
+    }
+    problems
+}
+
+#[cfg(not(feature = "webhook"))]
+pub fn ping_start(
+    _config: &Config,
+    _selected_channel_ids: &[String],
+    _run_id: &str,
+) -> Vec<String> {
+    Vec::new()
+}
+
+/// URL of a Healthchecks ping endpoint, tagged with the run ID.
+#[cfg(feature = "webhook")]
+fn healthchecks_url(healthchecks: &HealthchecksChannel, endpoint: &str, run_id: &str) -> String {
+    format!("{}{endpoint}?rid={run_id}", healthchecks.ping_url())
+}
+
+/// URL of a Pushover API method.
+#[cfg(feature = "webhook")]
+pub fn pushover_url(pushover: &PushoverChannel, method: &str) -> String {
+    format!("{}/1/{method}", pushover.api_url.trim_end_matches('/'))
+}
+
+/// Publishes the completion event JSON to an MQTT topic.
+#[cfg(feature = "webhook")]
+fn send_mqtt(
+    config: &Config,
+    channel: &Channel,
+    mqtt: &MqttChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let payload = mqtt_payload(channel, event).to_string();
+    let broker = Broker::parse(&mqtt.broker)
+        .map_err(|error| DeliveryError::config(format!("unusable mqtt broker ({error})")))?;
+    let client_id = mqtt_client_id(mqtt, event);
+    let session = Session {
+        client_id: &client_id,
+        username: mqtt.username.as_deref(),
+        password: mqtt.password.as_deref(),
+    };
+    let message = Message {
+        topic: &mqtt.topic,
+        payload: payload.as_bytes(),
+        qos: mqtt.qos,
+        retain: mqtt.retain,
+    };
+    let timeout = config.http.timeout.unwrap_or(crate::mqtt::DEFAULT_TIMEOUT);
+
+    delivery.attempts = 0;
+    loop {
+        delivery.attempts += 1;
+        let error = match crate::mqtt::publish(&broker, &session, &message, timeout) {
+            Ok(()) => return Ok(()),
+            // retrying would be refused the same way.
+            Err(MqttError::Refused(reason)) => {
+                return Err(DeliveryError::config(format!(
+                    "mqtt broker refused the connection ({reason})"
+                )));
+            }
+            Err(MqttError::Timeout) => {
+                DeliveryError::new(DeliveryErrorKind::Timeout, "mqtt broker timed out")
+            }
+            Err(MqttError::Network(reason)) => DeliveryError::new(
+                DeliveryErrorKind::Network,
+                format!("mqtt publish failed ({reason})"),
+            ),
+        };
+        if delivery.attempts > mqtt.retries {
+            return Err(error);
+        }
+        std::thread::sleep(retry_delay(delivery.attempts - 1));
+    }
+}
+
+/// The channel's `client_id`, or one unique to the run, so concurrent runs
+/// do not disconnect each other.
+#[cfg(feature = "webhook")]
+pub fn mqtt_client_id(mqtt: &MqttChannel, event: &CompletionEvent) -> String {
+    mqtt.client_id.clone().unwrap_or_else(|| {
+        let run = event.run_id.split('-').next().unwrap_or_default();
+        format!("brb-{run}")
+    })
+}
+
+/// Publishes a message to an ntfy topic.
+#[cfg(feature = "webhook")]
+fn send_ntfy(
+    config: &Config,
+    channel: &Channel,
+    ntfy: &NtfyChannel,
+    event: &CompletionEvent,
+    delivery: &mut DeliveryResult,
+) -> Result<(), DeliveryError> {
+    let (body, headers) = ntfy_message(config, ntfy, channel.template.as_deref(), event);
+    let mut headers = ntfy_headers(headers);
+    headers.extend(config.http.headers.clone());
+    if let Some(authorization) = ntfy.authorization() {
+        headers.insert("Authorization".to_string(), authorization);
+    }
+    if failure_ack(channel, event).is_some() {
+        headers.insert(
+            "Actions".to_string(),
+            ntfy_ack_action(
+                messages(config).acknowledge,
+                &ntfy_ack_url(&ntfy.topic_url()),
+                &event.run_id,
+            ),
+        );
+    }
+
+    let request = webhook_client(&config.http)?
+        .post(ntfy.topic_url())
+        .headers(build_headers(&headers).map_err(DeliveryError::config)?)
+        .body(body);
+    send_with_retries(&request, ntfy.retries, &mut delivery.attempts).map(|_| ())
+}
+
+/// ntfy's message headers with non-ASCII values, such as an emoji in the
+/// title, sent as RFC 2047 encoded words, which ntfy decodes; HTTP headers
+/// cannot carry them as they are.
+#[cfg(feature = "webhook")]
+fn ntfy_headers(headers: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    use base64::Engine;
+
+    headers
+        .into_iter()
+        .map(|(name, value)| {
+            if value.is_ascii() {
+                (name, value)
+            } else {
+                let encoded = base64::engine::general_purpose::STANDARD.encode(&value);
+                (name, format!("=?UTF-8?B?{encoded}?="))
+            }
+        })
+        .collect()
+}
+
+/// URL of a Bot API method. It carries the bot token, so it is never shown.
+#[cfg(feature = "webhook")]
+pub fn telegram_url(telegram: &TelegramChannel, method: &str) -> String {
+    format!(
+        "{}/bot{}/{method}",
+        telegram.api_url.trim_end_matches('/'),
+        telegram.bot_token.trim()
+    )
+}
+
+/// Builds the HTTP client for a webhook's settings.
+#[cfg(feature = "webhook")]
+fn webhook_client(http: &HttpConfig) -> Result<reqwest::blocking::Client, DeliveryError> {
+    let mut client = reqwest::blocking::Client::builder().user_agent(
+        http.user_agent
+            .clone()
+            .unwrap_or_else(|| format!("brb/{}", env!("CARGO_PKG_VERSION"))),
+    );
+    if let Some(timeout) = http.timeout {
+        client = client.timeout(timeout);
+    }
+    if let Some(proxy) = &http.proxy {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|_| DeliveryError::config("invalid webhook proxy url"))?;
+        client = client.proxy(proxy);
+    }
+    client
+        .build()
+        .map_err(|_| DeliveryError::config("failed to build HTTP client"))
+}
+
+/// Posts the "running" message on each selected channel with `progress`,
+/// so the final status can edit it and follow-ups can reply to it. Returns
+/// what went wrong; a channel whose message could not be posted just sends
+/// its final status as usual.
+#[cfg(feature = "webhook")]
+pub fn post_progress(
+    config: &Config,
+    selected_channel_ids: &[String],
+    build_event: &dyn Fn() -> CompletionEvent,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut event = None;
+    for channel_id in selected_channel_ids {
+        let kind = config
+            .channels
+            .get(channel_id)
+            .filter(|channel| channel.enabled)
+            .map(|channel| &channel.kind);
+        let posts = match kind {
+            Some(ChannelConfig::Webhook(webhook)) => webhook.progress,
+            Some(ChannelConfig::Telegram(telegram)) => telegram.progress,
+            _ => false,
+        };
+        if !posts {
+            continue;
+        }
+
+        let event: &CompletionEvent = event.get_or_insert_with(build_event);
+        let posted = match kind {
+            Some(ChannelConfig::Webhook(webhook)) => {
+                post_webhook_progress(config, channel_id, webhook, event)
+            }
+            Some(ChannelConfig::Telegram(telegram)) => {
+                post_telegram_progress(config, telegram, event)
+            }
+            _ => continue,
+        }
+        .and_then(|message| {
+            let store = StateStore::open().map_err(|error| error.to_string())?;
+            save_captures(
+                &store,
+                channel_id,
+                &run_key(&event.run_id),
+                message,
+                Utc::now(),
+            )
+        });
+        if let Err(error) = posted {
+            problems.push(format!(
+                "could not post the running message on `{channel_id}`: {}",
+                redact_sensitive(&error)
+            ));
+        }
+    }
+    problems
+}
+
+/// Posts a webhook channel's "running" message and captures what identifies
+/// it.
+#[cfg(feature = "webhook")]
+fn post_webhook_progress(
+    config: &Config,
+    channel_id: &str,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+) -> Result<BTreeMap<String, String>, String> {
+    let url = webhook
+        .url
+        .first()
+        .ok_or_else(|| "channel has no url".to_string())?;
+    let captured = if webhook
+        .extra
+        .values()
+        .any(|value| value.contains("{captured."))
+    {
+        StateStore::open()
+            .map(|store| load_captures(&store, channel_id, &event.fingerprint))
+            .unwrap_or_default()
+    } else {
+        BTreeMap::new()
+    };
+    let body = progress_payload(config, webhook, event, &captured);
+    let http = config.http.for_webhook(webhook);
+    let client = webhook_client(&http).map_err(|error| error.message)?;
+    let mut headers = http.headers.clone();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    let request = client
+        .post(progress_post_url(webhook.format, url))
+        .headers(build_headers(&headers)?)
+        .body(body.to_string());
+    let response = send_with_retries(&request, 0, &mut 0).map_err(|error| error.message)?;
+    capture_message(webhook.format, &response)
+}
+
+/// Posts a Telegram channel's "running" message and captures its ID.
+#[cfg(feature = "webhook")]
+fn post_telegram_progress(
+    config: &Config,
+    telegram: &TelegramChannel,
+    event: &CompletionEvent,
+) -> Result<BTreeMap<String, String>, String> {
+    let body = telegram_progress_message(config, telegram, event);
+    let client = webhook_client(&config.http).map_err(|error| error.message)?;
+    let mut headers = config.http.headers.clone();
+    headers.insert("Content-Type".to_string(), "application/json".to_string());
+    let request = client
+        .post(telegram_url(telegram, "sendMessage"))
+        .headers(build_headers(&headers)?)
+        .body(body.to_string());
+    let response = send_with_retries(&request, 0, &mut 0).map_err(|error| error.message)?;
+    capture_telegram_message(&response)
+}
+
+#[cfg(not(feature = "webhook"))]
+pub fn post_progress(
+    _config: &Config,
+    _selected_channel_ids: &[String],
+    _build_event: &dyn Fn() -> CompletionEvent,
+) -> Vec<String> {
+    Vec::new()
+}
+
+/// Keeps the values a channel's `capture` names from the response it got,
+/// for the next delivery of the same command. With several URLs the first
+/// one that accepted the payload is used.
+#[cfg(feature = "webhook")]
+fn record_captures(
+    channel_id: &str,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+    response: &str,
+    delivery: &mut DeliveryResult,
+) {
+    if webhook.capture.is_empty() {
+        return;
+    }
+
+    let captured = extract_captures(&webhook.capture, response);
+    if captured.is_empty() {
+        return;
+    }
+    if let Err(error) = StateStore::open()
+        .map_err(|error| error.to_string())
+        .and_then(|store| {
+            save_captures(
+                &store,
+                channel_id,
+                &event.fingerprint,
+                captured.clone(),
+                Utc::now(),
+            )
+        })
+    {
+        eprintln!("brb: could not keep values captured by `{channel_id}`: {error}");
+    }
+    delivery.captured = captured;
+}
+
+/// Sends one webhook request, retrying network errors, 429 and 5xx
+/// responses up to `retries` times. Returns the accepted response's body.
+#[cfg(feature = "webhook")]
+fn send_with_retries(
+    request: &reqwest::blocking::RequestBuilder,
+    retries: u32,
+    attempts: &mut u32,
+) -> Result<String, DeliveryError> {
+    *attempts = 0;
+    loop {
+        *attempts += 1;
+        // the same request, including the idempotency key, is resent on retry.
+        let outcome = request
+            .try_clone()
+            .ok_or_else(|| DeliveryError::config("webhook request could not be retried"))?
+            .send();
+
+        let (error, retry_after) = match outcome {
+            Ok(response) if response.status().is_success() => {
+                return Ok(response.text().unwrap_or_default());
+            }
+            Ok(response) => {
+                let status = response.status().as_u16();
+                let error = DeliveryError::new(
+                    DeliveryErrorKind::Http { status },
+                    format!("webhook returned HTTP {status}"),
+                );
+                if !(response.status().is_server_error() || status == 429) {
+                    return Err(error);
+                }
+                let retry_after = response
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .filter(|_| status == 429 || status == 503)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| parse_retry_after(value, Utc::now()));
+                (error, retry_after)
+            }
+            Err(error) if error.is_timeout() => (
+                DeliveryError::new(DeliveryErrorKind::Timeout, "webhook request timed out"),
+                None,
+            ),
+            Err(_) => (
+                DeliveryError::new(DeliveryErrorKind::Network, "webhook request failed"),
+                None,
+            ),
+        };
+
+        // a rate limit that says when to come back is worth one retry even
+        // when none are configured.
+        let allowed = match retry_after {
+            Some(_) => retries.max(1),
+            None => retries,
+        };
+        if *attempts > allowed {
+            return Err(error);
+        }
+
+        let delay = match retry_after {
+            Some(wait) if wait > MAX_RETRY_AFTER => {
+                return Err(DeliveryError {
+                    message: format!(
+                        "{} and asked to retry after {}s",
+                        error.message,
+                        wait.as_secs()
+                    ),
+                    ..error
+                });
+            }
+            Some(wait) => wait,
+            None => retry_delay(*attempts - 1),
+        };
+        std::thread::sleep(delay);
+    }
+}
+
+#[cfg(feature = "webhook")]
+fn gzip(body: &[u8]) -> std::io::Result<Vec<u8>> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+/// Longest `Retry-After` a webhook delivery waits for before giving up.
+#[cfg(feature = "webhook")]
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(120);
+
+/// Parses a `Retry-After` header value, either delay seconds or an HTTP
+/// date, into how long to wait from `now`. Dates in the past wait zero.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
     }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Exponential backoff starting at one second, capped at thirty.
+#[cfg(feature = "webhook")]
+fn retry_delay(attempt: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempt).min(30))
 }
 
-fn build_headers(
-    raw_headers: &std::collections::BTreeMap<String, String>,
-) -> Result<HeaderMap, String> {
+#[cfg(feature = "webhook")]
+fn build_headers(raw_headers: &BTreeMap<String, String>) -> Result<HeaderMap, String> {
     let mut headers = HeaderMap::new();
 
     for (key, value) in raw_headers {
@@ -158,47 +2352,111 @@ fn build_headers(
     Ok(headers)
 }
 
-fn send_custom(custom: &CustomChannel, event: &CompletionEvent) -> Result<(), String> {
-    let mut command = Command::new(&custom.exec);
+fn send_custom(
+    channel: &Channel,
+    custom: &CustomChannel,
+    event: &CompletionEvent,
+) -> Result<(), DeliveryError> {
+    let files = SecretFiles::write(&custom.env_files).map_err(DeliveryError::spawn)?;
+    let (program, args) = custom_invocation(custom);
+    let mut command = Command::new(program);
     command
-        .args(&custom.args)
+        .args(args)
         .envs(&custom.env)
+        .envs(files.paths())
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::piped());
 
-    let mut child = command
-        .spawn()
-        .map_err(|_| format!("failed to start custom notifier `{}`", custom.exec))?;
+    let mut child = command.spawn().map_err(|_| {
+        DeliveryError::spawn(format!("failed to start custom notifier `{}`", custom.exec))
+    })?;
 
-    let payload =
-        serde_json::to_vec(event).map_err(|_| "failed to encode event payload".to_string())?;
+    let body = custom_payload(channel, custom, event);
+    let payload = serde_json::to_vec(&body)
+        .map_err(|_| DeliveryError::config("failed to encode event payload"))?;
     if let Some(stdin) = child.stdin.as_mut() {
         use std::io::Write;
         stdin
             .write_all(&payload)
-            .map_err(|_| "failed writing event payload to custom notifier".to_string())?;
+            .map_err(|_| DeliveryError::spawn("failed writing event payload to custom notifier"))?;
     }
 
     let output = child
         .wait_with_output()
-        .map_err(|_| "failed waiting for custom notifier process".to_string())?;
+        .map_err(|_| DeliveryError::spawn("failed waiting for custom notifier process"))?;
 
     if output.status.success() {
         return Ok(());
     }
 
+    let kind = DeliveryErrorKind::Exit {
+        code: output.status.code(),
+    };
     let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
     if stderr.is_empty() {
-        Err("custom notifier exited with non-zero status".to_string())
+        Err(DeliveryError::new(
+            kind,
+            "custom notifier exited with non-zero status",
+        ))
     } else {
-        Err(format!(
-            "custom notifier failed: {}",
-            truncate_for_error(&stderr, 200)
+        Err(DeliveryError::new(
+            kind,
+            format!(
+                "custom notifier failed: {}",
+                truncate_for_error(&stderr, 200)
+            ),
         ))
     }
 }
 
+/// Private temporary files backing a custom channel's `env_files`, removed
+/// when dropped.
+struct SecretFiles {
+    files: Vec<(String, PathBuf)>,
+}
+
+impl SecretFiles {
+    fn write(values: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut files = Self { files: Vec::new() };
+        for (name, value) in values {
+            let mut suffix = [0_u8; 8];
+            getrandom::fill(&mut suffix)
+                .map_err(|_| "failed to name a secret file for custom notifier".to_string())?;
+            let suffix = suffix
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+            let path = env::temp_dir().join(format!("brb-{suffix}"));
+
+            let mut file = private_file_options()
+                .create_new(true)
+                .open(&path)
+                .map_err(|_| format!("failed to create secret file for `{name}`"))?;
+            files.files.push((name.clone(), path));
+
+            use std::io::Write;
+            file.write_all(value.as_bytes())
+                .map_err(|_| format!("failed to write secret file for `{name}`"))?;
+        }
+        Ok(files)
+    }
+
+    fn paths(&self) -> impl Iterator<Item = (&str, &Path)> {
+        self.files
+            .iter()
+            .map(|(name, path)| (name.as_str(), path.as_path()))
+    }
+}
+
+impl Drop for SecretFiles {
+    fn drop(&mut self) {
+        for (_, path) in &self.files {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
 fn truncate_for_error(input: &str, max_chars: usize) -> String {
     if input.chars().count() <= max_chars {
         return input.to_string();
@@ -1,6 +1,27 @@
-use clap::{ArgAction, Command, CommandFactory, FromArgMatches, Parser, Subcommand};
+use crate::config::NotifyOn;
+use crate::output::ColorChoice;
+use crate::runner::Interactivity;
+use crate::units::parse_duration;
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Utc};
+use clap::{
+    ArgAction, Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
+/// Environment variable listing channels to notify, separated by commas.
+pub const CHANNELS_ENV: &str = "BRB_CHANNELS";
+
+/// Environment variable setting the minimum run duration worth notifying.
+pub const MIN_DURATION_ENV: &str = "BRB_MIN_DURATION";
+
+/// Environment variable overriding every channel's `notify_on`.
+pub const NOTIFY_ON_ENV: &str = "BRB_NOTIFY_ON";
+
+/// Environment variable naming the session runs are grouped under.
+pub const SESSION_ENV: &str = "BRB_SESSION";
+
 /// High-level action parsed from CLI arguments.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Action {
@@ -13,37 +34,169 @@ pub enum Action {
     /// Run a config management subcommand.
     Config(ConfigAction),
 
+    /// Run an aliases subcommand.
+    Aliases(AliasesAction),
+
+    /// Run a template subcommand.
+    Template(TemplateAction),
+
+    /// Run a secret management subcommand.
+    Secret(SecretAction),
+
+    /// Run a history subcommand.
+    History(HistoryAction),
+
+    /// Run a session subcommand.
+    Session(SessionAction),
+
+    /// Translate another tool's notification URLs into channel config.
+    Import(ImportAction),
+
+    /// Add a note to a recorded run.
+    Annotate {
+        /// Run ID, or an unambiguous prefix of one.
+        run_id: String,
+
+        /// Note text.
+        note: String,
+
+        /// Whether to post the note to channels.
+        post: bool,
+
+        /// Channels to post to; empty means the default channels.
+        channels: Vec<String>,
+    },
+
+    /// List `brb` runs in progress on this machine.
+    Status,
+
+    /// Check file permissions that could expose credentials.
+    Doctor,
+
     /// Run a wrapped command.
     Run(RunArgs),
 
+    /// Run a wrapped command several times and notify once with statistics.
+    Bench {
+        /// Number of runs.
+        runs: u32,
+
+        /// Options and command shared with a normal run.
+        args: RunArgs,
+    },
+
     /// Print help text.
     Help,
 
-    /// Print version text.
-    Version,
+    /// Print version text, or build details as JSON.
+    Version { json: bool },
 }
 
 /// Command execution arguments.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct RunArgs {
     /// Explicit channel IDs requested by repeated `--channel` flags.
     pub channels: Vec<String>,
 
+    /// Notify every configured channel, requested by `--all-channels`.
+    pub all_channels: bool,
+
+    /// Skip notifying for runs shorter than this, overriding `min_duration`.
+    pub min_duration: Option<Duration>,
+
+    /// Notification policy applied to every channel for this run.
+    pub notify_on: Option<NotifyOn>,
+
+    /// Interval between repeated runs requested by `--every`.
+    pub every: Option<Duration>,
+
+    /// Deadline after which no further repeated runs start.
+    pub until: Option<DateTime<Utc>>,
+
+    /// Maximum number of repeated runs.
+    pub max_runs: Option<u32>,
+
+    /// Named local lock held while the command runs.
+    pub lock: Option<String>,
+
+    /// Behaviour when the named lock is already held.
+    pub lock_mode: LockMode,
+
+    /// Path to write a JSON run report to.
+    pub report: Option<PathBuf>,
+
+    /// Project named in place of `brb` in notification titles.
+    pub project: Option<String>,
+
+    /// Session grouping related runs, or `auto` for the terminal session.
+    pub session: Option<String>,
+
+    /// How output capture treats commands that need a terminal.
+    pub interactivity: Interactivity,
+
     /// Command and arguments to execute.
     pub command: Vec<String>,
+
+    /// Whether `--` separated the command from `brb`'s own flags.
+    pub separated: bool,
+}
+
+/// What to do when a `--lock` is held by another run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LockMode {
+    /// Wait until the lock is released.
+    #[default]
+    Wait,
+
+    /// Exit with an error immediately.
+    Fail,
 }
 
 /// `brb channels` subcommands.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ChannelsAction {
-    /// List configured channels.
-    List,
+    /// List configured channels with their recent delivery health, as JSON
+    /// when `json` is set.
+    List { json: bool },
 
-    /// Validate config.
-    Validate,
+    /// Validate config; unknown fields are errors unless `permissive`,
+    /// network channels are contacted when `probe` is set, and chat
+    /// credentials are checked when `auth` is set.
+    Validate {
+        permissive: bool,
+        probe: bool,
+        auth: bool,
+    },
 
     /// Send a test notification to one channel.
     Test { channel_id: String },
+
+    /// Render and show a desktop channel's notification for a sample run.
+    Preview {
+        channel_id: String,
+        status: PreviewStatus,
+        duration: Option<Duration>,
+    },
+
+    /// Print the body a channel would send for a sample run.
+    Render {
+        channel_id: String,
+        status: PreviewStatus,
+    },
+
+    /// Print the config reference for a channel type.
+    Docs { channel_type: String },
+}
+
+/// Outcome of the sample run shown by `brb channels preview`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum PreviewStatus {
+    /// The command exited 0.
+    #[default]
+    Success,
+
+    /// The command exited 1.
+    Failure,
 }
 
 /// `brb config` subcommands.
@@ -51,6 +204,99 @@ pub enum ChannelsAction {
 pub enum ConfigAction {
     /// Print config file path.
     Path,
+
+    /// Move a config file from a legacy location to the current path.
+    MigratePath,
+}
+
+/// `brb aliases` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasesAction {
+    /// List configured aliases.
+    List,
+}
+
+/// `brb template` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateAction {
+    /// Render a channel's or template's message without sending it.
+    Render {
+        /// Channel ID or template name.
+        target: String,
+
+        /// JSON file with event fields overriding the sample event.
+        event: Option<PathBuf>,
+
+        /// Exit code for the sample event.
+        exit_code: Option<i32>,
+    },
+}
+
+/// `brb secret` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretAction {
+    /// Encrypt stdin into `secrets.values.<name>` in the config file.
+    Encrypt { name: String },
+
+    /// Print a secret's plaintext.
+    Decrypt { name: String },
+
+    /// Edit a secret's plaintext in `$EDITOR` and re-encrypt it.
+    Edit { name: String },
+}
+
+/// `brb history` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryAction {
+    /// List recorded runs.
+    List {
+        /// Only runs with this command fingerprint.
+        fingerprint: Option<String>,
+
+        /// Only runs in this session.
+        session: Option<String>,
+    },
+
+    /// Print a recorded run's event as JSON.
+    Show {
+        /// Run ID, or an unambiguous prefix of one.
+        run_id: String,
+    },
+
+    /// Deliver a recorded run's event again.
+    Resend {
+        /// Run ID, or an unambiguous prefix of one.
+        run_id: String,
+
+        /// Channels to deliver to; empty means the default channels.
+        channels: Vec<String>,
+    },
+}
+
+/// `brb session` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionAction {
+    /// Send one notification summarising the session's runs.
+    End {
+        /// Session name; unset means `BRB_SESSION`, else the terminal session.
+        name: Option<String>,
+
+        /// Channels to notify; empty means the default channels.
+        channels: Vec<String>,
+    },
+}
+
+/// `brb import` subcommands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportAction {
+    /// An ntfy topic URL.
+    NtfyUrl { url: String },
+
+    /// An Apprise config file.
+    Apprise { path: PathBuf },
+
+    /// A Shoutrrr service URL.
+    Shoutrrr { url: String },
 }
 
 /// CLI parsing errors for invalid user input.
@@ -62,6 +308,10 @@ pub enum CliError {
     MissingChannelFlagValue,
     #[error("no command provided")]
     MissingCommand,
+    #[error("`--every` cannot be used with `brb bench`")]
+    BenchWithEvery,
+    #[error("invalid {name}: {reason}")]
+    InvalidEnv { name: &'static str, reason: String },
     #[error("{0}")]
     Clap(String),
 }
@@ -71,12 +321,17 @@ pub enum CliError {
     name = "brb",
     about = "run a command and notify when it completes",
     long_about = None,
-    disable_help_subcommand = true
+    disable_help_subcommand = true,
+    args_conflicts_with_subcommands = true
 )]
 struct CliArgs {
-    /// Repeated channel override for wrapped command execution.
-    #[arg(long = "channel", value_name = "channel-id", action = ArgAction::Append)]
-    channels: Vec<String>,
+    /// When to colour brb's own output.
+    #[arg(long, value_name = "when", global = true, default_value = "auto")]
+    color: ColorChoice,
+
+    /// Options for running the wrapped command.
+    #[command(flatten)]
+    run: RunFlags,
 
     /// Built-in management subcommands.
     #[command(subcommand)]
@@ -91,8 +346,118 @@ struct CliArgs {
     command: Vec<String>,
 }
 
+/// Flags shared by `brb <command>` and `brb run`.
+#[derive(Debug, Args)]
+struct RunFlags {
+    /// Repeated channel override for wrapped command execution.
+    #[arg(long = "channel", value_name = "channel-id", action = ArgAction::Append)]
+    channels: Vec<String>,
+
+    /// Notify every configured channel instead of the defaults.
+    #[arg(long = "all-channels", conflicts_with = "channels")]
+    all_channels: bool,
+
+    /// Skip notifications for runs shorter than this (e.g. `30s`).
+    #[arg(long = "min-duration", value_name = "duration", value_parser = parse_duration)]
+    min_duration: Option<Duration>,
+
+    /// Override every channel's notify_on for this run.
+    #[arg(long = "notify-on", value_name = "policy")]
+    notify_on: Option<NotifyOn>,
+
+    /// Repeat the wrapped command on a fixed interval (e.g. `30s`, `15m`, `1h30m`).
+    #[arg(long, value_name = "interval", value_parser = parse_duration)]
+    every: Option<Duration>,
+
+    /// Stop repeating after this time (`HH:MM` local time or RFC3339).
+    #[arg(long, value_name = "time", requires = "every", value_parser = parse_until)]
+    until: Option<DateTime<Utc>>,
+
+    /// Stop repeating after this many runs.
+    #[arg(
+        long = "max-runs",
+        value_name = "count",
+        requires = "every",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    max_runs: Option<u32>,
+
+    /// Hold a named local lock while the command runs.
+    #[arg(long, value_name = "name")]
+    lock: Option<String>,
+
+    /// Whether to wait for or fail on a held lock.
+    #[arg(
+        long = "lock-mode",
+        value_name = "mode",
+        requires = "lock",
+        default_value = "wait"
+    )]
+    lock_mode: LockMode,
+
+    /// Write a JSON report of the run and every delivery result to this path.
+    #[arg(long, value_name = "path")]
+    report: Option<PathBuf>,
+
+    /// Name the project in notification titles instead of `brb`.
+    #[arg(long, value_name = "name")]
+    project: Option<String>,
+
+    /// Group this run with others in the same session, threading their
+    /// notifications; `auto` uses the terminal session.
+    #[arg(long, value_name = "name")]
+    session: Option<String>,
+
+    /// Capture output through pipes even when the command looks interactive.
+    #[arg(long = "assume-non-interactive", conflicts_with = "force_pty")]
+    assume_non_interactive: bool,
+
+    /// Run the command under a pseudo-terminal so output is captured while it
+    /// still sees a terminal.
+    #[arg(long = "force-pty")]
+    force_pty: bool,
+}
+
 #[derive(Debug, Subcommand)]
 enum CliCommand {
+    /// Run a command and notify when it completes.
+    Run {
+        #[command(flatten)]
+        flags: RunFlags,
+
+        /// Wrapped command and args.
+        #[arg(
+            value_name = "command",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+
+    /// Run a command several times and notify once with duration statistics.
+    Bench {
+        /// Number of times to run the command.
+        #[arg(
+            short = 'n',
+            long,
+            value_name = "count",
+            default_value_t = 10,
+            value_parser = clap::value_parser!(u32).range(1..)
+        )]
+        runs: u32,
+
+        #[command(flatten)]
+        flags: RunFlags,
+
+        /// Wrapped command and args.
+        #[arg(
+            value_name = "command",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        command: Vec<String>,
+    },
+
     /// Initialise global config.
     Init,
 
@@ -107,15 +472,93 @@ enum CliCommand {
         #[command(subcommand)]
         action: Option<CliConfigAction>,
     },
+
+    /// Show saved command aliases.
+    Aliases {
+        #[command(subcommand)]
+        action: Option<CliAliasesAction>,
+    },
+
+    /// Preview templates.
+    Template {
+        #[command(subcommand)]
+        action: CliTemplateAction,
+    },
+
+    /// Manage age-encrypted secrets in the config file.
+    Secret {
+        #[command(subcommand)]
+        action: CliSecretAction,
+    },
+
+    /// Show and resend recorded runs.
+    History {
+        #[command(subcommand)]
+        action: Option<CliHistoryAction>,
+    },
+
+    /// Summarise runs grouped with `--session`.
+    Session {
+        #[command(subcommand)]
+        action: CliSessionAction,
+    },
+
+    /// Print channel config translated from other notification tools.
+    Import {
+        #[command(subcommand)]
+        action: CliImportAction,
+    },
+
+    /// Add a note to a recorded run, such as what caused it to fail.
+    Annotate {
+        /// Run ID, or an unambiguous prefix of one.
+        #[arg(value_name = "run-id")]
+        run_id: String,
+
+        /// Note text.
+        #[arg(long, value_name = "text")]
+        note: String,
+
+        /// Post the run with its notes to channels as a follow-up message.
+        #[arg(long)]
+        post: bool,
+
+        /// Channel to post to (repeatable, implies `--post`); defaults to
+        /// `default_channels`.
+        #[arg(long = "channel", value_name = "id")]
+        channels: Vec<String>,
+    },
+
+    /// List brb runs in progress on this machine.
+    Status,
+
+    /// Check config and data directory permissions.
+    Doctor,
 }
 
 #[derive(Debug, Subcommand)]
 enum CliChannelsAction {
-    /// List configured channels.
-    List,
+    /// List configured channels with their last delivery and error.
+    List {
+        /// Print the list as JSON.
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Validate config.
-    Validate,
+    Validate {
+        /// Report unknown fields as warnings instead of errors.
+        #[arg(long)]
+        permissive: bool,
+
+        /// Check each webhook is reachable (DNS, TCP, HEAD) without notifying.
+        #[arg(long)]
+        probe: bool,
+
+        /// Check chat webhook credentials with calls that post nothing.
+        #[arg(long)]
+        auth: bool,
+    },
 
     /// Send a test notification to one channel.
     Test {
@@ -123,32 +566,217 @@ enum CliChannelsAction {
         #[arg(value_name = "channel-id")]
         channel_id: String,
     },
+
+    /// Show a desktop channel's notification for a sample run.
+    Preview {
+        /// Desktop channel identifier.
+        #[arg(value_name = "channel-id")]
+        channel_id: String,
+
+        /// Outcome of the sample run.
+        #[arg(long, value_enum, default_value_t)]
+        status: PreviewStatus,
+
+        /// Duration of the sample run, e.g. `95m`.
+        #[arg(long, value_name = "duration", value_parser = parse_duration)]
+        duration: Option<Duration>,
+    },
+
+    /// Print the body a channel would send for a sample run.
+    Render {
+        /// Channel identifier.
+        #[arg(value_name = "channel-id")]
+        channel_id: String,
+
+        /// Outcome of the sample run.
+        #[arg(long, value_enum, default_value_t)]
+        status: PreviewStatus,
+    },
+
+    /// Print the fields, defaults and an example for a channel type.
+    Docs {
+        /// Channel type, e.g. `slack`.
+        #[arg(value_name = "type")]
+        channel_type: String,
+    },
 }
 
 #[derive(Debug, Subcommand)]
 enum CliConfigAction {
     /// Print config file path.
     Path,
+
+    /// Move a config file from a legacy location to the current path.
+    MigratePath,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliAliasesAction {
+    /// List configured aliases.
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliTemplateAction {
+    /// Render a channel's or template's message against a sample event.
+    Render {
+        /// Channel ID or template name.
+        #[arg(value_name = "channel-or-template")]
+        target: String,
+
+        /// JSON file with event fields overriding the sample event.
+        #[arg(long, value_name = "path")]
+        event: Option<PathBuf>,
+
+        /// Exit code for the sample event (non-zero renders a failure).
+        #[arg(long, value_name = "code", allow_negative_numbers = true)]
+        exit_code: Option<i32>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CliHistoryAction {
+    /// List recorded runs, newest last.
+    List {
+        /// Only list runs of commands with this fingerprint.
+        #[arg(long, value_name = "fingerprint")]
+        fingerprint: Option<String>,
+
+        /// Only list runs in this session.
+        #[arg(long, value_name = "name")]
+        session: Option<String>,
+    },
+
+    /// Print a recorded run's full event as JSON, including its snapshot.
+    Show {
+        /// Run ID, or an unambiguous prefix of one.
+        #[arg(value_name = "run-id")]
+        run_id: String,
+    },
+
+    /// Deliver a recorded run's event again, e.g. after fixing a channel.
+    Resend {
+        /// Run ID, or an unambiguous prefix of one.
+        #[arg(value_name = "run-id")]
+        run_id: String,
+
+        /// Channel to deliver to (repeatable); defaults to `default_channels`.
+        #[arg(long = "channel", value_name = "id")]
+        channels: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CliSessionAction {
+    /// Send one notification listing each run in the session with its status
+    /// and duration.
+    End {
+        /// Session name; defaults to `BRB_SESSION`, else the terminal session.
+        #[arg(value_name = "name")]
+        name: Option<String>,
+
+        /// Channel to notify (repeatable); defaults to `default_channels`.
+        #[arg(long = "channel", value_name = "id")]
+        channels: Vec<String>,
+    },
 }
 
-/// Returns clap-generated help text.
-pub fn usage() -> String {
-    cli_command().render_long_help().to_string()
+#[derive(Debug, Subcommand)]
+enum CliImportAction {
+    /// Translate an ntfy topic URL, e.g. `https://ntfy.sh/my-topic`.
+    NtfyUrl {
+        /// Topic URL.
+        #[arg(value_name = "url")]
+        url: String,
+    },
+
+    /// Translate the URLs in an Apprise config file (text or YAML).
+    Apprise {
+        /// Apprise config file.
+        #[arg(value_name = "config")]
+        path: PathBuf,
+    },
+
+    /// Translate a Shoutrrr service URL.
+    Shoutrrr {
+        /// Service URL.
+        #[arg(value_name = "url")]
+        url: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CliSecretAction {
+    /// Encrypt stdin and store it as a named secret.
+    Encrypt {
+        /// Secret name, referenced as `${secret:<name>}`.
+        #[arg(value_name = "name")]
+        name: String,
+    },
+
+    /// Print a secret's decrypted value.
+    Decrypt {
+        /// Secret name.
+        #[arg(value_name = "name")]
+        name: String,
+    },
+
+    /// Edit a secret in `$EDITOR`, creating it if missing.
+    Edit {
+        /// Secret name.
+        #[arg(value_name = "name")]
+        name: String,
+    },
+}
+
+/// Returns clap-generated help text, styled with ANSI escapes when `color`.
+pub fn usage(color: bool) -> String {
+    let help = cli_command().render_long_help();
+    if color {
+        help.ansi().to_string()
+    } else {
+        help.to_string()
+    }
+}
+
+/// A parsed command line: the action plus options that apply to every action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// What to do.
+    pub action: Action,
+
+    /// When to colour `brb`'s own output, from `--color`.
+    pub color: ColorChoice,
 }
 
 /// Parses CLI args into a structured action.
 pub fn parse_args(args: Vec<String>) -> Result<Action, CliError> {
+    parse_invocation(args).map(|invocation| invocation.action)
+}
+
+/// Parses CLI args into an action and the options shared by every action.
+pub fn parse_invocation(args: Vec<String>) -> Result<Invocation, CliError> {
+    let plain = |action| Invocation {
+        action,
+        color: ColorChoice::Auto,
+    };
     if args.is_empty() {
-        return Ok(Action::Help);
+        return Ok(plain(Action::Help));
     }
 
     let first = args[0].as_str();
     if matches!(first, "-h" | "--help") {
-        return Ok(Action::Help);
+        return Ok(plain(Action::Help));
     }
 
     if matches!(first, "-V" | "--version") {
-        return Ok(Action::Version);
+        return match &args[1..] {
+            [] => Ok(plain(Action::Version { json: false })),
+            [flag] if flag == "--json" => Ok(plain(Action::Version { json: true })),
+            _ => Err(CliError::Clap(format!(
+                "unexpected arguments after `{first}` (only `--json` is accepted)"
+            ))),
+        };
     }
 
     if first == "channels"
@@ -162,6 +790,7 @@ pub fn parse_args(args: Vec<String>) -> Result<Action, CliError> {
         return Err(CliError::MissingChannelFlagValue);
     }
 
+    let (args, separated) = expand_channel_shorthand(args);
     let mut argv = Vec::with_capacity(args.len() + 1);
     argv.push("brb".to_string());
     argv.extend(args);
@@ -171,39 +800,316 @@ pub fn parse_args(args: Vec<String>) -> Result<Action, CliError> {
         .map_err(|error| CliError::Clap(error.to_string()))?;
     let parsed =
         CliArgs::from_arg_matches(&matches).map_err(|error| CliError::Clap(error.to_string()))?;
+    let color = parsed.color;
+    action(parsed, separated).map(|action| Invocation { action, color })
+}
 
+fn action(parsed: CliArgs, separated: bool) -> Result<Action, CliError> {
     if let Some(subcommand) = parsed.subcommand {
         return match subcommand {
+            CliCommand::Run { flags, command } => run_action(flags, command, separated),
+            CliCommand::Bench {
+                runs,
+                flags,
+                command,
+            } => {
+                if flags.every.is_some() {
+                    return Err(CliError::BenchWithEvery);
+                }
+                let args = run_args(flags, command, separated)?;
+                Ok(Action::Bench { runs, args })
+            }
             CliCommand::Init => Ok(Action::Init),
+            CliCommand::Status => Ok(Action::Status),
+            CliCommand::Doctor => Ok(Action::Doctor),
             CliCommand::Channels { action } => {
                 let action = match action {
-                    Some(CliChannelsAction::List) | None => ChannelsAction::List,
-                    Some(CliChannelsAction::Validate) => ChannelsAction::Validate,
+                    Some(CliChannelsAction::List { json }) => ChannelsAction::List { json },
+                    None => ChannelsAction::List { json: false },
+                    Some(CliChannelsAction::Validate {
+                        permissive,
+                        probe,
+                        auth,
+                    }) => ChannelsAction::Validate {
+                        permissive,
+                        probe,
+                        auth,
+                    },
                     Some(CliChannelsAction::Test { channel_id }) => {
                         ChannelsAction::Test { channel_id }
                     }
+                    Some(CliChannelsAction::Preview {
+                        channel_id,
+                        status,
+                        duration,
+                    }) => ChannelsAction::Preview {
+                        channel_id,
+                        status,
+                        duration,
+                    },
+                    Some(CliChannelsAction::Render { channel_id, status }) => {
+                        ChannelsAction::Render { channel_id, status }
+                    }
+                    Some(CliChannelsAction::Docs { channel_type }) => {
+                        ChannelsAction::Docs { channel_type }
+                    }
                 };
                 Ok(Action::Channels(action))
             }
             CliCommand::Config { action } => {
                 let action = match action {
                     Some(CliConfigAction::Path) | None => ConfigAction::Path,
+                    Some(CliConfigAction::MigratePath) => ConfigAction::MigratePath,
                 };
                 Ok(Action::Config(action))
             }
+            CliCommand::Aliases { action } => {
+                let action = match action {
+                    Some(CliAliasesAction::List) | None => AliasesAction::List,
+                };
+                Ok(Action::Aliases(action))
+            }
+            CliCommand::Template { action } => {
+                let action = match action {
+                    CliTemplateAction::Render {
+                        target,
+                        event,
+                        exit_code,
+                    } => TemplateAction::Render {
+                        target,
+                        event,
+                        exit_code,
+                    },
+                };
+                Ok(Action::Template(action))
+            }
+            CliCommand::Secret { action } => {
+                let action = match action {
+                    CliSecretAction::Encrypt { name } => SecretAction::Encrypt { name },
+                    CliSecretAction::Decrypt { name } => SecretAction::Decrypt { name },
+                    CliSecretAction::Edit { name } => SecretAction::Edit { name },
+                };
+                Ok(Action::Secret(action))
+            }
+            CliCommand::History { action } => {
+                let action = match action {
+                    Some(CliHistoryAction::List {
+                        fingerprint,
+                        session,
+                    }) => HistoryAction::List {
+                        fingerprint,
+                        session,
+                    },
+                    None => HistoryAction::List {
+                        fingerprint: None,
+                        session: None,
+                    },
+                    Some(CliHistoryAction::Show { run_id }) => HistoryAction::Show { run_id },
+                    Some(CliHistoryAction::Resend { run_id, channels }) => {
+                        HistoryAction::Resend { run_id, channels }
+                    }
+                };
+                Ok(Action::History(action))
+            }
+            CliCommand::Session { action } => {
+                let action = match action {
+                    CliSessionAction::End { name, channels } => {
+                        SessionAction::End { name, channels }
+                    }
+                };
+                Ok(Action::Session(action))
+            }
+            CliCommand::Import { action } => {
+                let action = match action {
+                    CliImportAction::NtfyUrl { url } => ImportAction::NtfyUrl { url },
+                    CliImportAction::Apprise { path } => ImportAction::Apprise { path },
+                    CliImportAction::Shoutrrr { url } => ImportAction::Shoutrrr { url },
+                };
+                Ok(Action::Import(action))
+            }
+            CliCommand::Annotate {
+                run_id,
+                note,
+                post,
+                channels,
+            } => Ok(Action::Annotate {
+                run_id,
+                note,
+                post: post || !channels.is_empty(),
+                channels,
+            }),
         };
     }
 
-    if parsed.command.is_empty() {
+    run_action(parsed.run, parsed.command, separated)
+}
+
+fn run_action(flags: RunFlags, command: Vec<String>, separated: bool) -> Result<Action, CliError> {
+    run_args(flags, command, separated).map(Action::Run)
+}
+
+fn run_args(flags: RunFlags, command: Vec<String>, separated: bool) -> Result<RunArgs, CliError> {
+    if command.is_empty() {
         return Err(CliError::MissingCommand);
     }
 
-    Ok(Action::Run(RunArgs {
-        channels: parsed.channels,
-        command: parsed.command,
-    }))
+    Ok(RunArgs {
+        channels: flags.channels,
+        all_channels: flags.all_channels,
+        min_duration: flags.min_duration,
+        notify_on: flags.notify_on,
+        every: flags.every,
+        until: flags.until,
+        max_runs: flags.max_runs,
+        lock: flags.lock,
+        lock_mode: flags.lock_mode,
+        report: flags.report,
+        project: flags.project,
+        session: flags.session,
+        interactivity: if flags.force_pty {
+            Interactivity::Pty
+        } else if flags.assume_non_interactive {
+            Interactivity::NonInteractive
+        } else {
+            Interactivity::Auto
+        },
+        command,
+        separated,
+    })
+}
+
+/// Fills run options not given on the command line from `BRB_*` environment
+/// variables, read through `var`. Flags win over the environment, which wins
+/// over config; empty variables are ignored.
+pub fn apply_env_overrides(
+    args: &mut RunArgs,
+    var: impl Fn(&str) -> Option<String>,
+) -> Result<(), CliError> {
+    let var = |name| var(name).filter(|value| !value.trim().is_empty());
+
+    if args.channels.is_empty()
+        && !args.all_channels
+        && let Some(channels) = var(CHANNELS_ENV)
+    {
+        args.channels = channels
+            .split(',')
+            .map(str::trim)
+            .filter(|channel| !channel.is_empty())
+            .map(str::to_string)
+            .collect();
+    }
+
+    if args.min_duration.is_none()
+        && let Some(value) = var(MIN_DURATION_ENV)
+    {
+        let duration = parse_duration(value.trim()).map_err(|reason| CliError::InvalidEnv {
+            name: MIN_DURATION_ENV,
+            reason,
+        })?;
+        args.min_duration = Some(duration);
+    }
+
+    if args.notify_on.is_none()
+        && let Some(value) = var(NOTIFY_ON_ENV)
+    {
+        let notify_on = value
+            .trim()
+            .parse()
+            .map_err(|reason| CliError::InvalidEnv {
+                name: NOTIFY_ON_ENV,
+                reason,
+            })?;
+        args.notify_on = Some(notify_on);
+    }
+
+    if args.session.is_none()
+        && let Some(session) = var(SESSION_ENV)
+    {
+        args.session = Some(session.trim().to_string());
+    }
+
+    Ok(())
 }
 
 fn cli_command() -> Command {
     CliArgs::command().override_usage(include_str!("../assets/usage.txt").trim_end())
 }
+
+/// Rewrites `@channel` arguments before the wrapped command (or `--`) into
+/// `--channel=channel`, so `brb @slack @desktop -- make` selects both. The
+/// same applies after `brb run` and `brb bench`. Also returns whether `--`
+/// introduced the command.
+fn expand_channel_shorthand(args: Vec<String>) -> (Vec<String>, bool) {
+    let command = cli_command();
+    let bench_arguments = command
+        .find_subcommand("bench")
+        .into_iter()
+        .flat_map(Command::get_arguments);
+    let arguments = command
+        .get_arguments()
+        .chain(bench_arguments)
+        .collect::<Vec<_>>();
+    let takes_value = |flag: &str| {
+        arguments.iter().any(|arg| {
+            let long = flag
+                .strip_prefix("--")
+                .is_some_and(|flag| arg.get_long() == Some(flag));
+            let short = flag.strip_prefix('-').is_some_and(|flag| {
+                flag.len() == 1 && arg.get_short().is_some_and(|short| flag.starts_with(short))
+            });
+            (long || short) && arg.get_action().takes_values()
+        })
+    };
+
+    let mut expanded = Vec::with_capacity(args.len());
+    let mut separated = false;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" || !arg.starts_with(['-', '@']) {
+            let subcommand = expanded.is_empty() && matches!(arg.as_str(), "run" | "bench");
+            separated = arg == "--";
+            expanded.push(arg);
+            if subcommand {
+                continue;
+            }
+            break;
+        }
+
+        if let Some(channel) = arg.strip_prefix('@')
+            && !channel.is_empty()
+        {
+            expanded.push(format!("--channel={channel}"));
+            continue;
+        }
+
+        let needs_value = !arg.contains('=') && takes_value(&arg);
+        expanded.push(arg);
+        if needs_value && let Some(value) = args.next() {
+            expanded.push(value);
+        }
+    }
+
+    expanded.extend(args);
+    (expanded, separated)
+}
+
+/// Parses `--until` as either an RFC3339 timestamp or the next local `HH:MM`.
+fn parse_until(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let time = NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| format!("invalid time `{value}` (expected HH:MM or RFC3339)"))?;
+    let now = Local::now();
+    let mut date = now.date_naive();
+    if now.time() >= time {
+        date = date.succ_opt().unwrap_or(date);
+    }
+
+    Local
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|deadline| deadline.with_timezone(&Utc))
+        .ok_or_else(|| format!("time `{value}` does not exist in the local timezone"))
+}
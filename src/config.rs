@@ -1,12 +1,40 @@
+use crate::alias::RESERVED_NAMES;
+#[cfg(feature = "webhook")]
+use crate::capture::capture_pointer;
+use crate::diagnostic::{ConfigDiagnostic, closest_match};
+use crate::environment::{Environment, SystemEnvironment};
+use crate::event::{CompletionEvent, host_name};
+use crate::import::import_target;
+use crate::messages::Locale;
+use crate::perms::{create_private_dir, write_private};
+use crate::secrets::SecretResolver;
+use crate::template::validate_templates;
+use crate::units::{
+    deserialize_duration, deserialize_optional_duration, deserialize_optional_size,
+};
+use chrono::FixedOffset;
+use chrono::format::{Item, StrftimeItems};
 use directories::BaseDirs;
+use regex::Regex;
+#[cfg(feature = "webhook")]
+use reqwest::Method;
+#[cfg(feature = "webhook")]
+use reqwest::Url;
+#[cfg(feature = "webhook")]
+use reqwest::header::{HeaderName, HeaderValue};
 use serde::Deserialize;
 use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
 use std::fs;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
 /// The top-level YAML configuration structure.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     /// Config schema version.
@@ -16,241 +44,3706 @@ pub struct Config {
     #[serde(default)]
     pub default_channels: Vec<String>,
 
+    /// Runs finishing faster than this send no notifications.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub min_duration: Option<Duration>,
+
+    /// Channels notified instead of `default_channels` once a run takes long
+    /// enough; the tier with the longest matching `min_duration` wins.
+    #[serde(default)]
+    pub tiers: Vec<DurationTier>,
+
+    /// Messages and extra channels for specific exit codes.
+    #[serde(default)]
+    pub exit_codes: BTreeMap<i32, ExitCodeRule>,
+
+    /// Output patterns that notify channels while the command still runs.
+    #[serde(default)]
+    pub triggers: Vec<OutputTrigger>,
+
+    /// Regex finding a percentage or step count in the output, shown by
+    /// `brb status` while the command runs.
+    #[serde(default)]
+    pub progress_pattern: Option<String>,
+
+    /// Channels worked through in order when nobody acknowledges a failure.
+    #[serde(default)]
+    pub escalation: Vec<EscalationStep>,
+
+    /// Refuse to run unless `--` separates the wrapped command.
+    #[serde(default)]
+    pub strict_args: bool,
+
     /// Channel definitions keyed by channel ID.
-    pub channels: BTreeMap<String, ChannelConfig>,
+    pub channels: BTreeMap<String, Channel>,
+
+    /// Saved commands run by `brb <name>`.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, Alias>,
+
+    /// Local commands run after every wrapped command.
+    #[serde(default)]
+    pub hooks: Hooks,
+
+    /// Optional machine context sampled into completion events.
+    #[serde(default)]
+    pub context: ContextConfig,
+
+    /// Tool versions and hardware recorded with each run.
+    #[serde(default)]
+    pub snapshot: SnapshotConfig,
+
+    /// Include this machine's anonymous ID in completion events.
+    #[serde(default = "default_machine_id")]
+    pub machine_id: bool,
+
+    /// Emoji, colors and priorities used when rendering each status.
+    #[serde(default)]
+    pub appearance: AppearanceConfig,
+
+    /// How times are shown in rendered (human-readable) messages.
+    #[serde(default)]
+    pub display: DisplayConfig,
+
+    /// Named title/body templates and shared snippets channels can reference.
+    #[serde(default)]
+    pub templates: BTreeMap<String, TemplateDef>,
+
+    /// Masking applied to the command before it is sent anywhere.
+    #[serde(default)]
+    pub redact: RedactConfig,
+
+    /// HTTP settings every webhook channel inherits.
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    /// age-encrypted values channels can reference with `${secret:NAME}`.
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+
+    /// Whether unknown fields are errors (default) or ignored with a warning.
+    #[serde(default)]
+    pub unknown_fields: UnknownFields,
+}
+
+/// Channels for runs lasting at least `min_duration`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DurationTier {
+    /// Shortest run this tier applies to.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub min_duration: Duration,
+
+    /// Channels notified for these runs.
+    pub channels: Vec<String>,
+}
+
+/// One step of the escalation chain, notified when the failure was not
+/// acknowledged on the channels notified before it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EscalationStep {
+    /// Channels notified at this step.
+    pub channels: Vec<String>,
+}
+
+/// What a specific exit code means and who else should hear about it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExitCodeRule {
+    /// Shown in the title in place of the generic failure wording.
+    #[serde(default)]
+    pub message: Option<String>,
+
+    /// Channels notified in addition to the selected ones.
+    #[serde(default)]
+    pub channels: Vec<String>,
+}
+
+/// A pattern watched for in the command's output, notifying its channels the
+/// first time a line matches, before the command exits.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputTrigger {
+    /// Regex matched against each line of output.
+    pub pattern: String,
+
+    /// Stream whose lines are matched.
+    #[serde(default)]
+    pub stream: TriggerStream,
+
+    /// Channels notified when the trigger fires.
+    pub channels: Vec<String>,
+
+    /// Shown in the title in place of the generic "output matched" wording.
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Output stream an [`OutputTrigger`] watches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerStream {
+    /// Standard output and standard error.
+    #[default]
+    Any,
+
+    Stdout,
+
+    Stderr,
+}
+
+/// A saved invocation, run by `brb <name>`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Alias {
+    /// Command and arguments; arguments given after the alias name are
+    /// appended.
+    pub command: Vec<String>,
+
+    /// Channels notified instead of `default_channels`.
+    #[serde(default)]
+    pub channels: Vec<String>,
+
+    /// Notification policy applied to every channel.
+    #[serde(default)]
+    pub notify_on: Option<NotifyOn>,
+
+    /// Runs finishing faster than this send no notifications.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub min_duration: Option<Duration>,
+
+    /// Directory the command runs in; relative paths are resolved against
+    /// the config file's directory.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+
+    /// Environment variables added for the command.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Local commands run after this alias, following the global hooks.
+    #[serde(default)]
+    pub hooks: Hooks,
+
+    /// Replaces the top-level `progress_pattern` for this alias.
+    #[serde(default)]
+    pub progress_pattern: Option<String>,
+}
+
+/// Shell commands run locally once the wrapped command finishes, with the
+/// completion event as JSON on stdin.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Hooks {
+    /// Run when the command succeeds.
+    #[serde(default)]
+    pub on_success: Vec<String>,
+
+    /// Run when the command fails or cannot be started.
+    #[serde(default)]
+    pub on_failure: Vec<String>,
+}
+
+impl Hooks {
+    /// Hook commands for a completion with `status`.
+    pub fn for_status(&self, status: &str) -> &[String] {
+        if status == "success" {
+            &self.on_success
+        } else {
+            &self.on_failure
+        }
+    }
+}
+
+/// A named template: either a shared text snippet or a title/body pair.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum TemplateDef {
+    /// Text included elsewhere with `{> name}`.
+    Snippet(String),
+
+    /// Title and body a channel can use via `template: name`.
+    Message(MessageTemplate),
+}
+
+/// Title/body wording; a missing part falls back to the built-in text.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageTemplate {
+    /// Notification title.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// Notification body.
+    #[serde(default)]
+    pub body: Option<String>,
+}
+
+/// Credential masking for the command's argv.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RedactConfig {
+    /// Flags whose values are masked; defaults to common credential flags
+    /// such as `--password` and `--token`. An empty list disables this.
+    #[serde(default)]
+    pub flags: Option<Vec<String>>,
+
+    /// Regexes whose matches are masked in every argument.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+/// Encrypted secrets, decrypted with an external `age` binary on first use.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretsConfig {
+    /// age-compatible binary used to encrypt and decrypt (`age` or `rage`).
+    #[serde(default = "default_secrets_command")]
+    pub command: String,
+
+    /// Identity (private key) file; defaults to `age-identity.txt` next to
+    /// the config file.
+    #[serde(default)]
+    pub identity: Option<PathBuf>,
+
+    /// Armored ciphertexts keyed by secret name.
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            command: default_secrets_command(),
+            identity: None,
+            values: BTreeMap::new(),
+        }
+    }
+}
+
+/// Settings for human-readable message rendering.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Timezone used for rendered times.
+    #[serde(default)]
+    pub timezone: DisplayTimezone,
+
+    /// `strftime`-style format for rendered times.
+    #[serde(default = "default_timestamp_format")]
+    pub timestamp_format: String,
+
+    /// Message language (`en`, `de`, `fr`, `es`); defaults to `LANG`.
+    #[serde(default)]
+    pub locale: Option<String>,
+
+    /// How durations are written in rendered messages.
+    #[serde(default)]
+    pub durations: DurationStyle,
+
+    /// Longest command line, in characters, shown in rendered messages before
+    /// middle arguments are elided.
+    #[serde(default = "default_max_command_length")]
+    pub max_command_length: usize,
+}
+
+/// Style for durations in rendered messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DurationStyle {
+    /// Seconds with two decimals, e.g. `3.25s`.
+    #[default]
+    Seconds,
+
+    /// The two largest units, abbreviated, e.g. `1h 5m`.
+    Compact,
+
+    /// The two largest units in words, e.g. `1 hour 5 minutes`.
+    Long,
+}
+
+/// Timezone for rendered times: `local`, `utc`, or a fixed `+HH:MM` offset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DisplayTimezone {
+    /// The machine's local timezone.
+    #[default]
+    Local,
+
+    /// Coordinated Universal Time.
+    Utc,
+
+    /// A fixed offset from UTC.
+    Fixed(FixedOffset),
+}
+
+/// Status-to-appearance mapping shared by every channel.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppearanceConfig {
+    /// Appearance overrides for successful runs.
+    #[serde(default)]
+    pub success: StatusAppearance,
+
+    /// Appearance overrides for failed runs.
+    #[serde(default)]
+    pub failure: StatusAppearance,
+}
+
+/// Appearance overrides for one status; unset fields use built-in defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StatusAppearance {
+    /// Emoji prefixed to titles (empty string disables it).
+    #[serde(default)]
+    pub emoji: Option<String>,
+
+    /// Hex color (`#rrggbb`) used by chat channels.
+    #[serde(default)]
+    pub color: Option<String>,
+
+    /// Priority used by push and desktop channels.
+    #[serde(default)]
+    pub priority: Option<Priority>,
+}
+
+/// Notification priority, ordered from quietest to loudest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Silent where the channel supports it.
+    Min,
+
+    /// Below normal.
+    Low,
+
+    /// Normal priority.
+    Default,
+
+    /// Above normal; sticky or highlighted where supported.
+    High,
+
+    /// Most urgent; may bypass do-not-disturb on some channels.
+    Urgent,
+}
+
+/// Which machine context to sample at completion.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContextConfig {
+    /// Sample battery state and charge.
+    #[serde(default)]
+    pub battery: bool,
+
+    /// Sample free disk space on the working directory's volume.
+    #[serde(default)]
+    pub disk: bool,
+
+    /// Probe network reachability with a short TCP connect.
+    #[serde(default)]
+    pub network: bool,
+
+    /// `host:port` used for the network probe (defaults to `1.1.1.1:443`).
+    #[serde(default)]
+    pub network_target: Option<String>,
+
+    /// Free space below which a low-disk warning is added (defaults to 1 GiB).
+    #[serde(default, deserialize_with = "deserialize_optional_size")]
+    pub low_disk: Option<u64>,
+}
+
+/// What to record about the execution environment at completion.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnapshotConfig {
+    /// Tools whose versions are recorded: a program name, run with
+    /// `--version`, or a full command such as `python3 -V`.
+    #[serde(default)]
+    pub tools: Vec<String>,
+
+    /// Record the CPU model, core count and total memory.
+    #[serde(default)]
+    pub hardware: bool,
+}
+
+/// A channel definition plus the options shared by every channel type.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Channel {
+    /// Whether the channel is notified at all; a disabled one is skipped
+    /// wherever it is selected, and `--all-channels` leaves it out.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    /// Which completions this channel is notified about.
+    #[serde(default)]
+    pub notify_on: NotifyOn,
+
+    /// Send at most one success per window, summarizing held completions;
+    /// failures go out straight away.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub digest: Option<Duration>,
+
+    /// Name of a message template under `templates` used for wording.
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Only these event fields are sent in JSON payloads.
+    #[serde(default)]
+    pub include_fields: Option<Vec<String>>,
+
+    /// Event fields left out of JSON payloads.
+    #[serde(default)]
+    pub exclude_fields: Vec<String>,
+
+    /// Skip this channel when any of these channels delivered the same event.
+    #[serde(default)]
+    pub suppress_if_delivered: Vec<String>,
+
+    /// Steps applied in order to each event before it is delivered here.
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive")]
+    pub pipeline: Vec<Middleware>,
+
+    /// Wait for someone to acknowledge failure notifications, escalating
+    /// when nobody does.
+    #[serde(default)]
+    pub ack: Option<AckConfig>,
+
+    /// Type-specific channel settings.
+    #[serde(flatten)]
+    pub kind: ChannelConfig,
+}
+
+/// Acknowledgement settings for a channel's failure notifications.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AckConfig {
+    /// How long to wait for an acknowledgement.
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub timeout: Duration,
+
+    /// Channels notified when nobody acknowledges in time.
+    #[serde(default)]
+    pub escalate_to: Vec<String>,
+}
+
+/// One step of a channel's event pipeline.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Middleware {
+    /// Drops events that do not match.
+    Filter(EventFilter),
+
+    /// Drops events the policy does not notify about.
+    NotifyOn(NotifyOn),
+
+    /// Masks credentials in the command and captured output.
+    Redact(RedactConfig),
+
+    /// Adds labels to the event.
+    Enrich(BTreeMap<String, String>),
+
+    /// Words the message with a title/body template under `templates`.
+    Template(String),
+
+    /// Chooses the event fields JSON payloads carry.
+    Fields(FieldSelection),
+}
+
+/// Event fields kept by a `fields` step: only `include` when it is set,
+/// otherwise all but `exclude`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldSelection {
+    /// Only these event fields are sent.
+    #[serde(default)]
+    pub include: Option<Vec<String>>,
+
+    /// Event fields left out.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Conditions for a `filter` step. Unset conditions always match.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventFilter {
+    /// Required event status, `success` or `failure`.
+    #[serde(default)]
+    pub status: Option<String>,
+
+    /// Shortest run that passes.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub min_duration: Option<Duration>,
+
+    /// Regex matched against the space-joined command.
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// Command fingerprint that must match exactly.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+}
+
+/// Per-channel notification policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyOn {
+    /// Notify on every completion.
+    #[default]
+    Always,
+
+    /// Notify only when the command succeeds.
+    Success,
+
+    /// Notify only when the command fails.
+    Failure,
+
+    /// Notify only when the status differs from the previous repeated run.
+    Change,
 }
 
-/// A single channel definition.
+/// A single channel type definition.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum ChannelConfig {
-    /// Local desktop notification channel.
+    /// Local desktop notification.
+    ///
+    /// ```yaml
+    /// type: desktop
+    /// group: true
+    /// dismiss_success_after: 10s
+    /// ```
     Desktop(DesktopChannel),
 
-    /// Generic webhook channel.
+    /// HTTP request carrying the completion event, or a chat message in one
+    /// of the preset formats.
+    ///
+    /// ```yaml
+    /// type: webhook
+    /// url: https://example.com/hooks/brb
+    /// headers:
+    ///   Authorization: Bearer ${BRB_HOOK_TOKEN}
+    /// retries: 2
+    /// ```
     Webhook(WebhookChannel),
 
-    /// External command-based custom channel.
+    /// Slack message laid out with Block Kit, sent to an incoming webhook.
+    ///
+    /// ```yaml
+    /// type: slack
+    /// url: https://hooks.slack.com/services/T000/B000/XXXX
+    /// attach_output: true
+    /// ```
+    Slack(SlackChannel),
+
+    /// Google Chat card sent to an incoming webhook.
+    ///
+    /// ```yaml
+    /// type: googlechat
+    /// url: https://chat.googleapis.com/v1/spaces/AAAA/messages?key=KEY&token=TOKEN
+    /// ```
+    GoogleChat(GoogleChatChannel),
+
+    /// Telegram message sent by a bot through the Bot API.
+    ///
+    /// ```yaml
+    /// type: telegram
+    /// bot_token: 123456:ABC-DEF1234ghIkl
+    /// chat_id: "-1001234"
+    /// ```
+    Telegram(TelegramChannel),
+
+    /// Push notification published to an ntfy topic with title, priority and
+    /// tag headers.
+    ///
+    /// ```yaml
+    /// type: ntfy
+    /// topic: builds
+    /// failure:
+    ///   priority: urgent
+    /// ```
+    Ntfy(NtfyChannel),
+
+    /// Push notification sent to a user's devices through Pushover.
+    ///
+    /// ```yaml
+    /// type: pushover
+    /// user_key: uQiRzpo4DXghDmr9QzzfQu27cmVRsG
+    /// api_token: azGDORePK8gMaC0QOYAMyEEuzJnyUi
+    /// ```
+    Pushover(PushoverChannel),
+
+    /// Completion event JSON published to an MQTT topic.
+    ///
+    /// ```yaml
+    /// type: mqtt
+    /// broker: mqtts://broker.example.com
+    /// topic: home/brb/completed
+    /// qos: 1
+    /// ```
+    Mqtt(MqttChannel),
+
+    /// Markdown message posted to a Zulip stream topic by a bot.
+    ///
+    /// ```yaml
+    /// type: zulip
+    /// site: https://example.zulipchat.com
+    /// bot_email: brb-bot@example.zulipchat.com
+    /// api_key: gjA04ZYcqXKalvYMA8OeXSfzUOLrtbZv
+    /// stream: builds
+    /// ```
+    Zulip(ZulipChannel),
+
+    /// Healthchecks.io check pinged when the command starts and finishes.
+    ///
+    /// ```yaml
+    /// type: healthchecks
+    /// check: 5b1e0c2a-7d4f-4a8e-9c3b-2f6d8e1a4b70
+    /// attach_output: true
+    /// ```
+    Healthchecks(HealthchecksChannel),
+
+    /// Any of the other types described by one Shoutrrr or Apprise service
+    /// URL.
+    ///
+    /// It is replaced by the channel the URL describes when config loads.
+    ///
+    /// ```yaml
+    /// type: url
+    /// target: ntfy://builds
+    /// ```
+    Url(UrlChannel),
+
+    /// Your own notifier process, given the completion event as JSON on
+    /// stdin.
+    ///
+    /// ```yaml
+    /// type: custom
+    /// exec: sh
+    /// args: ["-c", "cat > /tmp/brb-last-event.json"]
+    /// ```
     Custom(CustomChannel),
 }
 
 /// Configuration for `type: desktop`.
 #[derive(Debug, Clone, Deserialize, Default)]
 #[serde(deny_unknown_fields)]
-pub struct DesktopChannel {}
+pub struct DesktopChannel {
+    /// Replace the previous notification for the same command instead of
+    /// stacking a new one.
+    #[serde(default)]
+    pub group: bool,
 
-/// Configuration for `type: webhook`.
-#[derive(Debug, Clone, Deserialize)]
-#[serde(deny_unknown_fields)]
-pub struct WebhookChannel {
-    /// Target URL for webhook delivery.
-    pub url: String,
+    /// Dismiss success notifications after this long; failures then stay
+    /// until dismissed.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub dismiss_success_after: Option<Duration>,
 
-    /// HTTP method (defaults to POST).
-    #[serde(default = "default_http_method")]
-    pub method: String,
+    /// What to do while the OS is in Do Not Disturb / Focus mode.
+    #[serde(default)]
+    pub when_dnd: WhenDnd,
 
-    /// Optional HTTP headers.
+    /// Channels notified instead when `when_dnd` is `escalate`.
     #[serde(default)]
-    pub headers: BTreeMap<String, String>,
+    pub escalate_to: Vec<String>,
+
+    /// Keep running up to this long after notifying, to learn whether the
+    /// notification was clicked or dismissed (Linux only).
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub wait: Option<Duration>,
+
+    /// Offer a "Re-run" action that runs the command again. Needs `wait`.
+    #[serde(default)]
+    pub rerun: bool,
+
+    /// Longest notification body in characters.
+    ///
+    /// Defaults to `1000`.
+    #[serde(default)]
+    pub max_message_length: Option<usize>,
+
+    /// Sounds picked by status and duration; the first matching rule wins.
+    #[serde(default)]
+    pub sounds: Vec<SoundRule>,
 }
 
-/// Configuration for `type: custom`.
+/// A sound for desktop notifications about matching runs. Unset conditions
+/// always match.
 #[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
-pub struct CustomChannel {
-    /// Executable name or path.
+pub struct SoundRule {
+    /// Required event status, `success` or `failure`.
+    #[serde(default)]
+    pub status: Option<String>,
+
+    /// Shortest run the rule applies to.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub min_duration: Option<Duration>,
+
+    /// Sound name: a freedesktop sound theme name on Linux, or a system
+    /// sound such as `Glass` on macOS.
+    pub sound: String,
+}
+
+/// Desktop channel behaviour during Do Not Disturb.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WhenDnd {
+    /// Notify anyway; the OS decides whether it is shown.
+    #[default]
+    Notify,
+
+    /// Drop the notification.
+    Skip,
+
+    /// Hold it and deliver it with the first notification after DnD ends.
+    Queue,
+
+    /// Notify the `escalate_to` channels instead.
+    Escalate,
+}
+
+/// Configuration for `type: slack`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SlackChannel {
+    /// Incoming webhook URL.
+    pub url: String,
+
+    /// Attach a tail of the command output to failure messages.
+    #[serde(default)]
+    pub attach_output: bool,
+
+    /// Number of trailing output lines attached when `attach_output` is set.
+    #[serde(default = "default_attach_lines")]
+    pub attach_lines: usize,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// Configuration for `type: googlechat`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GoogleChatChannel {
+    /// Incoming webhook URL, with its `key` and `token`.
+    pub url: String,
+
+    /// Attach a tail of the command output to failure messages.
+    #[serde(default)]
+    pub attach_output: bool,
+
+    /// Number of trailing output lines attached when `attach_output` is set.
+    #[serde(default = "default_attach_lines")]
+    pub attach_lines: usize,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// Configuration for `type: telegram`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TelegramChannel {
+    /// Bot token from BotFather, e.g. `123456:ABC-DEF...`.
+    pub bot_token: String,
+
+    /// Chat to message: a numeric chat ID or a public `@channelname`.
+    #[serde(deserialize_with = "deserialize_chat_id")]
+    pub chat_id: String,
+
+    /// Bot API server, for a self-hosted one.
+    #[serde(default = "default_telegram_api_url")]
+    pub api_url: String,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Post a "running" message when the command starts, edit it into the
+    /// final status, and reply to it with the run's follow-ups.
+    #[serde(default)]
+    pub progress: bool,
+}
+
+/// Configuration for `type: ntfy`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyChannel {
+    /// ntfy server, for a self-hosted one.
+    #[serde(default = "default_ntfy_server")]
+    pub server: String,
+
+    /// Topic to publish to.
+    pub topic: String,
+
+    /// Access token, sent as a bearer token.
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// Username for basic auth, with `password`.
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// Password for basic auth, with `username`.
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// Priority and tags for successful runs.
+    #[serde(default)]
+    pub success: NtfyStatus,
+
+    /// Priority and tags for failed runs.
+    #[serde(default)]
+    pub failure: NtfyStatus,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// ntfy headers for one status; unset fields use the defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NtfyStatus {
+    /// Message priority; `appearance` decides when unset.
+    #[serde(default)]
+    pub priority: Option<Priority>,
+
+    /// Message tags; emoji shortcodes such as `tada` show as icons. Defaults
+    /// to `brb` and the status.
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+}
+
+/// Configuration for `type: pushover`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PushoverChannel {
+    /// User or group key to notify.
+    pub user_key: String,
+
+    /// Application API token.
+    pub api_token: String,
+
+    /// Device to notify instead of all of the user's devices.
+    #[serde(default)]
+    pub device: Option<String>,
+
+    /// Notification sound, overriding the user's default.
+    #[serde(default)]
+    pub sound: Option<String>,
+
+    /// Pushover API server.
+    #[serde(default = "default_pushover_api_url")]
+    pub api_url: String,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// Configuration for `type: mqtt`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttChannel {
+    /// Broker URL, `mqtt://host[:port]` or `mqtts://host[:port]` for TLS.
+    pub broker: String,
+
+    /// Topic the completion event JSON is published to.
+    pub topic: String,
+
+    /// 0 (at most once), 1 (at least once) or 2 (exactly once).
+    #[serde(default)]
+    pub qos: u8,
+
+    /// Whether the broker keeps the last event for new subscribers.
+    #[serde(default)]
+    pub retain: bool,
+
+    /// Username, if the broker needs one.
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// Password, with `username`.
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// Client identifier; defaults to one unique to the run.
+    #[serde(default)]
+    pub client_id: Option<String>,
+
+    /// Retries after connection errors and timeouts.
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// Configuration for `type: zulip`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZulipChannel {
+    /// Zulip organization URL, e.g. `https://example.zulipchat.com`.
+    pub site: String,
+
+    /// The bot's email address.
+    pub bot_email: String,
+
+    /// The bot's API key.
+    pub api_key: String,
+
+    /// Stream to post to.
+    pub stream: String,
+
+    /// Topic to post under, with template placeholders such as `{project}`
+    /// filled in.
+    #[serde(default = "default_zulip_topic")]
+    pub topic: String,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+}
+
+/// Configuration for `type: healthchecks`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthchecksChannel {
+    /// The check's UUID, or its full ping URL for a self-hosted instance or
+    /// a slug URL.
+    pub check: String,
+
+    /// Attach a tail of the command output to the completion ping.
+    #[serde(default)]
+    pub attach_output: bool,
+
+    /// Number of trailing output lines attached when `attach_output` is set.
+    #[serde(default = "default_attach_lines")]
+    pub attach_lines: usize,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+}
+
+impl HealthchecksChannel {
+    /// The check's ping URL, without a trailing slash.
+    pub fn ping_url(&self) -> String {
+        let check = self.check.trim().trim_end_matches('/');
+        if check.contains("://") {
+            check.to_string()
+        } else {
+            format!("{HEALTHCHECKS_PING_URL}/{check}")
+        }
+    }
+}
+
+/// Ping server checks given by UUID alone are pinged on.
+pub const HEALTHCHECKS_PING_URL: &str = "https://hc-ping.com";
+
+#[cfg(feature = "webhook")]
+impl ZulipChannel {
+    /// `Authorization` header value for the bot's email and API key.
+    pub fn authorization(&self) -> String {
+        use base64::Engine;
+
+        let credentials = base64::engine::general_purpose::STANDARD.encode(format!(
+            "{}:{}",
+            self.bot_email.trim(),
+            self.api_key.trim()
+        ));
+        format!("Basic {credentials}")
+    }
+
+    /// URL of a REST API endpoint on the channel's site.
+    pub fn api_url(&self, endpoint: &str) -> String {
+        format!("{}/api/v1/{endpoint}", self.site.trim_end_matches('/'))
+    }
+}
+
+/// Configuration for `type: webhook`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookChannel {
+    /// Target URLs for webhook delivery; several fan the same payload out.
+    #[serde(alias = "urls", deserialize_with = "deserialize_urls")]
+    pub url: Vec<String>,
+
+    /// HTTP method.
+    #[serde(default = "default_http_method")]
+    pub method: String,
+
+    /// HTTP headers sent with each request.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Payload preset used to shape the request body.
+    #[serde(default)]
+    pub format: WebhookFormat,
+
+    /// Static fields merged into JSON request bodies. `{captured.NAME}` in
+    /// a value is filled from `capture`.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+
+    /// Values kept from a successful response, by name: a JSON pointer or a
+    /// `$.path` into the response body.
+    #[serde(default)]
+    pub capture: BTreeMap<String, String>,
+
+    /// Event fields renamed in `generic` JSON bodies, from event name to the
+    /// key the receiver expects.
+    #[serde(default)]
+    pub field_map: BTreeMap<String, String>,
+
+    /// Attach a tail of the command output to failure messages (chat formats only).
+    #[serde(default)]
+    pub attach_output: bool,
+
+    /// Post a "running" message when the command starts and edit it into the
+    /// final status (Slack Web API and Discord only). Slack replies to it
+    /// with the run's follow-ups.
+    #[serde(default)]
+    pub progress: bool,
+
+    /// Number of trailing output lines attached when `attach_output` is set.
+    #[serde(default = "default_attach_lines")]
+    pub attach_lines: usize,
+
+    /// Retries after network errors, HTTP 429 or 5xx responses.
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Header carrying the per-delivery idempotency key (empty disables it).
+    #[serde(default = "default_idempotency_header")]
+    pub idempotency_header: String,
+
+    /// age recipients the body is encrypted to before sending; the request
+    /// then carries an envelope instead of the payload.
+    #[serde(default)]
+    pub encrypt_to: Vec<String>,
+
+    /// Overrides `http.user_agent`.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+
+    /// Overrides `http.timeout`.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+
+    /// Overrides `http.proxy`.
+    #[serde(default)]
+    pub proxy: Option<String>,
+
+    /// Largest request body sent as is.
+    #[serde(default, deserialize_with = "deserialize_optional_size")]
+    pub max_payload: Option<u64>,
+
+    /// What happens to a body over `max_payload`.
+    #[serde(default)]
+    pub oversize: Oversize,
+
+    /// Longest rendered message in characters for chat formats; overrides
+    /// the platform's limit.
+    #[serde(default)]
+    pub max_message_length: Option<usize>,
+}
+
+/// Handling for webhook bodies over `max_payload`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Oversize {
+    /// Cut captured output, then digest entries, and mark the body
+    /// `truncated`.
+    #[default]
+    Truncate,
+
+    /// Send the body gzip-compressed with `Content-Encoding: gzip`.
+    Gzip,
+}
+
+/// HTTP defaults shared by webhook channels, which can override each one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Headers sent with every request; a channel's header of the same name
+    /// wins.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// `User-Agent` header; defaults to `brb/<version>`.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+
+    /// Limit for each request attempt; defaults to 30 seconds.
+    #[serde(default, deserialize_with = "deserialize_optional_duration")]
+    pub timeout: Option<Duration>,
+
+    /// HTTP(S) proxy URL all requests go through.
+    #[serde(default)]
+    pub proxy: Option<String>,
+}
+
+impl HttpConfig {
+    /// The settings `webhook` is sent with: its own, falling back to these.
+    /// Header names are compared case-insensitively.
+    pub fn for_webhook(&self, webhook: &WebhookChannel) -> HttpConfig {
+        let mut headers = self
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                !webhook
+                    .headers
+                    .keys()
+                    .any(|own| own.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<BTreeMap<_, _>>();
+        headers.extend(webhook.headers.clone());
+
+        HttpConfig {
+            headers,
+            user_agent: webhook
+                .user_agent
+                .clone()
+                .or_else(|| self.user_agent.clone()),
+            timeout: webhook.timeout.or(self.timeout),
+            proxy: webhook.proxy.clone().or_else(|| self.proxy.clone()),
+        }
+    }
+}
+
+/// Built-in webhook payload presets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// The raw completion event as JSON.
+    #[default]
+    Generic,
+
+    /// Slack incoming webhook message with a colored attachment.
+    Slack,
+
+    /// Discord webhook message with an embed.
+    Discord,
+
+    /// Microsoft Teams connector message card.
+    Teams,
+
+    /// ntfy plain-text message with title, tags and priority headers.
+    Ntfy,
+
+    /// CloudEvents 1.0 event in structured mode, carrying the completion
+    /// event as `data`.
+    CloudEvents,
+}
+
+/// Configuration for `type: url`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UrlChannel {
+    /// Service URL, e.g. `discord://token@id` or `tgram://bot-token/chat-id`.
+    pub target: String,
+}
+
+/// Configuration for `type: custom`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomChannel {
+    /// Executable name or path.
     pub exec: String,
 
-    /// Optional command-line arguments.
-    #[serde(default)]
-    pub args: Vec<String>,
+    /// Runs `exec` as a script through this interpreter. On Windows, `.cmd`,
+    /// `.bat` and `.ps1` files pick one from their extension when unset.
+    #[serde(default)]
+    pub interpreter: Option<Interpreter>,
+
+    /// Optional command-line arguments.
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Optional environment variable overrides.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Environment variables set to the path of a private temporary file
+    /// holding the value, for secrets that should not appear in the
+    /// notifier's environment either. Files are removed once it exits.
+    #[serde(default)]
+    pub env_files: BTreeMap<String, String>,
+
+    /// Static fields merged into the JSON event written to stdin.
+    #[serde(default)]
+    pub extra: BTreeMap<String, String>,
+}
+
+/// Interpreter a custom notifier script is run through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpreter {
+    /// `cmd /C`, for `.cmd` and `.bat` scripts.
+    Cmd,
+
+    /// Windows PowerShell (`powershell -File`).
+    Powershell,
+
+    /// PowerShell 7+ (`pwsh -File`).
+    Pwsh,
+
+    /// POSIX `sh`.
+    Sh,
+}
+
+/// Fully-loaded config plus where it came from.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    /// Absolute file path used for loading.
+    pub path: PathBuf,
+
+    /// Parsed and validated config.
+    pub config: Config,
+
+    /// Problems that did not stop loading, such as ignored unknown fields.
+    pub warnings: Vec<String>,
+
+    /// Whether no config file existed and the built-in fallback was used.
+    pub fallback: bool,
+}
+
+/// How unknown config fields are treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownFields {
+    /// Unknown fields fail config load.
+    #[default]
+    Error,
+
+    /// Unknown fields are dropped with a warning, so configs written for a
+    /// newer brb still load.
+    Warn,
+}
+
+/// Options for loading config.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoadOptions {
+    /// Overrides the config's own `unknown_fields` setting.
+    pub unknown_fields: Option<UnknownFields>,
+
+    /// Block under `environments` to apply; detected when unset.
+    pub environment: Option<RunEnvironment>,
+}
+
+/// Variable naming the `environments` block to apply instead of detecting
+/// one.
+pub const ENVIRONMENT_ENV: &str = "BRB_ENVIRONMENT";
+
+/// Variables CI services set. Most set `CI` too; these cover the rest.
+const CI_VARS: [&str; 10] = [
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "JENKINS_URL",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+    "BITBUCKET_BUILD_NUMBER",
+    "CODEBUILD_BUILD_ID",
+];
+
+/// Where brb is running, which picks the block under `environments` that
+/// applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunEnvironment {
+    /// A CI pipeline.
+    Ci,
+
+    /// A terminal someone is at.
+    Interactive,
+
+    /// Neither, such as cron or a script run in the background.
+    Unattended,
+}
+
+impl RunEnvironment {
+    /// Names of the blocks `environments` may hold.
+    pub const NAMES: [&'static str; 3] = ["ci", "interactive", "unattended"];
+
+    /// The block name for this environment.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ci => "ci",
+            Self::Interactive => "interactive",
+            Self::Unattended => "unattended",
+        }
+    }
+
+    /// Detects the environment of this process; see [`Self::detect_in`].
+    pub fn detect() -> Result<Self, ConfigError> {
+        Self::detect_in(
+            &SystemEnvironment,
+            io::stdin().is_terminal() && io::stderr().is_terminal(),
+        )
+    }
+
+    /// `BRB_ENVIRONMENT` when set, else `ci` when a CI service's variable
+    /// is set, else `interactive` when `terminal` (stdin and stderr are
+    /// terminals), else `unattended`.
+    pub fn detect_in(environment: &dyn Environment, terminal: bool) -> Result<Self, ConfigError> {
+        if let Some(name) = environment.var(ENVIRONMENT_ENV) {
+            return name.trim().parse().map_err(ConfigError::InvalidConfig);
+        }
+
+        let ci = CI_VARS.iter().any(|name| {
+            environment
+                .var(name)
+                .is_some_and(|value| !matches!(value.trim(), "" | "0" | "false"))
+        });
+        Ok(if ci {
+            Self::Ci
+        } else if terminal {
+            Self::Interactive
+        } else {
+            Self::Unattended
+        })
+    }
+}
+
+impl FromStr for RunEnvironment {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "ci" => Ok(Self::Ci),
+            "interactive" => Ok(Self::Interactive),
+            "unattended" => Ok(Self::Unattended),
+            _ => Err(format!(
+                "{ENVIRONMENT_ENV} is `{name}` (expected one of {})",
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Result of running `brb init`.
+#[derive(Debug, Clone)]
+pub enum InitStatus {
+    /// Config file was created.
+    Created(PathBuf),
+
+    /// Config file already existed.
+    AlreadyExists(PathBuf),
+}
+
+/// Result of moving the config file out of a legacy location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrateStatus {
+    /// The config file was moved.
+    Moved { from: PathBuf, to: PathBuf },
+
+    /// A config file already exists at the current path.
+    AlreadyCurrent(PathBuf),
+
+    /// No config file exists in any legacy location.
+    NothingFound,
+}
+
+/// Config loading/validation failures.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("unable to determine user config directory")]
+    NoConfigDirectory,
+    #[error("config file not found: {0}")]
+    NotFound(String),
+    #[error("failed to read config file: {0}")]
+    ReadFailed(#[from] std::io::Error),
+    #[error("invalid YAML config: {0}")]
+    ParseFailed(#[from] serde_yaml::Error),
+    #[error("invalid YAML config: {0}")]
+    Syntax(Box<ConfigDiagnostic>),
+    #[error("missing environment variable for interpolation: {0}")]
+    MissingEnvironmentVariable(String),
+    #[error("invalid environment interpolation expression in config value: {0}")]
+    InvalidInterpolation(String),
+    #[error("unknown secret for interpolation: {0}")]
+    UnknownSecret(String),
+    #[error("failed to decrypt secret `{name}`: {reason}")]
+    SecretFailed { name: String, reason: String },
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+    #[error("invalid config: {0}")]
+    Invalid(Box<ConfigDiagnostic>),
+}
+
+impl From<ConfigDiagnostic> for ConfigError {
+    fn from(diagnostic: ConfigDiagnostic) -> Self {
+        Self::Invalid(Box::new(diagnostic))
+    }
+}
+
+impl SlackChannel {
+    /// The same URL as a `format: slack` webhook, for the checks `brb
+    /// channels validate` runs on webhooks.
+    pub fn as_webhook(&self) -> WebhookChannel {
+        WebhookChannel {
+            url: vec![self.url.clone()],
+            format: WebhookFormat::Slack,
+            ..WebhookChannel::default()
+        }
+    }
+}
+
+impl NtfyChannel {
+    /// URL messages are published to.
+    pub fn topic_url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.server.trim_end_matches('/'),
+            self.topic.trim()
+        )
+    }
+
+    /// `Authorization` header value for the token or username and password.
+    #[cfg(feature = "webhook")]
+    pub fn authorization(&self) -> Option<String> {
+        use base64::Engine;
+
+        if let Some(token) = &self.token {
+            return Some(format!("Bearer {}", token.trim()));
+        }
+        let (username, password) = (self.username.as_ref()?, self.password.as_ref()?);
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        Some(format!("Basic {credentials}"))
+    }
+
+    /// The topic as a `format: ntfy` webhook carrying the channel's auth, for
+    /// acknowledgements and the checks `brb channels validate` runs.
+    #[cfg(feature = "webhook")]
+    pub fn as_webhook(&self) -> WebhookChannel {
+        WebhookChannel {
+            url: vec![self.topic_url()],
+            format: WebhookFormat::Ntfy,
+            headers: self
+                .authorization()
+                .map(|value| BTreeMap::from([("Authorization".to_string(), value)]))
+                .unwrap_or_default(),
+            retries: self.retries,
+            ..WebhookChannel::default()
+        }
+    }
+}
+
+impl Default for WebhookChannel {
+    fn default() -> Self {
+        Self {
+            url: Vec::new(),
+            method: default_http_method(),
+            headers: BTreeMap::new(),
+            format: WebhookFormat::default(),
+            extra: BTreeMap::new(),
+            capture: BTreeMap::new(),
+            field_map: BTreeMap::new(),
+            attach_output: false,
+            progress: false,
+            attach_lines: default_attach_lines(),
+            retries: 0,
+            idempotency_header: default_idempotency_header(),
+            encrypt_to: Vec::new(),
+            user_agent: None,
+            timeout: None,
+            proxy: None,
+            max_payload: None,
+            oversize: Oversize::default(),
+            max_message_length: None,
+        }
+    }
+}
+
+impl Channel {
+    /// Field names accepted by every channel type.
+    pub const SHARED_FIELDS: [&'static str; 9] = [
+        "enabled",
+        "notify_on",
+        "digest",
+        "template",
+        "include_fields",
+        "exclude_fields",
+        "suppress_if_delivered",
+        "pipeline",
+        "ack",
+    ];
+
+    /// Whether the event field `field` is sent to this channel.
+    pub fn includes_field(&self, field: &str) -> bool {
+        self.include_fields
+            .as_ref()
+            .is_none_or(|include| include.iter().any(|name| name == field))
+            && !self.exclude_fields.iter().any(|name| name == field)
+    }
+}
+
+impl From<ChannelConfig> for Channel {
+    fn from(kind: ChannelConfig) -> Self {
+        Self {
+            enabled: true,
+            notify_on: NotifyOn::default(),
+            digest: None,
+            template: None,
+            include_fields: None,
+            exclude_fields: Vec::new(),
+            suppress_if_delivered: Vec::new(),
+            pipeline: Vec::new(),
+            ack: None,
+            kind,
+        }
+    }
+}
+
+impl Channel {
+    /// Returns stable type label for display output.
+    pub fn type_name(&self) -> &'static str {
+        self.kind.type_name()
+    }
+}
+
+impl NotifyOn {
+    /// Returns whether a completion with `status` should be delivered, given the
+    /// status of the previous run when repeating.
+    pub fn allows(self, status: &str, previous_status: Option<&str>) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Success => status == "success",
+            Self::Failure => status != "success",
+            Self::Change => previous_status != Some(status),
+        }
+    }
+
+    /// Returns the policy as written in config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Success => "success",
+            Self::Failure => "failure",
+            Self::Change => "change",
+        }
+    }
+}
+
+impl Interpreter {
+    /// Program the script is passed to.
+    pub fn program(self) -> &'static str {
+        match self {
+            Self::Cmd => "cmd",
+            Self::Powershell => "powershell",
+            Self::Pwsh => "pwsh",
+            Self::Sh => "sh",
+        }
+    }
+
+    /// Interpreter implied by a Windows script's extension, if any.
+    pub fn for_script(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "cmd" | "bat" => Some(Self::Cmd),
+            "ps1" => Some(Self::Powershell),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for NotifyOn {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "always" => Ok(Self::Always),
+            "success" => Ok(Self::Success),
+            "failure" => Ok(Self::Failure),
+            "change" => Ok(Self::Change),
+            _ => Err(format!(
+                "invalid notify_on `{value}` (expected always, success, failure or change)"
+            )),
+        }
+    }
+}
+
+impl ChannelConfig {
+    /// Returns stable type label for display output.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Desktop(_) => "desktop",
+            Self::Webhook(_) => "webhook",
+            Self::Slack(_) => "slack",
+            Self::GoogleChat(_) => "googlechat",
+            Self::Telegram(_) => "telegram",
+            Self::Ntfy(_) => "ntfy",
+            Self::Pushover(_) => "pushover",
+            Self::Mqtt(_) => "mqtt",
+            Self::Zulip(_) => "zulip",
+            Self::Healthchecks(_) => "healthchecks",
+            Self::Url(_) => "url",
+            Self::Custom(_) => "custom",
+        }
+    }
+
+    /// Returns how many trailing output lines this channel wants captured.
+    pub fn capture_lines(&self) -> usize {
+        match self {
+            Self::Webhook(webhook)
+                if webhook.attach_output
+                    && matches!(
+                        webhook.format,
+                        WebhookFormat::Slack | WebhookFormat::Discord
+                    ) =>
+            {
+                webhook.attach_lines
+            }
+            Self::Slack(slack) if slack.attach_output => slack.attach_lines,
+            Self::GoogleChat(chat) if chat.attach_output => chat.attach_lines,
+            Self::Healthchecks(healthchecks) if healthchecks.attach_output => {
+                healthchecks.attach_lines
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Loads config from the global `config.yml` and validates it.
+pub fn load_config() -> Result<LoadedConfig, ConfigError> {
+    load_config_with(LoadOptions::default())
+}
+
+/// Loads and validates the global config file with explicit options. A
+/// config found only in a legacy location is loaded from there, with a
+/// warning pointing at `brb config migrate-path`; it is never moved here,
+/// since another environment may still expect it at the old path.
+pub fn load_config_with(options: LoadOptions) -> Result<LoadedConfig, ConfigError> {
+    let path = config_file_path()?;
+    if path.exists() {
+        return load_config_from_path_with(&path, options);
+    }
+
+    let legacy = legacy_config_paths()?;
+    let Some(from) = legacy.iter().find(|legacy| legacy.is_file()) else {
+        return Err(ConfigError::NotFound(path.display().to_string()));
+    };
+    let mut loaded = load_config_from_path_with(from, options)?;
+    loaded.warnings.insert(
+        0,
+        format!(
+            "using config from legacy location {}; run `brb config migrate-path` to move it to {}",
+            from.display(),
+            path.display()
+        ),
+    );
+    Ok(loaded)
+}
+
+/// Like [`load_config_with`], but when the config file does not exist uses
+/// the built-in desktop-only config instead of failing.
+pub fn load_config_or_fallback(options: LoadOptions) -> Result<LoadedConfig, ConfigError> {
+    match load_config_with(options) {
+        Err(ConfigError::NotFound(_)) if cfg!(feature = "desktop") => {
+            fallback_config(&config_file_path()?)
+        }
+        result => result,
+    }
+}
+
+/// Built-in config standing in for a missing file at `path`: every run
+/// notifies one desktop channel.
+pub fn fallback_config(path: &Path) -> Result<LoadedConfig, ConfigError> {
+    let parsed = parse_config(FALLBACK_CONFIG_YAML, LoadOptions::default())?;
+    Ok(LoadedConfig {
+        path: path.to_path_buf(),
+        config: parsed.config,
+        warnings: parsed.warnings,
+        fallback: true,
+    })
+}
+
+/// Creates a default global config file if it does not already exist.
+pub fn init_config() -> Result<InitStatus, ConfigError> {
+    let path = config_file_path()?;
+    if path.exists() {
+        return Ok(InitStatus::AlreadyExists(path));
+    }
+
+    let parent = path.parent().ok_or_else(|| {
+        ConfigError::InvalidConfig("calculated config path has no parent directory".to_string())
+    })?;
+
+    create_private_dir(parent)?;
+    write_private(&path, default_config_yaml())?;
+
+    Ok(InitStatus::Created(path))
+}
+
+/// Returns the absolute path to the global config file. `$XDG_CONFIG_HOME`
+/// is honored on every platform; otherwise the platform's config directory
+/// is used.
+pub fn config_file_path() -> Result<PathBuf, ConfigError> {
+    let base_dirs = BaseDirs::new().ok_or(ConfigError::NoConfigDirectory)?;
+    let dir = xdg_dir(env::var_os("XDG_CONFIG_HOME"), base_dirs.config_dir());
+    Ok(dir.join("brb").join("config.yml"))
+}
+
+/// Returns the directory used for brb's local runtime data (locks, state).
+/// `$XDG_DATA_HOME` is honored on every platform.
+pub fn data_dir() -> Result<PathBuf, ConfigError> {
+    let base_dirs = BaseDirs::new().ok_or(ConfigError::NoConfigDirectory)?;
+    let dir = xdg_dir(env::var_os("XDG_DATA_HOME"), base_dirs.data_local_dir());
+    Ok(dir.join("brb"))
+}
+
+/// The directory an XDG base directory variable names, or `fallback` when
+/// it is unset, empty or relative (the spec says to ignore relative paths).
+pub fn xdg_dir(value: Option<OsString>, fallback: &Path) -> PathBuf {
+    value
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| fallback.to_path_buf())
+}
+
+/// Places older setups may have left the config file, most likely first:
+/// the platform config directory (used before `$XDG_CONFIG_HOME` was
+/// honored everywhere), `~/.config` on platforms that default elsewhere,
+/// and a `config.yaml` spelling of the current file.
+pub fn legacy_config_paths() -> Result<Vec<PathBuf>, ConfigError> {
+    let base_dirs = BaseDirs::new().ok_or(ConfigError::NoConfigDirectory)?;
+    let current = config_file_path()?;
+    let mut paths = vec![
+        base_dirs.config_dir().join("brb").join("config.yml"),
+        base_dirs
+            .home_dir()
+            .join(".config")
+            .join("brb")
+            .join("config.yml"),
+        current.with_extension("yaml"),
+    ];
+    paths.retain(|path| *path != current);
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Moves the config file from a legacy location to the current path.
+pub fn migrate_config_path() -> Result<MigrateStatus, ConfigError> {
+    migrate_config(&config_file_path()?, &legacy_config_paths()?)
+}
+
+/// Moves the first existing file in `legacy` to `current`, unless `current`
+/// already exists. The moved file is left readable only by its owner.
+pub fn migrate_config(current: &Path, legacy: &[PathBuf]) -> Result<MigrateStatus, ConfigError> {
+    if current.exists() {
+        return Ok(MigrateStatus::AlreadyCurrent(current.to_path_buf()));
+    }
+    let Some(from) = legacy.iter().find(|path| path.is_file()) else {
+        return Ok(MigrateStatus::NothingFound);
+    };
+
+    let parent = current.parent().ok_or_else(|| {
+        ConfigError::InvalidConfig("calculated config path has no parent directory".to_string())
+    })?;
+    create_private_dir(parent)?;
+    // copied rather than renamed, which fails across filesystems.
+    write_private(current, fs::read(from)?)?;
+    fs::remove_file(from)?;
+
+    Ok(MigrateStatus::Moved {
+        from: from.clone(),
+        to: current.to_path_buf(),
+    })
+}
+
+/// Returns [`data_dir`], creating it so only the current user can enter it.
+pub fn private_data_dir() -> Result<PathBuf, ConfigError> {
+    let dir = data_dir()?;
+    create_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Validates static schema and cross-field constraints.
+pub fn validate_config(config: &Config) -> Result<(), ConfigError> {
+    if config.version != 1 {
+        return Err(ConfigDiagnostic::new(
+            "version",
+            format!("unsupported version {}; expected 1", config.version),
+        )
+        .into());
+    }
+
+    if config.channels.is_empty() {
+        return Err(
+            ConfigDiagnostic::new("channels", "at least one channel must be configured").into(),
+        );
+    }
+
+    if config.default_channels.is_empty() {
+        return Err(ConfigDiagnostic::new(
+            "default_channels",
+            "default_channels must include at least one channel id",
+        )
+        .into());
+    }
+
+    for (status, appearance) in [
+        ("success", &config.appearance.success),
+        ("failure", &config.appearance.failure),
+    ] {
+        if let Some(color) = &appearance.color
+            && parse_hex_color(color).is_none()
+        {
+            return Err(ConfigDiagnostic::new(
+                format!("appearance.{status}.color"),
+                format!("appearance.{status}.color `{color}` is not a #rrggbb hex color"),
+            )
+            .value(color)
+            .into());
+        }
+    }
+
+    let format_is_valid = StrftimeItems::new(&config.display.timestamp_format)
+        .all(|item| !matches!(item, Item::Error));
+    if !format_is_valid {
+        return Err(ConfigDiagnostic::new(
+            "display.timestamp_format",
+            format!(
+                "display.timestamp_format `{}` is not a valid strftime format",
+                config.display.timestamp_format
+            ),
+        )
+        .into());
+    }
+
+    if config.display.max_command_length < MIN_COMMAND_LENGTH {
+        return Err(ConfigDiagnostic::new(
+            "display.max_command_length",
+            format!(
+                "display.max_command_length must be at least {MIN_COMMAND_LENGTH} (got {})",
+                config.display.max_command_length
+            ),
+        )
+        .into());
+    }
+
+    if let Some(locale) = &config.display.locale
+        && Locale::parse(locale).is_none()
+    {
+        return Err(ConfigDiagnostic::new(
+            "display.locale",
+            format!(
+                "display.locale `{locale}` is not supported (expected one of {})",
+                Locale::SUPPORTED.join(", ")
+            ),
+        )
+        .value(locale)
+        .into());
+    }
+
+    for (index, pattern) in config.redact.patterns.iter().enumerate() {
+        if Regex::new(pattern).is_err() {
+            return Err(ConfigDiagnostic::new(
+                "redact.patterns",
+                format!("redact.patterns[{index}] `{pattern}` is not a valid regex"),
+            )
+            .value(pattern)
+            .into());
+        }
+    }
+
+    validate_templates(&config.templates).map_err(ConfigError::InvalidConfig)?;
+
+    for (channel_id, channel) in &config.channels {
+        if let Some(feature) = missing_channel_feature(&channel.kind) {
+            return Err(ConfigDiagnostic::new(
+                format!("channels.{channel_id}.type"),
+                format!(
+                    "channel `{channel_id}` is a {feature} channel, but this brb was built without the `{feature}` feature"
+                ),
+            )
+            .channel(channel_id)
+            .into());
+        }
+
+        validate_fields(
+            channel_id,
+            &format!("channels.{channel_id}"),
+            ("include_fields", channel.include_fields.as_deref()),
+            ("exclude_fields", &channel.exclude_fields),
+        )?;
+
+        if let ChannelConfig::Desktop(desktop) = &channel.kind {
+            validate_desktop(config, channel_id, desktop)?;
+        }
+        validate_message_length(channel_id, channel)?;
+        validate_suppression(config, channel_id)?;
+        validate_ack(config, channel_id)?;
+        validate_pipeline(config, channel_id, &channel.pipeline)?;
+
+        if let Some(name) = &channel.template {
+            validate_template_name(
+                config,
+                channel_id,
+                &format!("channels.{channel_id}.template"),
+                name,
+            )?;
+        }
+    }
+
+    #[cfg(feature = "webhook")]
+    validate_http(&config.http)?;
+    #[cfg(feature = "webhook")]
+    for (channel_id, channel) in &config.channels {
+        match &channel.kind {
+            ChannelConfig::Webhook(webhook) => validate_webhook(channel_id, webhook)?,
+            ChannelConfig::Slack(slack) => validate_slack(channel_id, slack)?,
+            ChannelConfig::GoogleChat(chat) => validate_googlechat(channel_id, chat)?,
+            ChannelConfig::Telegram(telegram) => validate_telegram(channel_id, telegram)?,
+            ChannelConfig::Ntfy(ntfy) => validate_ntfy(channel_id, ntfy)?,
+            ChannelConfig::Pushover(pushover) => validate_pushover(channel_id, pushover)?,
+            ChannelConfig::Mqtt(mqtt) => validate_mqtt(channel_id, mqtt)?,
+            ChannelConfig::Zulip(zulip) => validate_zulip(channel_id, zulip)?,
+            ChannelConfig::Healthchecks(healthchecks) => {
+                validate_healthchecks(channel_id, healthchecks)?
+            }
+            _ => {}
+        }
+    }
+
+    validate_tiers(config)?;
+    validate_exit_codes(config)?;
+    validate_triggers(config)?;
+    if let Some(pattern) = &config.progress_pattern {
+        validate_progress_pattern("progress_pattern", pattern)?;
+    }
+    validate_escalation(config)?;
+    validate_snapshot(&config.snapshot)?;
+    validate_hooks("hooks", &config.hooks)?;
+    for (name, alias) in &config.aliases {
+        validate_alias(config, name, alias)?;
+    }
+
+    for channel_id in &config.default_channels {
+        if !config.channels.contains_key(channel_id) {
+            return Err(ConfigDiagnostic::new(
+                "default_channels",
+                format!("default channel `{channel_id}` is not defined in channels"),
+            )
+            .value(channel_id)
+            .suggest(config.channels.keys().map(String::as_str))
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a channel's field selection: `include` and `exclude` are not both
+/// set, and name event fields. `field` is where the lists sit in config.
+fn validate_fields(
+    channel_id: &str,
+    field: &str,
+    include: (&str, Option<&[String]>),
+    exclude: (&str, &[String]),
+) -> Result<(), ConfigError> {
+    if include.1.is_some() && !exclude.1.is_empty() {
+        return Err(ConfigDiagnostic::new(
+            format!("{field}.{}", exclude.0),
+            format!(
+                "channel `{channel_id}` sets both {} and {}",
+                include.0, exclude.0
+            ),
+        )
+        .channel(channel_id)
+        .into());
+    }
+
+    for (list, fields) in [(include.0, include.1.unwrap_or_default()), exclude] {
+        if let Some(name) = fields
+            .iter()
+            .find(|name| !CompletionEvent::FIELDS.contains(&name.as_str()))
+        {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.{list}"),
+                format!("channel `{channel_id}` {list} has unknown event field `{name}`"),
+            )
+            .channel(channel_id)
+            .value(name)
+            .suggest(CompletionEvent::FIELDS)
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a channel's `template` names a title/body template.
+fn validate_template_name(
+    config: &Config,
+    channel_id: &str,
+    field: &str,
+    name: &str,
+) -> Result<(), ConfigError> {
+    if matches!(config.templates.get(name), Some(TemplateDef::Message(_))) {
+        return Ok(());
+    }
+
+    let message_templates = config
+        .templates
+        .iter()
+        .filter(|(_, template)| matches!(template, TemplateDef::Message(_)))
+        .map(|(name, _)| name.as_str());
+    Err(ConfigDiagnostic::new(
+        field,
+        format!(
+            "channel `{channel_id}` uses template `{name}`, which is not a title/body template in templates"
+        ),
+    )
+    .channel(channel_id)
+    .value(name)
+    .suggest(message_templates)
+    .into())
+}
+
+/// Checks the regexes, statuses, label names, templates and fields in a
+/// channel's pipeline.
+fn validate_pipeline(
+    config: &Config,
+    channel_id: &str,
+    pipeline: &[Middleware],
+) -> Result<(), ConfigError> {
+    for (index, step) in pipeline.iter().enumerate() {
+        let field = format!("channels.{channel_id}.pipeline[{index}]");
+        match step {
+            Middleware::Filter(filter) => {
+                if let Some(status) = &filter.status
+                    && !matches!(status.as_str(), "success" | "failure")
+                {
+                    return Err(ConfigDiagnostic::new(
+                        format!("{field}.status"),
+                        format!(
+                            "channel `{channel_id}` filters on unknown status `{status}` (expected success or failure)"
+                        ),
+                    )
+                    .channel(channel_id)
+                    .value(status)
+                    .suggest(["success", "failure"])
+                    .into());
+                }
+                if let Some(pattern) = &filter.command
+                    && Regex::new(pattern).is_err()
+                {
+                    return Err(ConfigDiagnostic::new(
+                        format!("{field}.command"),
+                        format!("channel `{channel_id}` filters on an invalid regex `{pattern}`"),
+                    )
+                    .channel(channel_id)
+                    .value(pattern)
+                    .into());
+                }
+            }
+            Middleware::Redact(redact) => {
+                if let Some(pattern) = redact
+                    .patterns
+                    .iter()
+                    .find(|pattern| Regex::new(pattern).is_err())
+                {
+                    return Err(ConfigDiagnostic::new(
+                        format!("{field}.patterns"),
+                        format!("channel `{channel_id}` redacts with an invalid regex `{pattern}`"),
+                    )
+                    .channel(channel_id)
+                    .value(pattern)
+                    .into());
+                }
+            }
+            Middleware::Enrich(labels) => {
+                if labels.keys().any(|name| name.trim().is_empty()) {
+                    return Err(ConfigDiagnostic::new(
+                        field,
+                        format!("channel `{channel_id}` adds a label with an empty name"),
+                    )
+                    .channel(channel_id)
+                    .into());
+                }
+            }
+            Middleware::Template(name) => {
+                validate_template_name(config, channel_id, &format!("{field}.template"), name)?;
+            }
+            Middleware::Fields(fields) => validate_fields(
+                channel_id,
+                &format!("{field}.fields"),
+                ("include", fields.include.as_deref()),
+                ("exclude", &fields.exclude),
+            )?,
+            Middleware::NotifyOn(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks `suppress_if_delivered` names existing channels and does not loop
+/// back to this channel.
+fn validate_ack(config: &Config, channel_id: &str) -> Result<(), ConfigError> {
+    let channel = &config.channels[channel_id];
+    let Some(ack) = &channel.ack else {
+        return Ok(());
+    };
+
+    let supported = match &channel.kind {
+        ChannelConfig::Desktop(_) | ChannelConfig::Ntfy(_) => true,
+        ChannelConfig::Webhook(webhook) => webhook.format == WebhookFormat::Ntfy,
+        ChannelConfig::Slack(_)
+        | ChannelConfig::GoogleChat(_)
+        | ChannelConfig::Telegram(_)
+        | ChannelConfig::Pushover(_)
+        | ChannelConfig::Mqtt(_)
+        | ChannelConfig::Zulip(_)
+        | ChannelConfig::Healthchecks(_)
+        | ChannelConfig::Url(_)
+        | ChannelConfig::Custom(_) => false,
+    };
+    if !supported {
+        return Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.ack"),
+            format!(
+                "channel `{channel_id}` sets ack, which needs a desktop channel or the ntfy format"
+            ),
+        )
+        .channel(channel_id)
+        .into());
+    }
+
+    let field = format!("channels.{channel_id}.ack.escalate_to");
+    for target in &ack.escalate_to {
+        let problem = if target == channel_id {
+            "is the channel itself"
+        } else if !config.channels.contains_key(target) {
+            "is not defined in channels"
+        } else {
+            continue;
+        };
+        return Err(ConfigDiagnostic::new(
+            field,
+            format!("channel `{channel_id}` escalates unacknowledged failures to `{target}`, which {problem}"),
+        )
+        .channel(channel_id)
+        .value(target)
+        .suggest(config.channels.keys().map(String::as_str))
+        .into());
+    }
+    Ok(())
+}
+
+fn validate_suppression(config: &Config, channel_id: &str) -> Result<(), ConfigError> {
+    let field = format!("channels.{channel_id}.suppress_if_delivered");
+    for other in &config.channels[channel_id].suppress_if_delivered {
+        if !config.channels.contains_key(other) {
+            return Err(ConfigDiagnostic::new(
+                field,
+                format!(
+                    "channel `{channel_id}` is suppressed by `{other}`, which is not defined in channels"
+                ),
+            )
+            .channel(channel_id)
+            .value(other)
+            .suggest(config.channels.keys().map(String::as_str))
+            .into());
+        }
+    }
+
+    // walk the suppression graph from this channel looking for a way back.
+    let mut path = vec![channel_id];
+    let mut stack = vec![(channel_id, 0)];
+    while let Some((current, next)) = stack.pop() {
+        let Some(other) = config.channels[current].suppress_if_delivered.get(next) else {
+            path.pop();
+            continue;
+        };
+        stack.push((current, next + 1));
+        if other == channel_id {
+            path.push(other);
+            return Err(ConfigDiagnostic::new(
+                field,
+                format!(
+                    "channels suppress each other in a cycle: {}",
+                    path.join(" -> ")
+                ),
+            )
+            .channel(channel_id)
+            .into());
+        }
+        if !path.contains(&other.as_str()) {
+            path.push(other);
+            stack.push((other, 0));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks each duration tier names defined channels and a distinct duration.
+fn validate_tiers(config: &Config) -> Result<(), ConfigError> {
+    for (index, tier) in config.tiers.iter().enumerate() {
+        let field = format!("tiers[{index}]");
+        if tier.channels.is_empty() {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.channels"),
+                format!("{field} must include at least one channel id"),
+            )
+            .into());
+        }
+
+        if config.tiers[..index]
+            .iter()
+            .any(|earlier| earlier.min_duration == tier.min_duration)
+        {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.min_duration"),
+                format!("{field} repeats the min_duration of an earlier tier"),
+            )
+            .into());
+        }
+
+        for channel_id in &tier.channels {
+            if !config.channels.contains_key(channel_id) {
+                return Err(ConfigDiagnostic::new(
+                    format!("{field}.channels"),
+                    format!(
+                        "{field} uses channel `{channel_id}`, which is not defined in channels"
+                    ),
+                )
+                .value(channel_id)
+                .suggest(config.channels.keys().map(String::as_str))
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks each escalation step names defined channels.
+fn validate_escalation(config: &Config) -> Result<(), ConfigError> {
+    for (index, step) in config.escalation.iter().enumerate() {
+        let field = format!("escalation[{index}]");
+        if step.channels.is_empty() {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.channels"),
+                format!("{field} must include at least one channel id"),
+            )
+            .into());
+        }
+
+        for channel_id in &step.channels {
+            if !config.channels.contains_key(channel_id) {
+                return Err(ConfigDiagnostic::new(
+                    format!("{field}.channels"),
+                    format!(
+                        "{field} escalates to channel `{channel_id}`, which is not defined in channels"
+                    ),
+                )
+                .value(channel_id)
+                .suggest(config.channels.keys().map(String::as_str))
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks every snapshot tool names a command.
+fn validate_snapshot(snapshot: &SnapshotConfig) -> Result<(), ConfigError> {
+    if let Some(index) = snapshot
+        .tools
+        .iter()
+        .position(|tool| tool.trim().is_empty())
+    {
+        return Err(ConfigDiagnostic::new(
+            format!("snapshot.tools[{index}]"),
+            format!("snapshot.tools[{index}] is empty"),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Checks exit code rules are for failures and name defined channels.
+fn validate_exit_codes(config: &Config) -> Result<(), ConfigError> {
+    for (code, rule) in &config.exit_codes {
+        let field = format!("exit_codes.{code}");
+        if *code == 0 {
+            return Err(ConfigDiagnostic::new(
+                field,
+                "exit_codes cannot have a rule for 0, which is success",
+            )
+            .into());
+        }
+
+        if rule.message.is_none() && rule.channels.is_empty() {
+            return Err(ConfigDiagnostic::new(
+                field,
+                format!("exit_codes.{code} needs a message or channels"),
+            )
+            .into());
+        }
+
+        for channel_id in &rule.channels {
+            if !config.channels.contains_key(channel_id) {
+                return Err(ConfigDiagnostic::new(
+                    format!("{field}.channels"),
+                    format!(
+                        "exit_codes.{code} uses channel `{channel_id}`, which is not defined in channels"
+                    ),
+                )
+                .value(channel_id)
+                .suggest(config.channels.keys().map(String::as_str))
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks each trigger's regex and that its channels exist.
+fn validate_triggers(config: &Config) -> Result<(), ConfigError> {
+    for (index, trigger) in config.triggers.iter().enumerate() {
+        let field = format!("triggers[{index}]");
+        if Regex::new(&trigger.pattern).is_err() {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.pattern"),
+                format!("{field} pattern `{}` is not a valid regex", trigger.pattern),
+            )
+            .value(&trigger.pattern)
+            .into());
+        }
+
+        if trigger.channels.is_empty() {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.channels"),
+                format!("{field} needs at least one channel"),
+            )
+            .into());
+        }
+
+        for channel_id in &trigger.channels {
+            if !config.channels.contains_key(channel_id) {
+                return Err(ConfigDiagnostic::new(
+                    format!("{field}.channels"),
+                    format!(
+                        "{field} uses channel `{channel_id}`, which is not defined in channels"
+                    ),
+                )
+                .value(channel_id)
+                .suggest(config.channels.keys().map(String::as_str))
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks an alias has a command, a name `brb` does not use itself, and
+/// channels that exist.
+fn validate_alias(config: &Config, name: &str, alias: &Alias) -> Result<(), ConfigError> {
+    if RESERVED_NAMES.contains(&name) {
+        return Err(ConfigDiagnostic::new(
+            format!("aliases.{name}"),
+            format!("alias `{name}` clashes with the `brb {name}` subcommand"),
+        )
+        .into());
+    }
+
+    if alias.command.is_empty() {
+        return Err(ConfigDiagnostic::new(
+            format!("aliases.{name}.command"),
+            format!("alias `{name}` must have a non-empty command"),
+        )
+        .into());
+    }
+
+    for channel_id in &alias.channels {
+        if !config.channels.contains_key(channel_id) {
+            return Err(ConfigDiagnostic::new(
+                format!("aliases.{name}.channels"),
+                format!(
+                    "alias `{name}` uses channel `{channel_id}`, which is not defined in channels"
+                ),
+            )
+            .value(channel_id)
+            .suggest(config.channels.keys().map(String::as_str))
+            .into());
+        }
+    }
+
+    if let Some(pattern) = &alias.progress_pattern {
+        validate_progress_pattern(&format!("aliases.{name}.progress_pattern"), pattern)?;
+    }
+    validate_hooks(&format!("aliases.{name}.hooks"), &alias.hooks)
+}
+
+/// Checks a `progress_pattern` compiles.
+fn validate_progress_pattern(field: &str, pattern: &str) -> Result<(), ConfigError> {
+    if Regex::new(pattern).is_err() {
+        return Err(ConfigDiagnostic::new(
+            field,
+            format!("{field} `{pattern}` is not a valid regex"),
+        )
+        .value(pattern)
+        .into());
+    }
+    Ok(())
+}
+
+/// Rejects blank hook commands, which would run an empty shell.
+fn validate_hooks(field: &str, hooks: &Hooks) -> Result<(), ConfigError> {
+    for (list, commands) in [
+        ("on_success", &hooks.on_success),
+        ("on_failure", &hooks.on_failure),
+    ] {
+        if commands.iter().any(|command| command.trim().is_empty()) {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.{list}"),
+                format!("{field}.{list} has an empty command"),
+            )
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks `max_message_length` is long enough and set where it applies.
+fn validate_message_length(channel_id: &str, channel: &Channel) -> Result<(), ConfigError> {
+    let max_message_length = match &channel.kind {
+        ChannelConfig::Desktop(desktop) => desktop.max_message_length,
+        ChannelConfig::Webhook(webhook) => webhook.max_message_length,
+        ChannelConfig::Slack(_)
+        | ChannelConfig::GoogleChat(_)
+        | ChannelConfig::Telegram(_)
+        | ChannelConfig::Ntfy(_)
+        | ChannelConfig::Pushover(_)
+        | ChannelConfig::Mqtt(_)
+        | ChannelConfig::Zulip(_)
+        | ChannelConfig::Healthchecks(_)
+        | ChannelConfig::Url(_)
+        | ChannelConfig::Custom(_) => None,
+    };
+    let Some(max_message_length) = max_message_length else {
+        return Ok(());
+    };
+
+    let field = format!("channels.{channel_id}.max_message_length");
+    if let ChannelConfig::Webhook(webhook) = &channel.kind
+        && matches!(
+            webhook.format,
+            WebhookFormat::Generic | WebhookFormat::CloudEvents
+        )
+    {
+        return Err(ConfigDiagnostic::new(
+            field,
+            format!(
+                "channel `{channel_id}` sets max_message_length, which only applies to the slack, discord, teams and ntfy formats"
+            ),
+        )
+        .channel(channel_id)
+        .into());
+    }
+    if max_message_length < MIN_MESSAGE_LENGTH {
+        return Err(ConfigDiagnostic::new(
+            field,
+            format!(
+                "channel `{channel_id}` max_message_length must be at least {MIN_MESSAGE_LENGTH} (got {max_message_length})"
+            ),
+        )
+        .channel(channel_id)
+        .into());
+    }
+    Ok(())
+}
+
+/// Checks a desktop channel's `rerun` and `escalate_to` settings.
+fn validate_desktop(
+    config: &Config,
+    channel_id: &str,
+    desktop: &DesktopChannel,
+) -> Result<(), ConfigError> {
+    if desktop.rerun && desktop.wait.is_none() {
+        return Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.rerun"),
+            format!("channel `{channel_id}` sets rerun without wait"),
+        )
+        .channel(channel_id)
+        .into());
+    }
+
+    let field = format!("channels.{channel_id}.escalate_to");
+    if desktop.when_dnd == WhenDnd::Escalate && desktop.escalate_to.is_empty() {
+        return Err(ConfigDiagnostic::new(
+            field,
+            format!("channel `{channel_id}` sets when_dnd: escalate without escalate_to"),
+        )
+        .channel(channel_id)
+        .into());
+    }
+
+    for target in &desktop.escalate_to {
+        let problem = match config.channels.get(target) {
+            None => "is not defined in channels",
+            Some(channel) if matches!(channel.kind, ChannelConfig::Desktop(_)) => {
+                "is a desktop channel"
+            }
+            Some(_) => continue,
+        };
+        return Err(ConfigDiagnostic::new(
+            field,
+            format!("channel `{channel_id}` escalates to `{target}`, which {problem}"),
+        )
+        .channel(channel_id)
+        .value(target)
+        .suggest(config.channels.keys().map(String::as_str))
+        .into());
+    }
+
+    for (index, rule) in desktop.sounds.iter().enumerate() {
+        let field = format!("channels.{channel_id}.sounds[{index}]");
+        if let Some(status) = &rule.status
+            && !matches!(status.as_str(), "success" | "failure")
+        {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.status"),
+                format!(
+                    "channel `{channel_id}` has a sound for unknown status `{status}` (expected success or failure)"
+                ),
+            )
+            .channel(channel_id)
+            .value(status)
+            .suggest(["success", "failure"])
+            .into());
+        }
+        if rule.sound.trim().is_empty() {
+            return Err(ConfigDiagnostic::new(
+                format!("{field}.sound"),
+                format!("channel `{channel_id}` has an empty sound name"),
+            )
+            .channel(channel_id)
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the shared `http` section's headers, user agent and proxy.
+#[cfg(feature = "webhook")]
+fn validate_http(http: &HttpConfig) -> Result<(), ConfigError> {
+    for (name, value) in &http.headers {
+        if HeaderName::try_from(name.as_str()).is_err() {
+            return Err(ConfigDiagnostic::new(
+                "http.headers",
+                format!("http.headers has an invalid header name `{name}`"),
+            )
+            .value(name)
+            .into());
+        }
+        if HeaderValue::try_from(value.as_str()).is_err() {
+            return Err(ConfigDiagnostic::new(
+                "http.headers",
+                format!("http.headers has an invalid value for header `{name}`"),
+            )
+            .value(name)
+            .into());
+        }
+    }
+
+    if let Some(user_agent) = &http.user_agent
+        && HeaderValue::try_from(user_agent.as_str()).is_err()
+    {
+        return Err(
+            ConfigDiagnostic::new("http.user_agent", "http.user_agent is invalid")
+                .value(user_agent)
+                .into(),
+        );
+    }
+
+    if let Some(problem) = http.proxy.as_deref().and_then(proxy_problem) {
+        return Err(
+            ConfigDiagnostic::new("http.proxy", format!("http.proxy has {problem}")).into(),
+        );
+    }
+
+    Ok(())
+}
+
+/// What is wrong with a proxy URL, if anything. The URL is never echoed, as
+/// it may carry credentials.
+#[cfg(feature = "webhook")]
+fn proxy_problem(proxy: &str) -> Option<String> {
+    match Url::parse(proxy) {
+        Err(error) => Some(format!("an invalid proxy url ({error})")),
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => Some(format!(
+            "an unsupported proxy scheme `{}` (expected http or https)",
+            url.scheme()
+        )),
+        Ok(_) => None,
+    }
+}
+
+/// The cargo feature a channel type needs, when this build left it out.
+fn missing_channel_feature(kind: &ChannelConfig) -> Option<&'static str> {
+    match kind {
+        ChannelConfig::Desktop(_) if !cfg!(feature = "desktop") => Some("desktop"),
+        ChannelConfig::Webhook(_)
+        | ChannelConfig::Slack(_)
+        | ChannelConfig::GoogleChat(_)
+        | ChannelConfig::Telegram(_)
+        | ChannelConfig::Ntfy(_)
+        | ChannelConfig::Pushover(_)
+        | ChannelConfig::Mqtt(_)
+        | ChannelConfig::Zulip(_)
+        | ChannelConfig::Healthchecks(_)
+            if !cfg!(feature = "webhook") =>
+        {
+            Some("webhook")
+        }
+        _ => None,
+    }
+}
+
+/// Checks `field_map` renames known event fields to distinct keys that no
+/// other field or `extra` key already uses.
+#[cfg(feature = "webhook")]
+fn validate_field_map(channel_id: &str, webhook: &WebhookChannel) -> Result<(), ConfigError> {
+    let field = format!("channels.{channel_id}.field_map");
+    let problem = |message: String, value: &str| -> ConfigError {
+        ConfigDiagnostic::new(field.clone(), message)
+            .channel(channel_id)
+            .value(value)
+            .into()
+    };
+
+    if !webhook.field_map.is_empty()
+        && !matches!(
+            webhook.format,
+            WebhookFormat::Generic | WebhookFormat::CloudEvents
+        )
+    {
+        return Err(ConfigDiagnostic::new(
+            field.clone(),
+            format!(
+                "channel `{channel_id}` field_map only applies to format: generic or cloudevents"
+            ),
+        )
+        .channel(channel_id)
+        .into());
+    }
+
+    let mut targets = Vec::new();
+    for (from, to) in &webhook.field_map {
+        if !CompletionEvent::FIELDS.contains(&from.as_str()) {
+            return Err(ConfigDiagnostic::new(
+                field.clone(),
+                format!("channel `{channel_id}` field_map has unknown event field `{from}`"),
+            )
+            .channel(channel_id)
+            .value(from)
+            .suggest(CompletionEvent::FIELDS)
+            .into());
+        }
+        if to.trim().is_empty() {
+            return Err(problem(
+                format!("channel `{channel_id}` field_map renames `{from}` to an empty key"),
+                from,
+            ));
+        }
+
+        let kept_field =
+            CompletionEvent::FIELDS.contains(&to.as_str()) && !webhook.field_map.contains_key(to);
+        if targets.contains(&to) || kept_field || webhook.extra.contains_key(to) {
+            return Err(problem(
+                format!(
+                    "channel `{channel_id}` field_map renames `{from}` to `{to}`, which another field already uses"
+                ),
+                to,
+            ));
+        }
+        targets.push(to);
+    }
+    Ok(())
+}
+
+/// Checks a Slack channel's URL, which is never echoed back since it is the
+/// credential.
+#[cfg(feature = "webhook")]
+fn validate_slack(channel_id: &str, slack: &SlackChannel) -> Result<(), ConfigError> {
+    validate_chat_url(channel_id, &slack.url)
+}
+
+/// Checks a Google Chat channel's webhook URL.
+#[cfg(feature = "webhook")]
+fn validate_googlechat(channel_id: &str, chat: &GoogleChatChannel) -> Result<(), ConfigError> {
+    validate_chat_url(channel_id, &chat.url)
+}
+
+/// Checks the incoming webhook URL of a chat channel type.
+#[cfg(feature = "webhook")]
+fn validate_chat_url(channel_id: &str, url: &str) -> Result<(), ConfigError> {
+    let problem = match Url::parse(url) {
+        Err(error) => Some(format!("an invalid url ({error})")),
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => Some(format!(
+            "an unsupported url scheme `{}` (expected http or https)",
+            url.scheme()
+        )),
+        Ok(url) if url.host_str().is_none_or(str::is_empty) => {
+            Some("a url without a host".to_string())
+        }
+        Ok(_) => None,
+    };
+
+    match problem {
+        Some(problem) => Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.url"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Checks a Healthchecks channel's check is a UUID or an http(s) ping URL.
+/// Either one is enough to ping the check, so it is never echoed back.
+#[cfg(feature = "webhook")]
+fn validate_healthchecks(
+    channel_id: &str,
+    healthchecks: &HealthchecksChannel,
+) -> Result<(), ConfigError> {
+    let check = healthchecks.check.trim();
+    let problem = if check.contains("://") {
+        match Url::parse(check) {
+            Err(error) => Some(format!("an invalid check url ({error})")),
+            Ok(url) if !matches!(url.scheme(), "http" | "https") => Some(format!(
+                "an unsupported check url scheme `{}` (expected http or https)",
+                url.scheme()
+            )),
+            Ok(url) if url.host_str().is_none_or(str::is_empty) => {
+                Some("a check url without a host".to_string())
+            }
+            Ok(_) => None,
+        }
+    } else if is_uuid(check) {
+        None
+    } else {
+        Some("a check that is neither a UUID nor a ping URL; copy either from the check's page on Healthchecks.io".to_string())
+    };
+
+    match problem {
+        Some(problem) => Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.check"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Whether `value` is a hyphenated UUID, as in `8f3e5b1e-5a4c-4d1e-9a57-3f2a1c9e7d10`.
+#[cfg(feature = "webhook")]
+fn is_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.char_indices().all(|(index, c)| match index {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Checks a Telegram channel's bot token, chat and API server. The token is
+/// never echoed back.
+#[cfg(feature = "webhook")]
+fn validate_telegram(channel_id: &str, telegram: &TelegramChannel) -> Result<(), ConfigError> {
+    let problem = |field: &str, problem: String| {
+        Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.{field}"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into())
+    };
+
+    let token = telegram.bot_token.trim();
+    if token.is_empty() {
+        return problem("bot_token", "an empty bot_token".to_string());
+    }
+    if !token.split_once(':').is_some_and(|(id, secret)| {
+        id.bytes().all(|byte| byte.is_ascii_digit()) && !secret.is_empty()
+    }) || token.contains(['/', ' '])
+    {
+        return problem(
+            "bot_token",
+            "a bot_token that is not in BotFather's `<bot id>:<secret>` form".to_string(),
+        );
+    }
+
+    if telegram.chat_id.trim().is_empty() {
+        return problem("chat_id", "an empty chat_id".to_string());
+    }
+
+    match Url::parse(&telegram.api_url) {
+        Err(error) => problem("api_url", format!("an invalid api_url ({error})")),
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => problem(
+            "api_url",
+            format!(
+                "an unsupported api_url scheme `{}` (expected http or https)",
+                url.scheme()
+            ),
+        ),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Checks a Pushover channel's keys and API server. The keys are never echoed
+/// back.
+#[cfg(feature = "webhook")]
+fn validate_pushover(channel_id: &str, pushover: &PushoverChannel) -> Result<(), ConfigError> {
+    let problem = |field: &str, problem: String| {
+        Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.{field}"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into())
+    };
+
+    // Pushover keys and tokens are 30 letters and digits.
+    for (field, value) in [
+        ("user_key", &pushover.user_key),
+        ("api_token", &pushover.api_token),
+    ] {
+        let value = value.trim();
+        if value.is_empty() {
+            return problem(field, format!("an empty {field}"));
+        }
+        if value.len() != 30 || !value.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+            return problem(
+                field,
+                format!("an invalid {field} (expected 30 letters and digits)"),
+            );
+        }
+    }
+
+    match Url::parse(&pushover.api_url) {
+        Err(error) => problem("api_url", format!("an invalid api_url ({error})")),
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => problem(
+            "api_url",
+            format!(
+                "an unsupported api_url scheme `{}` (expected http or https)",
+                url.scheme()
+            ),
+        ),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Checks a Zulip channel's site, bot credentials, stream and topic. The API
+/// key is never echoed back.
+#[cfg(feature = "webhook")]
+fn validate_zulip(channel_id: &str, zulip: &ZulipChannel) -> Result<(), ConfigError> {
+    let problem = |field: &str, problem: String| {
+        Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.{field}"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into())
+    };
+
+    match Url::parse(&zulip.site) {
+        Err(error) => return problem("site", format!("an invalid site ({error})")),
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => {
+            return problem(
+                "site",
+                format!(
+                    "an unsupported site scheme `{}` (expected http or https)",
+                    url.scheme()
+                ),
+            );
+        }
+        Ok(_) => {}
+    }
+
+    let bot_email = zulip.bot_email.trim();
+    if bot_email.is_empty() {
+        return problem("bot_email", "an empty bot_email".to_string());
+    }
+    if !bot_email.contains('@') {
+        return problem(
+            "bot_email",
+            format!("a bot_email `{bot_email}` that is not an email address"),
+        );
+    }
+    if zulip.api_key.trim().is_empty() {
+        return problem("api_key", "an empty api_key".to_string());
+    }
+    if zulip.stream.trim().is_empty() {
+        return problem("stream", "an empty stream".to_string());
+    }
+    if zulip.topic.trim().is_empty() {
+        return problem("topic", "an empty topic".to_string());
+    }
+    if let Err(error) = crate::template::check_placeholders(&zulip.topic) {
+        return problem("topic", format!("a topic with {error}"));
+    }
+    Ok(())
+}
+
+/// Checks an MQTT channel's broker, topic, QoS and credentials. The broker
+/// URL and credentials are never echoed back.
+#[cfg(feature = "webhook")]
+fn validate_mqtt(channel_id: &str, mqtt: &MqttChannel) -> Result<(), ConfigError> {
+    let problem = |field: &str, problem: String| {
+        Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.{field}"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into())
+    };
+
+    if let Err(error) = crate::mqtt::Broker::parse(&mqtt.broker) {
+        return problem("broker", format!("an unusable broker ({error})"));
+    }
+    let topic = mqtt.topic.as_str();
+    if topic.is_empty() {
+        return problem("topic", "an empty topic".to_string());
+    }
+    if topic.contains(['+', '#', '\0']) {
+        return problem(
+            "topic",
+            "a topic with wildcards (`+` and `#` only work when subscribing)".to_string(),
+        );
+    }
+    if mqtt.qos > 2 {
+        return problem(
+            "qos",
+            format!("an invalid qos {} (expected 0, 1 or 2)", mqtt.qos),
+        );
+    }
+    if mqtt.password.is_some() && mqtt.username.is_none() {
+        return problem("password", "a password without a username".to_string());
+    }
+    if mqtt
+        .client_id
+        .as_deref()
+        .is_some_and(|client_id| client_id.trim().is_empty())
+    {
+        return problem("client_id", "an empty client_id".to_string());
+    }
+    Ok(())
+}
+
+/// Checks an ntfy channel's server, topic and auth. Credentials are never
+/// echoed back.
+#[cfg(feature = "webhook")]
+fn validate_ntfy(channel_id: &str, ntfy: &NtfyChannel) -> Result<(), ConfigError> {
+    let problem = |field: &str, problem: String| {
+        Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.{field}"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into())
+    };
+
+    let topic = ntfy.topic.trim();
+    if topic.is_empty() {
+        return problem("topic", "an empty topic".to_string());
+    }
+    if topic.len() > 64
+        || !topic
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_'))
+    {
+        return problem(
+            "topic",
+            format!(
+                "topic `{topic}`, which ntfy does not accept (use up to 64 letters, digits, `-` and `_`)"
+            ),
+        );
+    }
+
+    match (&ntfy.token, &ntfy.username, &ntfy.password) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            return problem(
+                "token",
+                "both a token and a username or password (use one or the other)".to_string(),
+            );
+        }
+        (Some(token), None, None) if token.trim().is_empty() => {
+            return problem("token", "an empty token".to_string());
+        }
+        (None, Some(_), None) => {
+            return problem("password", "a username but no password".to_string());
+        }
+        (None, None, Some(_)) => {
+            return problem("username", "a password but no username".to_string());
+        }
+        _ => {}
+    }
+
+    for (status, overrides) in [("success", &ntfy.success), ("failure", &ntfy.failure)] {
+        if overrides
+            .tags
+            .iter()
+            .flatten()
+            .any(|tag| tag.trim().is_empty() || tag.contains(','))
+        {
+            return problem(
+                &format!("{status}.tags"),
+                format!("{status}.tags with an empty tag or a comma"),
+            );
+        }
+    }
+
+    match Url::parse(&ntfy.server) {
+        Err(error) => problem("server", format!("an invalid server ({error})")),
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => problem(
+            "server",
+            format!(
+                "an unsupported server scheme `{}` (expected http or https)",
+                url.scheme()
+            ),
+        ),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Checks `capture` names and paths, and that `extra` only uses values
+/// `capture` keeps.
+#[cfg(feature = "webhook")]
+fn validate_capture(channel_id: &str, webhook: &WebhookChannel) -> Result<(), ConfigError> {
+    let field = format!("channels.{channel_id}.capture");
+    for (name, path) in &webhook.capture {
+        if name.trim().is_empty() || name.contains(['{', '}']) {
+            return Err(ConfigDiagnostic::new(
+                field.clone(),
+                format!("channel `{channel_id}` capture has an invalid name `{name}`"),
+            )
+            .channel(channel_id)
+            .value(name)
+            .into());
+        }
+        if capture_pointer(path).is_none() {
+            return Err(ConfigDiagnostic::new(
+                field.clone(),
+                format!(
+                    "channel `{channel_id}` captures `{name}` from `{path}`, which is neither a JSON pointer (`/id`) nor a `$.id` path"
+                ),
+            )
+            .channel(channel_id)
+            .value(path)
+            .into());
+        }
+    }
+
+    for value in webhook.extra.values() {
+        let mut rest = value.as_str();
+        while let Some(start) = rest.find("{captured.") {
+            let tail = &rest[start + "{captured.".len()..];
+            let name = tail.split_once('}').map_or(tail, |(name, _)| name).trim();
+            if !webhook.capture.contains_key(name) {
+                return Err(ConfigDiagnostic::new(
+                    format!("channels.{channel_id}.extra"),
+                    format!(
+                        "channel `{channel_id}` uses `{{captured.{name}}}`, which capture does not define"
+                    ),
+                )
+                .channel(channel_id)
+                .value(name)
+                .suggest(webhook.capture.keys().map(String::as_str))
+                .into());
+            }
+            rest = tail;
+        }
+    }
+    Ok(())
+}
+
+/// Checks `progress` is set on a single Slack Web API or Discord URL, which
+/// are the only ones brb knows how to edit messages on.
+#[cfg(feature = "webhook")]
+fn validate_progress(channel_id: &str, webhook: &WebhookChannel) -> Result<(), ConfigError> {
+    if !webhook.progress {
+        return Ok(());
+    }
+
+    let problem = match webhook.format {
+        WebhookFormat::Slack
+            if !webhook
+                .url
+                .iter()
+                .all(|url| url.contains("/api/chat.postMessage")) =>
+        {
+            Some("needs the Slack Web API url (https://slack.com/api/chat.postMessage); incoming webhooks cannot edit messages")
+        }
+        WebhookFormat::Slack | WebhookFormat::Discord => None,
+        _ => Some("only applies to format: slack or discord"),
+    }
+    .or_else(|| (webhook.url.len() > 1).then_some("needs a single url"))
+    .or_else(|| (!webhook.encrypt_to.is_empty()).then_some("cannot be used with encrypt_to"));
+
+    match problem {
+        Some(problem) => Err(ConfigDiagnostic::new(
+            format!("channels.{channel_id}.progress"),
+            format!("channel `{channel_id}` progress {problem}"),
+        )
+        .channel(channel_id)
+        .into()),
+        None => Ok(()),
+    }
+}
+
+/// Checks a webhook's URL, method and headers so a broken channel fails at
+/// load rather than when the notification is sent.
+#[cfg(feature = "webhook")]
+fn validate_webhook(channel_id: &str, webhook: &WebhookChannel) -> Result<(), ConfigError> {
+    let field = |name: &str| format!("channels.{channel_id}.{name}");
+    // interpolated secrets may be in the URL, so it is never echoed back.
+    let url_error = |message: &str| -> ConfigError {
+        ConfigDiagnostic::new(
+            field("url"),
+            format!("channel `{channel_id}` has {message}"),
+        )
+        .channel(channel_id)
+        .into()
+    };
+
+    if webhook.url.is_empty() {
+        return Err(url_error("no url"));
+    }
+    for url in &webhook.url {
+        let url =
+            Url::parse(url).map_err(|error| url_error(&format!("an invalid url ({error})")))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(url_error(&format!(
+                "an unsupported url scheme `{}` (expected http or https)",
+                url.scheme()
+            )));
+        }
+        if url.host_str().is_none_or(str::is_empty) {
+            return Err(url_error("a url without a host"));
+        }
+    }
+
+    if Method::from_bytes(webhook.method.as_bytes()).is_err() {
+        return Err(ConfigDiagnostic::new(
+            field("method"),
+            format!(
+                "channel `{channel_id}` has an invalid HTTP method `{}`",
+                webhook.method
+            ),
+        )
+        .channel(channel_id)
+        .value(&webhook.method)
+        .into());
+    }
+
+    let mut header_names = webhook.headers.keys().collect::<Vec<_>>();
+    if !webhook.idempotency_header.is_empty() {
+        header_names.push(&webhook.idempotency_header);
+    }
+    for name in header_names {
+        if HeaderName::try_from(name.as_str()).is_err() {
+            return Err(ConfigDiagnostic::new(
+                field("headers"),
+                format!("channel `{channel_id}` has an invalid header name `{name}`"),
+            )
+            .channel(channel_id)
+            .value(name)
+            .into());
+        }
+    }
+    for (name, value) in &webhook.headers {
+        if HeaderValue::try_from(value.as_str()).is_err() {
+            return Err(ConfigDiagnostic::new(
+                field("headers"),
+                format!("channel `{channel_id}` has an invalid value for header `{name}`"),
+            )
+            .channel(channel_id)
+            .value(name)
+            .into());
+        }
+    }
+
+    if let Some(problem) = webhook.proxy.as_deref().and_then(proxy_problem) {
+        return Err(ConfigDiagnostic::new(
+            field("proxy"),
+            format!("channel `{channel_id}` has {problem}"),
+        )
+        .channel(channel_id)
+        .into());
+    }
+    if let Some(user_agent) = &webhook.user_agent
+        && HeaderValue::try_from(user_agent.as_str()).is_err()
+    {
+        return Err(ConfigDiagnostic::new(
+            field("user_agent"),
+            format!("channel `{channel_id}` has an invalid user_agent"),
+        )
+        .channel(channel_id)
+        .value(user_agent)
+        .into());
+    }
 
-    /// Optional environment variable overrides.
-    #[serde(default)]
-    pub env: BTreeMap<String, String>,
-}
+    validate_field_map(channel_id, webhook)?;
+    validate_capture(channel_id, webhook)?;
+    validate_progress(channel_id, webhook)?;
 
-/// Fully-loaded config plus where it came from.
-#[derive(Debug, Clone)]
-pub struct LoadedConfig {
-    /// Absolute file path used for loading.
-    pub path: PathBuf,
+    if let Some(recipient) = webhook
+        .encrypt_to
+        .iter()
+        .find(|recipient| !recipient.starts_with("age1") && !recipient.starts_with("ssh-"))
+    {
+        return Err(ConfigDiagnostic::new(
+            field("encrypt_to"),
+            format!(
+                "channel `{channel_id}` has an invalid age recipient `{recipient}` (expected age1... or an ssh public key)"
+            ),
+        )
+        .channel(channel_id)
+        .value(recipient)
+        .into());
+    }
 
-    /// Parsed and validated config.
-    pub config: Config,
+    Ok(())
 }
 
-/// Result of running `brb init`.
-#[derive(Debug, Clone)]
-pub enum InitStatus {
-    /// Config file was created.
-    Created(PathBuf),
+impl TemplateDef {
+    /// Template sources with their config path suffix, for error messages.
+    pub fn sources(&self) -> Vec<(&'static str, &str)> {
+        match self {
+            Self::Snippet(text) => vec![("", text.as_str())],
+            Self::Message(message) => [(".title", &message.title), (".body", &message.body)]
+                .into_iter()
+                .filter_map(|(part, source)| source.as_deref().map(|source| (part, source)))
+                .collect(),
+        }
+    }
 
-    /// Config file already existed.
-    AlreadyExists(PathBuf),
+    /// Text inserted where this template is included: a snippet, or a
+    /// message template's body.
+    pub fn include_source(&self) -> Option<&str> {
+        match self {
+            Self::Snippet(text) => Some(text),
+            Self::Message(message) => message.body.as_deref(),
+        }
+    }
 }
 
-/// Config loading/validation failures.
-#[derive(Debug, Error)]
-pub enum ConfigError {
-    #[error("unable to determine user config directory")]
-    NoConfigDirectory,
-    #[error("config file not found: {0}")]
-    NotFound(String),
-    #[error("failed to read config file: {0}")]
-    ReadFailed(#[from] std::io::Error),
-    #[error("invalid YAML config: {0}")]
-    ParseFailed(#[from] serde_yaml::Error),
-    #[error("missing environment variable for interpolation: {0}")]
-    MissingEnvironmentVariable(String),
-    #[error("invalid environment interpolation expression in config value: {0}")]
-    InvalidInterpolation(String),
-    #[error("invalid config: {0}")]
-    InvalidConfig(String),
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            timezone: DisplayTimezone::default(),
+            timestamp_format: default_timestamp_format(),
+            locale: None,
+            durations: DurationStyle::default(),
+            max_command_length: default_max_command_length(),
+        }
+    }
 }
 
-impl ChannelConfig {
-    /// Returns stable type label for display output.
-    pub fn type_name(&self) -> &'static str {
+impl TryFrom<String> for DisplayTimezone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_ascii_lowercase().as_str() {
+            "local" => return Ok(Self::Local),
+            "utc" | "z" => return Ok(Self::Utc),
+            _ => {}
+        }
+
+        value
+            .parse::<FixedOffset>()
+            .map(Self::Fixed)
+            .map_err(|_| format!("invalid timezone `{value}` (expected local, utc or +HH:MM)"))
+    }
+}
+
+impl Priority {
+    /// Returns the lowercase config/ntfy name of this priority.
+    pub fn as_str(self) -> &'static str {
         match self {
-            Self::Desktop(_) => "desktop",
-            Self::Webhook(_) => "webhook",
-            Self::Custom(_) => "custom",
+            Self::Min => "min",
+            Self::Low => "low",
+            Self::Default => "default",
+            Self::High => "high",
+            Self::Urgent => "urgent",
         }
     }
 }
 
-/// Loads config from the global `config.yml` and validates it.
-pub fn load_config() -> Result<LoadedConfig, ConfigError> {
-    let path = config_file_path()?;
-    if !path.exists() {
-        return Err(ConfigError::NotFound(path.display().to_string()));
+/// Parses a `#rrggbb` color into its numeric value.
+pub fn parse_hex_color(value: &str) -> Option<u32> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
     }
+    u32::from_str_radix(hex, 16).ok()
+}
 
-    let raw = fs::read_to_string(&path)?;
-    let mut config: Config = serde_yaml::from_str(&raw)?;
-    interpolate_env_values(&mut config)?;
-    validate_config(&config)?;
+fn default_timestamp_format() -> String {
+    "%H:%M".to_string()
+}
+
+/// Shortest `display.max_command_length`, which still leaves room for the
+/// program and its first and last arguments.
+const MIN_COMMAND_LENGTH: usize = 40;
 
-    Ok(LoadedConfig { path, config })
+/// Shortest `max_message_length`, which still leaves room for the command.
+const MIN_MESSAGE_LENGTH: usize = 40;
+
+fn default_max_command_length() -> usize {
+    200
 }
 
-/// Creates a default global config file if it does not already exist.
-pub fn init_config() -> Result<InitStatus, ConfigError> {
-    let path = config_file_path()?;
-    if path.exists() {
-        return Ok(InitStatus::AlreadyExists(path));
-    }
+fn default_secrets_command() -> String {
+    "age".to_string()
+}
 
-    let parent = path.parent().ok_or_else(|| {
-        ConfigError::InvalidConfig("calculated config path has no parent directory".to_string())
-    })?;
+fn default_machine_id() -> bool {
+    true
+}
 
-    fs::create_dir_all(parent)?;
-    fs::write(&path, default_config_yaml())?;
+fn default_enabled() -> bool {
+    true
+}
 
-    Ok(InitStatus::Created(path))
+fn default_http_method() -> String {
+    "POST".to_string()
 }
 
-/// Returns the absolute path to the global config file.
-pub fn config_file_path() -> Result<PathBuf, ConfigError> {
-    let base_dirs = BaseDirs::new().ok_or(ConfigError::NoConfigDirectory)?;
-    Ok(base_dirs.config_dir().join("brb").join("config.yml"))
+fn default_attach_lines() -> usize {
+    20
 }
 
-/// Validates static schema and cross-field constraints.
-pub fn validate_config(config: &Config) -> Result<(), ConfigError> {
-    if config.version != 1 {
-        return Err(ConfigError::InvalidConfig(format!(
-            "unsupported version {}; expected 1",
-            config.version
-        )));
-    }
+fn default_pushover_api_url() -> String {
+    "https://api.pushover.net".to_string()
+}
 
-    if config.channels.is_empty() {
-        return Err(ConfigError::InvalidConfig(
-            "at least one channel must be configured".to_string(),
-        ));
-    }
+fn default_zulip_topic() -> String {
+    "{project}".to_string()
+}
 
-    if config.default_channels.is_empty() {
-        return Err(ConfigError::InvalidConfig(
-            "default_channels must include at least one channel id".to_string(),
-        ));
+fn default_ntfy_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+fn default_idempotency_header() -> String {
+    "Idempotency-Key".to_string()
+}
+
+/// Deserializes a webhook `url`, which is either one URL or a list of them.
+fn deserialize_urls<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Urls {
+        One(String),
+        Many(Vec<String>),
     }
 
-    for channel_id in &config.default_channels {
-        if !config.channels.contains_key(channel_id) {
-            return Err(ConfigError::InvalidConfig(format!(
-                "default channel `{channel_id}` is not defined in channels"
-            )));
-        }
+    Ok(match Urls::deserialize(deserializer)? {
+        Urls::One(url) => vec![url],
+        Urls::Many(urls) => urls,
+    })
+}
+
+/// Deserializes a Telegram `chat_id`, which YAML gives as a number for chat
+/// IDs and a string for `@channelname`.
+fn deserialize_chat_id<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ChatId {
+        Id(i64),
+        Name(String),
     }
 
-    Ok(())
+    Ok(match ChatId::deserialize(deserializer)? {
+        ChatId::Id(id) => id.to_string(),
+        ChatId::Name(name) => name,
+    })
 }
 
-fn default_http_method() -> String {
-    "POST".to_string()
+/// Turns a YAML error into a positioned diagnostic, suggesting the closest
+/// expected name for unknown fields and variants.
+///
+/// `positions_valid` is false once the YAML has been rewritten; the position is
+/// then looked up from the error's field path in `source` instead.
+fn syntax_error(error: serde_yaml::Error, source: &str, positions_valid: bool) -> ConfigError {
+    let Some(location) = error.location() else {
+        return ConfigError::ParseFailed(error);
+    };
+
+    let full = error.to_string();
+    let message = full
+        .strip_suffix(&format!(
+            " at line {} column {}",
+            location.line(),
+            location.column()
+        ))
+        .unwrap_or(&full);
+    let field = message
+        .split_once(": ")
+        .map(|(path, _)| path)
+        .filter(|path| !path.contains(' '))
+        .map(str::to_string);
+
+    let unknown =
+        Regex::new(r"unknown (?:field|variant) `([^`]+)`, (?:expected (.*)|there are no fields)$")
+            .expect("valid unknown-name regex");
+    let suggestion = unknown.captures(message).and_then(|captures| {
+        let expected = captures.get(2).map_or("", |expected| expected.as_str());
+        let mut candidates = expected.split('`').skip(1).step_by(2).collect::<Vec<_>>();
+        // unknown fields in a channel are reported against its type-specific
+        // settings, which do not list the shared ones.
+        if message.starts_with("channels.") {
+            candidates.extend(Channel::SHARED_FIELDS);
+        }
+        closest_match(&captures[1], candidates).map(str::to_string)
+    });
+    let channel_id = message
+        .strip_prefix("channels.")
+        .and_then(|rest| rest.split([':', '.']).next())
+        .map(str::to_string);
+
+    let diagnostic = ConfigDiagnostic {
+        message: message.to_string(),
+        field,
+        channel_id,
+        suggestion,
+        ..Default::default()
+    };
+    let diagnostic = if positions_valid {
+        ConfigDiagnostic {
+            line: Some(location.line()),
+            column: Some(location.column()),
+            ..diagnostic
+        }
+    } else {
+        diagnostic.locate(source)
+    };
+    ConfigError::Syntax(Box::new(diagnostic))
 }
 
-fn interpolate_env_values(config: &mut Config) -> Result<(), ConfigError> {
+fn interpolate_values(config: &mut Config) -> Result<(), ConfigError> {
+    let mut secrets = SecretResolver::new(&config.secrets);
+    for value in config.http.headers.values_mut() {
+        *value = interpolate(value, &mut secrets)?;
+    }
+    if let Some(proxy) = &mut config.http.proxy {
+        *proxy = interpolate(proxy, &mut secrets)?;
+    }
     for channel in config.channels.values_mut() {
-        match channel {
+        for step in &mut channel.pipeline {
+            if let Middleware::Enrich(labels) = step {
+                for value in labels.values_mut() {
+                    *value = interpolate(value, &mut secrets)?;
+                }
+            }
+        }
+        match &mut channel.kind {
             ChannelConfig::Desktop(_) => {}
             ChannelConfig::Webhook(webhook) => {
-                webhook.url = interpolate_env(&webhook.url)?;
-                webhook.method = interpolate_env(&webhook.method)?;
+                for url in &mut webhook.url {
+                    *url = interpolate(url, &mut secrets)?;
+                }
+                webhook.method = interpolate(&webhook.method, &mut secrets)?;
+                webhook.idempotency_header =
+                    interpolate(&webhook.idempotency_header, &mut secrets)?;
                 for value in webhook.headers.values_mut() {
-                    *value = interpolate_env(value)?;
+                    *value = interpolate(value, &mut secrets)?;
+                }
+                for value in webhook.extra.values_mut() {
+                    *value = interpolate(value, &mut secrets)?;
+                }
+                for recipient in &mut webhook.encrypt_to {
+                    *recipient = interpolate(recipient, &mut secrets)?;
+                }
+                if let Some(proxy) = &mut webhook.proxy {
+                    *proxy = interpolate(proxy, &mut secrets)?;
+                }
+            }
+            ChannelConfig::Slack(slack) => {
+                slack.url = interpolate(&slack.url, &mut secrets)?;
+            }
+            ChannelConfig::GoogleChat(chat) => {
+                chat.url = interpolate(&chat.url, &mut secrets)?;
+            }
+            ChannelConfig::Telegram(telegram) => {
+                telegram.bot_token = interpolate(&telegram.bot_token, &mut secrets)?;
+                telegram.chat_id = interpolate(&telegram.chat_id, &mut secrets)?;
+            }
+            ChannelConfig::Pushover(pushover) => {
+                pushover.user_key = interpolate(&pushover.user_key, &mut secrets)?;
+                pushover.api_token = interpolate(&pushover.api_token, &mut secrets)?;
+            }
+            ChannelConfig::Mqtt(mqtt) => {
+                mqtt.broker = interpolate(&mqtt.broker, &mut secrets)?;
+                for value in [&mut mqtt.username, &mut mqtt.password]
+                    .into_iter()
+                    .flatten()
+                {
+                    *value = interpolate(value, &mut secrets)?;
+                }
+            }
+            ChannelConfig::Zulip(zulip) => {
+                zulip.bot_email = interpolate(&zulip.bot_email, &mut secrets)?;
+                zulip.api_key = interpolate(&zulip.api_key, &mut secrets)?;
+            }
+            ChannelConfig::Healthchecks(healthchecks) => {
+                healthchecks.check = interpolate(&healthchecks.check, &mut secrets)?;
+            }
+            ChannelConfig::Url(url) => {
+                url.target = interpolate(&url.target, &mut secrets)?;
+            }
+            ChannelConfig::Ntfy(ntfy) => {
+                ntfy.topic = interpolate(&ntfy.topic, &mut secrets)?;
+                for value in [&mut ntfy.token, &mut ntfy.username, &mut ntfy.password]
+                    .into_iter()
+                    .flatten()
+                {
+                    *value = interpolate(value, &mut secrets)?;
                 }
             }
             ChannelConfig::Custom(custom) => {
-                custom.exec = interpolate_env(&custom.exec)?;
+                custom.exec = interpolate(&custom.exec, &mut secrets)?;
                 for arg in &mut custom.args {
-                    *arg = interpolate_env(arg)?;
+                    *arg = interpolate(arg, &mut secrets)?;
                 }
                 for value in custom.env.values_mut() {
-                    *value = interpolate_env(value)?;
+                    *value = interpolate(value, &mut secrets)?;
+                }
+                for value in custom.env_files.values_mut() {
+                    *value = interpolate(value, &mut secrets)?;
+                }
+                for value in custom.extra.values_mut() {
+                    *value = interpolate(value, &mut secrets)?;
                 }
             }
         }
     }
 
+    for alias in config.aliases.values_mut() {
+        if let Some(cwd) = &mut alias.cwd {
+            *cwd = PathBuf::from(interpolate(&cwd.to_string_lossy(), &mut secrets)?);
+        }
+        for value in alias.env.values_mut() {
+            *value = interpolate(value, &mut secrets)?;
+        }
+    }
+
     Ok(())
 }
 
-fn interpolate_env(value: &str) -> Result<String, ConfigError> {
+/// Flags custom channel arguments that interpolate a secret: argv is visible
+/// to every user on the machine through `ps`. Runs before interpolation.
+fn secret_argument_warnings(config: &Config) -> Vec<String> {
+    config
+        .channels
+        .iter()
+        .filter_map(|(channel_id, channel)| match &channel.kind {
+            ChannelConfig::Custom(custom)
+                if custom.args.iter().any(|arg| arg.contains("${secret:")) =>
+            {
+                Some(format!(
+                    "`channels.{channel_id}.args` interpolates a secret, which is visible to \
+                     other processes; pass it through `env` or `env_files` instead"
+                ))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Expands `${env:NAME}` and `${secret:NAME}` references in a config value.
+fn interpolate(value: &str, secrets: &mut SecretResolver) -> Result<String, ConfigError> {
     let mut output = String::new();
     let mut rest = value;
 
     loop {
-        let Some(start) = rest.find("${env:") else {
+        let next = ["${env:", "${secret:", "${hostname}", "${user}", "${os}"]
+            .into_iter()
+            .filter_map(|prefix| rest.find(prefix).map(|start| (start, prefix)))
+            .min();
+        let Some((start, prefix)) = next else {
             output.push_str(rest);
             break;
         };
 
         output.push_str(&rest[..start]);
-        let placeholder = &rest[start + 6..];
+        let placeholder = &rest[start + prefix.len()..];
+        if let Some(builtin) = builtin_variable(prefix) {
+            output.push_str(&builtin);
+            rest = placeholder;
+            continue;
+        }
+
         let Some(end) = placeholder.find('}') else {
             return Err(ConfigError::InvalidInterpolation(value.to_string()));
         };
 
-        let env_name = &placeholder[..end];
-        if env_name.is_empty() {
+        let name = &placeholder[..end];
+        if name.is_empty() {
             return Err(ConfigError::InvalidInterpolation(value.to_string()));
         }
 
-        let env_value = std::env::var(env_name)
-            .map_err(|_| ConfigError::MissingEnvironmentVariable(env_name.to_string()))?;
-        output.push_str(&env_value);
+        if prefix == "${env:" {
+            let env_value = std::env::var(name)
+                .map_err(|_| ConfigError::MissingEnvironmentVariable(name.to_string()))?;
+            output.push_str(&env_value);
+        } else {
+            output.push_str(&secrets.get(name)?);
+        }
         rest = &placeholder[end + 1..];
     }
 
     Ok(output)
 }
 
+/// Value of a built-in `${hostname}`, `${user}` or `${os}` placeholder.
+fn builtin_variable(placeholder: &str) -> Option<String> {
+    match placeholder {
+        "${hostname}" => Some(host_name()),
+        "${user}" => Some(
+            ["USER", "USERNAME"]
+                .into_iter()
+                .find_map(|name| std::env::var(name).ok().filter(|user| !user.is_empty()))
+                .unwrap_or_else(|| "unknown-user".to_string()),
+        ),
+        "${os}" => Some(std::env::consts::OS.to_string()),
+        _ => None,
+    }
+}
+
+const FALLBACK_CONFIG_YAML: &str = "version: 1
+default_channels: [desktop]
+channels:
+  desktop:
+    type: desktop
+";
+
 fn default_config_yaml() -> &'static str {
     include_str!("../assets/default-config.yml")
 }
@@ -259,9 +3752,463 @@ fn default_config_yaml() -> &'static str {
 ///
 /// This helper is used by integration tests.
 pub fn load_config_from_path(path: &Path) -> Result<Config, ConfigError> {
+    load_config_from_path_with(path, LoadOptions::default()).map(|loaded| loaded.config)
+}
+
+/// Notices edits to a config file, for processes that outlive one run.
+#[derive(Debug, Clone)]
+pub struct ConfigWatch {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatch {
+    /// Starts watching `path` from its current state.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = modified_time(&path);
+        Self { path, modified }
+    }
+
+    /// Whether the file was modified, created or removed since the last call.
+    pub fn changed(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Loads and validates config from a specific path with explicit options.
+pub fn load_config_from_path_with(
+    path: &Path,
+    options: LoadOptions,
+) -> Result<LoadedConfig, ConfigError> {
     let raw = fs::read_to_string(path)?;
-    let mut config: Config = serde_yaml::from_str(&raw)?;
-    interpolate_env_values(&mut config)?;
+    let parsed = parse_config(&raw, options)?;
+    check_environments(&parsed, path, &raw)?;
+    let mut warnings = parsed.warnings;
+    warnings.extend(secret_argument_warnings(&parsed.config));
+    let config = finish_config(parsed.config, path).map_err(|error| match error {
+        ConfigError::Invalid(diagnostic) => ConfigError::Invalid(Box::new(diagnostic.locate(&raw))),
+        error => error,
+    })?;
+
+    Ok(LoadedConfig {
+        path: path.to_path_buf(),
+        config,
+        warnings,
+        fallback: false,
+    })
+}
+
+/// Fills in interpolated values and `type: url` channels, resolves alias
+/// directories against the config file's and validates the result.
+fn finish_config(mut config: Config, path: &Path) -> Result<Config, ConfigError> {
+    interpolate_values(&mut config)?;
+    resolve_url_channels(&mut config)?;
+    if let Some(dir) = path.parent() {
+        for alias in config.aliases.values_mut() {
+            if let Some(cwd) = &mut alias.cwd
+                && cwd.is_relative()
+            {
+                *cwd = dir.join(&*cwd);
+            }
+        }
+    }
     validate_config(&config)?;
     Ok(config)
 }
+
+/// Validates every `environments` block merged over the rest of the file,
+/// whichever one applies, so a block's mistake shows up before brb runs
+/// where it is used. Problems the rest of the file has too are reported
+/// where they are; the others point into the block. A block whose
+/// `${env:…}` or `${secret:…}` values can't be filled in here is only
+/// checked as far as its fields parse.
+fn check_environments(parsed: &ParsedConfig, path: &Path, raw: &str) -> Result<(), ConfigError> {
+    let mut base = None;
+    for (name, config) in &parsed.environments {
+        let error = match finish_config(config.clone(), path) {
+            Ok(_) => continue,
+            Err(
+                ConfigError::MissingEnvironmentVariable(_)
+                | ConfigError::UnknownSecret(_)
+                | ConfigError::SecretFailed { .. },
+            ) => continue,
+            Err(error) => error,
+        };
+        let base = base.get_or_insert_with(|| {
+            parsed
+                .base
+                .clone()
+                .map(|config| finish_config(config, path))
+        });
+        return Err(match (base, error) {
+            (Some(Err(ConfigError::Invalid(base))), ConfigError::Invalid(diagnostic))
+                if base.message == diagnostic.message =>
+            {
+                ConfigError::Invalid(Box::new((**base).clone().locate(raw)))
+            }
+            (_, ConfigError::Invalid(diagnostic)) => in_environment(
+                ConfigError::Invalid(Box::new(locate_in_environment(*diagnostic, raw, name))),
+                name,
+            ),
+            (_, error) => error,
+        });
+    }
+    Ok(())
+}
+
+/// Positions a problem found with `environments.{name}` applied at its field
+/// inside the block, or at the block when the field is not set there.
+fn locate_in_environment(diagnostic: ConfigDiagnostic, raw: &str, name: &str) -> ConfigDiagnostic {
+    let field = diagnostic.field.clone();
+    let scoped = [
+        field
+            .as_ref()
+            .map(|field| format!("environments.{name}.{field}")),
+        Some(format!("environments.{name}")),
+    ];
+    for scope in scoped.into_iter().flatten() {
+        let located = ConfigDiagnostic {
+            field: Some(scope),
+            ..diagnostic.clone()
+        }
+        .locate(raw);
+        if located.line.is_some() {
+            return ConfigDiagnostic { field, ..located };
+        }
+    }
+    diagnostic
+}
+
+/// Replaces each `type: url` channel with the channel its target describes.
+/// Targets often carry tokens, so errors never echo them.
+fn resolve_url_channels(config: &mut Config) -> Result<(), ConfigError> {
+    for (channel_id, channel) in &mut config.channels {
+        let ChannelConfig::Url(url) = &channel.kind else {
+            continue;
+        };
+        let problem = |message: String| -> ConfigError {
+            ConfigDiagnostic::new(
+                format!("channels.{channel_id}.target"),
+                format!("channel `{channel_id}` has {message}"),
+            )
+            .channel(channel_id)
+            .into()
+        };
+
+        let imported = import_target(&url.target)
+            .map_err(|error| problem(format!("an unusable target ({error})")))?;
+        channel.kind = serde_yaml::from_value(serde_yaml::Value::Mapping(imported.fields))
+            .map_err(|error| problem(format!("an unusable target ({error})")))?;
+    }
+    Ok(())
+}
+
+/// Reads config YAML into a single value. `<<` merge keys are applied,
+/// documents separated by `---` are merged in order, later mappings
+/// overriding earlier keys recursively, and top-level `x-` keys (places to
+/// define anchors) are dropped. The flag is `true` when any of these changed
+/// the structure, so positions in the value no longer match the file.
+pub fn config_value(raw: &str) -> Result<(serde_yaml::Value, bool), serde_yaml::Error> {
+    let mut documents = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(raw) {
+        documents.push(serde_yaml::Value::deserialize(document)?);
+    }
+
+    let multiple = documents.len() > 1;
+    let mut merged_keys = false;
+    let mut value = serde_yaml::Value::Null;
+    for mut document in documents {
+        let before = document.clone();
+        document.apply_merge()?;
+        merged_keys |= document != before;
+        merge_documents(&mut value, document);
+    }
+
+    let mut extensions = false;
+    if let serde_yaml::Value::Mapping(mapping) = &mut value {
+        let before = mapping.len();
+        mapping.retain(|key, _| !key.as_str().is_some_and(|key| key.starts_with("x-")));
+        extensions = mapping.len() != before;
+    }
+    Ok((value, multiple || merged_keys || extensions))
+}
+
+fn merge_documents(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    match (base, overlay) {
+        (_, serde_yaml::Value::Null) => {}
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_documents(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Takes the top-level `environments` mapping out of `value`, checking that
+/// every block is named after a [`RunEnvironment`]. `None` when there is no
+/// such mapping.
+fn take_environments(
+    value: &mut serde_yaml::Value,
+) -> Result<Option<serde_yaml::Mapping>, ConfigError> {
+    let Some(environments) = value
+        .as_mapping_mut()
+        .and_then(|mapping| mapping.remove("environments"))
+    else {
+        return Ok(None);
+    };
+    let serde_yaml::Value::Mapping(blocks) = environments else {
+        return Err(ConfigDiagnostic::new(
+            "environments",
+            format!(
+                "environments must map {} to config overrides",
+                RunEnvironment::NAMES.join(", ")
+            ),
+        )
+        .into());
+    };
+    for name in blocks.keys() {
+        let name = name.as_str().unwrap_or_default();
+        if !RunEnvironment::NAMES.contains(&name) {
+            return Err(ConfigDiagnostic::new(
+                format!("environments.{name}"),
+                format!(
+                    "unknown environment `{name}` (expected one of {})",
+                    RunEnvironment::NAMES.join(", ")
+                ),
+            )
+            .value(name)
+            .suggest(RunEnvironment::NAMES)
+            .into());
+        }
+    }
+    Ok(Some(blocks))
+}
+
+/// `raw` with its top-level `environments` block blanked out line for line,
+/// so the rest of the file parses with its positions intact. `None` unless
+/// what remains reads back as `base`.
+fn without_environments(raw: &str, base: &serde_yaml::Value) -> Option<String> {
+    let mut inside = false;
+    let lines = raw
+        .lines()
+        .map(|line| {
+            if line.starts_with("environments:") {
+                inside = true;
+            } else if inside && !line.is_empty() && !line.starts_with([' ', '\t', '#']) {
+                inside = false;
+            }
+            if inside { "" } else { line }
+        })
+        .collect::<Vec<_>>();
+
+    let source = lines.join("\n");
+    let (value, _) = config_value(&source).ok()?;
+    (value == *base).then_some(source)
+}
+
+/// Config YAML read into the config to use, plus what is needed to check
+/// the `environments` blocks that were not picked.
+struct ParsedConfig {
+    /// The config for the environment brb runs in.
+    config: Config,
+
+    /// Problems that did not stop parsing.
+    warnings: Vec<String>,
+
+    /// Every `environments` block, merged over the rest of the file.
+    environments: Vec<(String, Config)>,
+
+    /// The file without its `environments` blocks, when it has any.
+    base: Option<Config>,
+}
+
+/// Deserializes config YAML. Every `environments` block is checked merged
+/// over the rest of the file, as a later document would be, and the block
+/// for the environment brb runs in is the one used.
+fn parse_config(raw: &str, options: LoadOptions) -> Result<ParsedConfig, ConfigError> {
+    let (mut value, restructured) =
+        config_value(raw).map_err(|error| syntax_error(error, raw, true))?;
+    let blocks = take_environments(&mut value).map_err(|error| match error {
+        ConfigError::Invalid(diagnostic) => ConfigError::Invalid(Box::new(diagnostic.locate(raw))),
+        error => error,
+    })?;
+    let Some(blocks) = blocks else {
+        let source = (!restructured).then(|| raw.to_string());
+        let (config, warnings) = deserialize_config(value, source, raw, options.unknown_fields)?;
+        return Ok(ParsedConfig {
+            config,
+            warnings,
+            environments: Vec::new(),
+            base: None,
+        });
+    };
+
+    let base_source = if restructured {
+        None
+    } else {
+        without_environments(raw, &value)
+    };
+    let environment = match options.environment {
+        Some(environment) => environment,
+        None => RunEnvironment::detect()?,
+    };
+    let mut active = None;
+    let mut environments = Vec::new();
+    for (name, block) in blocks {
+        let name = name.as_str().unwrap_or_default();
+        let mut merged = value.clone();
+        merge_documents(&mut merged, block);
+        match deserialize_config(merged, None, raw, options.unknown_fields) {
+            Ok((config, warnings)) => {
+                if name == environment.name() {
+                    active = Some((config.clone(), warnings));
+                }
+                environments.push((name.to_string(), config));
+            }
+            Err(error) => {
+                // a problem outside the blocks is reported where it is.
+                let base = deserialize_config(
+                    value.clone(),
+                    base_source.clone(),
+                    raw,
+                    options.unknown_fields,
+                );
+                return Err(match base {
+                    Err(base) if same_problem(&base, &error) => base,
+                    _ => in_environment(error, name),
+                });
+            }
+        }
+    }
+
+    // the rest of the file may only be complete with a block applied.
+    let base = deserialize_config(value, base_source, raw, options.unknown_fields);
+    let (base, (config, warnings)) = match (active, base) {
+        (Some(active), base) => (base.ok().map(|(config, _)| config), active),
+        (None, base) => {
+            let base = base?;
+            (Some(base.0.clone()), base)
+        }
+    };
+    Ok(ParsedConfig {
+        config,
+        warnings,
+        environments,
+        base,
+    })
+}
+
+/// Deserializes `value`. `source` is the text it was read from, when that
+/// lines up with `raw`, and is parsed instead so errors carry exact positions.
+/// With `unknown_fields: warn`, each unknown field is removed and reported as
+/// a warning until the rest parses.
+fn deserialize_config(
+    mut value: serde_yaml::Value,
+    source: Option<String>,
+    raw: &str,
+    unknown_fields: Option<UnknownFields>,
+) -> Result<(Config, Vec<String>), ConfigError> {
+    let mode = unknown_fields.unwrap_or_else(|| {
+        value
+            .get("unknown_fields")
+            .and_then(|mode| serde_yaml::from_value(mode.clone()).ok())
+            .unwrap_or_default()
+    });
+
+    let mut warnings = Vec::new();
+    let exact = source.is_some();
+    let mut source = match source {
+        Some(source) => source,
+        None => serde_yaml::to_string(&value)?,
+    };
+    loop {
+        let error = match serde_yaml::from_str(&source) {
+            Ok(config) => return Ok((config, warnings)),
+            Err(error) => error,
+        };
+
+        // positions only match the file until a field has been removed.
+        let positions_valid = exact && warnings.is_empty();
+        if mode == UnknownFields::Error {
+            return Err(syntax_error(error, raw, positions_valid));
+        }
+        let Some((path, field)) = unknown_field(&error.to_string()) else {
+            return Err(syntax_error(error, raw, positions_valid));
+        };
+        if !remove_field(&mut value, &path, &field) {
+            return Err(syntax_error(error, raw, positions_valid));
+        }
+
+        let location = if path.is_empty() {
+            "top level".to_string()
+        } else {
+            format!("`{path}`")
+        };
+        warnings.push(format!("ignoring unknown field `{field}` in {location}"));
+        source = serde_yaml::to_string(&value)?;
+    }
+}
+
+fn same_problem(left: &ConfigError, right: &ConfigError) -> bool {
+    match (left, right) {
+        (ConfigError::Syntax(left), ConfigError::Syntax(right)) => left.message == right.message,
+        _ => false,
+    }
+}
+
+/// Says which `environments` block an error only shows up with.
+fn in_environment(error: ConfigError, name: &str) -> ConfigError {
+    let scoped = |diagnostic: Box<ConfigDiagnostic>| {
+        Box::new(ConfigDiagnostic {
+            message: format!("with environments.{name}: {}", diagnostic.message),
+            ..*diagnostic
+        })
+    };
+    match error {
+        ConfigError::Syntax(diagnostic) => ConfigError::Syntax(scoped(diagnostic)),
+        ConfigError::Invalid(diagnostic) => ConfigError::Invalid(scoped(diagnostic)),
+        error => error,
+    }
+}
+
+/// Extracts the dotted path and name from an unknown-field error message.
+fn unknown_field(message: &str) -> Option<(String, String)> {
+    let pattern =
+        Regex::new(r"^(?:([^\s:]+): )?unknown field `([^`]+)`").expect("valid unknown-field regex");
+    let captures = pattern.captures(message)?;
+    let path = captures.get(1).map_or("", |path| path.as_str());
+    Some((path.to_string(), captures[2].to_string()))
+}
+
+fn remove_field(value: &mut serde_yaml::Value, path: &str, field: &str) -> bool {
+    let mut current = value;
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        let Some(next) = current.get_mut(segment) else {
+            return false;
+        };
+        current = next;
+    }
+
+    current
+        .as_mapping_mut()
+        .and_then(|mapping| mapping.remove(field))
+        .is_some()
+}
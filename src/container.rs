@@ -0,0 +1,130 @@
+use crate::environment::{Environment, SystemEnvironment};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const SERVICE_ACCOUNT_NAMESPACE: &str = "var/run/secrets/kubernetes.io/serviceaccount/namespace";
+const SHORT_ID_LEN: usize = 12;
+
+/// The container or Kubernetes pod a run happened in, so notifications from
+/// ephemeral containers say more than a generated hostname.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContainerInfo {
+    /// `kubernetes`, `docker`, `podman`, or whatever the `container`
+    /// environment variable names (e.g. `lxc`).
+    pub runtime: String,
+
+    /// Short (12 digit) container ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+
+    /// Container name, when the runtime exposes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Image the container was started from, when the runtime exposes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+
+    /// Kubernetes pod name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pod: Option<String>,
+
+    /// Kubernetes namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// Kubernetes node, when exposed to the pod as `NODE_NAME`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+}
+
+/// Detects the container this process runs in, or `None` on a plain host.
+pub fn detect_container() -> Option<ContainerInfo> {
+    detect_container_in(&SystemEnvironment, Path::new("/"))
+}
+
+/// Detects the container from `environment` and the files under `root`:
+/// Kubernetes service variables, `/.dockerenv`, `/run/.containerenv`, the
+/// `container` variable and container IDs in `/proc/self/cgroup` or
+/// `/proc/self/mountinfo`.
+pub fn detect_container_in(environment: &dyn Environment, root: &Path) -> Option<ContainerInfo> {
+    let var = |name: &str| {
+        environment
+            .var(name)
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    let read = |path: &str| fs::read_to_string(root.join(path)).ok();
+
+    let cgroup = read("proc/self/cgroup").unwrap_or_default();
+    // mountinfo also lists image layer IDs; only the container's own
+    // `/containers/<id>/` files give its ID away.
+    let id = container_id(&cgroup).or_else(|| {
+        let mountinfo = read("proc/self/mountinfo")?;
+        mountinfo
+            .lines()
+            .filter(|line| line.contains("containers/"))
+            .find_map(container_id)
+    });
+
+    if var("KUBERNETES_SERVICE_HOST").is_some() {
+        return Some(ContainerInfo {
+            runtime: "kubernetes".to_string(),
+            id,
+            pod: var("POD_NAME")
+                .or_else(|| var("HOSTNAME"))
+                .or_else(|| Some(environment.hostname())),
+            namespace: var("POD_NAMESPACE").or_else(|| {
+                read(SERVICE_ACCOUNT_NAMESPACE)
+                    .map(|namespace| namespace.trim().to_string())
+                    .filter(|namespace| !namespace.is_empty())
+            }),
+            node: var("NODE_NAME"),
+            ..Default::default()
+        });
+    }
+
+    if let Some(containerenv) = read("run/.containerenv") {
+        let field = |key: &str| containerenv_field(&containerenv, key);
+        return Some(ContainerInfo {
+            runtime: "podman".to_string(),
+            id: field("id")
+                .map(|id| id.chars().take(SHORT_ID_LEN).collect())
+                .or(id),
+            name: field("name"),
+            image: field("image"),
+            ..Default::default()
+        });
+    }
+
+    let runtime = if root.join(".dockerenv").exists() || cgroup.contains("/docker") {
+        "docker".to_string()
+    } else if cgroup.contains("libpod") {
+        "podman".to_string()
+    } else {
+        var("container")?
+    };
+    Some(ContainerInfo {
+        runtime,
+        id,
+        ..Default::default()
+    })
+}
+
+/// The first 64 digit hex container ID in cgroup or mount paths such as
+/// `/docker/<id>` or `/var/lib/docker/containers/<id>/hostname`, shortened.
+fn container_id(text: &str) -> Option<String> {
+    text.split(|c: char| !c.is_ascii_hexdigit())
+        .find(|run| run.len() == 64)
+        .map(|id| id[..SHORT_ID_LEN].to_ascii_lowercase())
+}
+
+/// A `key="value"` line from podman's `/run/.containerenv`.
+fn containerenv_field(containerenv: &str, key: &str) -> Option<String> {
+    containerenv.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        let value = value.trim().trim_matches('"');
+        (name.trim() == key && !value.is_empty()).then(|| value.to_string())
+    })
+}
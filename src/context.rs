@@ -0,0 +1,167 @@
+use crate::config::ContextConfig;
+use serde::{Deserialize, Serialize};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::Command;
+use std::time::Duration;
+
+const DEFAULT_NETWORK_TARGET: &str = "1.1.1.1:443";
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(1);
+const LOW_DISK_BYTES: u64 = 1024 * 1024 * 1024;
+const LOW_BATTERY_PERCENT: u8 = 20;
+
+/// Machine state sampled at completion to explain unexpected failures.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineContext {
+    /// Whether the machine is running on battery power.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_battery: Option<bool>,
+
+    /// Battery charge percentage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub battery_percent: Option<u8>,
+
+    /// Free bytes on the working directory's volume.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_free_bytes: Option<u64>,
+
+    /// Whether the network probe target accepted a connection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network_reachable: Option<bool>,
+
+    /// Human-readable warnings derived from the samples above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Samples the enabled context sources, or returns `None` when none are enabled.
+pub fn sample_context(config: &ContextConfig) -> Option<MachineContext> {
+    if !config.battery && !config.disk && !config.network {
+        return None;
+    }
+
+    let mut context = MachineContext::default();
+
+    if config.battery
+        && let Some((on_battery, percent)) = battery_state()
+    {
+        context.on_battery = Some(on_battery);
+        context.battery_percent = percent;
+    }
+
+    if config.disk {
+        context.disk_free_bytes = disk_free_bytes();
+    }
+
+    if config.network {
+        let target = config
+            .network_target
+            .as_deref()
+            .unwrap_or(DEFAULT_NETWORK_TARGET);
+        context.network_reachable = Some(network_reachable(target));
+    }
+
+    context.warnings = context_warnings(config, &context);
+    Some(context)
+}
+
+/// Derives warnings for low disk, low battery and unreachable network.
+pub fn context_warnings(config: &ContextConfig, context: &MachineContext) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if let Some(free) = context.disk_free_bytes
+        && free < config.low_disk.unwrap_or(LOW_DISK_BYTES)
+    {
+        warnings.push(format!("low disk space ({} MB free)", free / (1024 * 1024)));
+    }
+
+    if context.on_battery == Some(true) {
+        match context.battery_percent {
+            Some(percent) if percent < LOW_BATTERY_PERCENT => {
+                warnings.push(format!("low battery ({percent}%)"));
+            }
+            Some(percent) => warnings.push(format!("running on battery ({percent}%)")),
+            None => warnings.push("running on battery".to_string()),
+        }
+    }
+
+    if context.network_reachable == Some(false) {
+        warnings.push("network unreachable".to_string());
+    }
+
+    warnings
+}
+
+fn network_reachable(target: &str) -> bool {
+    let Ok(addresses) = target.to_socket_addrs() else {
+        return false;
+    };
+
+    addresses
+        .into_iter()
+        .any(|address| TcpStream::connect_timeout(&address, NETWORK_TIMEOUT).is_ok())
+}
+
+#[cfg(unix)]
+fn disk_free_bytes() -> Option<u64> {
+    let output = Command::new("df").args(["-Pk", "."]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // POSIX `df -P` output: header line, then `fs blocks used available ...`.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let available_kb = stdout
+        .lines()
+        .nth(1)?
+        .split_whitespace()
+        .nth(3)?
+        .parse::<u64>()
+        .ok()?;
+    Some(available_kb * 1024)
+}
+
+#[cfg(not(unix))]
+fn disk_free_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn battery_state() -> Option<(bool, Option<u8>)> {
+    let entries = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let kind = std::fs::read_to_string(path.join("type")).unwrap_or_default();
+        if kind.trim() != "Battery" {
+            continue;
+        }
+
+        let status = std::fs::read_to_string(path.join("status")).unwrap_or_default();
+        let percent = std::fs::read_to_string(path.join("capacity"))
+            .ok()
+            .and_then(|capacity| capacity.trim().parse::<u8>().ok());
+        return Some((status.trim() == "Discharging", percent));
+    }
+
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn battery_state() -> Option<(bool, Option<u8>)> {
+    let output = Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !stdout.contains("InternalBattery") {
+        return None;
+    }
+
+    let on_battery = stdout.contains("'Battery Power'");
+    let percent = stdout
+        .split_whitespace()
+        .find_map(|word| word.trim_end_matches(';').strip_suffix('%'))
+        .and_then(|percent| percent.parse::<u8>().ok());
+    Some((on_battery, percent))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn battery_state() -> Option<(bool, Option<u8>)> {
+    None
+}
@@ -0,0 +1,230 @@
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+use std::collections::HashMap;
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+use std::sync::{Arc, Mutex, OnceLock, mpsc};
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+use std::time::{Duration, Instant};
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+use zbus::blocking::{Connection, MessageIterator};
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+use zbus::zvariant::Value;
+
+/// Bus name and interface of the freedesktop notification server.
+pub const NOTIFICATIONS: &str = "org.freedesktop.Notifications";
+
+/// Object path the notification server is reached at.
+pub const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+
+/// A freedesktop notification request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Notification {
+    /// Notification title.
+    pub summary: String,
+
+    /// Notification body.
+    pub body: String,
+
+    /// Action keys and their labels; `default` is a click on the notification.
+    pub actions: Vec<(String, String)>,
+
+    /// String hints, such as `x-dunst-stack-tag`.
+    pub hints: Vec<(String, String)>,
+
+    /// `0` (low), `1` (normal) or `2` (critical).
+    pub urgency: u8,
+
+    /// Ask the server not to keep the notification in its history.
+    pub transient: bool,
+
+    /// Milliseconds until the notification expires; `-1` leaves it to the
+    /// server and `0` never expires.
+    pub expire_timeout: i32,
+}
+
+/// A signal the notification server sent about a notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signal {
+    /// The user picked one of the notification's actions.
+    ActionInvoked { id: u32, action: String },
+
+    /// The notification went away: `1` expired, `2` dismissed by the user,
+    /// `3` closed by a call, `4` undefined.
+    Closed { id: u32, reason: u32 },
+}
+
+impl Signal {
+    /// The notification this signal is about.
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::ActionInvoked { id, .. } | Self::Closed { id, .. } => *id,
+        }
+    }
+}
+
+/// Reads a notification server signal from its member name and body,
+/// ignoring signals other than `ActionInvoked` and `NotificationClosed`.
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+pub fn parse_signal(message: &zbus::Message) -> Option<Signal> {
+    let header = message.header();
+    if header.interface()?.as_str() != NOTIFICATIONS {
+        return None;
+    }
+
+    let body = message.body();
+    match header.member()?.as_str() {
+        "ActionInvoked" => {
+            let (id, action) = body.deserialize::<(u32, String)>().ok()?;
+            Some(Signal::ActionInvoked { id, action })
+        }
+        "NotificationClosed" => {
+            let (id, reason) = body.deserialize::<(u32, u32)>().ok()?;
+            Some(Signal::Closed { id, reason })
+        }
+        _ => None,
+    }
+}
+
+/// A notification that has been shown, whose signals can still be waited
+/// for.
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+pub struct Watch {
+    id: u32,
+    received: mpsc::Receiver<Signal>,
+}
+
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+impl Watch {
+    /// Waits up to `wait` for the notification to be acted on or closed.
+    /// Returns `None` when neither happened in time.
+    pub fn wait(self, wait: Duration) -> Option<Signal> {
+        let deadline = Instant::now() + wait;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let signal = self.received.recv_timeout(remaining).ok()?;
+            if signal.id() == self.id {
+                return Some(signal);
+            }
+        }
+    }
+}
+
+/// The session bus connection notifications are sent over, shared by every
+/// notification `brb` shows, with one thread passing notification signals on
+/// to whoever is waiting for them.
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+pub struct SessionBus {
+    connection: Connection,
+    listeners: OnceLock<Result<Listeners, String>>,
+}
+
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+type Listeners = Arc<Mutex<Vec<mpsc::Sender<Signal>>>>;
+
+/// Connects to the session bus the first time it is needed. The error says
+/// why there is none, as over plain SSH.
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+pub fn session_bus() -> Result<&'static SessionBus, String> {
+    static BUS: OnceLock<Result<SessionBus, String>> = OnceLock::new();
+    BUS.get_or_init(|| {
+        Connection::session()
+            .map(|connection| SessionBus {
+                connection,
+                listeners: OnceLock::new(),
+            })
+            .map_err(|error| format!("failed to connect to the D-Bus session bus: {error}"))
+    })
+    .as_ref()
+    .map_err(Clone::clone)
+}
+
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+impl SessionBus {
+    /// Shows `notification` without waiting for a response. The server's
+    /// reply confirms it was accepted.
+    pub fn notify(&self, notification: &Notification) -> Result<u32, String> {
+        notify(&self.connection, notification)
+    }
+
+    /// Shows `notification`, listening for its signals from before it is
+    /// sent so a quick click cannot be missed.
+    pub fn notify_and_watch(&self, notification: &Notification) -> Result<Watch, String> {
+        let listeners = self
+            .listeners
+            .get_or_init(|| self.listen())
+            .as_ref()
+            .map_err(Clone::clone)?;
+        let (signals, received) = mpsc::channel();
+        if let Ok(mut listeners) = listeners.lock() {
+            listeners.push(signals);
+        }
+
+        let id = self.notify(notification)?;
+        Ok(Watch { id, received })
+    }
+
+    /// Subscribes to notification signals and starts the thread that passes
+    /// them on. Listeners that stopped waiting are dropped at the next signal.
+    fn listen(&self) -> Result<Listeners, String> {
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface(NOTIFICATIONS)
+            .and_then(|rule| rule.path(OBJECT_PATH))
+            .map(|rule| rule.build())
+            .map_err(|error| format!("invalid D-Bus match rule: {error}"))?;
+        let messages = MessageIterator::for_match_rule(rule, &self.connection, None)
+            .map_err(|error| format!("failed to subscribe to notification signals: {error}"))?;
+
+        let listeners = Listeners::default();
+        let passed_on = Arc::clone(&listeners);
+        std::thread::spawn(move || {
+            for message in messages.flatten() {
+                let Some(signal) = parse_signal(&message) else {
+                    continue;
+                };
+                if let Ok(mut listeners) = passed_on.lock() {
+                    listeners.retain(|listener| listener.send(signal.clone()).is_ok());
+                }
+            }
+        });
+        Ok(listeners)
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+fn notify(connection: &Connection, notification: &Notification) -> Result<u32, String> {
+    let actions = notification
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+        .collect::<Vec<_>>();
+    let mut hints = HashMap::from([("urgency", Value::from(notification.urgency))]);
+    if notification.transient {
+        hints.insert("transient", Value::from(true));
+    }
+    for (name, value) in &notification.hints {
+        hints.insert(name.as_str(), Value::from(value.as_str()));
+    }
+
+    let reply = connection
+        .call_method(
+            Some(NOTIFICATIONS),
+            OBJECT_PATH,
+            Some(NOTIFICATIONS),
+            "Notify",
+            &(
+                "brb",
+                0u32,
+                "",
+                notification.summary.as_str(),
+                notification.body.as_str(),
+                actions,
+                hints,
+                notification.expire_timeout,
+            ),
+        )
+        .map_err(|error| format!("notification server rejected the notification: {error}"))?;
+    reply
+        .body()
+        .deserialize::<u32>()
+        .map_err(|error| format!("unexpected reply from notification server: {error}"))
+}
@@ -0,0 +1,178 @@
+use std::fmt;
+
+/// A config problem tied to a field, with its position in the YAML source and
+/// a "did you mean" suggestion when one is close enough.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDiagnostic {
+    /// What is wrong, e.g. "default channel `slak` is not defined in channels".
+    pub message: String,
+
+    /// Dotted path of the offending field, e.g. `channels.ci.template`.
+    pub field: Option<String>,
+
+    /// Channel the problem belongs to, when it is channel-scoped.
+    pub channel_id: Option<String>,
+
+    /// Offending value, used to pinpoint the column in the source.
+    pub value: Option<String>,
+
+    /// 1-based line in the config file.
+    pub line: Option<usize>,
+
+    /// 1-based column in the config file.
+    pub column: Option<usize>,
+
+    /// Closest valid alternative to the offending value.
+    pub suggestion: Option<String>,
+}
+
+impl ConfigDiagnostic {
+    /// Starts a diagnostic for `field`.
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            field: Some(field.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Records the channel the problem belongs to.
+    pub fn channel(mut self, channel_id: &str) -> Self {
+        self.channel_id = Some(channel_id.to_string());
+        self
+    }
+
+    /// Records the offending value.
+    pub fn value(mut self, value: &str) -> Self {
+        self.value = Some(value.to_string());
+        self
+    }
+
+    /// Suggests the closest of `candidates` to the offending value, if any.
+    pub fn suggest<'a>(mut self, candidates: impl IntoIterator<Item = &'a str>) -> Self {
+        if let Some(value) = &self.value {
+            self.suggestion = closest_match(value, candidates).map(str::to_string);
+        }
+        self
+    }
+
+    /// Fills in the line and column by finding the field (and value) in the
+    /// YAML source. Positions already set are kept.
+    pub fn locate(mut self, source: &str) -> Self {
+        if self.line.is_none()
+            && let Some(field) = &self.field
+            && let Some((line, column)) = locate_field(source, field, self.value.as_deref())
+        {
+            self.line = Some(line);
+            self.column = Some(column);
+        }
+        self
+    }
+}
+
+impl fmt::Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, " (line {line}, column {column})")?,
+            (Some(line), None) => write!(f, " (line {line})")?,
+            _ => {}
+        }
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "; did you mean `{suggestion}`?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the candidate closest to `input` by edit distance, if it is close
+/// enough to plausibly be a typo.
+pub fn closest_match<'a>(
+    input: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let limit = input.chars().count().div_ceil(3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != input)
+        .map(|candidate| (edit_distance(input, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right = right.chars().collect::<Vec<_>>();
+    let mut previous = (0..=right.len()).collect::<Vec<_>>();
+
+    for (i, left_ch) in left.chars().enumerate() {
+        let mut current = vec![i + 1; right.len() + 1];
+        for (j, right_ch) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(left_ch != *right_ch);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[right.len()]
+}
+
+/// Finds a dotted block-mapping path (e.g. `channels.ci.template`) in YAML
+/// source by indentation, then the value within that entry when given.
+/// Returns a 1-based line and column.
+fn locate_field(source: &str, field: &str, value: Option<&str>) -> Option<(usize, usize)> {
+    let lines = source.lines().collect::<Vec<_>>();
+    let mut start = 0;
+    let mut parent_indent = None;
+    let mut found = None;
+
+    for segment in field.split('.') {
+        let mut hit = None;
+        for (index, line) in lines.iter().enumerate().skip(start) {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let indent = line.len() - trimmed.len();
+            if parent_indent.is_some_and(|parent| indent <= parent) {
+                break;
+            }
+            let key = trimmed.trim_start_matches(['"', '\'']);
+            if key
+                .strip_prefix(segment)
+                .is_some_and(|rest| rest.trim_start_matches(['"', '\'']).starts_with(':'))
+            {
+                hit = Some((index, indent));
+                break;
+            }
+        }
+
+        let (index, indent) = hit?;
+        found = Some((index, indent));
+        start = index + 1;
+        parent_indent = Some(indent);
+    }
+
+    let (key_line, key_indent) = found?;
+    if let Some(value) = value {
+        for (index, line) in lines.iter().enumerate().skip(key_line) {
+            let trimmed = line.trim_start();
+            if index > key_line && !trimmed.is_empty() && line.len() - trimmed.len() <= key_indent {
+                break;
+            }
+
+            // skip the key itself on the entry's first line.
+            let offset = if index == key_line {
+                line.find(':').map_or(0, |colon| colon + 1)
+            } else {
+                0
+            };
+            if let Some(column) = line[offset..].find(value) {
+                return Some((index + 1, offset + column + 1));
+            }
+        }
+    }
+
+    Some((key_line + 1, key_indent + 1))
+}
@@ -0,0 +1,95 @@
+use crate::event::CompletionEvent;
+use crate::state::StateStore;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Summary of an earlier completion folded into a digest notification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DigestEntry {
+    /// Run ID of the held completion.
+    pub run_id: String,
+
+    /// `success` or `failure`.
+    pub status: String,
+
+    /// Command argv.
+    pub command: Vec<String>,
+
+    /// Wrapped command exit code.
+    pub exit_code: i32,
+
+    /// Total duration in milliseconds.
+    pub duration_ms: u128,
+
+    /// UTC finish timestamp (RFC3339).
+    pub finished_at: String,
+}
+
+/// Outcome of offering a completion to a digest channel.
+#[derive(Debug, Clone)]
+pub enum DigestDecision {
+    /// Deliver now; the event carries every completion held since the last send.
+    Send(Box<CompletionEvent>),
+
+    /// Held until the digest window elapses.
+    Hold,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DigestState {
+    /// RFC3339 timestamp of the last delivery.
+    last_sent_at: Option<String>,
+    pending: Vec<DigestEntry>,
+}
+
+/// Applies a channel's digest window to a completion.
+///
+/// The first completion after the window has elapsed is delivered along with
+/// all completions held since the previous delivery; successes inside the
+/// window are held. Failures are never held: they are delivered straight away
+/// and carry whatever was held before them, so a failure that ends a burst is
+/// not left waiting for a later run.
+pub fn apply_digest(
+    store: &StateStore,
+    channel_id: &str,
+    window: Duration,
+    event: &CompletionEvent,
+    now: DateTime<Utc>,
+) -> Result<DigestDecision, String> {
+    let name = format!("digest-{channel_id}");
+    let _lock = store.lock(&name).map_err(|error| error.to_string())?;
+    let mut state: DigestState = store.load(&name);
+
+    let window = chrono::TimeDelta::from_std(window).unwrap_or(chrono::TimeDelta::MAX);
+    let due = state
+        .last_sent_at
+        .as_deref()
+        .and_then(|last_sent_at| DateTime::parse_from_rfc3339(last_sent_at).ok())
+        .is_none_or(|last_sent_at| now - last_sent_at.with_timezone(&Utc) >= window);
+
+    if !due && event.exit_code == 0 {
+        state.pending.push(DigestEntry::from(event));
+        store.save(&name, &state)?;
+        return Ok(DigestDecision::Hold);
+    }
+
+    let mut digested = event.clone();
+    digested.digest = std::mem::take(&mut state.pending);
+    state.last_sent_at = Some(now.to_rfc3339());
+    store.save(&name, &state)?;
+    Ok(DigestDecision::Send(Box::new(digested)))
+}
+
+impl From<&CompletionEvent> for DigestEntry {
+    fn from(event: &CompletionEvent) -> Self {
+        Self {
+            run_id: event.run_id.clone(),
+            status: event.status.clone(),
+            command: event.command.clone(),
+            exit_code: event.exit_code,
+            duration_ms: event.duration_ms,
+            finished_at: event.finished_at.clone(),
+        }
+    }
+}
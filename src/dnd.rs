@@ -0,0 +1,111 @@
+use crate::digest::DigestEntry;
+use crate::event::CompletionEvent;
+use crate::state::StateStore;
+use std::env;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+/// Environment variable forcing do-not-disturb detection (`1` or `0`).
+pub const DND_ENV: &str = "BRB_DND";
+
+/// Whether the OS is in Do Not Disturb / Focus mode. `BRB_DND` overrides
+/// detection; when the state cannot be read, DnD is assumed off.
+pub fn dnd_active() -> bool {
+    match env::var(DND_ENV).as_deref() {
+        Ok("1" | "true") => return true,
+        Ok("0" | "false") => return false,
+        _ => {}
+    }
+    detect()
+}
+
+/// GNOME's notification banners switch and dunst's pause state.
+#[cfg(target_os = "linux")]
+fn detect() -> bool {
+    let read = |program: &str, args: &[&str]| {
+        Command::new(program)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    read(
+        "gsettings",
+        &["get", "org.gnome.desktop.notifications", "show-banners"],
+    )
+    .is_some_and(|banners| banners == "false")
+        || read("dunstctl", &["is-paused"]).is_some_and(|paused| paused == "true")
+}
+
+/// Focus modes record active assertions in the DoNotDisturb database.
+#[cfg(target_os = "macos")]
+fn detect() -> bool {
+    let Some(home) = env::var_os("HOME") else {
+        return false;
+    };
+    let path = std::path::Path::new(&home).join("Library/DoNotDisturb/DB/Assertions.json");
+    let Ok(raw) = std::fs::read_to_string(path) else {
+        // older releases only expose the legacy preference.
+        return Command::new("defaults")
+            .args([
+                "-currentHost",
+                "read",
+                "com.apple.notificationcenterui",
+                "doNotDisturb",
+            ])
+            .output()
+            .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "1");
+    };
+
+    serde_json::from_str::<serde_json::Value>(&raw)
+        .ok()
+        .and_then(|value| {
+            value["data"].as_array().map(|data| {
+                data.iter().any(|entry| {
+                    entry["storeAssertionRecords"]
+                        .as_array()
+                        .is_some_and(|records| !records.is_empty())
+                })
+            })
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn detect() -> bool {
+    false
+}
+
+/// Holds a completion for a desktop channel until do-not-disturb ends.
+pub fn queue_for_dnd(
+    store: &StateStore,
+    channel_id: &str,
+    event: &CompletionEvent,
+) -> Result<(), String> {
+    let name = format!("dnd-{channel_id}");
+    let _lock = store.lock(&name).map_err(|error| error.to_string())?;
+    let mut queued: Vec<DigestEntry> = store.load(&name);
+    queued.push(DigestEntry::from(event));
+    store.save(&name, &queued)
+}
+
+/// Returns `event` with the completions queued during do-not-disturb folded
+/// into its digest, clearing the queue.
+pub fn release_dnd_queue(
+    store: &StateStore,
+    channel_id: &str,
+    event: &CompletionEvent,
+) -> Result<CompletionEvent, String> {
+    let name = format!("dnd-{channel_id}");
+    let _lock = store.lock(&name).map_err(|error| error.to_string())?;
+    let queued: Vec<DigestEntry> = store.load(&name);
+
+    let mut released = event.clone();
+    if !queued.is_empty() {
+        released.digest.splice(0..0, queued);
+        store.save(&name, &Vec::<DigestEntry>::new())?;
+    }
+    Ok(released)
+}
@@ -0,0 +1,113 @@
+use crate::config::config_value;
+use crate::perms::shared_mode;
+use regex::Regex;
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+
+/// Checks the config file and data directory for permissions that expose
+/// credentials to other users. Returns one message per problem.
+pub fn permission_problems(config_path: &Path, data_dir: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if shared_mode(config_path).is_some_and(|mode| mode & 0o004 != 0)
+        && let Ok(raw) = fs::read_to_string(config_path)
+    {
+        let fields = literal_credentials(&raw);
+        if !fields.is_empty() {
+            problems.push(format!(
+                "{} is readable by every user and holds credentials in {}; run `chmod 600 {}` or move them into `${{secret:...}}`",
+                config_path.display(),
+                fields.join(", "),
+                config_path.display()
+            ));
+        }
+    }
+
+    for dir in [
+        data_dir.to_path_buf(),
+        data_dir.join("state"),
+        data_dir.join("locks"),
+    ] {
+        if shared_mode(&dir).is_some() {
+            problems.push(format!(
+                "{} is open to other users; run `chmod 700 {}`",
+                dir.display(),
+                dir.display()
+            ));
+        }
+    }
+
+    if let Ok(entries) = fs::read_dir(data_dir.join("state")) {
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            if path.is_file() && shared_mode(&path).is_some() {
+                problems.push(format!(
+                    "{} is readable by other users; run `chmod 600 {}`",
+                    path.display(),
+                    path.display()
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+/// Config fields, as dotted paths, that hold a credential written out
+/// literally rather than through `${env:...}` or `${secret:...}`.
+pub fn literal_credentials(raw: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    if let Ok((value, _)) = config_value(raw) {
+        collect_credentials(&value, "", &mut fields);
+    }
+    fields
+}
+
+fn collect_credentials(value: &Value, path: &str, fields: &mut Vec<String>) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let Some(key) = key.as_str() else {
+                    continue;
+                };
+                // encrypted secrets are safe to read.
+                if path.is_empty() && key == "secrets" {
+                    continue;
+                }
+                let child = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{path}.{key}")
+                };
+                if let Value::String(text) = value
+                    && is_credential(key, text)
+                {
+                    fields.push(child);
+                } else {
+                    collect_credentials(value, &child, fields);
+                }
+            }
+        }
+        Value::Sequence(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_credentials(item, &format!("{path}[{index}]"), fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_credential(key: &str, value: &str) -> bool {
+    if value.trim().is_empty() || value.contains("${") {
+        return false;
+    }
+
+    let sensitive_key = Regex::new(r"(?i)token|secret|passw|api[_-]?key|authorization")
+        .expect("valid credential key regex");
+    let sensitive_url = Regex::new(
+        r"(?i)^https?://([^/\s@]+:[^/\s@]+@|[^\s]*[?&](token|key|secret|sig)=|(hooks\.slack\.com/services|discord(app)?\.com/api/webhooks|[^/\s]*webhook\.office\.com)/)",
+    )
+    .expect("valid credential url regex");
+
+    sensitive_key.is_match(key) || sensitive_url.is_match(value)
+}
@@ -0,0 +1,93 @@
+use crate::event::{host_name, new_run_id};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+/// What building runs and events reads from the machine. The library's
+/// plain entry points use [`SystemEnvironment`]; the `_in` variants take any
+/// implementation, so embedders and tests can make events reproducible.
+pub trait Environment {
+    /// Current time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// This machine's hostname.
+    fn hostname(&self) -> String;
+
+    /// The process working directory, when it can be read.
+    fn current_dir(&self) -> Option<PathBuf>;
+
+    /// An environment variable, when set to valid UTF-8.
+    fn var(&self, name: &str) -> Option<String>;
+
+    /// A fresh ID for a run.
+    fn run_id(&self) -> String;
+}
+
+/// The real clock, hostname, working directory and environment variables.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemEnvironment;
+
+impl Environment for SystemEnvironment {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn hostname(&self) -> String {
+        host_name()
+    }
+
+    fn current_dir(&self) -> Option<PathBuf> {
+        env::current_dir().ok()
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        env::var(name).ok()
+    }
+
+    fn run_id(&self) -> String {
+        new_run_id()
+    }
+}
+
+/// Fixed values for every reading, so the same run always produces the same
+/// event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedEnvironment {
+    /// Returned by [`Environment::now`].
+    pub now: DateTime<Utc>,
+
+    /// Returned by [`Environment::hostname`].
+    pub hostname: String,
+
+    /// Returned by [`Environment::current_dir`].
+    pub current_dir: Option<PathBuf>,
+
+    /// Looked up by [`Environment::var`]; anything else is unset.
+    pub vars: BTreeMap<String, String>,
+
+    /// Returned by [`Environment::run_id`].
+    pub run_id: String,
+}
+
+impl Environment for FixedEnvironment {
+    fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    fn hostname(&self) -> String {
+        self.hostname.clone()
+    }
+
+    fn current_dir(&self) -> Option<PathBuf> {
+        self.current_dir.clone()
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        self.vars.get(name).cloned()
+    }
+
+    fn run_id(&self) -> String {
+        self.run_id.clone()
+    }
+}
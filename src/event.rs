@@ -1,23 +1,50 @@
+use crate::bench::BenchSummary;
+use crate::container::ContainerInfo;
+use crate::context::MachineContext;
+use crate::digest::DigestEntry;
+use crate::environment::{Environment, SystemEnvironment};
+use crate::fingerprint::fingerprint;
+use crate::lock::LockInfo;
 use crate::runner::RunResult;
+use crate::snapshot::EnvironmentSnapshot;
 use chrono::SecondsFormat;
-use serde::Serialize;
-use std::env;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 
 /// Serialized payload sent to webhook/custom channels.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionEvent {
     /// Constant tool identifier.
     pub tool: String,
 
+    /// Random UUID identifying this run.
+    pub run_id: String,
+
     /// `success` when exit code is 0, otherwise `failure`.
     pub status: String,
 
     /// Command argv.
     pub command: Vec<String>,
 
+    /// Stable ID for the command that ignores transient flags and
+    /// credentials, for telling runs of the same command apart from others.
+    #[serde(default)]
+    pub fingerprint: String,
+
     /// Working directory where `brb` was invoked.
     pub cwd: String,
 
+    /// Project the command belongs to, from `--project` or derived from the
+    /// working directory; named in place of `brb` in titles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+
+    /// Session grouping related runs, from `--session` or `BRB_SESSION`;
+    /// channels that support threads reply to the session's first message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+
     /// UTC start timestamp (RFC3339).
     pub started_at: String,
 
@@ -27,57 +54,283 @@ pub struct CompletionEvent {
     /// Total duration in milliseconds.
     pub duration_ms: u128,
 
+    /// Time `brb` itself spent on this run before delivery (loading config,
+    /// starting the command, building the event), in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overhead_ms: Option<u128>,
+
     /// Wrapped command exit code.
     pub exit_code: i32,
 
+    /// Message configured for this exit code under `exit_codes`, or for the
+    /// trigger that fired under `triggers`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_reason: Option<String>,
+
+    /// Output line that fired a trigger while the command was still running;
+    /// such events have the status `triggered`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_match: Option<String>,
+
     /// Hostname when available.
     pub host: String,
+
+    /// Container or Kubernetes pod the run happened in, when detected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerInfo>,
+
+    /// Anonymous random ID for this machine, unless disabled in config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+
+    /// Other `brb` runs on this machine still in progress when this one
+    /// finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other_runs: Option<usize>,
+
+    /// Named lock details when the run used `--lock`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock: Option<LockInfo>,
+
+    /// Machine context sampled at completion when enabled in config.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<MachineContext>,
+
+    /// Tool versions and hardware recorded when enabled in config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<EnvironmentSnapshot>,
+
+    /// Labels added by a channel's `enrich` pipeline steps.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+
+    /// Earlier completions held by a digest channel and delivered with this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub digest: Vec<DigestEntry>,
+
+    /// Notes added after the run with `brb annotate`, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+
+    /// Duration statistics when the event summarises a `brb bench`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bench: Option<BenchSummary>,
+
+    /// Runs in the session, oldest first, when the event summarises
+    /// `brb session end`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub session_runs: Vec<DigestEntry>,
+
+    /// Captured output tail used for chat attachments; never serialized.
+    #[serde(skip)]
+    pub output_tail: Option<String>,
+
+    /// Index under `triggers` of the trigger that sent this event, which is
+    /// a message of its own next to the run's completion; never serialized.
+    #[serde(skip)]
+    pub trigger: Option<usize>,
+
+    /// Status of the run before this one when `--every` or `--repeat` runs
+    /// the command again, for `notify_on: change`; never serialized.
+    #[serde(skip)]
+    pub previous_status: Option<String>,
+
+    /// The note `brb annotate --post` sends on its own, as a reply to the
+    /// run's message; never serialized.
+    #[serde(skip)]
+    pub posted_note: Option<String>,
 }
 
 impl CompletionEvent {
+    /// Top-level payload field names, for per-channel field selection.
+    pub const FIELDS: [&'static str; 27] = [
+        "tool",
+        "run_id",
+        "status",
+        "command",
+        "fingerprint",
+        "cwd",
+        "project",
+        "session",
+        "started_at",
+        "finished_at",
+        "duration_ms",
+        "overhead_ms",
+        "exit_code",
+        "exit_reason",
+        "output_match",
+        "host",
+        "container",
+        "machine_id",
+        "other_runs",
+        "lock",
+        "context",
+        "snapshot",
+        "labels",
+        "digest",
+        "notes",
+        "bench",
+        "session_runs",
+    ];
+
     /// Builds a completion event from a finished wrapped command.
     pub fn from_run(run: &RunResult) -> Self {
-        let cwd = env::current_dir()
-            .map(|path| path.display().to_string())
-            .unwrap_or_else(|_| ".".to_string());
+        Self::from_run_in(run, &SystemEnvironment)
+    }
 
-        let host = hostname::get()
-            .ok()
-            .map(|name| name.to_string_lossy().to_string())
-            .filter(|name| !name.is_empty())
-            .unwrap_or_else(|| "unknown-host".to_string());
+    /// Builds a completion event, reading the run ID, hostname and working
+    /// directory from `environment`.
+    pub fn from_run_in(run: &RunResult, environment: &dyn Environment) -> Self {
+        let cwd = run
+            .cwd
+            .clone()
+            .or_else(|| environment.current_dir())
+            .map_or_else(|| ".".to_string(), |path| path.display().to_string());
 
         Self {
             tool: "brb".to_string(),
-            status: if run.exit_code == 0 {
-                "success".to_string()
-            } else {
-                "failure".to_string()
-            },
+            run_id: environment.run_id(),
+            status: status_for(run.exit_code).to_string(),
             command: run.command.clone(),
+            fingerprint: fingerprint(&run.command),
             cwd,
+            project: None,
+            session: None,
             started_at: run.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
             finished_at: run.finished_at.to_rfc3339_opts(SecondsFormat::Millis, true),
             duration_ms: run.duration.as_millis(),
+            overhead_ms: None,
             exit_code: run.exit_code,
-            host,
+            exit_reason: None,
+            output_match: None,
+            host: environment.hostname(),
+            container: None,
+            machine_id: None,
+            other_runs: None,
+            lock: None,
+            context: None,
+            snapshot: None,
+            labels: BTreeMap::new(),
+            digest: vec![],
+            notes: vec![],
+            bench: None,
+            session_runs: vec![],
+            output_tail: run.output_tail.clone(),
+            trigger: None,
+            previous_status: None,
+            posted_note: None,
+        }
+    }
+
+    /// The host as shown in notifications, naming the container or pod
+    /// when there is one, e.g. `api-7d9f (pod in staging)` or
+    /// `a1b2c3d4e5f6 (docker container)`.
+    pub fn host_label(&self) -> String {
+        let Some(container) = &self.container else {
+            return self.host.clone();
+        };
+        if container.runtime == "kubernetes" {
+            let pod = container.pod.as_ref().unwrap_or(&self.host);
+            return match &container.namespace {
+                Some(namespace) => format!("{pod} (pod in {namespace})"),
+                None => format!("{pod} (pod)"),
+            };
+        }
+        let name = container.name.as_ref().unwrap_or(&self.host);
+        match &container.id {
+            // docker names the container's host after its ID.
+            Some(id) if id != name => format!("{name} ({} container {id})", container.runtime),
+            _ => format!("{name} ({} container)", container.runtime),
+        }
+    }
+
+    /// Returns a copy with fields replaced by those in a JSON object, e.g. a
+    /// partial event supplied to `brb template render --event`. `status`
+    /// follows `exit_code` unless given explicitly.
+    pub fn with_overrides(&self, overrides: &Value) -> Result<Self, String> {
+        let Value::Object(overrides) = overrides else {
+            return Err("event overrides must be a JSON object".to_string());
+        };
+
+        let mut merged = serde_json::to_value(self).map_err(|error| error.to_string())?;
+        if let Value::Object(fields) = &mut merged {
+            fields.extend(overrides.clone());
+        }
+        let mut event: Self =
+            serde_json::from_value(merged).map_err(|error| format!("invalid event: {error}"))?;
+        event.output_tail = self.output_tail.clone();
+        event.trigger = self.trigger;
+        event.previous_status.clone_from(&self.previous_status);
+        event.posted_note.clone_from(&self.posted_note);
+        if !overrides.contains_key("status") {
+            event.status = status_for(event.exit_code).to_string();
         }
+        Ok(event)
     }
 
     /// Creates a synthetic event used by `brb channels test`.
     pub fn test_event() -> Self {
+        Self::test_event_in(&SystemEnvironment)
+    }
+
+    /// Creates the synthetic `brb channels test` event from `environment`.
+    pub fn test_event_in(environment: &dyn Environment) -> Self {
+        let now = environment.now();
         let run = RunResult {
             command: vec![
                 "brb".to_string(),
                 "channels".to_string(),
                 "test".to_string(),
             ],
-            started_at: chrono::Utc::now(),
-            finished_at: chrono::Utc::now(),
+            started_at: now,
+            finished_at: now,
             duration: std::time::Duration::from_millis(1),
             exit_code: 0,
             spawn_error: None,
+            output_tail: None,
+            cwd: None,
         };
-        Self::from_run(&run)
+        Self::from_run_in(&run, environment)
     }
 }
+
+/// `success` for exit code 0, otherwise `failure`.
+pub fn status_for(exit_code: i32) -> &'static str {
+    if exit_code == 0 { "success" } else { "failure" }
+}
+
+/// Generates a random (version 4) UUID.
+pub fn new_run_id() -> String {
+    let mut bytes = [0u8; 16];
+    if getrandom::fill(&mut bytes).is_err() {
+        // extremely unlikely; fall back to something unique enough per machine.
+        let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        bytes[..8].copy_from_slice(&nanos.to_le_bytes());
+        bytes[8..12].copy_from_slice(&std::process::id().to_le_bytes());
+    }
+
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// This machine's hostname, or `unknown-host` when it cannot be read.
+pub fn host_name() -> String {
+    hostname::get()
+        .ok()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown-host".to_string())
+}
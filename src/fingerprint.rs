@@ -0,0 +1,86 @@
+use crate::config::RedactConfig;
+use crate::redact::redact_command;
+use std::path::Path;
+
+/// Flags that change how a command reports rather than what it runs, so they
+/// are left out of fingerprints.
+pub const TRANSIENT_FLAGS: &[&str] = &[
+    "-v",
+    "-vv",
+    "-vvv",
+    "--verbose",
+    "-q",
+    "--quiet",
+    "--color",
+    "--colour",
+    "--no-color",
+    "-j",
+    "--jobs",
+    "--progress",
+    "--no-progress",
+];
+
+/// Stable ID for what a command does: 16 hex digits hashed from
+/// [`normalized_command`].
+pub fn fingerprint(command: &[String]) -> String {
+    // FNV-1a, which unlike std's hasher is fixed across Rust releases.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for arg in normalized_command(command) {
+        for byte in arg.bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+/// The parts of a command a fingerprint covers: the program's file name and
+/// the arguments, with credential values masked and transient flags dropped.
+pub fn normalized_command(command: &[String]) -> Vec<String> {
+    let command = redact_command(&RedactConfig::default(), command);
+    let mut args = command.into_iter();
+    let Some(program) = args.next() else {
+        return Vec::new();
+    };
+    let program = Path::new(&program)
+        .file_name()
+        .map_or(program.clone(), |name| name.to_string_lossy().into_owned());
+
+    let mut normalized = vec![program];
+    let mut previous: Option<String> = None;
+    for arg in args {
+        let flag = previous.take();
+        if flag.is_some_and(|flag| is_transient_value(&flag, &arg)) {
+            continue;
+        }
+        if TRANSIENT_FLAGS.contains(&arg.as_str()) {
+            previous = Some(arg);
+            continue;
+        }
+        if arg
+            .split_once('=')
+            .is_some_and(|(flag, _)| TRANSIENT_FLAGS.contains(&flag))
+            || is_short_jobs(&arg)
+        {
+            continue;
+        }
+        normalized.push(arg);
+    }
+    normalized
+}
+
+/// Whether `value` is the separate value of the transient `flag`, as in
+/// `--jobs 8` or `--color always`.
+fn is_transient_value(flag: &str, value: &str) -> bool {
+    match flag {
+        "-j" | "--jobs" => !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()),
+        "--color" | "--colour" => matches!(value, "always" | "never" | "auto"),
+        _ => false,
+    }
+}
+
+/// `-j8` style job counts.
+fn is_short_jobs(arg: &str) -> bool {
+    arg.strip_prefix("-j")
+        .is_some_and(|count| is_transient_value("-j", count))
+}
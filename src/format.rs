@@ -0,0 +1,1125 @@
+use crate::capture::apply_captures;
+use crate::config::{
+    Channel, Config, CustomChannel, DesktopChannel, DisplayTimezone, GoogleChatChannel,
+    HealthchecksChannel, NtfyChannel, Oversize, Priority, PushoverChannel, SlackChannel,
+    StatusAppearance, TelegramChannel, TemplateDef, WebhookChannel, WebhookFormat, ZulipChannel,
+    parse_hex_color,
+};
+use crate::event::CompletionEvent;
+use crate::messages::{Locale, Messages};
+use crate::secrets::encrypt_to;
+use crate::template;
+use chrono::{DateTime, Local, Utc};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+
+const SUCCESS_COLOR: u32 = 0x2eb67d;
+const FAILURE_COLOR: u32 = 0xe01e5a;
+const MAX_ATTACHMENT_CHARS: usize = 1500;
+
+/// Ends text cut to fit a message length limit.
+pub const TRUNCATION_MARKER: &str = "… [truncated]";
+
+/// Most text a platform accepts in one message field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Limit {
+    Chars(usize),
+    Bytes(usize),
+}
+
+impl Limit {
+    fn size(self, text: &str) -> usize {
+        match self {
+            Self::Chars(_) => text.chars().count(),
+            Self::Bytes(_) => text.len(),
+        }
+    }
+
+    fn max(self) -> usize {
+        match self {
+            Self::Chars(max) | Self::Bytes(max) => max,
+        }
+    }
+}
+
+/// Notification servers accept far more, but show long bodies badly.
+const DESKTOP_BODY_LIMIT: Limit = Limit::Chars(1000);
+
+/// Discord's limit on embed titles.
+const DISCORD_TITLE_LIMIT: Limit = Limit::Chars(256);
+
+/// Block Kit limits on header text, section fields and section text.
+const SLACK_HEADER_LIMIT: Limit = Limit::Chars(150);
+const SLACK_FIELD_LIMIT: Limit = Limit::Chars(2000);
+const SLACK_SECTION_LIMIT: Limit = Limit::Chars(3000);
+
+/// Pushover's limits on the title and message, in characters.
+const PUSHOVER_TITLE_LIMIT: usize = 250;
+const PUSHOVER_MESSAGE_LIMIT: usize = 1024;
+
+/// Zulip's default limits on topic names and message content, in characters.
+const ZULIP_TOPIC_LIMIT: Limit = Limit::Chars(60);
+const ZULIP_CONTENT_LIMIT: Limit = Limit::Chars(10_000);
+
+/// Google Chat rejects messages over 32,000 bytes; card text gets most of
+/// that, leaving room for the rest of the card.
+const GOOGLE_CHAT_TEXT_LIMIT: Limit = Limit::Bytes(24_000);
+
+/// Healthchecks.io keeps the first 100 kB of a ping's body.
+const HEALTHCHECKS_BODY_LIMIT: Limit = Limit::Bytes(100_000);
+
+/// Telegram's limit on message text, counted after HTML entities are parsed.
+const TELEGRAM_TEXT_LIMIT: Limit = Limit::Chars(4096);
+
+/// CloudEvents `type` of the events the `cloudevents` format sends.
+const CLOUDEVENTS_TYPE: &str = "com.github.mcmanussliam.brb.run.completed";
+
+/// Limit on the message field of a chat format: Slack's text length, Discord's
+/// embed description, about 28 KB for a Teams card and ntfy's message size.
+fn format_limit(format: WebhookFormat) -> Option<Limit> {
+    match format {
+        WebhookFormat::Generic | WebhookFormat::CloudEvents => None,
+        WebhookFormat::Slack => Some(Limit::Chars(40_000)),
+        WebhookFormat::Discord => Some(Limit::Chars(4096)),
+        WebhookFormat::Teams => Some(Limit::Bytes(28_000)),
+        WebhookFormat::Ntfy => Some(Limit::Bytes(4096)),
+    }
+}
+
+/// Cuts `text` so that it and `reserved` more units fit `limit`, ending it
+/// with [`TRUNCATION_MARKER`] when anything was cut.
+fn fit_text(text: String, limit: Option<Limit>, reserved: usize) -> String {
+    let Some(limit) = limit else {
+        return text;
+    };
+    let budget = limit.max().saturating_sub(reserved);
+    if limit.size(&text) <= budget {
+        return text;
+    }
+
+    let budget = budget.saturating_sub(limit.size(TRUNCATION_MARKER));
+    let mut used = 0;
+    let mut cut = text
+        .chars()
+        .take_while(|ch| {
+            used += limit.size(ch.encode_utf8(&mut [0; 4]));
+            used <= budget
+        })
+        .collect::<String>();
+    cut.push_str(TRUNCATION_MARKER);
+    cut
+}
+
+/// Resolved presentation for one event's status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Appearance {
+    /// Emoji for the `{emoji}` template placeholder; empty when disabled.
+    pub emoji: String,
+
+    /// RGB color used by chat channels.
+    pub color: u32,
+
+    /// Priority used by push and desktop channels.
+    pub priority: Priority,
+}
+
+/// Title and body shown to people, after applying any channel template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageText {
+    /// Notification title.
+    pub title: String,
+
+    /// Notification body.
+    pub body: String,
+}
+
+/// Request body produced for a webhook delivery.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookPayload {
+    /// JSON body sent with `Content-Type: application/json`, or
+    /// `application/cloudevents+json` for the `cloudevents` format.
+    Json(Value),
+
+    /// Plain-text body plus headers the receiver reads metadata from.
+    Text {
+        body: String,
+        headers: BTreeMap<String, String>,
+    },
+}
+
+/// Resolves the configured appearance for the event's status, falling back to
+/// ✅/green/default for success and ❌/red/high for failure.
+pub fn appearance(config: &Config, event: &CompletionEvent) -> Appearance {
+    let (emoji, color, priority) = if looks_successful(event) {
+        ("✅", SUCCESS_COLOR, Priority::Default)
+    } else {
+        ("❌", FAILURE_COLOR, Priority::High)
+    };
+    let overrides = status_appearance(config, event);
+
+    Appearance {
+        emoji: overrides.emoji.clone().unwrap_or_else(|| emoji.to_string()),
+        color: overrides
+            .color
+            .as_deref()
+            .and_then(parse_hex_color)
+            .unwrap_or(color),
+        priority: overrides.priority.unwrap_or(priority),
+    }
+}
+
+/// The `appearance` overrides for the event's status, as configured.
+pub fn status_appearance<'a>(config: &'a Config, event: &CompletionEvent) -> &'a StatusAppearance {
+    if looks_successful(event) {
+        &config.appearance.success
+    } else {
+        &config.appearance.failure
+    }
+}
+
+fn looks_successful(event: &CompletionEvent) -> bool {
+    // a fired trigger looks like the failure it usually warns of.
+    event.exit_code == 0 && event.output_match.is_none()
+}
+
+/// Block Kit message for a `slack` channel: the title as a header, the
+/// command, duration, host and exit code as fields, then whatever else the
+/// body carries and any attached output, in an attachment coloured by status.
+pub fn slack_blocks(
+    config: &Config,
+    slack: &SlackChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> Value {
+    let MessageText { title, body } = message_text(config, template, event);
+    let messages = messages(config);
+    let [command_label, duration_label, host_label, exit_code_label] = messages.fields;
+    let command = render_command(config, &event.command);
+    let field = |label: &str, value: String| {
+        let text = format!("*{label}*\n{value}");
+        json!({ "type": "mrkdwn", "text": fit_text(text, Some(SLACK_FIELD_LIMIT), 0) })
+    };
+    let section = |text: String| {
+        json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": fit_text(text, Some(SLACK_SECTION_LIMIT), 0) },
+        })
+    };
+
+    let mut blocks = vec![
+        json!({
+            "type": "header",
+            "text": {
+                "type": "plain_text",
+                "text": fit_text(title.clone(), Some(SLACK_HEADER_LIMIT), 0),
+                "emoji": true,
+            },
+        }),
+        json!({
+            "type": "section",
+            "fields": [
+                field(command_label, format!("`{command}`")),
+                field(duration_label, render_duration(config, event.duration_ms)),
+                field(host_label, event.host_label()),
+                field(exit_code_label, event.exit_code.to_string()),
+            ],
+        }),
+    ];
+
+    // the built-in body opens with the command line the fields already show.
+    let details = match template {
+        Some(_) => body,
+        None => body.lines().skip(1).collect::<Vec<_>>().join("\n"),
+    };
+    if !details.trim().is_empty() {
+        blocks.push(section(details));
+    }
+    if let Some(output) = output_attachment(slack.attach_output, event) {
+        blocks.push(section(format!("*{}*\n```{output}```", messages.output)));
+    }
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": format!(
+                "{} {} · {}",
+                messages.finished,
+                render_timestamp(config, &event.finished_at),
+                event.run_id
+            ),
+        }],
+    }));
+
+    json!({
+        "text": format!("{title}: {command}"),
+        "attachments": [{
+            "color": format!("#{:06x}", appearance(config, event).color),
+            "blocks": blocks,
+        }],
+    })
+}
+
+/// Bot API `sendMessage` body for a `telegram` channel: the title in bold
+/// above the body, as HTML.
+pub fn telegram_message(
+    config: &Config,
+    telegram: &TelegramChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> Value {
+    let MessageText { title, body } = message_text(config, template, event);
+    let title = fit_text(title, Some(TELEGRAM_TEXT_LIMIT), 1);
+    let body = fit_text(body, Some(TELEGRAM_TEXT_LIMIT), title.chars().count() + 1);
+    json!({
+        "chat_id": telegram.chat_id,
+        "text": format!("<b>{}</b>\n{}", escape_html(&title), escape_html(&body)),
+        "parse_mode": "HTML",
+        "disable_web_page_preview": true,
+    })
+}
+
+/// Message for an `ntfy` channel: the body as plain text, with the title,
+/// priority and tags for the run's status as headers.
+pub fn ntfy_message(
+    config: &Config,
+    ntfy: &NtfyChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> (String, BTreeMap<String, String>) {
+    let MessageText { title, body } = message_text(config, template, event);
+    let overrides = if event.exit_code == 0 {
+        &ntfy.success
+    } else {
+        &ntfy.failure
+    };
+    let priority = overrides
+        .priority
+        .unwrap_or_else(|| appearance(config, event).priority);
+    let tags = overrides
+        .tags
+        .clone()
+        .unwrap_or_else(|| vec!["brb".to_string(), event.status.clone()]);
+
+    let mut headers = BTreeMap::from([
+        ("Title".to_string(), title),
+        ("Priority".to_string(), priority.as_str().to_string()),
+    ]);
+    if !tags.is_empty() {
+        headers.insert("Tags".to_string(), tags.join(","));
+    }
+    (
+        fit_text(body, format_limit(WebhookFormat::Ntfy), 0),
+        headers,
+    )
+}
+
+/// Messages API body for a `pushover` channel, without the user key and API
+/// token, which are added when sending. The priority follows `appearance`;
+/// emergency messages repeat every minute for an hour until acknowledged.
+pub fn pushover_message(
+    config: &Config,
+    pushover: &PushoverChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> Value {
+    let MessageText { title, body } = message_text(config, template, event);
+    let priority = pushover_priority(appearance(config, event).priority);
+    let mut message = json!({
+        "title": fit_text(title, Some(Limit::Chars(PUSHOVER_TITLE_LIMIT)), 0),
+        "message": fit_text(body, Some(Limit::Chars(PUSHOVER_MESSAGE_LIMIT)), 0),
+        "priority": priority,
+    });
+    if let Ok(finished_at) = DateTime::parse_from_rfc3339(&event.finished_at) {
+        message["timestamp"] = json!(finished_at.timestamp());
+    }
+    if priority == 2 {
+        message["retry"] = json!(60);
+        message["expire"] = json!(3600);
+    }
+    if let Some(device) = &pushover.device {
+        message["device"] = json!(device);
+    }
+    if let Some(sound) = &pushover.sound {
+        message["sound"] = json!(sound);
+    }
+    message
+}
+
+/// Messages API body for a `zulip` channel: the title in bold above the body
+/// in a code block, posted to the stream under the rendered topic.
+pub fn zulip_message(
+    config: &Config,
+    zulip: &ZulipChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> Value {
+    let MessageText { title, body } = message_text(config, template, event);
+    let values = template_values(config, event, &title, &body);
+    let topic = template::render(&BTreeMap::new(), &zulip.topic, &values);
+
+    // a fence longer than any run of backticks in the body cannot be closed
+    // by it.
+    let longest = body
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    let title = format!("**{}**", title.replace('*', "\\*"));
+    let overhead = title.chars().count() + 2 * fence.chars().count() + 3;
+    let body = fit_text(body, Some(ZULIP_CONTENT_LIMIT), overhead);
+    json!({
+        "type": "stream",
+        "to": zulip.stream.trim(),
+        "topic": fit_text(topic.trim().to_string(), Some(ZULIP_TOPIC_LIMIT), 0),
+        "content": format!("{title}\n{fence}\n{body}\n{fence}"),
+    })
+}
+
+/// Card message for a `googlechat` channel: the title and host as the card
+/// header, the command, duration and exit code as labelled text, then
+/// whatever else the body carries and any attached output. Runs in a session
+/// share a thread.
+pub fn googlechat_message(
+    config: &Config,
+    chat: &GoogleChatChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> Value {
+    let MessageText { title, body } = message_text(config, template, event);
+    let messages = messages(config);
+    let [command_label, duration_label, _, exit_code_label] = messages.fields;
+    let field = |label: &str, text: String| json!({ "decoratedText": { "topLabel": label, "text": escape_html(&text) } });
+    let paragraph = |text: String| {
+        let text = fit_text(text, Some(GOOGLE_CHAT_TEXT_LIMIT), 0);
+        json!({ "textParagraph": { "text": escape_html(&text) } })
+    };
+
+    let mut widgets = vec![
+        field(command_label, render_command(config, &event.command)),
+        field(duration_label, render_duration(config, event.duration_ms)),
+        field(exit_code_label, event.exit_code.to_string()),
+    ];
+    // the built-in body opens with the command line the fields already show.
+    let details = match template {
+        Some(_) => body,
+        None => body.lines().skip(1).collect::<Vec<_>>().join("\n"),
+    };
+    if !details.trim().is_empty() {
+        widgets.push(paragraph(details));
+    }
+    let mut sections = vec![json!({ "widgets": widgets })];
+    if let Some(output) = output_attachment(chat.attach_output, event) {
+        sections.push(json!({
+            "header": messages.output,
+            "widgets": [paragraph(output)],
+        }));
+    }
+
+    let mut message = json!({
+        "cardsV2": [{
+            "cardId": "brb",
+            "card": {
+                "header": { "title": title, "subtitle": event.host_label() },
+                "sections": sections,
+            },
+        }],
+    });
+    if let Some(session) = &event.session {
+        message["thread"] = json!({ "threadKey": format!("brb-{session}") });
+    }
+    message
+}
+
+/// The plain-text body of a completion ping, which Healthchecks shows in the
+/// check's event log.
+pub fn healthchecks_body(
+    config: &Config,
+    healthchecks: &HealthchecksChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> String {
+    let MessageText { title, body } = message_text(config, template, event);
+    let messages = messages(config);
+    let [_, duration_label, _, exit_code_label] = messages.fields;
+    let mut text = format!(
+        "{title}\n\n{body}\n\n{exit_code_label}: {}\n{duration_label}: {}",
+        event.exit_code,
+        render_duration(config, event.duration_ms)
+    );
+    let output = event
+        .output_tail
+        .as_deref()
+        .map(str::trim_end)
+        .filter(|tail| healthchecks.attach_output && !tail.is_empty());
+    if let Some(output) = output {
+        text.push_str(&format!("\n\n{}:\n{output}", messages.output));
+    }
+    fit_text(text, Some(HEALTHCHECKS_BODY_LIMIT), 0)
+}
+
+/// Pushover's priority scale, from -2 (no notification) to 2 (emergency).
+pub fn pushover_priority(priority: Priority) -> i8 {
+    match priority {
+        Priority::Min => -2,
+        Priority::Low => -1,
+        Priority::Default => 0,
+        Priority::High => 1,
+        Priority::Urgent => 2,
+    }
+}
+
+/// Escapes the characters Telegram's HTML parse mode treats as markup.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Colour of "running" messages, between the success and failure colours.
+const RUNNING_COLOR: u32 = 0x9e9e9e;
+
+/// Body of the "running" message a `progress` channel posts when a command
+/// starts, later edited into the final status. `captured` fills the
+/// `{captured.*}` placeholders in `extra`, as for the final status.
+pub fn progress_payload(
+    config: &Config,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+    captured: &BTreeMap<String, String>,
+) -> Value {
+    let title = format!("{}: {}", project_name(event), messages(config).running);
+    let command = render_command(config, &event.command);
+    let host = event.host_label();
+    let mut body = match webhook.format {
+        WebhookFormat::Discord => json!({
+            "content": title,
+            "embeds": [{
+                "title": title,
+                "description": format!("```\n{command}\n```"),
+                "color": RUNNING_COLOR,
+                "footer": { "text": host },
+            }],
+        }),
+        _ => json!({
+            "text": format!("{title}: {command}"),
+            "attachments": [{
+                "color": format!("#{RUNNING_COLOR:06x}"),
+                "title": title,
+                "text": format!("```{command}```"),
+                "footer": host,
+            }],
+        }),
+    };
+    merge_extra(&mut body, &webhook.extra);
+    apply_captures(&mut body, &webhook.extra, captured);
+    body
+}
+
+/// Text of the "running" message a Telegram channel with `progress` posts
+/// when a command starts, later edited into the final status.
+pub fn telegram_progress_message(
+    config: &Config,
+    telegram: &TelegramChannel,
+    event: &CompletionEvent,
+) -> Value {
+    let title = format!("{}: {}", project_name(event), messages(config).running);
+    let command = fit_text(
+        render_command(config, &event.command),
+        Some(TELEGRAM_TEXT_LIMIT),
+        title.chars().count() + 1,
+    );
+    json!({
+        "chat_id": telegram.chat_id,
+        "text": format!("<b>{}</b>\n<code>{}</code>", escape_html(&title), escape_html(&command)),
+        "parse_mode": "HTML",
+        "disable_web_page_preview": true,
+    })
+}
+
+/// Returns the message catalog for the configured (or environment) locale.
+pub fn messages(config: &Config) -> &'static Messages {
+    Locale::resolve(config.display.locale.as_deref()).messages()
+}
+
+/// The project named in titles, or `brb` when the event has none.
+fn project_name(event: &CompletionEvent) -> &str {
+    event.project.as_deref().unwrap_or("brb")
+}
+
+/// Short notification title, e.g. `brb: success` or `brb: failed (exit 2)`,
+/// prefixed with the status emoji when `appearance` sets one.
+pub fn notification_title(config: &Config, event: &CompletionEvent) -> String {
+    let messages = messages(config);
+    let name = project_name(event);
+    let title = match &event.exit_reason {
+        _ if event.output_match.is_some() => format!(
+            "{name}: {} ({})",
+            event.exit_reason.as_deref().unwrap_or(messages.matched),
+            messages.running
+        ),
+        Some(reason) => format!("{name}: {reason} ({})", messages.exit(event.exit_code)),
+        None if event.exit_code == 0 => format!("{name}: {}", messages.success),
+        None => format!("{name}: {}", messages.failed(event.exit_code)),
+    };
+
+    // titles only carry an emoji that `appearance` asks for.
+    match status_appearance(config, event).emoji.as_deref() {
+        Some(emoji) if !emoji.is_empty() => format!("{emoji} {title}"),
+        _ => title,
+    }
+}
+
+/// Renders an RFC3339 event timestamp in the configured display timezone and
+/// format. Unparseable input is returned unchanged.
+pub fn render_timestamp(config: &Config, timestamp: &str) -> String {
+    let Ok(parsed) = DateTime::parse_from_rfc3339(timestamp) else {
+        return timestamp.to_string();
+    };
+
+    let format = config.display.timestamp_format.as_str();
+    match config.display.timezone {
+        DisplayTimezone::Local => parsed.with_timezone(&Local).format(format).to_string(),
+        DisplayTimezone::Utc => parsed.with_timezone(&Utc).format(format).to_string(),
+        DisplayTimezone::Fixed(offset) => parsed.with_timezone(&offset).format(format).to_string(),
+    }
+}
+
+/// Renders a duration in the configured style and locale.
+pub fn render_duration(config: &Config, duration_ms: u128) -> String {
+    messages(config).duration(duration_ms, config.display.durations)
+}
+
+/// Renders a command line for people, shortened to
+/// `display.max_command_length` (see [`elide_command`]).
+pub fn render_command(config: &Config, command: &[String]) -> String {
+    elide_command(command, config.display.max_command_length)
+}
+
+/// Joins a command line, keeping it within `max_chars` by eliding middle
+/// arguments: the program and the first and last arguments stay, and as many
+/// others as fit are kept from both ends. Commands whose ends alone are too
+/// long are cut in the middle of the text instead.
+pub fn elide_command(command: &[String], max_chars: usize) -> String {
+    let full = command.join(" ");
+    if full.chars().count() <= max_chars {
+        return full;
+    }
+
+    // each kept argument costs its text and a space; the marker costs " … ".
+    let width = |arg: &String| arg.chars().count() + 1;
+    let mut used = width(&command[0]) + 1;
+    let (mut head, mut tail) = (1, 0);
+    let mut from_front = true;
+    while head + tail < command.len() {
+        let arg = if from_front {
+            &command[head]
+        } else {
+            &command[command.len() - 1 - tail]
+        };
+        if used + width(arg) > max_chars {
+            break;
+        }
+        used += width(arg);
+        if from_front {
+            head += 1;
+        } else {
+            tail += 1;
+        }
+        from_front = !from_front;
+    }
+
+    if head < 2 || tail == 0 {
+        let chars: Vec<char> = full.chars().collect();
+        let end = max_chars.saturating_sub(1) / 2;
+        let start = max_chars.saturating_sub(1) - end;
+        return format!(
+            "{}…{}",
+            chars[..start].iter().collect::<String>(),
+            chars[chars.len() - end..].iter().collect::<String>()
+        );
+    }
+
+    format!(
+        "{} … {}",
+        command[..head].join(" "),
+        command[command.len() - tail..].join(" ")
+    )
+}
+
+/// Notification body: the command, its duration and finish time, plus any
+/// bench statistics, context warnings, earlier runs held by a digest and the
+/// runs a session summary covers.
+pub fn notification_body(config: &Config, event: &CompletionEvent) -> String {
+    let mut body = match &event.output_match {
+        Some(line) => format!(
+            "{} ({}, {})\n{}: {line}",
+            render_command(config, &event.command),
+            render_duration(config, event.duration_ms),
+            messages(config).running,
+            messages(config).matched
+        ),
+        None => format!(
+            "{} ({}, {} {})",
+            render_command(config, &event.command),
+            render_duration(config, event.duration_ms),
+            messages(config).finished,
+            render_timestamp(config, &event.finished_at)
+        ),
+    };
+    if let Some(bench) = &event.bench {
+        let stats = [bench.min_ms, bench.mean_ms, bench.p95_ms, bench.max_ms]
+            .map(|duration_ms| render_duration(config, duration_ms));
+        body.push_str(&format!(
+            "\n{}",
+            messages(config).bench(bench.runs, bench.failures, &stats)
+        ));
+    }
+    if let Some(context) = &event.context
+        && !context.warnings.is_empty()
+    {
+        body.push_str(&format!("\n{}", context.warnings.join("; ")));
+    }
+    if let Some(count) = event.other_runs.filter(|count| *count > 0) {
+        body.push_str(&format!("\n{}", messages(config).still_running(count)));
+    }
+    if !event.digest.is_empty() {
+        body.push_str(&format!(
+            "\n{} ({}):",
+            messages(config).earlier,
+            event.digest.len()
+        ));
+        for entry in &event.digest {
+            let marker = if entry.exit_code == 0 { "ok" } else { "FAIL" };
+            body.push_str(&format!(
+                "\n- {marker} {} ({}, {})",
+                render_command(config, &entry.command),
+                render_duration(config, entry.duration_ms),
+                render_timestamp(config, &entry.finished_at)
+            ));
+        }
+    }
+    if !event.session_runs.is_empty() {
+        body.push_str(&format!(
+            "\n{} ({}):",
+            messages(config).session(event.session.as_deref().unwrap_or_default()),
+            event.session_runs.len()
+        ));
+        for entry in &event.session_runs {
+            let marker = if entry.exit_code == 0 { "ok" } else { "FAIL" };
+            body.push_str(&format!(
+                "\n- {marker} {} ({})",
+                render_command(config, &entry.command),
+                render_duration(config, entry.duration_ms)
+            ));
+        }
+    }
+    for note in &event.notes {
+        body.push_str(&format!("\n{}: {note}", messages(config).note));
+    }
+    body
+}
+
+/// Title and body for a desktop notification, with the body cut to the
+/// channel's `max_message_length` or the built-in limit.
+pub fn desktop_text(
+    config: &Config,
+    desktop: &DesktopChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> MessageText {
+    let MessageText { title, body } = message_text(config, template, event);
+    let limit = desktop
+        .max_message_length
+        .map_or(DESKTOP_BODY_LIMIT, Limit::Chars);
+    MessageText {
+        title,
+        body: fit_text(body, Some(limit), 0),
+    }
+}
+
+/// Renders the title and body for a channel, using the named template from
+/// `templates` when given. Template parts that are not set keep the built-in
+/// wording.
+pub fn message_text(
+    config: &Config,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> MessageText {
+    let title = notification_title(config, event);
+    let body = notification_body(config, event);
+    let Some(TemplateDef::Message(message)) = template.and_then(|name| config.templates.get(name))
+    else {
+        return MessageText { title, body };
+    };
+
+    let values = template_values(config, event, &title, &body);
+    let render = |source: &Option<String>, fallback: String| match source {
+        Some(source) => template::render(&config.templates, source, &values),
+        None => fallback,
+    };
+
+    MessageText {
+        title: render(&message.title, title),
+        body: render(&message.body, body),
+    }
+}
+
+/// Values for each of [`template::PLACEHOLDERS`].
+fn template_values(
+    config: &Config,
+    event: &CompletionEvent,
+    title: &str,
+    body: &str,
+) -> BTreeMap<&'static str, String> {
+    BTreeMap::from([
+        ("emoji", appearance(config, event).emoji),
+        ("title", title.to_string()),
+        ("body", body.to_string()),
+        ("command", render_command(config, &event.command)),
+        ("status", event.status.clone()),
+        ("exit_code", event.exit_code.to_string()),
+        ("duration", render_duration(config, event.duration_ms)),
+        ("finished", render_timestamp(config, &event.finished_at)),
+        ("host", event.host.clone()),
+        ("cwd", event.cwd.clone()),
+        ("project", project_name(event).to_string()),
+        ("run_id", event.run_id.clone()),
+    ])
+}
+
+/// Returns the idempotency key for delivering `event` to `channel_id`; stable
+/// across retries of the same delivery.
+pub fn idempotency_key(event: &CompletionEvent, channel_id: &str) -> String {
+    format!("{}:{channel_id}{}", event.run_id, message_suffix(event))
+}
+
+/// Tells a run's fired triggers and posted notes apart from its completion:
+/// they are new messages, not retries of the run's.
+fn message_suffix(event: &CompletionEvent) -> String {
+    match (event.trigger, event.notes.len()) {
+        (Some(trigger), _) => format!(":trigger-{trigger}"),
+        (None, 0) => String::new(),
+        (None, notes) => format!(":note-{notes}"),
+    }
+}
+
+/// Wraps an event body as a structured-mode CloudEvent. Its `id` is unique
+/// per run and message, and `subject` is the command's fingerprint.
+fn cloud_event(event: &CompletionEvent, data: Value) -> Value {
+    let mut cloud_event = json!({
+        "specversion": "1.0",
+        "id": format!("{}{}", event.run_id, message_suffix(event)),
+        "source": format!("brb://{}", event.host),
+        "type": CLOUDEVENTS_TYPE,
+        "time": event.finished_at,
+        "datacontenttype": "application/json",
+        "data": data,
+    });
+    // CloudEvents forbids an empty `subject`.
+    if !event.fingerprint.is_empty() {
+        cloud_event["subject"] = Value::String(event.fingerprint.clone());
+    }
+    cloud_event
+}
+
+/// Merges static `extra` fields into a JSON object body, overriding existing keys.
+pub fn merge_extra(body: &mut Value, extra: &BTreeMap<String, String>) {
+    if let Value::Object(object) = body {
+        for (key, value) in extra {
+            object.insert(key.clone(), Value::String(value.clone()));
+        }
+    }
+}
+
+/// Returns the captured output tail to attach, if the channel asked for it and
+/// the command failed. Long tails keep their end, which is usually the error.
+pub fn output_attachment(attach_output: bool, event: &CompletionEvent) -> Option<String> {
+    if !attach_output || event.exit_code == 0 {
+        return None;
+    }
+
+    let tail = event.output_tail.as_deref()?.trim_end();
+    if tail.is_empty() {
+        return None;
+    }
+
+    let count = tail.chars().count();
+    if count <= MAX_ATTACHMENT_CHARS {
+        return Some(tail.to_string());
+    }
+
+    let kept = tail
+        .chars()
+        .skip(count - MAX_ATTACHMENT_CHARS)
+        .collect::<String>();
+    Some(format!("...{kept}"))
+}
+
+/// Reshapes a completion event into the body expected by a webhook channel's
+/// preset, including any `extra` fields and output attachment. `template`
+/// names the channel's message template, if any.
+pub fn webhook_payload(
+    config: &Config,
+    webhook: &WebhookChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> WebhookPayload {
+    let mut payload = preset_payload(config, webhook, template, event);
+    if let WebhookPayload::Json(body) = &mut payload {
+        merge_extra(body, &webhook.extra);
+    }
+    payload
+}
+
+/// Builds the request body for a webhook channel, leaving out event fields the
+/// channel excludes and renaming those in its `field_map`. Only the `generic`
+/// and `cloudevents` formats carry event fields; `extra` keys are always kept.
+pub fn channel_payload(
+    config: &Config,
+    channel: &Channel,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+) -> WebhookPayload {
+    let mut payload = webhook_payload(config, webhook, channel.template.as_deref(), event);
+    if let WebhookPayload::Json(body) = &mut payload {
+        match webhook.format {
+            WebhookFormat::Generic => {
+                select_fields(body, channel, &webhook.extra);
+                rename_fields(body, &webhook.field_map);
+            }
+            // `extra` keys sit beside `data`, as extension attributes.
+            WebhookFormat::CloudEvents => {
+                if let Some(data) = body.get_mut("data") {
+                    select_fields(data, channel, &BTreeMap::new());
+                    rename_fields(data, &webhook.field_map);
+                }
+            }
+            _ => {}
+        }
+    }
+    payload
+}
+
+/// Renames top-level keys of a JSON event body per a channel's `field_map`.
+pub fn rename_fields(body: &mut Value, field_map: &BTreeMap<String, String>) {
+    let Value::Object(object) = body else {
+        return;
+    };
+    let renamed = field_map
+        .iter()
+        .filter_map(|(from, to)| Some((to.clone(), object.remove(from)?)))
+        .collect::<Vec<_>>();
+    object.extend(renamed);
+}
+
+/// Renders a webhook channel's payload, cutting captured output and then the
+/// oldest digest entries until it fits `max_payload` when the channel
+/// truncates oversize bodies. A cut body is marked `truncated`. Returns
+/// whether anything was cut, or an error when even the cut body is too large.
+pub fn fitted_payload(
+    config: &Config,
+    channel: &Channel,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+) -> Result<(WebhookPayload, bool), String> {
+    let payload = channel_payload(config, channel, webhook, event);
+    let Some(max) = webhook.max_payload else {
+        return Ok((payload, false));
+    };
+    if webhook.oversize != Oversize::Truncate || payload_size(&payload) <= max {
+        return Ok((payload, false));
+    }
+
+    let mut event = event.clone();
+    loop {
+        let tail_chars = event
+            .output_tail
+            .as_deref()
+            .map_or(0, |tail| tail.chars().count());
+        if tail_chars > 0 {
+            event.output_tail = event
+                .output_tail
+                .as_deref()
+                .map(|tail| tail.chars().skip(tail_chars.div_ceil(2)).collect())
+                .filter(|tail: &String| !tail.is_empty());
+        } else if !event.digest.is_empty() {
+            event.digest.drain(..event.digest.len().div_ceil(2));
+        } else {
+            return Err(format!(
+                "webhook body is still over max_payload ({max} bytes) after truncation"
+            ));
+        }
+
+        let mut payload = channel_payload(config, channel, webhook, &event);
+        mark_truncated(&mut payload);
+        if payload_size(&payload) <= max {
+            return Ok((payload, true));
+        }
+    }
+}
+
+/// Size in bytes of the request body `payload` is sent as.
+pub fn payload_size(payload: &WebhookPayload) -> u64 {
+    let size = match payload {
+        WebhookPayload::Json(body) => serde_json::to_vec(body).map_or(0, |body| body.len()),
+        WebhookPayload::Text { body, .. } => body.len(),
+    };
+    size as u64
+}
+
+fn mark_truncated(payload: &mut WebhookPayload) {
+    match payload {
+        WebhookPayload::Json(Value::Object(body)) => {
+            body.insert("truncated".to_string(), Value::Bool(true));
+        }
+        WebhookPayload::Json(_) => {}
+        WebhookPayload::Text { body, .. } => body.push_str("\n[truncated]"),
+    }
+}
+
+/// Encrypts a webhook body to the channel's `encrypt_to` recipients and wraps
+/// it in a JSON envelope. Preset headers are dropped, since they would carry
+/// the message in plain text.
+pub fn seal_payload(
+    config: &Config,
+    webhook: &WebhookChannel,
+    event: &CompletionEvent,
+    payload: WebhookPayload,
+) -> Result<WebhookPayload, String> {
+    let (content_type, plaintext) = match payload {
+        WebhookPayload::Json(body) => (
+            "application/json",
+            serde_json::to_vec(&body).map_err(|_| "failed to encode event payload".to_string())?,
+        ),
+        WebhookPayload::Text { body, .. } => ("text/plain", body.into_bytes()),
+    };
+    let ciphertext = encrypt_to(&config.secrets, &webhook.encrypt_to, &plaintext)
+        .map_err(|error| format!("failed to encrypt payload: {error}"))?;
+
+    Ok(WebhookPayload::Json(json!({
+        "tool": "brb",
+        "envelope": "age",
+        "version": 1,
+        "run_id": event.run_id,
+        "content_type": content_type,
+        "ciphertext": ciphertext,
+    })))
+}
+
+/// Drops event fields `channel` does not include from a JSON event body,
+/// keeping any `extra` keys.
+pub fn select_fields(body: &mut Value, channel: &Channel, extra: &BTreeMap<String, String>) {
+    if let Value::Object(object) = body {
+        object.retain(|key, _| extra.contains_key(key) || channel.includes_field(key));
+    }
+}
+
+/// The event JSON a `custom` channel's notifier reads on stdin, with `extra`
+/// merged in.
+pub fn custom_payload(channel: &Channel, custom: &CustomChannel, event: &CompletionEvent) -> Value {
+    let mut body = serde_json::to_value(event).unwrap_or(Value::Null);
+    merge_extra(&mut body, &custom.extra);
+    select_fields(&mut body, channel, &custom.extra);
+    body
+}
+
+/// The event JSON an `mqtt` channel publishes.
+pub fn mqtt_payload(channel: &Channel, event: &CompletionEvent) -> Value {
+    let mut body = serde_json::to_value(event).unwrap_or(Value::Null);
+    select_fields(&mut body, channel, &BTreeMap::new());
+    body
+}
+
+fn preset_payload(
+    config: &Config,
+    webhook: &WebhookChannel,
+    template: Option<&str>,
+    event: &CompletionEvent,
+) -> WebhookPayload {
+    let MessageText { title, body } = message_text(config, template, event);
+    let Appearance {
+        color, priority, ..
+    } = appearance(config, event);
+    let attachment = output_attachment(webhook.attach_output, event);
+    let limit = webhook
+        .max_message_length
+        .map(Limit::Chars)
+        .or(format_limit(webhook.format));
+    // the body gives way first; markup and the output attachment stay whole.
+    let size = |text: &str| limit.map_or(0, |limit| limit.size(text));
+
+    match webhook.format {
+        WebhookFormat::Generic => {
+            WebhookPayload::Json(serde_json::to_value(event).unwrap_or(Value::Null))
+        }
+        WebhookFormat::CloudEvents => WebhookPayload::Json(cloud_event(
+            event,
+            serde_json::to_value(event).unwrap_or(Value::Null),
+        )),
+        WebhookFormat::Slack => {
+            let section = attachment.map_or_else(String::new, |output| {
+                format!("\n*{}*\n```{output}```", messages(config).output)
+            });
+            let body = fit_text(body, limit, size("``````") + size(&section));
+            let text = format!("```{body}```{section}");
+            WebhookPayload::Json(json!({
+                "text": format!("{title}: {}", render_command(config, &event.command)),
+                "attachments": [{
+                    "color": format!("#{color:06x}"),
+                    "title": title,
+                    "text": text,
+                    "footer": event.host_label(),
+                }],
+            }))
+        }
+        WebhookFormat::Discord => {
+            let section = attachment.map_or_else(String::new, |output| {
+                format!("\n**{}**\n```\n{output}\n```", messages(config).output)
+            });
+            let body = fit_text(body, limit, size("```\n\n```") + size(&section));
+            let description = format!("```\n{body}\n```{section}");
+            let title = fit_text(title, Some(DISCORD_TITLE_LIMIT), 0);
+            WebhookPayload::Json(json!({
+                "content": title,
+                "embeds": [{
+                    "title": title,
+                    "description": description,
+                    "color": color,
+                    "footer": { "text": event.host_label() },
+                    "timestamp": event.finished_at,
+                }],
+            }))
+        }
+        WebhookFormat::Teams => {
+            // the card limit covers the whole card, so both titles count.
+            let reserved = size(&format!("`` on {}", event.host_label())) + 2 * size(&title);
+            let body = fit_text(body, limit, reserved);
+            WebhookPayload::Json(json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": title,
+                "themeColor": format!("{color:06X}"),
+                "title": title,
+                "text": format!("`{}` on {}", body, event.host_label()),
+            }))
+        }
+        WebhookFormat::Ntfy => {
+            let body = fit_text(body, limit, 0);
+            let headers = BTreeMap::from([
+                ("Title".to_string(), title),
+                (
+                    "Tags".to_string(),
+                    if event.exit_code == 0 {
+                        "white_check_mark"
+                    } else {
+                        "x"
+                    }
+                    .to_string(),
+                ),
+                ("Priority".to_string(), priority.as_str().to_string()),
+            ]);
+            WebhookPayload::Text { body, headers }
+        }
+    }
+}
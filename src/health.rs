@@ -0,0 +1,146 @@
+use crate::channels::DeliveryResult;
+use crate::config::Config;
+use crate::state::StateStore;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const HEALTH: &str = "channel-health";
+
+/// How deliveries to one channel have gone lately, as shown by
+/// `brb channels list`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelHealth {
+    /// UTC timestamp (RFC3339) of the last successful delivery.
+    pub last_success: Option<String>,
+
+    /// The last failed delivery.
+    pub last_error: Option<DeliveryFailure>,
+}
+
+/// A failed delivery kept in channel health.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryFailure {
+    /// UTC timestamp (RFC3339) of the failure.
+    pub at: String,
+
+    /// Error message, already redacted.
+    pub message: String,
+}
+
+/// Records the outcome of each delivery at `at` (RFC3339, with enough
+/// precision to order a failure and a success in the same second). Skipped and
+/// queued deliveries sent nothing, so they leave the health as it was.
+pub fn record_deliveries(
+    store: &StateStore,
+    results: &[DeliveryResult],
+    at: &str,
+) -> Result<(), String> {
+    if results
+        .iter()
+        .all(|result| result.success && !result.delivered())
+    {
+        return Ok(());
+    }
+
+    let _lock = store.lock(HEALTH).map_err(|error| error.to_string())?;
+    let mut health: BTreeMap<String, ChannelHealth> = store.load(HEALTH);
+    for result in results {
+        if result.delivered() {
+            health
+                .entry(result.channel_id.clone())
+                .or_default()
+                .last_success = Some(at.to_string());
+        } else if !result.success {
+            health
+                .entry(result.channel_id.clone())
+                .or_default()
+                .last_error = Some(DeliveryFailure {
+                at: at.to_string(),
+                message: result
+                    .error
+                    .as_ref()
+                    .map_or("unknown notification error", |error| error.message.as_str())
+                    .to_string(),
+            });
+        }
+    }
+    store.save(HEALTH, &health)
+}
+
+/// Recorded health of every channel that has been delivered to, by ID.
+pub fn channel_health(store: &StateStore) -> BTreeMap<String, ChannelHealth> {
+    store.load(HEALTH)
+}
+
+/// One channel's row in `brb channels list`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChannelOverview {
+    /// Channel ID from config.
+    pub id: String,
+
+    /// Channel type, e.g. `webhook`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+
+    /// Whether runs notify the channel without `--channel`.
+    pub default: bool,
+
+    /// Whether `--all-channels` selects the channel.
+    pub enabled: bool,
+
+    /// Which completions the channel is notified about.
+    pub notify_on: &'static str,
+
+    /// UTC timestamp (RFC3339) of the last successful delivery.
+    pub last_success: Option<String>,
+
+    /// The last failed delivery.
+    pub last_error: Option<DeliveryFailure>,
+}
+
+impl ChannelOverview {
+    /// Whether the channel's most recent delivery failed. Timestamps are
+    /// compared as instants, since older records only kept whole seconds.
+    pub fn failing(&self) -> bool {
+        match (&self.last_error, &self.last_success) {
+            (Some(error), Some(success)) => {
+                match (parse_timestamp(&error.at), parse_timestamp(success)) {
+                    (Some(error), Some(success)) => error > success,
+                    _ => error.at > *success,
+                }
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// Every configured channel by ID, with its recorded health.
+pub fn channel_overview(
+    config: &Config,
+    mut health: BTreeMap<String, ChannelHealth>,
+) -> Vec<ChannelOverview> {
+    config
+        .channels
+        .iter()
+        .map(|(channel_id, channel)| {
+            let health = health.remove(channel_id).unwrap_or_default();
+            ChannelOverview {
+                id: channel_id.clone(),
+                kind: channel.type_name(),
+                default: config.default_channels.contains(channel_id),
+                enabled: channel.enabled,
+                notify_on: channel.notify_on.name(),
+                last_success: health.last_success,
+                last_error: health.last_error,
+            }
+        })
+        .collect()
+}
+
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
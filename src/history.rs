@@ -0,0 +1,64 @@
+use crate::event::CompletionEvent;
+use crate::state::StateStore;
+
+/// Completed runs kept in history; older runs are dropped first.
+pub const HISTORY_LIMIT: usize = 100;
+
+const HISTORY: &str = "history";
+
+/// Appends a completed run to the history, dropping the oldest runs beyond
+/// [`HISTORY_LIMIT`].
+pub fn record_run(store: &StateStore, event: &CompletionEvent) -> Result<(), String> {
+    let _lock = store.lock(HISTORY).map_err(|error| error.to_string())?;
+    let mut runs: Vec<CompletionEvent> = store.load(HISTORY);
+    runs.push(event.clone());
+    if runs.len() > HISTORY_LIMIT {
+        runs.drain(..runs.len() - HISTORY_LIMIT);
+    }
+    store.save(HISTORY, &runs)
+}
+
+/// Recorded runs, oldest first.
+pub fn recent_runs(store: &StateStore) -> Vec<CompletionEvent> {
+    store.load(HISTORY)
+}
+
+/// Finds a recorded run by its ID or an unambiguous prefix of it.
+pub fn find_run(store: &StateStore, run_id: &str) -> Result<CompletionEvent, String> {
+    let runs = recent_runs(store);
+    Ok(runs[run_index(&runs, run_id)?].clone())
+}
+
+/// Adds a note to a recorded run, returning the run with all its notes.
+pub fn annotate_run(
+    store: &StateStore,
+    run_id: &str,
+    note: &str,
+) -> Result<CompletionEvent, String> {
+    let _lock = store.lock(HISTORY).map_err(|error| error.to_string())?;
+    let mut runs: Vec<CompletionEvent> = store.load(HISTORY);
+    let index = run_index(&runs, run_id)?;
+    runs[index].notes.push(note.to_string());
+    store.save(HISTORY, &runs)?;
+    Ok(runs.swap_remove(index))
+}
+
+/// Position of the run matching `run_id` exactly, or else as the only run it
+/// is a prefix of.
+fn run_index(runs: &[CompletionEvent], run_id: &str) -> Result<usize, String> {
+    if let Some(index) = runs.iter().position(|run| run.run_id == run_id) {
+        return Ok(index);
+    }
+
+    let mut matches = runs
+        .iter()
+        .enumerate()
+        .filter(|(_, run)| run.run_id.starts_with(run_id));
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) if !run_id.is_empty() => Ok(index),
+        (Some(_), _) => Err(format!(
+            "run id `{run_id}` matches more than one run in history"
+        )),
+        (None, _) => Err(format!("no run `{run_id}` in history")),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::event::CompletionEvent;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs each hook through the platform shell, one after another, in the
+/// event's working directory with the event as JSON on stdin. Output goes to
+/// the terminal. Returns a message for each hook that failed; hooks never
+/// change brb's exit code.
+pub fn run_hooks<'a>(
+    commands: impl IntoIterator<Item = &'a String>,
+    event: &CompletionEvent,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let payload = match serde_json::to_vec(event) {
+        Ok(payload) => payload,
+        Err(error) => return vec![format!("failed to encode event for hooks: {error}")],
+    };
+
+    for command in commands {
+        if let Err(problem) = run_hook(command, &event.cwd, &payload) {
+            problems.push(format!("hook `{command}` {problem}"));
+        }
+    }
+
+    problems
+}
+
+fn run_hook(command: &str, cwd: &str, payload: &[u8]) -> Result<(), String> {
+    let mut child = shell(command)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("failed to start: {error}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // a hook may exit without reading stdin; that is not a failure.
+        let _ = stdin.write_all(payload);
+    }
+
+    let status = child
+        .wait()
+        .map_err(|error| format!("failed while running: {error}"))?;
+    match status.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(format!("exited with status {code}")),
+        None => Err("was terminated by a signal".to_string()),
+    }
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
@@ -1,5 +1,50 @@
+pub mod ack;
+pub mod active;
+pub mod alias;
+pub mod bench;
+pub mod capture;
+pub mod cargo;
 pub mod channels;
 pub mod cli;
 pub mod config;
+pub mod container;
+pub mod context;
+pub mod dbus;
+pub mod diagnostic;
+pub mod digest;
+pub mod dnd;
+pub mod doctor;
+pub mod environment;
 pub mod event;
+pub mod fingerprint;
+pub mod format;
+pub mod health;
+pub mod history;
+pub mod hooks;
+pub mod import;
+pub mod lock;
+pub mod machine_id;
+pub mod messages;
+#[cfg(feature = "webhook")]
+pub mod mqtt;
+pub mod ncprefs;
+pub mod output;
+pub mod perms;
+pub mod pipeline;
+pub mod probe;
+pub mod progress;
+pub mod project;
+pub mod redact;
+pub mod reference;
+pub mod report;
 pub mod runner;
+pub mod secrets;
+pub mod session;
+pub mod snapshot;
+pub mod state;
+pub mod template;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+pub mod trigger;
+pub mod units;
+pub mod version;
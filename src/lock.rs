@@ -0,0 +1,130 @@
+use crate::config::{ConfigError, private_data_dir};
+use crate::perms::{create_private_dir, private_file_options};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// A held named lock; released when dropped.
+#[derive(Debug)]
+pub struct RunLock {
+    /// Open lock file holding the OS-level lock.
+    _file: File,
+
+    /// Contention details reported in the completion event.
+    pub info: LockInfo,
+}
+
+/// Lock acquisition details included in completion events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockInfo {
+    /// Lock name passed to `--lock`.
+    pub name: String,
+
+    /// Whether another run held the lock when this run asked for it.
+    pub contended: bool,
+
+    /// Time spent waiting for the lock in milliseconds.
+    pub waited_ms: u128,
+}
+
+/// Lock acquisition failures.
+#[derive(Debug, Error)]
+pub enum LockError {
+    #[error("invalid lock name `{0}` (use letters, digits, `.`, `_` or `-`)")]
+    InvalidName(String),
+    #[error("lock `{name}` is held by another brb run{holder}")]
+    Busy { name: String, holder: String },
+    #[error("failed to use lock file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+/// Acquires a named lock under brb's data directory.
+///
+/// When `wait` is false an already-held lock fails immediately with
+/// [`LockError::Busy`]; otherwise this blocks until the holder exits.
+pub fn acquire_lock(name: &str, wait: bool) -> Result<RunLock, LockError> {
+    acquire_lock_in(&private_data_dir()?.join("locks"), name, wait)
+}
+
+/// Acquires a named lock inside an explicit directory.
+///
+/// This helper is used by integration tests.
+pub fn acquire_lock_in(dir: &Path, name: &str, wait: bool) -> Result<RunLock, LockError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-'))
+        && !name.starts_with('.');
+    if !valid {
+        return Err(LockError::InvalidName(name.to_string()));
+    }
+
+    let path = dir.join(format!("{name}.lock"));
+    let io_error = |source| LockError::Io {
+        path: path.clone(),
+        source,
+    };
+
+    create_private_dir(dir).map_err(io_error)?;
+    let mut file = private_file_options()
+        .read(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(io_error)?;
+
+    let started = Instant::now();
+    let contended = match file.try_lock() {
+        Ok(()) => false,
+        Err(TryLockError::WouldBlock) => {
+            if !wait {
+                return Err(LockError::Busy {
+                    name: name.to_string(),
+                    holder: lock_holder(&mut file),
+                });
+            }
+
+            eprintln!("brb: waiting for lock `{name}`{}", lock_holder(&mut file));
+            file.lock().map_err(io_error)?;
+            true
+        }
+        Err(TryLockError::Error(error)) => return Err(io_error(error)),
+    };
+    let waited = if contended {
+        started.elapsed()
+    } else {
+        Duration::ZERO
+    };
+
+    // best effort: the pid only helps people work out who holds the lock.
+    let _ = file
+        .set_len(0)
+        .and_then(|()| file.rewind())
+        .and_then(|()| write!(file, "{}", std::process::id()));
+
+    Ok(RunLock {
+        _file: file,
+        info: LockInfo {
+            name: name.to_string(),
+            contended,
+            waited_ms: waited.as_millis(),
+        },
+    })
+}
+
+fn lock_holder(file: &mut File) -> String {
+    let mut pid = String::new();
+    if file.rewind().is_ok() && file.read_to_string(&mut pid).is_ok() && !pid.trim().is_empty() {
+        format!(" (pid {})", pid.trim())
+    } else {
+        String::new()
+    }
+}
@@ -0,0 +1,39 @@
+use crate::config::private_data_dir;
+use crate::event::new_run_id;
+use crate::perms::write_private;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// File in the data directory holding this machine's ID.
+const MACHINE_ID_FILE: &str = "machine-id";
+
+/// This machine's anonymous ID, generated on first use and stored in the
+/// data directory. `None` when the data directory cannot be used.
+pub fn machine_id() -> Option<String> {
+    let dir = private_data_dir().ok()?;
+    load_or_create(&dir.join(MACHINE_ID_FILE)).ok()
+}
+
+/// Reads the ID stored at `path`, writing a new random one when the file is
+/// missing or does not hold a valid ID.
+pub fn load_or_create(path: &Path) -> io::Result<String> {
+    match fs::read_to_string(path) {
+        Ok(raw) if is_valid(raw.trim()) => return Ok(raw.trim().to_string()),
+        Ok(_) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => return Err(error),
+    }
+
+    let id = new_run_id();
+    write_private(path, format!("{id}\n"))?;
+    Ok(id)
+}
+
+fn is_valid(id: &str) -> bool {
+    id.len() == 36
+        && id.char_indices().all(|(index, ch)| match index {
+            8 | 13 | 18 | 23 => ch == '-',
+            _ => ch.is_ascii_hexdigit(),
+        })
+}
@@ -1,8 +1,57 @@
-use brb_cli::channels::{DeliveryResult, notify_selected};
-use brb_cli::cli::{Action, ChannelsAction, ConfigAction, RunArgs, parse_args, usage};
-use brb_cli::config::{ConfigError, InitStatus, config_file_path, init_config, load_config};
-use brb_cli::event::CompletionEvent;
-use brb_cli::runner::run_command;
+use brb_cli::active::{active_runs_dir, progress_watcher, register_run, running_commands};
+use brb_cli::alias::expand_alias;
+use brb_cli::bench::{BenchSummary, combined_run};
+use brb_cli::channels::{
+    DeliveryResult, Interaction, channels_for_event, notify_selected, ping_start, post_progress,
+    preflight_problems, show_desktop, tiered_channels, with_exit_code_channels,
+};
+use brb_cli::cli::{
+    Action, AliasesAction, ChannelsAction, ConfigAction, HistoryAction, ImportAction, LockMode,
+    PreviewStatus, RunArgs, SESSION_ENV, SecretAction, SessionAction, TemplateAction,
+    apply_env_overrides, parse_invocation, usage,
+};
+use brb_cli::config::{
+    Config, ConfigError, ConfigWatch, InitStatus, LoadOptions, LoadedConfig, MigrateStatus,
+    UnknownFields, config_file_path, data_dir, init_config, load_config_or_fallback,
+    load_config_with, migrate_config_path,
+};
+use brb_cli::container::detect_container;
+use brb_cli::context::sample_context;
+use brb_cli::diagnostic::closest_match;
+use brb_cli::doctor::permission_problems;
+use brb_cli::environment::SystemEnvironment;
+use brb_cli::event::{CompletionEvent, new_run_id, status_for};
+use brb_cli::fingerprint::fingerprint;
+use brb_cli::format::{messages, render_duration};
+use brb_cli::health::{channel_health, channel_overview, record_deliveries};
+use brb_cli::history::{annotate_run, find_run, recent_runs, record_run};
+use brb_cli::hooks::run_hooks;
+use brb_cli::import::{import_apprise_config, import_ntfy_url, import_shoutrrr, render_channels};
+use brb_cli::lock::{LockError, acquire_lock};
+use brb_cli::machine_id::machine_id;
+use brb_cli::output::{ColorChoice, Output};
+use brb_cli::perms::private_file_options;
+use brb_cli::probe::{AuthResult, ProbeResult, check_channels_auth, probe_channels};
+use brb_cli::project::detect_project;
+use brb_cli::redact::redact_command;
+use brb_cli::reference::{channel_reference, render_reference};
+use brb_cli::report::RunReport;
+use brb_cli::runner::{
+    CaptureMode, Interactivity, RunOptions, RunResult, capture_mode, run_command_with,
+};
+use brb_cli::secrets::{decrypt, encrypt, secrets_from_source, set_secret_in_source};
+use brb_cli::session::{AUTO_SESSION, end_session, resolve_session, session_runs, session_summary};
+use brb_cli::snapshot::sample_snapshot;
+use brb_cli::state::StateStore;
+use brb_cli::template::{desktop_preview, preview, render_body};
+use brb_cli::trigger::TriggerWatch;
+use brb_cli::version::build_info;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use regex::Regex;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -13,13 +62,23 @@ enum AppError {
     Cli(#[from] brb_cli::cli::CliError),
     #[error(transparent)]
     Config(#[from] ConfigError),
+    #[error(transparent)]
+    Lock(#[from] LockError),
 }
 
 fn main() {
-    let code = match run() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let (result, out) = match parse_invocation(args) {
+        Ok(invocation) => {
+            let out = Output::detect(invocation.color);
+            (run(invocation.action, &out), out)
+        }
+        Err(error) => (Err(error.into()), Output::detect(ColorChoice::Auto)),
+    };
+    let code = match result {
         Ok(code) => code,
         Err(error) => {
-            eprintln!("brb: {error}");
+            eprintln!("brb: {}", out.stderr.failure(&error.to_string()));
             1
         }
     };
@@ -27,23 +86,46 @@ fn main() {
     std::process::exit(code);
 }
 
-fn run() -> Result<i32, AppError> {
-    let args = std::env::args().skip(1).collect::<Vec<_>>();
-    let action = parse_args(args)?;
-
+fn run(action: Action, out: &Output) -> Result<i32, AppError> {
     match action {
         Action::Help => {
-            println!("{}", usage());
+            println!("{}", usage(out.stdout.enabled()));
             Ok(0)
         }
-        Action::Version => {
+        Action::Version { json: false } => {
             println!("brb {}", env!("CARGO_PKG_VERSION"));
             Ok(0)
         }
+        Action::Version { json: true } => {
+            let info = serde_json::to_string_pretty(&build_info()).map_err(|error| {
+                AppError::Message(format!("failed to encode build info: {error}"))
+            })?;
+            println!("{info}");
+            Ok(0)
+        }
         Action::Init => handle_init(),
-        Action::Channels(action) => handle_channels(action),
+        // the reference needs no config, so a broken one cannot hide it.
+        Action::Channels(ChannelsAction::Docs { channel_type }) => {
+            handle_channel_docs(&channel_type)
+        }
+        Action::Channels(action) => handle_channels(action, out),
         Action::Config(action) => handle_config(action),
-        Action::Run(args) => handle_run(args),
+        Action::Aliases(action) => handle_aliases(action, out),
+        Action::Template(action) => handle_template(action, out),
+        Action::Secret(action) => handle_secret(action),
+        Action::History(action) => handle_history(action, out),
+        Action::Session(action) => handle_session(action, out),
+        Action::Import(action) => handle_import(action),
+        Action::Annotate {
+            run_id,
+            note,
+            post,
+            channels,
+        } => handle_annotate(&run_id, &note, post, channels, out),
+        Action::Status => handle_status(out),
+        Action::Doctor => handle_doctor(out),
+        Action::Run(args) => handle_run(args, out),
+        Action::Bench { runs, args } => handle_bench(runs, args, out),
     }
 }
 
@@ -60,62 +142,222 @@ fn handle_init() -> Result<i32, AppError> {
     }
 }
 
-fn handle_channels(action: ChannelsAction) -> Result<i32, AppError> {
-    let loaded = load_config()?;
+fn handle_channels(action: ChannelsAction, out: &Output) -> Result<i32, AppError> {
+    // validate is strict unless asked otherwise, whatever the config says.
+    let options = match action {
+        ChannelsAction::Validate { permissive, .. } => LoadOptions {
+            unknown_fields: Some(if permissive {
+                UnknownFields::Warn
+            } else {
+                UnknownFields::Error
+            }),
+            ..LoadOptions::default()
+        },
+        _ => LoadOptions::default(),
+    };
+    let loaded = load(options, out)?;
 
     match action {
-        ChannelsAction::List => {
-            println!("Config: {}\n", loaded.path.display());
+        ChannelsAction::List { json } => {
+            let health = StateStore::open()
+                .map(|store| channel_health(&store))
+                .unwrap_or_default();
+            let channels = channel_overview(&loaded.config, health);
+            if json {
+                let json = serde_json::to_string_pretty(&channels).map_err(|error| {
+                    AppError::Message(format!("failed to encode channels: {error}"))
+                })?;
+                println!("{json}");
+                return Ok(0);
+            }
 
-            println!("Channels:");
-            for (channel_id, channel) in &loaded.config.channels {
-                let default_label = if loaded.config.default_channels.contains(channel_id) {
-                    " (default)"
+            let style = out.stdout;
+            println!(
+                "{} {}\n",
+                style.heading("Config:"),
+                style.dim(&loaded.path.display().to_string())
+            );
+
+            println!("{}", style.heading("Channels:"));
+            for channel in &channels {
+                let default_label = if channel.default {
+                    style.dim(" (default)")
                 } else {
-                    ""
+                    String::new()
+                };
+                let disabled_label = if channel.enabled {
+                    String::new()
+                } else {
+                    style.dim(" (disabled)")
                 };
                 println!(
-                    "  {} [{}]{}",
-                    channel_id,
-                    channel.type_name(),
-                    default_label
+                    "  {} [{}]{}{} {}",
+                    style.name(&channel.id),
+                    channel.kind,
+                    default_label,
+                    disabled_label,
+                    style.dim(&format!("notify on {}", channel.notify_on))
                 );
+                let delivered = channel
+                    .last_success
+                    .as_deref()
+                    .map_or_else(|| "never".to_string(), whole_seconds);
+                println!("    last delivered: {delivered}");
+                if let Some(error) = &channel.last_error {
+                    let line =
+                        format!("last error: {} {}", whole_seconds(&error.at), error.message);
+                    if channel.failing() {
+                        println!("    {}", style.failure(&line));
+                    } else {
+                        println!("    {}", style.dim(&line));
+                    }
+                }
             }
 
             println!();
             Ok(0)
         }
-        ChannelsAction::Validate => {
-            println!("brb: config is valid ({})\n", loaded.path.display());
-            Ok(0)
+        ChannelsAction::Validate { probe, auth, .. } => {
+            let problems = preflight_problems(&loaded.config);
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("brb: {}", out.stderr.failure(&problem.to_string()));
+                }
+                return Ok(1);
+            }
+
+            println!(
+                "brb: {} ({})\n",
+                out.stdout.success("config is valid"),
+                loaded.path.display()
+            );
+            let mut passed = true;
+            if probe {
+                let results = probe_channels(&loaded.config);
+                if results.is_empty() {
+                    println!("brb: no network channels to probe\n");
+                } else {
+                    println!("{}", out.stdout.heading("Probes:"));
+                    for result in &results {
+                        let line = result.to_string();
+                        if result.reachable() {
+                            println!("  {line}");
+                        } else {
+                            println!("  {}", out.stdout.failure(&line));
+                        }
+                    }
+                    println!();
+                    passed &= results.iter().all(ProbeResult::reachable);
+                }
+            }
+            if auth {
+                let results = check_channels_auth(&loaded.config);
+                if results.is_empty() {
+                    println!("brb: no network channels to check\n");
+                } else {
+                    println!("{}", out.stdout.heading("Credentials:"));
+                    for result in &results {
+                        let line = result.to_string();
+                        if result.ok() {
+                            println!("  {line}");
+                        } else {
+                            println!("  {}", out.stdout.failure(&line));
+                        }
+                    }
+                    println!();
+                    passed &= results.iter().all(AuthResult::ok);
+                }
+            }
+            Ok(if passed { 0 } else { 1 })
         }
         ChannelsAction::Test { channel_id } => {
             if !loaded.config.channels.contains_key(&channel_id) {
+                let channel_ids = loaded.config.channels.keys().map(String::as_str);
+                let hint = closest_match(&channel_id, channel_ids)
+                    .map(|suggestion| format!("; did you mean `{suggestion}`?"))
+                    .unwrap_or_default();
                 return Err(AppError::Message(format!(
-                    "channel `{channel_id}` is not defined in config"
+                    "channel `{channel_id}` is not defined in config{hint}"
                 )));
             }
 
-            let event = CompletionEvent::test_event();
-            let results =
-                notify_selected(&loaded.config, std::slice::from_ref(&channel_id), &event);
+            let mut event = CompletionEvent::test_event();
+            event.container = detect_container();
+            if loaded.config.machine_id {
+                event.machine_id = machine_id();
+            }
+            let results = notify(&loaded.config, std::slice::from_ref(&channel_id), &event);
             let result = &results[0];
 
             if result.success {
-                println!("brb: test notification delivered on `{channel_id}`\n");
+                println!(
+                    "brb: {} on `{channel_id}`\n",
+                    out.stdout.success("test notification delivered")
+                );
                 Ok(0)
             } else {
                 let reason = result
                     .error
-                    .as_deref()
-                    .unwrap_or("unknown notification error");
-                eprintln!("brb: test notification failed on `{channel_id}`: {reason}\n");
+                    .as_ref()
+                    .map_or("unknown notification error", |error| error.message.as_str());
+                eprintln!(
+                    "brb: {} on `{channel_id}`: {reason}\n",
+                    out.stderr.failure("test notification failed")
+                );
                 Ok(1)
             }
         }
+        ChannelsAction::Preview {
+            channel_id,
+            status,
+            duration,
+        } => {
+            let mut event = CompletionEvent::test_event();
+            event.command = ["brb", "channels", "preview"].map(String::from).to_vec();
+            if status == PreviewStatus::Failure {
+                event.exit_code = 1;
+                event.status = status_for(1).to_string();
+            }
+            if let Some(duration) = duration {
+                event.duration_ms = duration.as_millis();
+            }
+
+            print!(
+                "{}",
+                desktop_preview(&loaded.config, &channel_id, &event).map_err(AppError::Message)?
+            );
+            match show_desktop(&loaded.config, &loaded.config.channels[&channel_id], &event) {
+                Ok(()) => Ok(0),
+                Err(error) => {
+                    eprintln!("brb: could not show the preview: {}", error.message);
+                    Ok(1)
+                }
+            }
+        }
+        ChannelsAction::Render { channel_id, status } => {
+            let mut event = CompletionEvent::test_event();
+            event.command = ["brb", "channels", "render"].map(String::from).to_vec();
+            if status == PreviewStatus::Failure {
+                event.exit_code = 1;
+                event.status = status_for(1).to_string();
+            }
+
+            print!(
+                "{}",
+                render_body(&loaded.config, &channel_id, &event).map_err(AppError::Message)?
+            );
+            Ok(0)
+        }
+        ChannelsAction::Docs { channel_type } => handle_channel_docs(&channel_type),
     }
 }
 
+fn handle_channel_docs(channel_type: &str) -> Result<i32, AppError> {
+    let reference = channel_reference(channel_type).map_err(AppError::Message)?;
+    print!("{}", render_reference(reference));
+    Ok(0)
+}
+
 fn handle_config(action: ConfigAction) -> Result<i32, AppError> {
     match action {
         ConfigAction::Path => {
@@ -123,30 +365,1018 @@ fn handle_config(action: ConfigAction) -> Result<i32, AppError> {
             println!("{}", path.display());
             Ok(0)
         }
+        ConfigAction::MigratePath => {
+            match migrate_config_path()? {
+                MigrateStatus::Moved { from, to } => {
+                    println!(
+                        "brb: moved config from {} to {}",
+                        from.display(),
+                        to.display()
+                    );
+                }
+                MigrateStatus::AlreadyCurrent(path) => {
+                    println!("brb: config already at {}", path.display());
+                }
+                MigrateStatus::NothingFound => {
+                    println!("brb: no config found in a legacy location");
+                }
+            }
+            Ok(0)
+        }
     }
 }
 
-fn handle_run(args: RunArgs) -> Result<i32, AppError> {
-    let loaded = load_config()?;
-    let selected_channels = resolve_channels(&loaded.config.default_channels, &args.channels)?;
+fn handle_template(action: TemplateAction, out: &Output) -> Result<i32, AppError> {
+    let loaded = load(LoadOptions::default(), out)?;
+
+    match action {
+        TemplateAction::Render {
+            target,
+            event,
+            exit_code,
+        } => {
+            let mut sample = CompletionEvent::test_event();
+            if let Some(exit_code) = exit_code {
+                sample.exit_code = exit_code;
+                sample.status = status_for(exit_code).to_string();
+                sample.exit_reason = loaded
+                    .config
+                    .exit_codes
+                    .get(&exit_code)
+                    .and_then(|rule| rule.message.clone());
+            }
+            if let Some(path) = event {
+                let raw = std::fs::read_to_string(&path).map_err(|error| {
+                    AppError::Message(format!("failed to read {}: {error}", path.display()))
+                })?;
+                let overrides = serde_json::from_str(&raw).map_err(|error| {
+                    AppError::Message(format!("invalid event JSON in {}: {error}", path.display()))
+                })?;
+                sample = sample
+                    .with_overrides(&overrides)
+                    .map_err(AppError::Message)?;
+            }
+
+            print!(
+                "{}",
+                preview(&loaded.config, &target, &sample).map_err(AppError::Message)?
+            );
+            Ok(0)
+        }
+    }
+}
+
+fn handle_secret(action: SecretAction) -> Result<i32, AppError> {
+    let path = config_file_path()?;
+    let source = std::fs::read_to_string(&path).map_err(ConfigError::ReadFailed)?;
+    let secrets = secrets_from_source(&source)?;
+
+    let (name, plaintext) = match action {
+        SecretAction::Decrypt { name } => {
+            let armored = secrets
+                .values
+                .get(&name)
+                .ok_or_else(|| ConfigError::UnknownSecret(name.clone()))?;
+            print!("{}", decrypt(&secrets, armored).map_err(AppError::Message)?);
+            return Ok(0);
+        }
+        SecretAction::Encrypt { name } => {
+            let mut plaintext = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut plaintext)
+                .map_err(|error| AppError::Message(format!("failed to read stdin: {error}")))?;
+            (name, plaintext)
+        }
+        SecretAction::Edit { name } => {
+            let current = match secrets.values.get(&name) {
+                Some(armored) => decrypt(&secrets, armored).map_err(AppError::Message)?,
+                None => String::new(),
+            };
+            let edited = edit_in_editor(&current)?;
+            if edited == current {
+                eprintln!("brb: secret `{name}` unchanged");
+                return Ok(0);
+            }
+            (name, edited)
+        }
+    };
+
+    let armored = encrypt(&secrets, &plaintext).map_err(AppError::Message)?;
+    std::fs::write(&path, set_secret_in_source(&source, &name, &armored)).map_err(|error| {
+        AppError::Message(format!("failed to write {}: {error}", path.display()))
+    })?;
+    eprintln!("brb: stored secret `{name}` in {}", path.display());
+    Ok(0)
+}
+
+/// Opens `contents` in `$VISUAL`/`$EDITOR` via a private temp file and returns
+/// the edited text. The temp file is removed afterwards.
+fn edit_in_editor(contents: &str) -> Result<String, AppError> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let path = std::env::temp_dir().join(format!("brb-secret-{}.txt", std::process::id()));
+
+    let written = private_file_options()
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, contents.as_bytes()));
+    if let Err(error) = written {
+        return Err(AppError::Message(format!(
+            "failed to create temp file for editing: {error}"
+        )));
+    }
+
+    // editors like `code --wait` come with arguments, so go through the shell.
+    let mut shell = if cfg!(windows) {
+        let mut shell = std::process::Command::new("cmd");
+        shell
+            .arg("/C")
+            .arg(format!("{editor} \"{}\"", path.display()));
+        shell
+    } else {
+        let mut shell = std::process::Command::new("sh");
+        shell
+            .arg("-c")
+            .arg(format!("{editor} \"$1\""))
+            .arg("sh")
+            .arg(&path);
+        shell
+    };
+    let status = shell.status();
+    let edited = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            return Err(AppError::Message(format!(
+                "editor `{editor}` exited with {status}"
+            )));
+        }
+        Err(error) => {
+            return Err(AppError::Message(format!(
+                "failed to run editor `{editor}`: {error}"
+            )));
+        }
+    }
+    edited.map_err(|error| AppError::Message(format!("failed to read edited secret: {error}")))
+}
+
+fn handle_status(out: &Output) -> Result<i32, AppError> {
+    let running = running_commands(&active_runs_dir()?);
+    if running.is_empty() {
+        println!("brb: no runs in progress\n");
+        return Ok(0);
+    }
+
+    println!("{}", out.stdout.heading("Running:"));
+    for run in &running {
+        let progress = run
+            .progress
+            .as_deref()
+            .map(|progress| {
+                let elapsed = chrono::DateTime::parse_from_rfc3339(&run.started_at)
+                    .map(|started_at| (Utc::now() - started_at.to_utc()).num_milliseconds())
+                    .unwrap_or_default();
+                let elapsed = render_duration(&Config::default(), elapsed.max(0) as u128);
+                format!(
+                    "  {}",
+                    out.stdout.name(&format!("{progress} after {elapsed}"))
+                )
+            })
+            .unwrap_or_default();
+        println!(
+            "  pid {:<7}  {}  {}{progress}",
+            run.pid,
+            out.stdout.dim(&run.started_at.to_string()),
+            run.command.join(" ")
+        );
+    }
+
+    println!();
+    Ok(0)
+}
+
+fn handle_doctor(out: &Output) -> Result<i32, AppError> {
+    let config_path = config_file_path()?;
+    let problems = permission_problems(&config_path, &data_dir()?);
+    if problems.is_empty() {
+        println!(
+            "brb: {}\n",
+            out.stdout.success("no permission problems found")
+        );
+        return Ok(0);
+    }
+
+    for problem in &problems {
+        eprintln!("brb: {}", out.stderr.warning(&problem.to_string()));
+    }
+    Ok(1)
+}
+
+fn handle_aliases(action: AliasesAction, out: &Output) -> Result<i32, AppError> {
+    let loaded = load(LoadOptions::default(), out)?;
+
+    match action {
+        AliasesAction::List => {
+            let style = out.stdout;
+            println!(
+                "{} {}\n",
+                style.heading("Config:"),
+                style.dim(&loaded.path.display().to_string())
+            );
+
+            println!("{}", style.heading("Aliases:"));
+            for (name, alias) in &loaded.config.aliases {
+                let channels = if alias.channels.is_empty() {
+                    String::new()
+                } else {
+                    format!(" [{}]", alias.channels.join(", "))
+                };
+                println!(
+                    "  {}: {}{channels}",
+                    style.name(name),
+                    alias.command.join(" ")
+                );
+            }
+
+            println!();
+            Ok(0)
+        }
+    }
+}
+
+fn handle_history(action: HistoryAction, out: &Output) -> Result<i32, AppError> {
+    let store = StateStore::open()?;
+
+    match action {
+        HistoryAction::List {
+            fingerprint,
+            session,
+        } => {
+            let runs: Vec<_> = recent_runs(&store)
+                .into_iter()
+                .filter(|run| fingerprint.as_ref().is_none_or(|f| run.fingerprint == *f))
+                .filter(|run| session.is_none() || run.session == session)
+                .collect();
+            if runs.is_empty() {
+                match (fingerprint, session) {
+                    (Some(fingerprint), _) => {
+                        println!("brb: no runs recorded with fingerprint {fingerprint}\n")
+                    }
+                    (None, Some(session)) => {
+                        println!("brb: no runs recorded in session {session}\n")
+                    }
+                    (None, None) => println!("brb: no runs recorded yet\n"),
+                }
+                return Ok(0);
+            }
+
+            let style = out.stdout;
+            println!("{}", style.heading("Runs:"));
+            for run in &runs {
+                let notes = match run.notes.len() {
+                    0 => String::new(),
+                    1 => " (1 note)".to_string(),
+                    count => format!(" ({count} notes)"),
+                };
+                println!(
+                    "  {}  {}  {}  {}  {}{notes}",
+                    style.name(&run.run_id[..run.run_id.len().min(8)]),
+                    style.dim(&run.finished_at.to_string()),
+                    run.fingerprint,
+                    style.status(&run.status, &format!("{:<7}", run.status)),
+                    run.command.join(" ")
+                );
+            }
+
+            println!();
+            Ok(0)
+        }
+        HistoryAction::Show { run_id } => {
+            let event = find_run(&store, &run_id).map_err(AppError::Message)?;
+            let json = serde_json::to_string_pretty(&event)
+                .map_err(|error| AppError::Message(format!("failed to encode run: {error}")))?;
+            println!("{json}");
+            Ok(0)
+        }
+        HistoryAction::Resend { run_id, channels } => {
+            let loaded = load(LoadOptions::default(), out)?;
+            let event = find_run(&store, &run_id).map_err(AppError::Message)?;
+            let channels = if channels.is_empty() {
+                loaded.config.default_channels.clone()
+            } else {
+                channels
+            };
+
+            let results = notify(&loaded.config, &channels, &event);
+            Ok(print_followups("resent", &event.run_id, &results, out))
+        }
+    }
+}
+
+fn handle_import(action: ImportAction) -> Result<i32, AppError> {
+    let channels = match action {
+        ImportAction::NtfyUrl { url } => import_ntfy_url(&url).map(|channel| vec![channel]),
+        ImportAction::Apprise { path } => std::fs::read_to_string(&path)
+            .map_err(|error| format!("failed to read {}: {error}", path.display()))
+            .and_then(|config| import_apprise_config(&config))
+            .map(|imported| {
+                for skipped in &imported.skipped {
+                    eprintln!("brb: skipped {skipped}");
+                }
+                imported.channels
+            }),
+        ImportAction::Shoutrrr { url } => import_shoutrrr(&url),
+    }
+    .map_err(AppError::Message)?;
+    if channels.is_empty() {
+        return Err(AppError::Message("nothing to import".to_string()));
+    }
+
+    print!("{}", render_channels(&channels));
+    eprintln!(
+        "brb: add these to `channels` in {}; tokens are in plain text, so consider `brb secret encrypt`",
+        config_file_path()?.display()
+    );
+    Ok(0)
+}
+
+fn handle_annotate(
+    run_id: &str,
+    note: &str,
+    post: bool,
+    channels: Vec<String>,
+    out: &Output,
+) -> Result<i32, AppError> {
+    let store = StateStore::open()?;
+    let mut event = annotate_run(&store, run_id, note).map_err(AppError::Message)?;
+    println!("brb: added note to run {}", event.run_id);
+    if !post {
+        println!();
+        return Ok(0);
+    }
+
+    let loaded = load(LoadOptions::default(), out)?;
+    let channels = if channels.is_empty() {
+        loaded.config.default_channels.clone()
+    } else {
+        channels
+    };
+    // only the new note is posted, as a reply to the run's message.
+    event.posted_note = Some(note.to_string());
+    let results = notify(&loaded.config, &channels, &event);
+    Ok(print_followups(
+        "posted the note for",
+        &event.run_id,
+        &results,
+        out,
+    ))
+}
+
+fn handle_session(action: SessionAction, out: &Output) -> Result<i32, AppError> {
+    match action {
+        SessionAction::End { name, channels } => {
+            let requested = name
+                .or_else(|| std::env::var(SESSION_ENV).ok())
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| AUTO_SESSION.to_string());
+            let session = resolve_session(&requested, &SystemEnvironment).ok_or_else(|| {
+                AppError::Message(
+                    "could not tell which terminal session this is; name the session".to_string(),
+                )
+            })?;
+
+            let store = StateStore::open()?;
+            let runs = session_runs(&store, &session);
+            let Some(event) = session_summary(&session, &runs, &SystemEnvironment) else {
+                println!("brb: no runs recorded in session {session}\n");
+                return Ok(0);
+            };
+
+            let loaded = load(LoadOptions::default(), out)?;
+            let channels = if channels.is_empty() {
+                loaded.config.default_channels.clone()
+            } else {
+                channels
+            };
+            let results = notify(&loaded.config, &channels, &event);
+            if let Err(error) = end_session(&store, &session, &channels, Utc::now()) {
+                eprintln!("brb: could not end session {session}: {error}");
+            }
+            for result in &results {
+                if result.success {
+                    println!(
+                        "brb: {} of {} runs in session {session} on `{}`",
+                        out.stdout.success("sent a summary"),
+                        runs.len(),
+                        result.channel_id
+                    );
+                } else {
+                    let reason = result
+                        .error
+                        .as_ref()
+                        .map_or("unknown notification error", |error| error.message.as_str());
+                    eprintln!(
+                        "brb: {} on `{}`: {reason}",
+                        out.stderr.failure("delivering the session summary failed"),
+                        result.channel_id
+                    );
+                }
+            }
+
+            println!();
+            Ok(i32::from(!results.iter().all(|result| result.success)))
+        }
+    }
+}
+
+/// A recorded delivery timestamp cut to whole seconds for display.
+fn whole_seconds(timestamp: &str) -> String {
+    DateTime::parse_from_rfc3339(timestamp).map_or_else(
+        |_| timestamp.to_string(),
+        |timestamp| timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+}
+
+/// Sends `event` to `channels`, recording how each delivery went for
+/// `brb channels list`.
+fn notify(config: &Config, channels: &[String], event: &CompletionEvent) -> Vec<DeliveryResult> {
+    let results = notify_selected(config, channels, event);
+    // sub-second precision orders a failure and a retry that succeeds.
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+    if let Err(error) = StateStore::open()
+        .map_err(|error| error.to_string())
+        .and_then(|store| record_deliveries(&store, &results, &now))
+    {
+        eprintln!("brb: failed to record channel health: {error}");
+    }
+    results
+}
+
+/// Prints how delivering a recorded run went on each channel, returning the
+/// exit code: `1` when any channel failed.
+fn print_followups(done: &str, run_id: &str, results: &[DeliveryResult], out: &Output) -> i32 {
+    for result in results {
+        if let Some(reason) = &result.skipped {
+            println!(
+                "brb: skipped `{}`: {}",
+                result.channel_id,
+                out.stdout.dim(reason)
+            );
+        } else if result.success {
+            println!(
+                "brb: {} run {run_id} on `{}`",
+                out.stdout.success(done),
+                result.channel_id
+            );
+        } else {
+            let reason = result
+                .error
+                .as_ref()
+                .map_or("unknown notification error", |error| error.message.as_str());
+            eprintln!(
+                "brb: {} on `{}`: {reason}",
+                out.stderr
+                    .failure(&format!("delivering run {run_id} failed")),
+                result.channel_id
+            );
+        }
+    }
+
+    println!();
+    if results.iter().all(|result| result.success) {
+        0
+    } else {
+        1
+    }
+}
+
+fn handle_run(mut args: RunArgs, out: &Output) -> Result<i32, AppError> {
+    let mut started = Instant::now();
+    apply_env_overrides(&mut args, |name| std::env::var(name).ok())?;
+    args.session = args
+        .session
+        .as_deref()
+        .and_then(|session| resolve_session(session, &SystemEnvironment));
+    let mut loaded = load_for_run(out)?;
+    if loaded.fallback {
+        eprintln!(
+            "brb: no config at {}; notifying on the desktop only. Run `brb init` to set up channels",
+            loaded.path.display()
+        );
+    }
+    // an alias name is never ambiguous, so strict_args does not apply to it.
+    let alias = expand_alias(&loaded.config, &mut args);
+    if loaded.config.strict_args && !args.separated && alias.is_none() {
+        return Err(AppError::Message(strict_args_message(&args.command)));
+    }
+    let (mut selected_channels, mut run_options) =
+        prepare_run(&args, alias.as_deref(), &mut loaded.config)?;
+    note_uncaptured(&args, &run_options);
+    // repeated runs can go on for days, so config edits apply from the next run.
+    let mut watch = ConfigWatch::new(&loaded.path);
+
+    let mut runs = 0u32;
+    let mut previous_status: Option<String> = None;
+    let mut report = RunReport::default();
+    loop {
+        if runs > 0 {
+            started = Instant::now();
+        }
+        if runs > 0 && watch.changed() {
+            match reload_for_run(&args, alias.as_deref(), out) {
+                Ok((reloaded, channels, options)) => {
+                    eprintln!("brb: reloaded config ({})", reloaded.path.display());
+                    loaded = reloaded;
+                    selected_channels = channels;
+                    run_options = options;
+                }
+                Err(error) => {
+                    eprintln!(
+                        "brb: config changed but is invalid, keeping the previous one: {error}"
+                    );
+                }
+            }
+        }
+
+        let lock = args
+            .lock
+            .as_deref()
+            .map(|name| acquire_lock(name, args.lock_mode == LockMode::Wait))
+            .transpose()?;
+
+        let command = redact_command(&loaded.config.redact, &args.command);
+        // best effort: other runs only mention this one while it is registered.
+        let active = active_runs_dir()
+            .map_err(|error| error.to_string())
+            .and_then(|dir| register_run(&dir, &command))
+            .inspect_err(|error| eprintln!("brb: failed to register run: {error}"))
+            .ok()
+            .map(Arc::new);
+
+        // chat channels with `progress` get a "running" message to edit later.
+        let run_id = new_run_id();
+        let build_event = started_event(&loaded.config, &args, &run_options, &run_id);
+        for problem in post_progress(&loaded.config, &selected_channels, &build_event) {
+            eprintln!("brb: {problem}");
+        }
+        // healthchecks channels are told the run started, to time it.
+        for problem in ping_start(&loaded.config, &selected_channels, &run_id) {
+            eprintln!("brb: {problem}");
+        }
+
+        // triggers notify their channels from the output threads as lines match.
+        let triggers = if loaded.config.triggers.is_empty() {
+            None
+        } else {
+            TriggerWatch::new(&loaded.config, build_event)
+        };
+        let mut options = run_options.clone();
+        options
+            .on_line
+            .extend(triggers.as_ref().map(TriggerWatch::watcher));
+        // progress goes to the run's entry, where `brb status` reads it.
+        if let (Some(pattern), Some(active)) =
+            (progress_pattern(&loaded.config, alias.as_deref()), &active)
+            && let Ok(pattern) = Regex::new(pattern)
+        {
+            options
+                .on_line
+                .push(progress_watcher(pattern, Arc::clone(active)));
+        }
+        let run = run_command_with(&args.command, &options);
+        runs += 1;
+        if let Some(error) = &run.spawn_error {
+            eprintln!("brb: {error}");
+        }
+        for result in triggers.iter().flat_map(|triggers| triggers.finish()) {
+            if let Some(error) = &result.error {
+                eprintln!(
+                    "brb: trigger notification to `{}` failed: {error}",
+                    result.channel_id
+                );
+            }
+        }
+
+        let mut event = completion_event(&loaded.config, &run, run_id);
+        if args.project.is_some() {
+            event.project.clone_from(&args.project);
+        }
+        event.session.clone_from(&args.session);
+        event.other_runs = active.as_ref().map(|active| active.others().len());
+        event.lock = lock.map(|lock| lock.info);
+
+        let alias_hooks = alias
+            .as_deref()
+            .and_then(|name| loaded.config.aliases.get(name))
+            .map(|alias| alias.hooks.for_status(&event.status))
+            .unwrap_or_default();
+        let hooks = loaded.config.hooks.for_status(&event.status);
+        let hooks_started = Instant::now();
+        for problem in run_hooks(hooks.iter().chain(alias_hooks), &event) {
+            eprintln!("brb: {problem}");
+        }
+
+        // the command, lock waits and hooks are the user's time, not brb's.
+        let lock_wait = event.lock.as_ref().map_or(Duration::ZERO, |lock| {
+            Duration::from_millis(lock.waited_ms as u64)
+        });
+        let overhead = started
+            .elapsed()
+            .saturating_sub(run.duration)
+            .saturating_sub(lock_wait)
+            .saturating_sub(hooks_started.elapsed());
+        event.overhead_ms = Some(overhead.as_millis());
+
+        // default channels follow the duration tiers; explicit ones do not.
+        let selected = if by_default(&args) {
+            tiered_channels(&loaded.config, &event)
+        } else {
+            &selected_channels
+        };
+        let selected = with_exit_code_channels(&loaded.config, selected, &event);
+        event.previous_status.clone_from(&previous_status);
+        let channels = channels_for_event(&loaded.config, &selected, &event);
+        let delivery_started = Instant::now();
+        let results = notify(&loaded.config, &channels, &event);
+        let delivery_time = delivery_started.elapsed();
+        print_summary(run.exit_code, &results, out);
+
+        if let Err(error) = StateStore::open()
+            .map_err(|error| error.to_string())
+            .and_then(|store| record_run(&store, &event))
+        {
+            eprintln!("brb: failed to record run history: {error}");
+        }
+
+        if let Some(path) = &args.report {
+            report.push(&event, &results, delivery_time);
+            if let Err(error) = report.write(path) {
+                eprintln!("brb: {error}");
+            }
+        }
+
+        previous_status = Some(event.status);
+
+        // a desktop notification's "Re-run" action starts the next run now.
+        if results
+            .iter()
+            .any(|result| result.interaction == Some(Interaction::Rerun))
+        {
+            continue;
+        }
+
+        let Some(every) = args.every else {
+            return Ok(run.exit_code);
+        };
+
+        if run.spawn_error.is_some() || args.max_runs.is_some_and(|max| runs >= max) {
+            return Ok(run.exit_code);
+        }
+
+        // intervals are measured start-to-start; overrunning commands restart immediately.
+        let wait = every.saturating_sub(run.duration);
+        if args
+            .until
+            .is_some_and(|until| Utc::now() + TimeDelta::from_std(wait).unwrap_or_default() > until)
+        {
+            return Ok(run.exit_code);
+        }
+
+        std::thread::sleep(wait);
+    }
+}
+
+fn handle_bench(runs: u32, mut args: RunArgs, out: &Output) -> Result<i32, AppError> {
+    apply_env_overrides(&mut args, |name| std::env::var(name).ok())?;
+    args.session = args
+        .session
+        .as_deref()
+        .and_then(|session| resolve_session(session, &SystemEnvironment));
+    let mut loaded = load_for_run(out)?;
+    if loaded.fallback {
+        eprintln!(
+            "brb: no config at {}; notifying on the desktop only. Run `brb init` to set up channels",
+            loaded.path.display()
+        );
+    }
+    let alias = expand_alias(&loaded.config, &mut args);
+    if loaded.config.strict_args && !args.separated && alias.is_none() {
+        return Err(AppError::Message(strict_args_message(&args.command)));
+    }
+    let (selected_channels, run_options) =
+        prepare_run(&args, alias.as_deref(), &mut loaded.config)?;
+    note_uncaptured(&args, &run_options);
+    let config = &loaded.config;
+
+    // the lock covers every run, so other work cannot skew the timings.
+    let lock = args
+        .lock
+        .as_deref()
+        .map(|name| acquire_lock(name, args.lock_mode == LockMode::Wait))
+        .transpose()?;
+
+    let command = redact_command(&config.redact, &args.command);
+    let active = active_runs_dir()
+        .map_err(|error| error.to_string())
+        .and_then(|dir| register_run(&dir, &command))
+        .inspect_err(|error| eprintln!("brb: failed to register run: {error}"))
+        .ok();
+
+    let run_id = new_run_id();
+    let build_event = started_event(config, &args, &run_options, &run_id);
+    for problem in post_progress(config, &selected_channels, &build_event) {
+        eprintln!("brb: {problem}");
+    }
+    for problem in ping_start(config, &selected_channels, &run_id) {
+        eprintln!("brb: {problem}");
+    }
+
+    let mut finished = Vec::new();
+    for index in 1..=runs {
+        let run = run_command_with(&args.command, &run_options);
+        eprintln!(
+            "brb: run {index}/{runs}: {} (exit {})",
+            render_duration(config, run.duration.as_millis()),
+            run.exit_code
+        );
+        let spawn_error = run.spawn_error.clone();
+        finished.push(run);
+        // a command that could not start will not start on the next run either.
+        if let Some(error) = spawn_error {
+            eprintln!("brb: {error}");
+            break;
+        }
+    }
+
+    let (Some(run), Some(summary)) = (combined_run(&finished), BenchSummary::from_runs(&finished))
+    else {
+        return Err(AppError::Message(
+            "bench finished without any runs".to_string(),
+        ));
+    };
+    let mut event = completion_event(config, &run, run_id);
+    if args.project.is_some() {
+        event.project.clone_from(&args.project);
+    }
+    event.session.clone_from(&args.session);
+    event.other_runs = active.as_ref().map(|active| active.others().len());
+    event.lock = lock.map(|lock| lock.info);
+    let stats = [
+        summary.min_ms,
+        summary.mean_ms,
+        summary.p95_ms,
+        summary.max_ms,
+    ]
+    .map(|duration_ms| render_duration(config, duration_ms));
+    eprintln!(
+        "brb: {}",
+        messages(config).bench(summary.runs, summary.failures, &stats)
+    );
+    event.bench = Some(summary);
+
+    let alias_hooks = alias
+        .as_deref()
+        .and_then(|name| config.aliases.get(name))
+        .map(|alias| alias.hooks.for_status(&event.status))
+        .unwrap_or_default();
+    for problem in run_hooks(
+        config
+            .hooks
+            .for_status(&event.status)
+            .iter()
+            .chain(alias_hooks),
+        &event,
+    ) {
+        eprintln!("brb: {problem}");
+    }
+
+    let selected = if by_default(&args) {
+        tiered_channels(config, &event)
+    } else {
+        &selected_channels
+    };
+    let selected = with_exit_code_channels(config, selected, &event);
+    let channels = channels_for_event(config, &selected, &event);
+    let delivery_started = Instant::now();
+    let results = notify(config, &channels, &event);
+    let delivery_time = delivery_started.elapsed();
+    print_summary(event.exit_code, &results, out);
+
+    if let Err(error) = StateStore::open()
+        .map_err(|error| error.to_string())
+        .and_then(|store| record_run(&store, &event))
+    {
+        eprintln!("brb: failed to record run history: {error}");
+    }
+
+    if let Some(path) = &args.report {
+        let mut report = RunReport::default();
+        report.push(&event, &results, delivery_time);
+        if let Err(error) = report.write(path) {
+            eprintln!("brb: {error}");
+        }
+    }
+
+    Ok(event.exit_code)
+}
+
+/// Builds the completion event for a finished run, applying the config's
+/// redaction, exit code messages, project, container, machine context,
+/// environment snapshot and machine ID.
+fn completion_event(config: &Config, run: &RunResult, run_id: String) -> CompletionEvent {
+    let mut event = CompletionEvent::from_run(run);
+    event.run_id = run_id;
+    event.command = redact_command(&config.redact, &event.command);
+    // configured redaction can mask more than the fingerprint's defaults.
+    event.fingerprint = fingerprint(&event.command);
+    event.exit_reason = config
+        .exit_codes
+        .get(&event.exit_code)
+        .and_then(|rule| rule.message.clone());
+    event.project = detect_project(Path::new(&event.cwd));
+    event.container = detect_container();
+    event.context = sample_context(&config.context);
+    event.snapshot = sample_snapshot(&config.snapshot);
+    if config.machine_id {
+        event.machine_id = machine_id();
+    }
+    event
+}
+
+/// The `progress_pattern` for a run: the alias's, else the top-level one.
+fn progress_pattern<'a>(config: &'a Config, alias: Option<&str>) -> Option<&'a str> {
+    alias
+        .and_then(|name| config.aliases.get(name))
+        .and_then(|alias| alias.progress_pattern.as_deref())
+        .or(config.progress_pattern.as_deref())
+}
+
+/// Builds the event for a run that has just started, which its "running"
+/// messages show and triggers fill in when they fire. Context and snapshots
+/// are sampled when it is called, not before the command starts.
+fn started_event(
+    config: &Config,
+    args: &RunArgs,
+    options: &RunOptions,
+    run_id: &str,
+) -> impl Fn() -> CompletionEvent + Send + Sync + 'static {
+    let config = config.clone();
+    let now = Utc::now();
+    let run = RunResult {
+        command: args.command.clone(),
+        started_at: now,
+        finished_at: now,
+        duration: Duration::ZERO,
+        exit_code: 0,
+        spawn_error: None,
+        output_tail: None,
+        cwd: options.cwd.clone(),
+    };
+    let run_id = run_id.to_string();
+    let project = args.project.clone();
+    let session = args.session.clone();
+    move || {
+        let mut event = completion_event(&config, &run, run_id.clone());
+        if project.is_some() {
+            event.project.clone_from(&project);
+        }
+        event.session.clone_from(&session);
+        event
+    }
+}
+
+/// Applies run flags to `config` and works out the channels and run options.
+fn prepare_run(
+    args: &RunArgs,
+    alias: Option<&str>,
+    config: &mut Config,
+) -> Result<(Vec<String>, RunOptions), AppError> {
+    if args.min_duration.is_some() {
+        config.min_duration = args.min_duration;
+    }
+    if let Some(notify_on) = args.notify_on {
+        for channel in config.channels.values_mut() {
+            channel.notify_on = notify_on;
+        }
+    }
+
+    let selected_channels = if args.all_channels {
+        config
+            .channels
+            .iter()
+            .filter(|(_, channel)| channel.enabled)
+            .map(|(channel_id, _)| channel_id.clone())
+            .collect()
+    } else {
+        resolve_channels(&config.default_channels, &args.channels)?
+    };
     for channel_id in &selected_channels {
-        if !loaded.config.channels.contains_key(channel_id) {
+        if !config.channels.contains_key(channel_id) {
             return Err(AppError::Message(format!(
                 "selected channel `{channel_id}` is not defined in config"
             )));
         }
     }
 
-    let run = run_command(&args.command);
-    if let Some(error) = &run.spawn_error {
-        eprintln!("brb: {error}");
+    let tier_channels = config
+        .tiers
+        .iter()
+        .filter(|_| by_default(args))
+        .flat_map(|tier| &tier.channels)
+        .chain(config.exit_codes.values().flat_map(|rule| &rule.channels));
+
+    // triggers and progress only see output piped through `brb`.
+    let watches_output = !config.triggers.is_empty() || progress_pattern(config, alias).is_some();
+    let alias = alias.and_then(|name| config.aliases.get(name));
+    let run_options = RunOptions {
+        capture_lines: selected_channels
+            .iter()
+            .chain(tier_channels)
+            .filter_map(|channel_id| config.channels.get(channel_id))
+            .filter(|channel| channel.enabled)
+            .map(|channel| channel.kind.capture_lines())
+            .chain(watches_output.then_some(1))
+            .max()
+            .unwrap_or(0),
+        cwd: alias.and_then(|alias| alias.cwd.clone()),
+        env: alias.map(|alias| alias.env.clone()).unwrap_or_default(),
+        interactivity: args.interactivity,
+        on_line: Vec::new(),
+    };
+
+    Ok((selected_channels, run_options))
+}
+
+/// Says when a channel wants captured output that this run will not capture,
+/// because the command looks interactive or no pseudo-terminal is available.
+fn note_uncaptured(args: &RunArgs, options: &RunOptions) {
+    let mode = capture_mode(&args.command, options, std::io::stdin().is_terminal());
+    if options.capture_lines == 0 || mode != CaptureMode::Inherit {
+        return;
+    }
+    if args.interactivity == Interactivity::Pty {
+        eprintln!("brb: `script` is not available for --force-pty; output will not be captured");
+    } else {
+        eprintln!(
+            "brb: `{}` looks interactive, so its output will not be captured. Use --force-pty to capture it through a terminal, or --assume-non-interactive to capture it anyway",
+            args.command[0]
+        );
     }
+}
+
+/// Whether the run notifies the channels config picks rather than ones given
+/// with `--channel`, `--all-channels` or an alias.
+fn by_default(args: &RunArgs) -> bool {
+    args.channels.is_empty() && !args.all_channels
+}
+
+/// Loads the config again for the next run of a repeated command.
+fn reload_for_run(
+    args: &RunArgs,
+    alias: Option<&str>,
+    out: &Output,
+) -> Result<(LoadedConfig, Vec<String>, RunOptions), AppError> {
+    let mut loaded = load_for_run(out)?;
+    let (channels, options) = prepare_run(args, alias, &mut loaded.config)?;
+    Ok((loaded, channels, options))
+}
+
+/// Loads the global config, printing any load warnings.
+fn load(options: LoadOptions, out: &Output) -> Result<LoadedConfig, AppError> {
+    let loaded = load_config_with(options)?;
+    print_warnings(&loaded, out);
+    Ok(loaded)
+}
 
-    let event = CompletionEvent::from_run(&run);
-    let results = notify_selected(&loaded.config, &selected_channels, &event);
-    print_summary(run.exit_code, &results);
+/// Loads the global config for a run, falling back to the built-in
+/// desktop-only config when there is no config file.
+fn load_for_run(out: &Output) -> Result<LoadedConfig, AppError> {
+    let loaded = load_config_or_fallback(LoadOptions::default())?;
+    print_warnings(&loaded, out);
+    Ok(loaded)
+}
 
-    Ok(run.exit_code)
+fn print_warnings(loaded: &LoadedConfig, out: &Output) {
+    for warning in &loaded.warnings {
+        eprintln!("brb: {} {warning}", out.stderr.warning("warning:"));
+    }
+}
+
+/// Explains a `strict_args` refusal, showing how the argv was split.
+fn strict_args_message(command: &[String]) -> String {
+    let argv = std::env::args().skip(1).collect::<Vec<_>>();
+    let flags = &argv[..argv.len().saturating_sub(command.len())];
+    let mut fixed = flags.to_vec();
+    fixed.push("--".to_string());
+    fixed.extend_from_slice(command);
+    format!(
+        "strict_args requires `--` before the wrapped command; brb read flags [{}] and command [{}]. Run `brb {}` if that split is right",
+        flags.join(" "),
+        command.join(" "),
+        fixed.join(" ")
+    )
 }
 
 fn resolve_channels(
@@ -168,33 +1398,62 @@ fn resolve_channels(
     Ok(channels)
 }
 
-fn print_summary(exit_code: i32, results: &[DeliveryResult]) {
+fn print_summary(exit_code: i32, results: &[DeliveryResult], out: &Output) {
     let total = results.len();
-    let sent = results.iter().filter(|result| result.success).count();
+    let sent = results.iter().filter(|result| result.delivered()).count();
+    let queued = results
+        .iter()
+        .filter(|result| result.queued)
+        .map(|result| result.channel_id.as_str())
+        .collect::<Vec<_>>();
+    let skipped = results
+        .iter()
+        .filter(|result| !result.queued)
+        .filter_map(|result| {
+            let reason = result.skipped.as_deref()?;
+            Some(format!("{} ({reason})", result.channel_id))
+        })
+        .collect::<Vec<_>>();
     let failed = results
         .iter()
         .filter(|result| !result.success)
         .map(|result| {
             let reason = result
                 .error
-                .as_deref()
-                .unwrap_or("unknown notification error");
+                .as_ref()
+                .map_or("unknown notification error", |error| error.message.as_str());
             format!("{} ({reason})", result.channel_id)
         })
         .collect::<Vec<_>>();
 
+    let unacknowledged = results
+        .iter()
+        .filter(|result| result.acknowledged == Some(false))
+        .map(|result| result.channel_id.as_str())
+        .collect::<Vec<_>>();
+
+    let style = out.stderr;
     let command_label = if exit_code == 0 {
-        "command succeeded"
+        style.success("command succeeded")
     } else {
-        "command failed"
+        style.failure("command failed")
     };
 
-    if failed.is_empty() {
-        eprintln!("brb: {command_label} (exit {exit_code}); notifications sent {sent}/{total}");
-    } else {
-        eprintln!(
-            "brb: {command_label} (exit {exit_code}); notifications sent {sent}/{total}; failed: {}",
-            failed.join(", ")
-        );
+    let mut summary =
+        format!("brb: {command_label} (exit {exit_code}); notifications sent {sent}/{total}");
+    if !queued.is_empty() {
+        summary.push_str(&format!("; queued: {}", queued.join(", ")));
+    }
+    if !skipped.is_empty() {
+        summary.push_str(&format!("; skipped: {}", skipped.join(", ")));
+    }
+    if !unacknowledged.is_empty() {
+        let unacknowledged = format!("unacknowledged: {}", unacknowledged.join(", "));
+        summary.push_str(&format!("; {}", style.warning(&unacknowledged)));
+    }
+    if !failed.is_empty() {
+        let failed = format!("failed: {}", failed.join(", "));
+        summary.push_str(&format!("; {}", style.failure(&failed)));
     }
+    eprintln!("{summary}");
 }
@@ -0,0 +1,350 @@
+use crate::config::DurationStyle;
+use crate::environment::{Environment, SystemEnvironment};
+
+/// Locales with a built-in message catalog.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// English.
+    #[default]
+    En,
+
+    /// German.
+    De,
+
+    /// French.
+    Fr,
+
+    /// Spanish.
+    Es,
+}
+
+/// User-facing notification strings for one locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Messages {
+    /// Title suffix for successful runs.
+    pub success: &'static str,
+
+    /// Status of a command still in progress, for edit-in-place messages.
+    pub running: &'static str,
+
+    /// Title suffix for failed runs; `{code}` is replaced by the exit code.
+    pub failed: &'static str,
+
+    /// Exit code shown after a configured exit code message.
+    pub exit: &'static str,
+
+    /// Label before the finish time in message bodies.
+    pub finished: &'static str,
+
+    /// Heading for attached command output.
+    pub output: &'static str,
+
+    /// Title suffix and body label for an output line that fired a trigger.
+    pub matched: &'static str,
+
+    /// Heading for completions held by a digest channel.
+    pub earlier: &'static str,
+
+    /// Heading for the runs summarised by `brb session end`; `{name}` is
+    /// replaced by the session.
+    pub session: &'static str,
+
+    /// Label before a note added with `brb annotate`.
+    pub note: &'static str,
+
+    /// Bench statistics line; `{runs}`, `{min}`, `{mean}`, `{p95}`, `{max}`
+    /// and `{failed}` are replaced.
+    pub bench: &'static str,
+
+    /// Field labels in Slack messages: command, duration, host and exit code.
+    pub fields: [&'static str; 4],
+
+    /// Singular and plural notes on other runs in progress; `{count}` is
+    /// replaced by their number.
+    pub still_running: (&'static str, &'static str),
+
+    /// Desktop notification action that runs the command again.
+    pub rerun: &'static str,
+
+    /// Notification action that acknowledges a failure.
+    pub acknowledge: &'static str,
+
+    /// Decimal separator for rendered numbers.
+    pub decimal: &'static str,
+
+    /// Separator between groups of thousands.
+    pub thousands: &'static str,
+
+    /// Space between a number and an abbreviated unit such as `s`.
+    pub unit_space: &'static str,
+
+    /// Singular and plural words for hours, minutes and seconds.
+    pub units: [(&'static str, &'static str); 3],
+}
+
+const EN: Messages = Messages {
+    success: "success",
+    running: "running…",
+    failed: "failed (exit {code})",
+    exit: "exit {code}",
+    finished: "finished",
+    output: "output",
+    matched: "output matched",
+    earlier: "earlier runs",
+    session: "session {name}",
+    note: "note",
+    bench: "{runs} runs: min {min}, mean {mean}, p95 {p95}, max {max}, {failed} failed",
+    fields: ["Command", "Duration", "Host", "Exit code"],
+    still_running: (
+        "{count} other brb run still in progress",
+        "{count} other brb runs still in progress",
+    ),
+    rerun: "Re-run",
+    acknowledge: "Acknowledge",
+    decimal: ".",
+    thousands: ",",
+    unit_space: "",
+    units: [
+        ("hour", "hours"),
+        ("minute", "minutes"),
+        ("second", "seconds"),
+    ],
+};
+
+const DE: Messages = Messages {
+    success: "erfolgreich",
+    running: "läuft…",
+    failed: "fehlgeschlagen (Exit-Code {code})",
+    exit: "Exit-Code {code}",
+    finished: "beendet",
+    output: "Ausgabe",
+    matched: "Treffer in der Ausgabe",
+    earlier: "frühere Läufe",
+    session: "Sitzung {name}",
+    note: "Notiz",
+    bench: "{runs} Läufe: min {min}, Mittel {mean}, p95 {p95}, max {max}, {failed} fehlgeschlagen",
+    fields: ["Befehl", "Dauer", "Host", "Exit-Code"],
+    still_running: (
+        "{count} weiterer brb-Lauf noch aktiv",
+        "{count} weitere brb-Läufe noch aktiv",
+    ),
+    rerun: "Erneut ausführen",
+    acknowledge: "Bestätigen",
+    decimal: ",",
+    thousands: ".",
+    unit_space: " ",
+    units: [
+        ("Stunde", "Stunden"),
+        ("Minute", "Minuten"),
+        ("Sekunde", "Sekunden"),
+    ],
+};
+
+const FR: Messages = Messages {
+    success: "réussi",
+    running: "en cours…",
+    failed: "échec (code {code})",
+    exit: "code {code}",
+    finished: "terminé",
+    output: "sortie",
+    matched: "motif trouvé dans la sortie",
+    earlier: "exécutions précédentes",
+    session: "session {name}",
+    note: "note",
+    bench: "{runs} exécutions : min {min}, moyenne {mean}, p95 {p95}, max {max}, {failed} en échec",
+    fields: ["Commande", "Durée", "Hôte", "Code de sortie"],
+    still_running: (
+        "{count} autre exécution brb encore en cours",
+        "{count} autres exécutions brb encore en cours",
+    ),
+    rerun: "Relancer",
+    acknowledge: "Acquitter",
+    decimal: ",",
+    thousands: "\u{202f}",
+    unit_space: " ",
+    units: [
+        ("heure", "heures"),
+        ("minute", "minutes"),
+        ("seconde", "secondes"),
+    ],
+};
+
+const ES: Messages = Messages {
+    success: "completado",
+    running: "en ejecución…",
+    failed: "falló (código {code})",
+    exit: "código {code}",
+    finished: "terminado",
+    output: "salida",
+    matched: "coincidencia en la salida",
+    earlier: "ejecuciones anteriores",
+    session: "sesión {name}",
+    note: "nota",
+    bench: "{runs} ejecuciones: mín {min}, media {mean}, p95 {p95}, máx {max}, {failed} fallidas",
+    fields: ["Comando", "Duración", "Host", "Código de salida"],
+    still_running: (
+        "{count} ejecución de brb más en curso",
+        "{count} ejecuciones de brb más en curso",
+    ),
+    rerun: "Volver a ejecutar",
+    acknowledge: "Confirmar",
+    decimal: ",",
+    thousands: ".",
+    unit_space: " ",
+    units: [
+        ("hora", "horas"),
+        ("minuto", "minutos"),
+        ("segundo", "segundos"),
+    ],
+};
+
+impl Locale {
+    /// Language codes accepted in config.
+    pub const SUPPORTED: &'static [&'static str] = &["en", "de", "fr", "es"];
+
+    /// Parses a language tag such as `de`, `fr-CA` or `es_ES.UTF-8`.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match language.as_str() {
+            "en" => Some(Self::En),
+            "de" => Some(Self::De),
+            "fr" => Some(Self::Fr),
+            "es" => Some(Self::Es),
+            _ => None,
+        }
+    }
+
+    /// Resolves the locale from config, then `LC_ALL`, `LC_MESSAGES` and `LANG`,
+    /// falling back to English.
+    pub fn resolve(configured: Option<&str>) -> Self {
+        Self::resolve_in(configured, &SystemEnvironment)
+    }
+
+    /// Like [`Locale::resolve`], reading variables from `environment`.
+    pub fn resolve_in(configured: Option<&str>, environment: &dyn Environment) -> Self {
+        if let Some(locale) = configured.and_then(Self::parse) {
+            return locale;
+        }
+
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| environment.var(name))
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    /// Returns this locale's message catalog.
+    pub fn messages(self) -> &'static Messages {
+        match self {
+            Self::En => &EN,
+            Self::De => &DE,
+            Self::Fr => &FR,
+            Self::Es => &ES,
+        }
+    }
+}
+
+impl Messages {
+    /// Returns the failure title suffix for `exit_code`.
+    pub fn failed(&self, exit_code: i32) -> String {
+        self.failed.replace("{code}", &exit_code.to_string())
+    }
+
+    /// Returns the heading for a session summary, e.g. `session release`.
+    pub fn session(&self, name: &str) -> String {
+        self.session.replace("{name}", name)
+    }
+
+    /// Returns the exit code label for `exit_code`, e.g. `exit 137`.
+    pub fn exit(&self, exit_code: i32) -> String {
+        self.exit.replace("{code}", &exit_code.to_string())
+    }
+
+    /// Returns the note on `count` other runs in progress, e.g.
+    /// `2 other brb runs still in progress`.
+    pub fn still_running(&self, count: usize) -> String {
+        let (singular, plural) = self.still_running;
+        let text = if count == 1 { singular } else { plural };
+        text.replace("{count}", &count.to_string())
+    }
+
+    /// Returns the bench statistics line for `runs` runs, `failed` of which
+    /// failed, from rendered min, mean, p95 and max durations.
+    pub fn bench(&self, runs: usize, failed: usize, durations: &[String; 4]) -> String {
+        let [min, mean, p95, max] = durations;
+        self.bench
+            .replace("{runs}", &runs.to_string())
+            .replace("{failed}", &failed.to_string())
+            .replace("{min}", min)
+            .replace("{mean}", mean)
+            .replace("{p95}", p95)
+            .replace("{max}", max)
+    }
+
+    /// Formats `value` with `places` decimals, grouping thousands.
+    pub fn number(&self, value: f64, places: usize) -> String {
+        let formatted = format!("{value:.places$}");
+        let (integer, fraction) = formatted
+            .split_once('.')
+            .map_or((formatted.as_str(), None), |(integer, fraction)| {
+                (integer, Some(fraction))
+            });
+        let (sign, digits) = integer
+            .strip_prefix('-')
+            .map_or(("", integer), |digits| ("-", digits));
+
+        let mut grouped = String::new();
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index) % 3 == 0 {
+                grouped.push_str(self.thousands);
+            }
+            grouped.push(digit);
+        }
+
+        match fraction {
+            Some(fraction) => format!("{sign}{grouped}{}{fraction}", self.decimal),
+            None => format!("{sign}{grouped}"),
+        }
+    }
+
+    /// Formats a duration in the given style, e.g. `3.25s`, `1h 5m` or
+    /// `1 hour 5 minutes` in English.
+    pub fn duration(&self, duration_ms: u128, style: DurationStyle) -> String {
+        let whole = duration_ms / 1000;
+        let parts = [whole / 3600, whole / 60 % 60, whole % 60];
+        let first = parts.iter().position(|part| *part > 0);
+
+        // under a minute, every style keeps fractions of a second.
+        let Some(first) = first.filter(|first| *first < 2 && style != DurationStyle::Seconds)
+        else {
+            let seconds = self.number(duration_ms as f64 / 1000.0, 2);
+            return match style {
+                DurationStyle::Long if duration_ms == 1000 => format!("1 {}", self.units[2].0),
+                DurationStyle::Long if duration_ms.is_multiple_of(1000) => {
+                    format!("{whole} {}", self.units[2].1)
+                }
+                DurationStyle::Long => format!("{seconds} {}", self.units[2].1),
+                _ => format!("{seconds}{}s", self.unit_space),
+            };
+        };
+
+        (first..=first + 1)
+            .filter(|unit| parts[*unit] > 0)
+            .map(|unit| match style {
+                DurationStyle::Long => {
+                    let (singular, plural) = self.units[unit];
+                    let word = if parts[unit] == 1 { singular } else { plural };
+                    format!("{} {word}", parts[unit])
+                }
+                _ => format!("{}{}", parts[unit], ["h", "m", "s"][unit]),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
@@ -0,0 +1,306 @@
+use reqwest::Url;
+use rustls::pki_types::ServerName;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Limit for connecting and for each read or write when `http.timeout` is
+/// unset.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Keep-alive sent with `CONNECT`; `brb` disconnects long before it matters.
+const KEEP_ALIVE_SECS: u16 = 60;
+
+/// Identifier of the one `PUBLISH` a connection sends.
+const PACKET_ID: u16 = 1;
+
+const CONNECT: u8 = 0x10;
+const CONNACK: u8 = 0x20;
+const PUBLISH: u8 = 0x30;
+const PUBACK: u8 = 0x40;
+const PUBREC: u8 = 0x50;
+const PUBREL: u8 = 0x62;
+const PUBCOMP: u8 = 0x70;
+const DISCONNECT: u8 = 0xe0;
+
+/// Where an MQTT broker listens, from an `mqtt://` or `mqtts://` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broker {
+    /// Broker host name or address.
+    pub host: String,
+
+    /// Broker port; 1883, or 8883 with TLS, unless the URL names one.
+    pub port: u16,
+
+    /// Whether to connect over TLS.
+    pub tls: bool,
+}
+
+impl Broker {
+    /// Parses a broker URL. Errors never echo the URL.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let url = Url::parse(raw.trim()).map_err(|error| format!("invalid url ({error})"))?;
+        let tls = match url.scheme() {
+            "mqtt" | "tcp" => false,
+            "mqtts" | "ssl" => true,
+            scheme => {
+                return Err(format!(
+                    "unsupported scheme `{scheme}` (expected mqtt or mqtts)"
+                ));
+            }
+        };
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err("credentials in the url (set username and password instead)".to_string());
+        }
+        let host = url
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| "no host".to_string())?;
+        Ok(Self {
+            host: host.trim_matches(['[', ']']).to_string(),
+            port: url.port().unwrap_or(if tls { 8883 } else { 1883 }),
+            tls,
+        })
+    }
+}
+
+/// Who to connect as.
+#[derive(Debug, Clone, Copy)]
+pub struct Session<'a> {
+    /// Client identifier; the broker drops an older connection with the same
+    /// one.
+    pub client_id: &'a str,
+
+    /// Username, if the broker needs one.
+    pub username: Option<&'a str>,
+
+    /// Password, sent with `username`.
+    pub password: Option<&'a str>,
+}
+
+/// A message to publish.
+#[derive(Debug, Clone, Copy)]
+pub struct Message<'a> {
+    /// Topic name, without wildcards.
+    pub topic: &'a str,
+
+    /// Message body.
+    pub payload: &'a [u8],
+
+    /// 0 (at most once), 1 (at least once) or 2 (exactly once).
+    pub qos: u8,
+
+    /// Whether the broker keeps the message for later subscribers.
+    pub retain: bool,
+}
+
+/// Why talking to a broker failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MqttError {
+    /// The broker answered `CONNECT` with a refusal, e.g. bad credentials.
+    Refused(String),
+
+    /// The broker did not answer in time.
+    Timeout,
+
+    /// The broker could not be reached, or broke off or garbled the exchange.
+    Network(String),
+}
+
+impl From<io::Error> for MqttError {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Self::Timeout,
+            kind => Self::Network(kind.to_string()),
+        }
+    }
+}
+
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+/// Connects to `broker` as `session`, publishes `message` and waits for the
+/// acknowledgements its QoS calls for before disconnecting.
+pub fn publish(
+    broker: &Broker,
+    session: &Session,
+    message: &Message,
+    timeout: Duration,
+) -> Result<(), MqttError> {
+    let mut stream = connect(broker, session, timeout)?;
+
+    let mut body = Vec::new();
+    put_str(&mut body, message.topic.as_bytes());
+    if message.qos > 0 {
+        body.extend(PACKET_ID.to_be_bytes());
+    }
+    body.extend(message.payload);
+    let flags = (message.qos.min(2) << 1) | u8::from(message.retain);
+    stream.write_all(&packet(PUBLISH | flags, &body))?;
+
+    match message.qos {
+        0 => {}
+        1 => await_packet(&mut stream, PUBACK)?,
+        _ => {
+            await_packet(&mut stream, PUBREC)?;
+            stream.write_all(&packet(PUBREL, &PACKET_ID.to_be_bytes()))?;
+            await_packet(&mut stream, PUBCOMP)?;
+        }
+    }
+
+    stream.write_all(&packet(DISCONNECT, &[]))?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Connects to `broker` as `session` and disconnects again, publishing
+/// nothing.
+pub fn check_credentials(
+    broker: &Broker,
+    session: &Session,
+    timeout: Duration,
+) -> Result<(), MqttError> {
+    let mut stream = connect(broker, session, timeout)?;
+    stream.write_all(&packet(DISCONNECT, &[]))?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Opens a connection and completes the `CONNECT`/`CONNACK` exchange.
+fn connect(
+    broker: &Broker,
+    session: &Session,
+    timeout: Duration,
+) -> Result<Box<dyn Stream>, MqttError> {
+    let address = (broker.host.as_str(), broker.port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| MqttError::Network(format!("could not resolve `{}`", broker.host)))?;
+    let tcp = TcpStream::connect_timeout(&address, timeout)?;
+    tcp.set_read_timeout(Some(timeout))?;
+    tcp.set_write_timeout(Some(timeout))?;
+    let mut stream: Box<dyn Stream> = if broker.tls {
+        tls_stream(&broker.host, tcp)?
+    } else {
+        Box::new(tcp)
+    };
+
+    let mut body = Vec::new();
+    put_str(&mut body, b"MQTT");
+    // protocol level 4 is MQTT 3.1.1, which MQTT 5 brokers also accept.
+    body.push(4);
+    // a clean session leaves nothing behind on the broker.
+    let mut flags = 0x02;
+    if session.username.is_some() {
+        flags |= 0x80;
+    }
+    if session.username.is_some() && session.password.is_some() {
+        flags |= 0x40;
+    }
+    body.push(flags);
+    body.extend(KEEP_ALIVE_SECS.to_be_bytes());
+    put_str(&mut body, session.client_id.as_bytes());
+    if let Some(username) = session.username {
+        put_str(&mut body, username.as_bytes());
+        if let Some(password) = session.password {
+            put_str(&mut body, password.as_bytes());
+        }
+    }
+    stream.write_all(&packet(CONNECT, &body))?;
+
+    let (kind, connack) = read_packet(&mut stream)?;
+    if kind & 0xf0 != CONNACK || connack.len() != 2 {
+        return Err(MqttError::Network("not an MQTT broker".to_string()));
+    }
+    match connack[1] {
+        0 => Ok(stream),
+        1 => Err(MqttError::Refused(
+            "MQTT 3.1.1 is not supported".to_string(),
+        )),
+        2 => Err(MqttError::Refused("client_id rejected".to_string())),
+        3 => Err(MqttError::Refused("server unavailable".to_string())),
+        4 => Err(MqttError::Refused("bad username or password".to_string())),
+        5 => Err(MqttError::Refused("not authorized".to_string())),
+        code => Err(MqttError::Refused(format!("return code {code}"))),
+    }
+}
+
+/// Wraps `tcp` in TLS, checking the broker's certificate against the bundled
+/// web PKI roots.
+fn tls_stream(host: &str, tcp: TcpStream) -> Result<Box<dyn Stream>, MqttError> {
+    let roots = rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|error| MqttError::Network(format!("tls setup failed ({error})")))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    let name = ServerName::try_from(host.to_string())
+        .map_err(|_| MqttError::Network(format!("`{host}` is not a valid TLS server name")))?;
+    let connection = rustls::ClientConnection::new(Arc::new(config), name)
+        .map_err(|error| MqttError::Network(format!("tls setup failed ({error})")))?;
+    Ok(Box::new(rustls::StreamOwned::new(connection, tcp)))
+}
+
+/// Reads packets until one of type `kind` acknowledges the publish.
+fn await_packet(stream: &mut dyn Stream, kind: u8) -> Result<(), MqttError> {
+    loop {
+        let (header, body) = read_packet(stream)?;
+        if header & 0xf0 == kind & 0xf0 && body.get(..2) == Some(&PACKET_ID.to_be_bytes()[..]) {
+            return Ok(());
+        }
+    }
+}
+
+/// A packet with `header` and `body`, its remaining length encoded in
+/// between.
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut length = body.len();
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+    packet.extend(body);
+    packet
+}
+
+/// Reads one packet's header byte and body.
+fn read_packet(stream: &mut dyn Stream) -> io::Result<(u8, Vec<u8>)> {
+    let mut byte = [0u8; 1];
+    stream.read_exact(&mut byte)?;
+    let header = byte[0];
+
+    let mut length = 0usize;
+    for shift in 0..4 {
+        stream.read_exact(&mut byte)?;
+        length |= usize::from(byte[0] & 0x7f) << (7 * shift);
+        if byte[0] & 0x80 == 0 {
+            let mut body = vec![0u8; length];
+            stream.read_exact(&mut body)?;
+            return Ok((header, body));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "malformed remaining length",
+    ))
+}
+
+/// Appends a length-prefixed string.
+fn put_str(buffer: &mut Vec<u8>, value: &[u8]) {
+    let length = u16::try_from(value.len()).unwrap_or(u16::MAX);
+    buffer.extend(length.to_be_bytes());
+    buffer.extend(&value[..usize::from(length)]);
+}
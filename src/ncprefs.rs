@@ -0,0 +1,127 @@
+#[cfg(target_os = "macos")]
+use std::process::Command;
+
+/// Bundle `osascript`'s `display notification` posts as.
+pub const SCRIPT_EDITOR: &str = "com.apple.ScriptEditor2";
+
+/// Bundle terminal-notifier posts as.
+pub const TERMINAL_NOTIFIER: &str = "fr.julienxx.oss.terminal-notifier";
+
+/// Notification Center flag set while "Allow Notifications" is on.
+const ALLOW_NOTIFICATIONS: u64 = 1 << 25;
+
+/// Notification Center flag bits holding the alert style: 0 for None, 1 for
+/// Banners and 2 for Alerts.
+const ALERT_STYLE_SHIFT: u32 = 3;
+const ALERT_STYLE_MASK: u64 = 0b111;
+
+/// How Notification Center treats an app's notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppSetting {
+    /// Shown as banners or alerts.
+    Shown,
+
+    /// "Allow Notifications" is off, so they are dropped.
+    Disabled,
+
+    /// The alert style is None, so they only reach Notification Center's list.
+    Silent,
+}
+
+/// The setting for `bundle_id` in `defaults read com.apple.ncprefs apps`
+/// output. `None` when the app has no entry, as before it first notifies,
+/// when macOS shows its notifications.
+pub fn parse_app_setting(apps: &str, bundle_id: &str) -> Option<AppSetting> {
+    let entry = entries(apps)
+        .into_iter()
+        .find(|entry| value(entry, "bundle-id").as_deref() == Some(bundle_id))?;
+    let flags = value(&entry, "flags")?.parse::<u64>().ok()?;
+
+    Some(if flags & ALLOW_NOTIFICATIONS == 0 {
+        AppSetting::Disabled
+    } else if (flags >> ALERT_STYLE_SHIFT) & ALERT_STYLE_MASK == 0 {
+        AppSetting::Silent
+    } else {
+        AppSetting::Shown
+    })
+}
+
+/// Why notifications from `app` would not appear on screen, and where to
+/// change that.
+pub fn setting_problem(setting: AppSetting, app: &str) -> Option<String> {
+    match setting {
+        AppSetting::Shown => None,
+        AppSetting::Disabled => Some(format!(
+            "macOS has notifications turned off for {app}; turn on Allow Notifications under System Settings → Notifications → {app}"
+        )),
+        AppSetting::Silent => Some(format!(
+            "macOS shows {app} notifications with the alert style None, so nothing appears on screen; choose Banners or Alerts under System Settings → Notifications → {app}"
+        )),
+    }
+}
+
+/// Why a notification posted as `bundle_id` (named `app` in System
+/// Settings) would not appear. `None` when it would, or the settings cannot
+/// be read.
+#[cfg(target_os = "macos")]
+pub fn notifier_problem(bundle_id: &str, app: &str) -> Option<String> {
+    let output = Command::new("defaults")
+        .args(["read", "com.apple.ncprefs", "apps"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let setting = parse_app_setting(&String::from_utf8_lossy(&output.stdout), bundle_id)?;
+    setting_problem(setting, app)
+}
+
+/// The text of each `{ ... }` dictionary directly inside the top-level
+/// array of an old-style property list.
+fn entries(plist: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in plist.chars() {
+        if depth >= 2 {
+            current.push(c);
+        }
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            '(' | '{' => {
+                depth += 1;
+                if depth == 2 && c == '{' {
+                    current.clear();
+                }
+            }
+            ')' | '}' => {
+                if depth == 2 && c == '}' {
+                    entries.push(std::mem::take(&mut current));
+                }
+                depth = depth.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// The value of a top-level `key = value;` line in a dictionary's text,
+/// without quotes.
+fn value(entry: &str, key: &str) -> Option<String> {
+    entry.lines().find_map(|line| {
+        let (name, value) = line.trim().split_once(" = ")?;
+        (name.trim_matches('"') == key)
+            .then(|| value.trim_end_matches(';').trim_matches('"').to_string())
+    })
+}
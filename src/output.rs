@@ -0,0 +1,125 @@
+use clap::ValueEnum;
+use std::io::IsTerminal;
+
+/// When `brb` colours its own output, set with `--color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Colour terminals, unless `NO_COLOR`, `CLICOLOR` or `TERM=dumb` say not to.
+    #[default]
+    Auto,
+
+    /// Always colour, even when piped.
+    Always,
+
+    /// Never colour.
+    Never,
+}
+
+/// Whether to colour a stream, following `choice` and the `NO_COLOR`,
+/// `CLICOLOR_FORCE`, `CLICOLOR` and `TERM` conventions read through `var`.
+/// `--color always` and `--color never` win over the environment.
+pub fn color_enabled(
+    choice: ColorChoice,
+    is_terminal: bool,
+    var: impl Fn(&str) -> Option<String>,
+) -> bool {
+    match choice {
+        ColorChoice::Always => return true,
+        ColorChoice::Never => return false,
+        ColorChoice::Auto => {}
+    }
+
+    let set = |name| var(name).filter(|value| !value.is_empty());
+    if set("NO_COLOR").is_some() {
+        return false;
+    }
+    if set("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+        return true;
+    }
+    if set("CLICOLOR").is_some_and(|value| value == "0") {
+        return false;
+    }
+    is_terminal && set("TERM").is_none_or(|term| term != "dumb")
+}
+
+/// Styling for one output stream; every method returns the text unchanged
+/// when colour is off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    enabled: bool,
+}
+
+impl Style {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    /// Whether this stream is coloured.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Section headings such as `Channels:`.
+    pub fn heading(&self, text: &str) -> String {
+        self.paint("1", text)
+    }
+
+    /// Names picked out of a line: channel IDs, aliases, run IDs.
+    pub fn name(&self, text: &str) -> String {
+        self.paint("36", text)
+    }
+
+    /// Secondary detail such as paths and timestamps.
+    pub fn dim(&self, text: &str) -> String {
+        self.paint("2", text)
+    }
+
+    pub fn success(&self, text: &str) -> String {
+        self.paint("32", text)
+    }
+
+    pub fn warning(&self, text: &str) -> String {
+        self.paint("33", text)
+    }
+
+    pub fn failure(&self, text: &str) -> String {
+        self.paint("31", text)
+    }
+
+    /// `text` coloured by a run status: green for `success`, red for
+    /// `failure`, plain otherwise.
+    pub fn status(&self, status: &str, text: &str) -> String {
+        match status {
+            "success" => self.success(text),
+            "failure" => self.failure(text),
+            _ => text.to_string(),
+        }
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.enabled && !text.is_empty() {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Styles for `brb`'s stdout and stderr, decided separately so piping one
+/// keeps it plain while the other still colours the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Output {
+    pub stdout: Style,
+    pub stderr: Style,
+}
+
+impl Output {
+    /// Resolves `choice` against the process environment and terminals.
+    pub fn detect(choice: ColorChoice) -> Self {
+        let var = |name: &str| std::env::var(name).ok();
+        Self {
+            stdout: Style::new(color_enabled(choice, std::io::stdout().is_terminal(), var)),
+            stderr: Style::new(color_enabled(choice, std::io::stderr().is_terminal(), var)),
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::config::{Channel, Config, EventFilter, FieldSelection, Middleware};
+use crate::event::CompletionEvent;
+use crate::redact::{redact_command, redact_text};
+use regex::Regex;
+use std::borrow::Cow;
+
+/// An event on its way to one channel, as its pipeline left it: the event,
+/// and the channel with the template and fields the steps chose.
+#[derive(Debug, Clone)]
+pub struct Prepared<'a> {
+    /// The event after every step.
+    pub event: Cow<'a, CompletionEvent>,
+
+    /// The channel, with `template` and the field selection as set by
+    /// `template` and `fields` steps.
+    pub channel: Cow<'a, Channel>,
+}
+
+/// Steps that decide whether a run's completion is sent to `channel` at
+/// all: the top-level `min_duration` as a `filter`, then the channel's
+/// `notify_on`. They only apply to completions, not to test notifications,
+/// resends or notes.
+pub fn selection_steps(config: &Config, channel: &Channel) -> Vec<Middleware> {
+    let mut steps = Vec::new();
+    if let Some(min_duration) = config.min_duration {
+        steps.push(Middleware::Filter(EventFilter {
+            min_duration: Some(min_duration),
+            ..EventFilter::default()
+        }));
+    }
+    steps.push(Middleware::NotifyOn(channel.notify_on));
+    steps
+}
+
+/// Steps every event goes through on its way to `channel`: the top-level
+/// `redact`, the channel's `template` and `include_fields`/`exclude_fields`,
+/// then its own `pipeline`, whose steps can change what the earlier ones set.
+pub fn delivery_steps(config: &Config, channel: &Channel) -> Vec<Middleware> {
+    let mut steps = vec![Middleware::Redact(config.redact.clone())];
+    if let Some(template) = &channel.template {
+        steps.push(Middleware::Template(template.clone()));
+    }
+    if channel.include_fields.is_some() || !channel.exclude_fields.is_empty() {
+        steps.push(Middleware::Fields(FieldSelection {
+            include: channel.include_fields.clone(),
+            exclude: channel.exclude_fields.clone(),
+        }));
+    }
+    steps.extend(channel.pipeline.iter().cloned());
+    steps
+}
+
+/// Runs `event` through `channel`'s [`delivery_steps`].
+pub fn prepare<'a>(
+    config: &Config,
+    channel: &'a Channel,
+    event: &'a CompletionEvent,
+) -> Result<Prepared<'a>, String> {
+    run_pipeline(&delivery_steps(config, channel), channel, event)
+}
+
+/// Runs `event` through each step of `pipeline` in order. The result borrows
+/// `event` and `channel` when no step changed them; the error is why a
+/// `filter` or `notify_on` step dropped it.
+pub fn run_pipeline<'a>(
+    pipeline: &[Middleware],
+    channel: &'a Channel,
+    event: &'a CompletionEvent,
+) -> Result<Prepared<'a>, String> {
+    let mut event = Cow::Borrowed(event);
+    let mut channel = Cow::Borrowed(channel);
+
+    for step in pipeline {
+        match step {
+            Middleware::Filter(filter) => {
+                if let Some(reason) = filter_miss(filter, &event) {
+                    return Err(reason);
+                }
+            }
+            Middleware::NotifyOn(notify_on) => {
+                if !notify_on.allows(&event.status, event.previous_status.as_deref()) {
+                    return Err(format!("filtered: notify_on {}", notify_on.name()));
+                }
+            }
+            Middleware::Redact(redact) => {
+                let command = redact_command(redact, &event.command);
+                let tail = event
+                    .output_tail
+                    .as_deref()
+                    .map(|tail| redact_text(redact, tail));
+                if command != event.command || tail != event.output_tail {
+                    let event = event.to_mut();
+                    event.command = command;
+                    event.output_tail = tail;
+                }
+            }
+            Middleware::Enrich(labels) => {
+                event.to_mut().labels.extend(labels.clone());
+            }
+            Middleware::Template(name) => {
+                if channel.template.as_ref() != Some(name) {
+                    channel.to_mut().template = Some(name.clone());
+                }
+            }
+            Middleware::Fields(fields) => {
+                if channel.include_fields != fields.include
+                    || channel.exclude_fields != fields.exclude
+                {
+                    let channel = channel.to_mut();
+                    channel.include_fields.clone_from(&fields.include);
+                    channel.exclude_fields.clone_from(&fields.exclude);
+                }
+            }
+        }
+    }
+
+    Ok(Prepared { event, channel })
+}
+
+/// Why `event` fails `filter`, if it does.
+fn filter_miss(filter: &EventFilter, event: &CompletionEvent) -> Option<String> {
+    if let Some(status) = &filter.status
+        && *status != event.status
+    {
+        return Some(format!("filtered: status is not {status}"));
+    }
+
+    if let Some(min) = filter.min_duration
+        && event.duration_ms < min.as_millis()
+    {
+        return Some("filtered: run was too short".to_string());
+    }
+
+    // patterns are checked at config load.
+    if let Some(pattern) = &filter.command
+        && Regex::new(pattern).is_ok_and(|regex| !regex.is_match(&event.command.join(" ")))
+    {
+        return Some("filtered: command did not match".to_string());
+    }
+
+    if let Some(fingerprint) = &filter.fingerprint
+        && *fingerprint != event.fingerprint
+    {
+        return Some("filtered: fingerprint did not match".to_string());
+    }
+
+    None
+}
@@ -0,0 +1,591 @@
+#[cfg(feature = "webhook")]
+use crate::channels::{pushover_url, telegram_url};
+use crate::config::Config;
+#[cfg(feature = "webhook")]
+use crate::config::{
+    ChannelConfig, HttpConfig, MqttChannel, PushoverChannel, TelegramChannel, WebhookChannel,
+    WebhookFormat, ZulipChannel,
+};
+#[cfg(feature = "webhook")]
+use crate::mqtt::{Broker, MqttError, Session, check_credentials};
+#[cfg(feature = "webhook")]
+use reqwest::Url;
+use serde::Serialize;
+use std::fmt;
+#[cfg(feature = "webhook")]
+use std::net::{TcpStream, ToSocketAddrs};
+#[cfg(feature = "webhook")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "webhook")]
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Connectivity check result for one network channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    /// Channel id from config.
+    pub channel_id: String,
+
+    /// DNS resolution time.
+    pub dns_ms: Option<u128>,
+
+    /// TCP connect time.
+    pub connect_ms: Option<u128>,
+
+    /// `HEAD` request round trip.
+    pub head_ms: Option<u128>,
+
+    /// Status returned to the `HEAD` request. Any status means the endpoint
+    /// answered; many webhooks reject `HEAD` with 404 or 405.
+    pub http_status: Option<u16>,
+
+    /// Why the probe stopped, when it did not get through every stage.
+    pub error: Option<String>,
+}
+
+impl ProbeResult {
+    /// Whether the endpoint answered an HTTP request, or accepted a TCP
+    /// connection for channels that are not HTTP.
+    pub fn reachable(&self) -> bool {
+        self.error.is_none() && self.connect_ms.is_some()
+    }
+}
+
+impl fmt::Display for ProbeResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages = [
+            ("dns", self.dns_ms),
+            ("connect", self.connect_ms),
+            ("head", self.head_ms),
+        ]
+        .into_iter()
+        .filter_map(|(stage, ms)| ms.map(|ms| format!("{stage} {ms}ms")))
+        .collect::<Vec<_>>();
+
+        let outcome = match (&self.error, self.http_status) {
+            (None, Some(status)) => format!("reachable, HTTP {status}"),
+            (None, None) if self.connect_ms.is_some() => "reachable".to_string(),
+            (error, _) => format!("unreachable: {}", error.as_deref().unwrap_or("no response")),
+        };
+        write!(f, "{}: {outcome}", self.channel_id)?;
+        if !stages.is_empty() {
+            write!(f, " ({})", stages.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of checking a chat channel's credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthStatus {
+    /// The platform accepted the credentials.
+    Accepted,
+
+    /// The platform rejected the credentials.
+    Rejected,
+
+    /// The check could not tell, e.g. the request failed.
+    Failed,
+
+    /// The channel's format has no call that checks credentials without
+    /// posting a message.
+    Skipped,
+}
+
+/// Credential check result for one webhook channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthResult {
+    /// Channel id from config.
+    pub channel_id: String,
+
+    /// What the check found.
+    pub status: AuthStatus,
+
+    /// The platform's answer or why the check stopped.
+    pub detail: String,
+}
+
+impl AuthResult {
+    /// Whether nothing suggests the channel's credentials are bad.
+    pub fn ok(&self) -> bool {
+        matches!(self.status, AuthStatus::Accepted | AuthStatus::Skipped)
+    }
+}
+
+impl fmt::Display for AuthResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match self.status {
+            AuthStatus::Accepted => "credentials accepted",
+            AuthStatus::Rejected => "credentials rejected",
+            AuthStatus::Failed => "check failed",
+            AuthStatus::Skipped => "not checked",
+        };
+        write!(f, "{}: {outcome} ({})", self.channel_id, self.detail)
+    }
+}
+
+/// Probes every network channel without sending a notification. Other channel
+/// types are skipped.
+#[cfg(feature = "webhook")]
+pub fn probe_channels(config: &Config) -> Vec<ProbeResult> {
+    config
+        .channels
+        .iter()
+        .filter_map(|(channel_id, channel)| match &channel.kind {
+            ChannelConfig::Webhook(webhook) => Some(probe_webhook(channel_id, webhook)),
+            ChannelConfig::Slack(slack) => Some(probe_webhook(channel_id, &slack.as_webhook())),
+            ChannelConfig::GoogleChat(chat) => Some(probe_url(channel_id, &chat.url)),
+            ChannelConfig::Telegram(telegram) => Some(probe_url(channel_id, &telegram.api_url)),
+            ChannelConfig::Ntfy(ntfy) => Some(probe_url(channel_id, &ntfy.topic_url())),
+            ChannelConfig::Pushover(pushover) => Some(probe_url(channel_id, &pushover.api_url)),
+            ChannelConfig::Zulip(zulip) => Some(probe_url(channel_id, &zulip.site)),
+            ChannelConfig::Mqtt(mqtt) => Some(probe_broker(channel_id, mqtt)),
+            // a request to the check's own URL would count as a ping.
+            ChannelConfig::Healthchecks(healthchecks) => {
+                Some(probe_url(channel_id, &server_url(&healthchecks.ping_url())))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The scheme and host of `url`, which can be probed without touching the
+/// rest of it.
+#[cfg(feature = "webhook")]
+fn server_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut url) => {
+            url.set_path("/");
+            url.set_query(None);
+            url.to_string()
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Without the `webhook` feature there are no network channels to probe.
+#[cfg(not(feature = "webhook"))]
+pub fn probe_channels(_config: &Config) -> Vec<ProbeResult> {
+    Vec::new()
+}
+
+/// Checks the credentials of every webhook channel with a call that posts
+/// nothing. Other channel types are skipped.
+#[cfg(feature = "webhook")]
+pub fn check_channels_auth(config: &Config) -> Vec<AuthResult> {
+    config
+        .channels
+        .iter()
+        .filter_map(|(channel_id, channel)| match &channel.kind {
+            ChannelConfig::Webhook(webhook) => {
+                Some(check_webhook_auth(config, channel_id, webhook))
+            }
+            ChannelConfig::Slack(slack) => {
+                Some(check_webhook_auth(config, channel_id, &slack.as_webhook()))
+            }
+            ChannelConfig::GoogleChat(_) => Some(AuthResult {
+                channel_id: channel_id.clone(),
+                status: AuthStatus::Skipped,
+                detail: "google chat webhooks have no call that posts nothing".to_string(),
+            }),
+            ChannelConfig::Telegram(telegram) => {
+                Some(check_telegram_auth(config, channel_id, telegram))
+            }
+            ChannelConfig::Ntfy(ntfy) => {
+                Some(check_webhook_auth(config, channel_id, &ntfy.as_webhook()))
+            }
+            ChannelConfig::Pushover(pushover) => {
+                Some(check_pushover_auth(config, channel_id, pushover))
+            }
+            ChannelConfig::Mqtt(mqtt) => Some(check_mqtt_auth(channel_id, mqtt)),
+            ChannelConfig::Zulip(zulip) => Some(check_zulip_auth(config, channel_id, zulip)),
+            ChannelConfig::Healthchecks(_) => Some(AuthResult {
+                channel_id: channel_id.clone(),
+                status: AuthStatus::Skipped,
+                detail: "healthchecks pings have no call that records nothing".to_string(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Without the `webhook` feature there are no credentials to check.
+#[cfg(not(feature = "webhook"))]
+pub fn check_channels_auth(_config: &Config) -> Vec<AuthResult> {
+    Vec::new()
+}
+
+/// Checks a webhook channel's credentials against each of its URLs,
+/// reporting the first URL that is not accepted or else the last one.
+///
+/// - `slack`: posts an empty payload, which Slack refuses with HTTP 400 for a
+///   working webhook and with 403, 404 or 410 for a revoked or unknown one.
+/// - `discord`: fetches the webhook, which needs its token.
+/// - `ntfy`: asks the topic's `/auth` endpoint whether the headers may publish.
+#[cfg(feature = "webhook")]
+pub fn check_webhook_auth(
+    config: &Config,
+    channel_id: &str,
+    webhook: &WebhookChannel,
+) -> AuthResult {
+    let result = |status, detail: String| AuthResult {
+        channel_id: channel_id.to_string(),
+        status,
+        detail,
+    };
+    let skipped = match webhook.format {
+        WebhookFormat::Generic => Some("generic webhooks have no credential check"),
+        WebhookFormat::CloudEvents => Some("cloudevents webhooks have no credential check"),
+        WebhookFormat::Teams => Some("teams webhooks have no call that posts nothing"),
+        _ => None,
+    };
+    if let Some(reason) = skipped {
+        return result(AuthStatus::Skipped, reason.to_string());
+    }
+
+    let http = config.http.for_webhook(webhook);
+    let Some(client) = auth_client(&http) else {
+        return result(
+            AuthStatus::Failed,
+            "failed to build HTTP client".to_string(),
+        );
+    };
+
+    let mut last = result(AuthStatus::Failed, "no url".to_string());
+    for url in &webhook.url {
+        let request = match webhook.format {
+            WebhookFormat::Slack => client
+                .post(url)
+                .header("Content-Type", "application/json")
+                .body("{}"),
+            WebhookFormat::Discord => client.get(url),
+            _ => {
+                let (base, query) = url.split_once('?').unwrap_or((url, ""));
+                let mut auth_url = format!("{}/auth", base.trim_end_matches('/'));
+                if !query.is_empty() {
+                    auth_url = format!("{auth_url}?{query}");
+                }
+                client.get(auth_url)
+            }
+        };
+        let request = http.headers.iter().fold(request, |request, (name, value)| {
+            request.header(name.as_str(), value.as_str())
+        });
+
+        let (status, detail) = send_auth_check(request, |code| auth_status(webhook.format, code));
+        last = result(status, detail);
+        if status != AuthStatus::Accepted {
+            break;
+        }
+    }
+    last
+}
+
+/// Checks a Telegram bot token with the Bot API's `getMe`, which answers 401
+/// or 404 for a revoked or unknown token.
+#[cfg(feature = "webhook")]
+pub fn check_telegram_auth(
+    config: &Config,
+    channel_id: &str,
+    telegram: &TelegramChannel,
+) -> AuthResult {
+    let (status, detail) = match auth_client(&config.http) {
+        Some(client) => {
+            send_auth_check(
+                client.get(telegram_url(telegram, "getMe")),
+                |code| match code {
+                    200 => AuthStatus::Accepted,
+                    401 | 404 => AuthStatus::Rejected,
+                    _ => AuthStatus::Failed,
+                },
+            )
+        }
+        None => (
+            AuthStatus::Failed,
+            "failed to build HTTP client".to_string(),
+        ),
+    };
+    AuthResult {
+        channel_id: channel_id.to_string(),
+        status,
+        detail,
+    }
+}
+
+/// Checks a Pushover user key and API token with `users/validate.json`, which
+/// answers 400 when either is invalid.
+#[cfg(feature = "webhook")]
+pub fn check_pushover_auth(
+    config: &Config,
+    channel_id: &str,
+    pushover: &PushoverChannel,
+) -> AuthResult {
+    let (status, detail) = match auth_client(&config.http) {
+        Some(client) => {
+            let mut body = serde_json::json!({
+                "token": pushover.api_token.trim(),
+                "user": pushover.user_key.trim(),
+            });
+            if let Some(device) = &pushover.device {
+                body["device"] = serde_json::json!(device);
+            }
+            let request = client
+                .post(pushover_url(pushover, "users/validate.json"))
+                .header("Content-Type", "application/json")
+                .body(body.to_string());
+            send_auth_check(request, |code| match code {
+                200 => AuthStatus::Accepted,
+                400 => AuthStatus::Rejected,
+                _ => AuthStatus::Failed,
+            })
+        }
+        None => (
+            AuthStatus::Failed,
+            "failed to build HTTP client".to_string(),
+        ),
+    };
+    AuthResult {
+        channel_id: channel_id.to_string(),
+        status,
+        detail,
+    }
+}
+
+/// Checks a Zulip bot's email and API key with `users/me`, which answers 401
+/// when either is wrong.
+#[cfg(feature = "webhook")]
+pub fn check_zulip_auth(config: &Config, channel_id: &str, zulip: &ZulipChannel) -> AuthResult {
+    let (status, detail) = match auth_client(&config.http) {
+        Some(client) => send_auth_check(
+            client
+                .get(zulip.api_url("users/me"))
+                .header("Authorization", zulip.authorization()),
+            |code| match code {
+                200 => AuthStatus::Accepted,
+                401 | 403 => AuthStatus::Rejected,
+                _ => AuthStatus::Failed,
+            },
+        ),
+        None => (
+            AuthStatus::Failed,
+            "failed to build HTTP client".to_string(),
+        ),
+    };
+    AuthResult {
+        channel_id: channel_id.to_string(),
+        status,
+        detail,
+    }
+}
+
+/// Checks MQTT credentials by connecting and disconnecting without
+/// publishing.
+#[cfg(feature = "webhook")]
+pub fn check_mqtt_auth(channel_id: &str, mqtt: &MqttChannel) -> AuthResult {
+    let client_id = format!("brb-check-{}", std::process::id());
+    let session = Session {
+        client_id: mqtt.client_id.as_deref().unwrap_or(&client_id),
+        username: mqtt.username.as_deref(),
+        password: mqtt.password.as_deref(),
+    };
+    let outcome = Broker::parse(&mqtt.broker)
+        .map_err(MqttError::Network)
+        .and_then(|broker| check_credentials(&broker, &session, PROBE_TIMEOUT));
+    let (status, detail) = match outcome {
+        Ok(()) => (AuthStatus::Accepted, "connected".to_string()),
+        Err(MqttError::Refused(reason)) => (AuthStatus::Rejected, reason),
+        Err(MqttError::Timeout) => (AuthStatus::Failed, "broker timed out".to_string()),
+        Err(MqttError::Network(reason)) => (AuthStatus::Failed, reason),
+    };
+    AuthResult {
+        channel_id: channel_id.to_string(),
+        status,
+        detail,
+    }
+}
+
+/// HTTP client for credential checks, honouring the user agent and proxy.
+#[cfg(feature = "webhook")]
+fn auth_client(http: &HttpConfig) -> Option<reqwest::blocking::Client> {
+    let mut client = reqwest::blocking::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .user_agent(
+            http.user_agent
+                .clone()
+                .unwrap_or_else(|| format!("brb/{}", env!("CARGO_PKG_VERSION"))),
+        );
+    if let Some(proxy) = http
+        .proxy
+        .as_deref()
+        .and_then(|proxy| reqwest::Proxy::all(proxy).ok())
+    {
+        client = client.proxy(proxy);
+    }
+    client.build().ok()
+}
+
+/// Sends a credential check, reading the outcome from the HTTP status with
+/// `status_for` and keeping the start of the answer as the detail.
+#[cfg(feature = "webhook")]
+fn send_auth_check(
+    request: reqwest::blocking::RequestBuilder,
+    status_for: impl Fn(u16) -> AuthStatus,
+) -> (AuthStatus, String) {
+    // errors never include the URL, which may carry a token.
+    match request.send() {
+        Ok(response) => {
+            let code = response.status().as_u16();
+            let answer = response
+                .text()
+                .unwrap_or_default()
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .take(80)
+                .collect::<String>();
+            let detail = if answer.is_empty() {
+                format!("HTTP {code}")
+            } else {
+                format!("HTTP {code} {answer}")
+            };
+            (status_for(code), detail)
+        }
+        Err(error) if error.is_timeout() => (AuthStatus::Failed, "request timed out".to_string()),
+        Err(_) => (AuthStatus::Failed, "request failed".to_string()),
+    }
+}
+
+/// What an HTTP status from a format's credential check means.
+#[cfg(feature = "webhook")]
+fn auth_status(format: WebhookFormat, code: u16) -> AuthStatus {
+    match (format, code) {
+        (WebhookFormat::Slack, 400) => AuthStatus::Accepted,
+        (WebhookFormat::Slack, 403 | 404 | 410) => AuthStatus::Rejected,
+        (WebhookFormat::Discord | WebhookFormat::Ntfy, 200) => AuthStatus::Accepted,
+        (WebhookFormat::Discord, 401 | 403 | 404) => AuthStatus::Rejected,
+        (WebhookFormat::Ntfy, 401 | 403) => AuthStatus::Rejected,
+        _ => AuthStatus::Failed,
+    }
+}
+
+/// Resolves, connects to and sends a `HEAD` request to each of a webhook's
+/// URLs, reporting the first URL that fails or else the last one probed.
+#[cfg(feature = "webhook")]
+pub fn probe_webhook(channel_id: &str, webhook: &WebhookChannel) -> ProbeResult {
+    let mut last = ProbeResult {
+        channel_id: channel_id.to_string(),
+        dns_ms: None,
+        connect_ms: None,
+        head_ms: None,
+        http_status: None,
+        error: Some("no url".to_string()),
+    };
+    for result in webhook.url.iter().map(|url| probe_url(channel_id, url)) {
+        let failed = result.error.is_some();
+        last = result;
+        if failed {
+            break;
+        }
+    }
+    last
+}
+
+/// Probes one URL, stopping at the first stage that fails.
+#[cfg(feature = "webhook")]
+fn probe_url(channel_id: &str, raw_url: &str) -> ProbeResult {
+    let mut result = ProbeResult {
+        channel_id: channel_id.to_string(),
+        dns_ms: None,
+        connect_ms: None,
+        head_ms: None,
+        http_status: None,
+        error: None,
+    };
+
+    // errors never include the URL, which may carry a token.
+    let Ok(url) = Url::parse(raw_url) else {
+        result.error = Some("invalid url".to_string());
+        return result;
+    };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        result.error = Some("url has no host or port".to_string());
+        return result;
+    };
+    if !probe_connect(&mut result, host, port) {
+        return result;
+    }
+
+    let client = match reqwest::blocking::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(_) => {
+            result.error = Some("failed to build HTTP client".to_string());
+            return result;
+        }
+    };
+
+    let started = Instant::now();
+    match client.head(url).send() {
+        Ok(response) => {
+            result.head_ms = Some(started.elapsed().as_millis());
+            result.http_status = Some(response.status().as_u16());
+        }
+        Err(error) if error.is_timeout() => {
+            result.error = Some("HEAD request timed out".to_string())
+        }
+        Err(_) => result.error = Some("HEAD request failed".to_string()),
+    }
+    result
+}
+
+/// Resolves and connects to an MQTT channel's broker. Nothing is sent, so
+/// TLS brokers are only checked up to the TCP connection.
+#[cfg(feature = "webhook")]
+pub fn probe_broker(channel_id: &str, mqtt: &MqttChannel) -> ProbeResult {
+    let mut result = ProbeResult {
+        channel_id: channel_id.to_string(),
+        dns_ms: None,
+        connect_ms: None,
+        head_ms: None,
+        http_status: None,
+        error: None,
+    };
+    match Broker::parse(&mqtt.broker) {
+        Ok(broker) => {
+            probe_connect(&mut result, &broker.host, broker.port);
+        }
+        Err(error) => result.error = Some(format!("invalid broker: {error}")),
+    }
+    result
+}
+
+/// Runs the DNS and TCP stages of a probe, recording their times or the
+/// error. Returns whether the connection was made.
+#[cfg(feature = "webhook")]
+fn probe_connect(result: &mut ProbeResult, host: &str, port: u16) -> bool {
+    let started = Instant::now();
+    let address = (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addresses| addresses.next());
+    result.dns_ms = Some(started.elapsed().as_millis());
+    let Some(address) = address else {
+        result.error = Some(format!("could not resolve `{host}`"));
+        return false;
+    };
+
+    let started = Instant::now();
+    if let Err(error) = TcpStream::connect_timeout(&address, PROBE_TIMEOUT) {
+        result.error = Some(format!(
+            "tcp connect to port {port} failed ({})",
+            error.kind()
+        ));
+        return false;
+    }
+    result.connect_ms = Some(started.elapsed().as_millis());
+    true
+}
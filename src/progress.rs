@@ -0,0 +1,120 @@
+use crate::capture::extract_captures;
+use crate::config::WebhookFormat;
+use crate::event::CompletionEvent;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// What a chat API's reply to a posted message is captured by, the way a
+/// channel's `capture` would be: Slack's `ts`, `channel` and `error`, or
+/// Discord's `id`.
+pub fn message_capture(format: WebhookFormat) -> BTreeMap<String, String> {
+    let paths: &[(&str, &str)] = match format {
+        WebhookFormat::Slack => &[
+            ("ts", "$.ts"),
+            ("channel", "$.channel"),
+            ("error", "$.error"),
+        ],
+        _ => &[("id", "$.id")],
+    };
+    paths
+        .iter()
+        .map(|(name, path)| (name.to_string(), path.to_string()))
+        .collect()
+}
+
+/// Captures the posted message from a chat API's reply with
+/// [`message_capture`]. Slack answers `200` with an `error` on failure, so
+/// that is checked too.
+pub fn capture_message(
+    format: WebhookFormat,
+    response: &str,
+) -> Result<BTreeMap<String, String>, String> {
+    let captured = extract_captures(&message_capture(format), response);
+    if let Some(error) = captured.get("error") {
+        return Err(format!("slack returned `{error}`"));
+    }
+    if message_id(format, &captured).is_none() {
+        return Err("response has no message id".to_string());
+    }
+    Ok(captured)
+}
+
+/// Captures the message a Telegram `sendMessage` posted: its `message_id`.
+pub fn capture_telegram_message(response: &str) -> Result<BTreeMap<String, String>, String> {
+    let capture = BTreeMap::from([("message_id".to_string(), "$.result.message_id".to_string())]);
+    let captured = extract_captures(&capture, response);
+    if !captured.contains_key("message_id") {
+        return Err("response has no message id".to_string());
+    }
+    Ok(captured)
+}
+
+/// The posted message's ID among values captured with [`message_capture`].
+pub fn message_id(format: WebhookFormat, captured: &BTreeMap<String, String>) -> Option<&str> {
+    let name = match format {
+        WebhookFormat::Slack => "ts",
+        _ => "id",
+    };
+    captured.get(name).map(String::as_str)
+}
+
+/// URL a "running" message is posted to. Discord only returns the message
+/// when asked to wait for it.
+pub fn progress_post_url(format: WebhookFormat, url: &str) -> String {
+    match format {
+        WebhookFormat::Discord if url.contains('?') => format!("{url}&wait=true"),
+        WebhookFormat::Discord => format!("{url}?wait=true"),
+        _ => url.to_string(),
+    }
+}
+
+/// Method and URL that edit a posted message: Slack's `chat.update` next to
+/// `chat.postMessage`, or Discord's message endpoint under the webhook.
+pub fn progress_edit_target(
+    format: WebhookFormat,
+    url: &str,
+    message_id: &str,
+) -> (&'static str, String) {
+    match format {
+        WebhookFormat::Slack => ("POST", url.replace("chat.postMessage", "chat.update")),
+        _ => {
+            let (base, query) = url.split_once('?').unwrap_or((url, ""));
+            let mut edit = format!("{}/messages/{message_id}", base.trim_end_matches('/'));
+            if !query.is_empty() {
+                edit = format!("{edit}?{query}");
+            }
+            ("PATCH", edit)
+        }
+    }
+}
+
+/// Whether an event follows up a run rather than finishing it: a trigger
+/// that fired while it ran, or a note posted afterwards. Follow-ups reply to
+/// the run's "running" message instead of editing it.
+pub fn is_follow_up(event: &CompletionEvent) -> bool {
+    event.trigger.is_some() || event.posted_note.is_some()
+}
+
+/// Points a final status body at the posted message, for Slack's
+/// `chat.update`; Discord finds the message from the URL.
+pub fn apply_progress(body: &mut Value, captured: &BTreeMap<String, String>) {
+    let Value::Object(object) = body else {
+        return;
+    };
+    if let (Some(channel), Some(ts)) = (captured.get("channel"), captured.get("ts")) {
+        object.insert("channel".to_string(), Value::String(channel.clone()));
+        object.insert("ts".to_string(), Value::String(ts.clone()));
+    }
+}
+
+/// Makes a follow-up body a reply in the posted message's Slack thread.
+/// Discord webhooks cannot reply, so their follow-ups are left as they are.
+pub fn apply_thread_reply(body: &mut Value, captured: &BTreeMap<String, String>) {
+    let Value::Object(object) = body else {
+        return;
+    };
+    if let (Some(channel), Some(ts)) = (captured.get("channel"), captured.get("ts")) {
+        object.insert("channel".to_string(), Value::String(channel.clone()));
+        object.insert("thread_ts".to_string(), Value::String(ts.clone()));
+    }
+}
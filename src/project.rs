@@ -0,0 +1,42 @@
+use crate::cargo::package_name;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Names the project `dir` belongs to: the nearest `Cargo.toml` package or
+/// `package.json` name, stopping at the git repository root, whose directory
+/// name is used when no manifest names the project. Outside a repository
+/// without a manifest, `dir`'s own name.
+pub fn detect_project(dir: &Path) -> Option<String> {
+    for ancestor in dir.ancestors() {
+        if let Some(name) = manifest_name(ancestor) {
+            return Some(name);
+        }
+        if ancestor.join(".git").exists() {
+            return dir_name(ancestor);
+        }
+    }
+    dir_name(dir)
+}
+
+/// The package name from `Cargo.toml` or `package.json` in `dir`. A virtual
+/// workspace manifest names nothing.
+fn manifest_name(dir: &Path) -> Option<String> {
+    let cargo = fs::read_to_string(dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|manifest| package_name(&manifest));
+    cargo.or_else(|| {
+        let manifest = fs::read_to_string(dir.join("package.json")).ok()?;
+        let manifest = serde_json::from_str::<Value>(&manifest).ok()?;
+        manifest["name"]
+            .as_str()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+    })
+}
+
+fn dir_name(dir: &Path) -> Option<String> {
+    dir.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
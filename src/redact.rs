@@ -0,0 +1,79 @@
+use crate::config::RedactConfig;
+use regex::Regex;
+
+/// Replacement for redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Flags whose values are masked when `redact.flags` is not set.
+pub const DEFAULT_FLAGS: &[&str] = &[
+    "--password",
+    "--passwd",
+    "--token",
+    "--access-token",
+    "--secret",
+    "--api-key",
+    "--apikey",
+    "--auth",
+];
+
+/// Masks credentials in a command's argv: the value after (or `=`-joined to)
+/// any configured flag, and every match of the configured patterns.
+pub fn redact_command(config: &RedactConfig, command: &[String]) -> Vec<String> {
+    let flags = config
+        .flags
+        .as_ref()
+        .map(|flags| flags.iter().map(String::as_str).collect::<Vec<_>>())
+        .unwrap_or_else(|| DEFAULT_FLAGS.to_vec());
+    let patterns = compile_patterns(config);
+    let is_flag = |arg: &str| flags.iter().any(|flag| flag.eq_ignore_ascii_case(arg));
+
+    let mut redacted = Vec::with_capacity(command.len());
+    let mut mask_next = false;
+    for arg in command {
+        if mask_next {
+            mask_next = false;
+            redacted.push(REDACTED.to_string());
+            continue;
+        }
+
+        if is_flag(arg) {
+            mask_next = true;
+            redacted.push(arg.clone());
+            continue;
+        }
+
+        if let Some((flag, _)) = arg.split_once('=')
+            && is_flag(flag)
+        {
+            redacted.push(format!("{flag}={REDACTED}"));
+            continue;
+        }
+
+        let mut arg = arg.clone();
+        for pattern in &patterns {
+            arg = pattern.replace_all(&arg, REDACTED).into_owned();
+        }
+        redacted.push(arg);
+    }
+
+    redacted
+}
+
+/// Masks every match of the configured patterns in free text, such as
+/// captured output. Flags only apply to argv.
+pub fn redact_text(config: &RedactConfig, text: &str) -> String {
+    compile_patterns(config)
+        .iter()
+        .fold(text.to_string(), |text, pattern| {
+            pattern.replace_all(&text, REDACTED).into_owned()
+        })
+}
+
+fn compile_patterns(config: &RedactConfig) -> Vec<Regex> {
+    // patterns are checked at config load.
+    config
+        .patterns
+        .iter()
+        .filter_map(|pattern| Regex::new(pattern).ok())
+        .collect()
+}
@@ -0,0 +1,102 @@
+use crate::diagnostic::closest_match;
+
+/// One field of a channel type, as `brb channels docs` describes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldReference {
+    /// Key in the channel block.
+    pub name: &'static str,
+
+    /// YAML shape of the value, e.g. `string` or `list of string`.
+    pub kind: &'static str,
+
+    /// Whether config fails to load without it.
+    pub required: bool,
+
+    /// Value used when it is unset, if it has one worth naming.
+    pub default: Option<&'static str>,
+
+    /// What the field does.
+    pub description: &'static str,
+}
+
+/// The reference for one channel type: what it is, its fields and an
+/// example block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelReference {
+    /// Value of `type`.
+    pub type_name: &'static str,
+
+    /// One-line summary.
+    pub summary: &'static str,
+
+    /// Fields specific to the type, in the order the config structs declare
+    /// them.
+    pub fields: &'static [FieldReference],
+
+    /// A channel block of this type, indented as it sits under a channel ID.
+    pub example: &'static str,
+}
+
+// `COMMON_FIELDS` and `CHANNEL_REFERENCES`, generated by `build.rs` from the
+// channel types in `config.rs`.
+include!(concat!(env!("OUT_DIR"), "/channel_reference.rs"));
+
+/// The reference for a channel type, or an error suggesting the closest
+/// known type.
+pub fn channel_reference(type_name: &str) -> Result<&'static ChannelReference, String> {
+    if let Some(reference) = CHANNEL_REFERENCES
+        .iter()
+        .find(|reference| reference.type_name == type_name)
+    {
+        return Ok(reference);
+    }
+
+    let types = CHANNEL_REFERENCES
+        .iter()
+        .map(|reference| reference.type_name);
+    let hint = match closest_match(type_name, types.clone()) {
+        Some(closest) => format!("; did you mean `{closest}`?"),
+        None => String::new(),
+    };
+    Err(format!(
+        "unknown channel type `{type_name}` (expected one of {}){hint}",
+        types.collect::<Vec<_>>().join(", ")
+    ))
+}
+
+/// Renders a channel type's reference as `brb channels docs` prints it.
+pub fn render_reference(reference: &ChannelReference) -> String {
+    let mut output = format!(
+        "type: {}\n{}\n\nFields:\n",
+        reference.type_name, reference.summary
+    );
+    push_fields(&mut output, reference.fields);
+
+    output.push_str("\nEvery channel also takes:\n");
+    push_fields(&mut output, COMMON_FIELDS);
+
+    output.push_str(&format!(
+        "\nExample:\n  channels:\n    {}:\n{}",
+        reference.type_name,
+        reference
+            .example
+            .lines()
+            .map(|line| format!("  {line}\n"))
+            .collect::<String>()
+    ));
+    output
+}
+
+fn push_fields(output: &mut String, fields: &[FieldReference]) {
+    for field in fields {
+        let detail = match (field.required, field.default) {
+            (true, _) => "required".to_string(),
+            (false, Some(default)) => format!("default {default}"),
+            (false, None) => "optional".to_string(),
+        };
+        output.push_str(&format!(
+            "  {} ({}, {detail})\n      {}\n",
+            field.name, field.kind, field.description
+        ));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::channels::DeliveryResult;
+use crate::event::CompletionEvent;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Machine-readable record of everything one `brb` invocation did.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    /// Constant tool identifier.
+    pub tool: String,
+
+    /// Exit code `brb` returns (the last run's exit code).
+    pub exit_code: i32,
+
+    /// One entry per wrapped command run (more than one with `--every`).
+    pub runs: Vec<ReportedRun>,
+}
+
+/// A single run and the delivery results it produced.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportedRun {
+    /// Completion event sent to channels.
+    pub event: CompletionEvent,
+
+    /// Per-channel delivery results.
+    pub deliveries: Vec<DeliveryResult>,
+
+    /// Time spent notifying every channel, in milliseconds.
+    pub delivery_ms: u128,
+}
+
+impl Default for RunReport {
+    fn default() -> Self {
+        Self {
+            tool: "brb".to_string(),
+            exit_code: 0,
+            runs: vec![],
+        }
+    }
+}
+
+impl RunReport {
+    /// Records one completed run, its delivery results and how long
+    /// delivering took.
+    pub fn push(
+        &mut self,
+        event: &CompletionEvent,
+        deliveries: &[DeliveryResult],
+        delivery_time: Duration,
+    ) {
+        self.exit_code = event.exit_code;
+        self.runs.push(ReportedRun {
+            event: event.clone(),
+            deliveries: deliveries.to_vec(),
+            delivery_ms: delivery_time.as_millis(),
+        });
+    }
+
+    /// Writes the report as pretty-printed JSON, replacing any existing file.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|error| format!("failed to encode report: {error}"))?;
+        fs::write(path, format!("{json}\n"))
+            .map_err(|error| format!("failed to write report {}: {error}", path.display()))
+    }
+}
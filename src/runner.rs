@@ -1,5 +1,12 @@
+use crate::environment::{Environment, SystemEnvironment};
 use chrono::{DateTime, Utc};
-use std::process::{Command, Stdio};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
 
 /// Captured result from executing a wrapped command.
@@ -22,15 +29,224 @@ pub struct RunResult {
 
     /// Spawn-time error message if the command failed to start.
     pub spawn_error: Option<String>,
+
+    /// Last lines of combined stdout/stderr when capture was requested.
+    pub output_tail: Option<String>,
+
+    /// Directory the command ran in, when it was not `brb`'s own.
+    pub cwd: Option<PathBuf>,
+}
+
+/// Options controlling how the wrapped command is executed.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Number of trailing output lines to capture (`0` keeps inherited stdio).
+    pub capture_lines: usize,
+
+    /// Working directory for the command; `brb`'s own when unset.
+    pub cwd: Option<PathBuf>,
+
+    /// Environment variables added to the command's inherited environment.
+    pub env: BTreeMap<String, String>,
+
+    /// How capture treats commands that need a terminal.
+    pub interactivity: Interactivity,
+
+    /// Called with each line of captured output as the command writes it.
+    pub on_line: Vec<LineWatcher>,
+}
+
+/// Output stream of the wrapped command. Under a pseudo-terminal both
+/// arrive as stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Callback given each non-empty line of captured output, without its line
+/// ending, from the thread reading that stream. A carriage return also ends
+/// a line, so each redraw of a progress bar is seen.
+#[derive(Clone)]
+pub struct LineWatcher(Arc<LineFn>);
+
+type LineFn = dyn Fn(OutputStream, &str) + Send + Sync;
+
+impl LineWatcher {
+    pub fn new(watch: impl Fn(OutputStream, &str) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(watch))
+    }
+
+    fn line(&self, stream: OutputStream, bytes: &[u8]) {
+        (self.0)(stream, &String::from_utf8_lossy(bytes));
+    }
+}
+
+impl fmt::Debug for LineWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LineWatcher(..)")
+    }
+}
+
+/// How capture treats a wrapped command that may need a terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Interactivity {
+    /// Pipe output unless stdin is a terminal and the command looks
+    /// interactive, in which case it keeps the terminal and nothing is
+    /// captured.
+    #[default]
+    Auto,
+
+    /// Always pipe output, requested by `--assume-non-interactive`.
+    NonInteractive,
+
+    /// Run under a pseudo-terminal so output is captured while the command
+    /// still sees a terminal, requested by `--force-pty`.
+    Pty,
+}
+
+/// How the wrapped command's output is connected for one run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureMode {
+    /// Inherited stdio; nothing is captured.
+    Inherit,
+
+    /// Piped stdout and stderr, teed to the terminal.
+    Pipe,
+
+    /// The given command line, which runs the command under `script(1)` with
+    /// its output piped and teed.
+    Pty(Vec<String>),
+}
+
+/// Picks how to connect the command's output, given whether `brb`'s stdin is
+/// a terminal.
+pub fn capture_mode(
+    command: &[String],
+    options: &RunOptions,
+    stdin_is_terminal: bool,
+) -> CaptureMode {
+    if options.capture_lines == 0 {
+        return CaptureMode::Inherit;
+    }
+    match options.interactivity {
+        Interactivity::NonInteractive => CaptureMode::Pipe,
+        Interactivity::Pty => pty_command(command).map_or(CaptureMode::Inherit, CaptureMode::Pty),
+        Interactivity::Auto if stdin_is_terminal && likely_interactive(command) => {
+            CaptureMode::Inherit
+        }
+        Interactivity::Auto => CaptureMode::Pipe,
+    }
+}
+
+/// Whether a command probably needs a terminal: remote shells, editors,
+/// pagers and full-screen tools, and container `run`/`exec`/`attach` given
+/// `-i` or `-t`.
+pub fn likely_interactive(command: &[String]) -> bool {
+    let Some(program) = command.first() else {
+        return false;
+    };
+    let program = program.rsplit('/').next().unwrap_or(program);
+    match program {
+        "ssh" | "mosh" | "telnet" | "vi" | "vim" | "nvim" | "nano" | "emacs" | "less" | "more"
+        | "man" | "top" | "htop" | "tmux" | "screen" => true,
+        "docker" | "podman" | "kubectl" => container_attaches_terminal(&command[1..]),
+        _ => false,
+    }
+}
+
+/// Whether container CLI arguments run, exec or attach with a terminal or
+/// stdin. Other subcommands use `-t` for other things, like a build's tag.
+fn container_attaches_terminal(args: &[String]) -> bool {
+    let mut positional = args.iter().filter(|arg| !arg.starts_with('-'));
+    let subcommand = match positional.next().map(String::as_str) {
+        Some("container") => positional.next().map(String::as_str),
+        subcommand => subcommand,
+    };
+    if !matches!(subcommand, Some("run" | "exec" | "attach")) {
+        return false;
+    }
+    args.iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| match arg.strip_prefix("--") {
+            Some(long) => {
+                let name = long.split('=').next().unwrap_or(long);
+                matches!(name, "interactive" | "tty" | "stdin")
+            }
+            None => arg.strip_prefix('-').is_some_and(|short| {
+                short
+                    .chars()
+                    .take_while(char::is_ascii_alphabetic)
+                    .any(|flag| flag == 'i' || flag == 't')
+            }),
+        })
+}
+
+/// The command line running `command` under `script(1)`, which gives it a
+/// pseudo-terminal and copies what it prints to its own stdout. `None` where
+/// `script` is unavailable.
+pub fn pty_command(command: &[String]) -> Option<Vec<String>> {
+    if command.is_empty() || !script_available() {
+        return None;
+    }
+    // util-linux `script` takes one shell command; BSD and macOS take argv.
+    if cfg!(target_os = "linux") {
+        let line = command
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let script = ["script", "-q", "-e", "-f", "-c", &line, "/dev/null"];
+        Some(script.into_iter().map(str::to_string).collect())
+    } else {
+        let script = ["script", "-q", "/dev/null"]
+            .into_iter()
+            .map(str::to_string);
+        Some(script.chain(command.iter().cloned()).collect())
+    }
+}
+
+fn script_available() -> bool {
+    cfg!(unix)
+        && std::env::var_os("PATH").is_some_and(|path| {
+            std::env::split_paths(&path).any(|dir| dir.join("script").is_file())
+        })
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
 }
 
 /// Runs a command with inherited stdio and returns completion metadata.
 pub fn run_command(command: &[String]) -> RunResult {
-    let started_at = Utc::now();
+    run_command_with(command, &RunOptions::default())
+}
+
+/// Runs a command, optionally teeing its output so a tail can be captured.
+pub fn run_command_with(command: &[String], options: &RunOptions) -> RunResult {
+    run_command_in(command, options, &SystemEnvironment)
+}
+
+/// Like [`run_command_with`], taking start and finish times from
+/// `environment`'s clock. The duration is always measured for real.
+pub fn run_command_in(
+    command: &[String],
+    options: &RunOptions,
+    environment: &dyn Environment,
+) -> RunResult {
+    let started_at = environment.now();
     let started = Instant::now();
 
     if command.is_empty() {
-        let finished_at = Utc::now();
+        let finished_at = environment.now();
         return RunResult {
             command: vec![],
             started_at,
@@ -38,19 +254,45 @@ pub fn run_command(command: &[String]) -> RunResult {
             duration: started.elapsed(),
             exit_code: 2,
             spawn_error: Some("no command provided".to_string()),
+            output_tail: None,
+            cwd: options.cwd.clone(),
         };
     }
 
-    let status = Command::new(&command[0])
-        .args(&command[1..])
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status();
+    let mode = capture_mode(command, options, io::stdin().is_terminal());
+    let argv = match &mode {
+        CaptureMode::Pty(script) => script.as_slice(),
+        _ => command,
+    };
+    let mut process = Command::new(&argv[0]);
+    process
+        .args(&argv[1..])
+        .envs(&options.env)
+        .stdin(Stdio::inherit());
+    if let Some(cwd) = &options.cwd {
+        process.current_dir(cwd);
+    }
+
+    let (status, output_tail) = if mode == CaptureMode::Inherit {
+        let status = process
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status();
+        (status, None)
+    } else {
+        let tail = Arc::new(Mutex::new(OutputTail::new(options.capture_lines)));
+        let status = process
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .and_then(|child| wait_teeing(child, &tail, &options.on_line));
+        let output_tail = tail.lock().ok().map(|tail| tail.render());
+        (status, output_tail)
+    };
 
     match status {
         Ok(status) => {
-            let finished_at = Utc::now();
+            let finished_at = environment.now();
             RunResult {
                 command: command.to_vec(),
                 started_at,
@@ -58,18 +300,142 @@ pub fn run_command(command: &[String]) -> RunResult {
                 duration: started.elapsed(),
                 exit_code: status.code().unwrap_or(1),
                 spawn_error: None,
+                output_tail,
+                cwd: options.cwd.clone(),
             }
         }
         Err(error) => {
-            let finished_at = Utc::now();
+            let finished_at = environment.now();
             RunResult {
                 command: command.to_vec(),
                 started_at,
                 finished_at,
                 duration: started.elapsed(),
                 exit_code: 127,
-                spawn_error: Some(format!("failed to start `{}`: {error}", command[0])),
+                spawn_error: Some(match &options.cwd {
+                    Some(cwd) => format!(
+                        "failed to start `{}` in {}: {error}",
+                        command[0],
+                        cwd.display()
+                    ),
+                    None => format!("failed to start `{}`: {error}", command[0]),
+                }),
+                output_tail: None,
+                cwd: options.cwd.clone(),
             }
         }
     }
 }
+
+fn wait_teeing(
+    mut child: Child,
+    tail: &Arc<Mutex<OutputTail>>,
+    on_line: &[LineWatcher],
+) -> io::Result<ExitStatus> {
+    let stdout = child.stdout.take().map(|pipe| {
+        let tail = Arc::clone(tail);
+        let watchers = on_line.to_vec();
+        thread::spawn(move || tee(pipe, io::stdout(), &tail, OutputStream::Stdout, &watchers))
+    });
+    let stderr = child.stderr.take().map(|pipe| {
+        let tail = Arc::clone(tail);
+        let watchers = on_line.to_vec();
+        thread::spawn(move || tee(pipe, io::stderr(), &tail, OutputStream::Stderr, &watchers))
+    });
+
+    let status = child.wait();
+    for handle in [stdout, stderr].into_iter().flatten() {
+        let _ = handle.join();
+    }
+    status
+}
+
+fn tee(
+    mut pipe: impl Read,
+    mut terminal: impl Write,
+    tail: &Mutex<OutputTail>,
+    stream: OutputStream,
+    watchers: &[LineWatcher],
+) {
+    let mut buffer = [0u8; 8192];
+    let mut line = Vec::new();
+    loop {
+        let read = match pipe.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+
+        let _ = terminal.write_all(&buffer[..read]);
+        let _ = terminal.flush();
+        if let Ok(mut tail) = tail.lock() {
+            tail.push(&buffer[..read]);
+        }
+        if watchers.is_empty() {
+            continue;
+        }
+        for byte in &buffer[..read] {
+            if !matches!(byte, b'\n' | b'\r') {
+                line.push(*byte);
+            } else if !line.is_empty() {
+                watchers
+                    .iter()
+                    .for_each(|watcher| watcher.line(stream, &line));
+                line.clear();
+            }
+        }
+    }
+    if !line.is_empty() {
+        watchers
+            .iter()
+            .for_each(|watcher| watcher.line(stream, &line));
+    }
+}
+
+/// Bounded buffer keeping only the last `max_lines` lines of output.
+struct OutputTail {
+    max_lines: usize,
+    lines: VecDeque<Vec<u8>>,
+    partial: Vec<u8>,
+}
+
+impl OutputTail {
+    fn new(max_lines: usize) -> Self {
+        Self {
+            max_lines,
+            lines: VecDeque::new(),
+            partial: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            if *byte == b'\n' {
+                let line = std::mem::take(&mut self.partial);
+                self.lines.push_back(line);
+                if self.lines.len() > self.max_lines {
+                    self.lines.pop_front();
+                }
+            } else {
+                self.partial.push(*byte);
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut lines = self
+            .lines
+            .iter()
+            .map(|line| {
+                String::from_utf8_lossy(line)
+                    .trim_end_matches('\r')
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+        if !self.partial.is_empty() {
+            lines.push(String::from_utf8_lossy(&self.partial).to_string());
+        }
+
+        let skip = lines.len().saturating_sub(self.max_lines);
+        lines[skip..].join("\n")
+    }
+}
@@ -0,0 +1,250 @@
+use crate::config::{ConfigError, SecretsConfig, config_file_path, config_value};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsStr;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Environment variable overriding the configured identity file.
+pub const IDENTITY_ENV: &str = "BRB_AGE_IDENTITY";
+
+/// Returns the age identity file used for `secrets`: `BRB_AGE_IDENTITY`,
+/// then `secrets.identity`, then `age-identity.txt` next to the config file.
+pub fn identity_path(secrets: &SecretsConfig) -> Result<PathBuf, ConfigError> {
+    if let Some(path) = env::var_os(IDENTITY_ENV).filter(|path| !path.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    if let Some(path) = &secrets.identity {
+        return Ok(path.clone());
+    }
+
+    let config_path = config_file_path()?;
+    let dir = config_path.parent().ok_or_else(|| {
+        ConfigError::InvalidConfig("calculated config path has no parent directory".to_string())
+    })?;
+    Ok(dir.join("age-identity.txt"))
+}
+
+/// Reads just the `secrets` block from config YAML, so secrets can be managed
+/// while the rest of the config references ones that do not exist yet.
+pub fn secrets_from_source(source: &str) -> Result<SecretsConfig, ConfigError> {
+    let (value, _) = config_value(source)?;
+    match value.get("secrets") {
+        Some(secrets) => Ok(serde_yaml::from_value(secrets.clone())?),
+        None => Ok(SecretsConfig::default()),
+    }
+}
+
+/// Decrypts an armored age ciphertext with the configured identity.
+pub fn decrypt(secrets: &SecretsConfig, armored: &str) -> Result<String, String> {
+    let identity = identity_path(secrets).map_err(|error| error.to_string())?;
+    let output = run_age(
+        secrets,
+        &[
+            OsStr::new("--decrypt"),
+            OsStr::new("--identity"),
+            identity.as_os_str(),
+        ],
+        armored.as_bytes(),
+    )?;
+    String::from_utf8(output).map_err(|_| "decrypted secret is not UTF-8".to_string())
+}
+
+/// Encrypts `plaintext` to the configured identity's recipient, returning an
+/// armored ciphertext suitable for `secrets.values`.
+pub fn encrypt(secrets: &SecretsConfig, plaintext: &str) -> Result<String, String> {
+    let identity = identity_path(secrets).map_err(|error| error.to_string())?;
+    let output = run_age(
+        secrets,
+        &[
+            OsStr::new("--encrypt"),
+            OsStr::new("--armor"),
+            OsStr::new("--identity"),
+            identity.as_os_str(),
+        ],
+        plaintext.as_bytes(),
+    )?;
+    String::from_utf8(output).map_err(|_| "age produced non-UTF-8 armor".to_string())
+}
+
+/// Encrypts `plaintext` to public `recipients`, returning armored ciphertext.
+/// No identity is needed.
+pub fn encrypt_to(
+    secrets: &SecretsConfig,
+    recipients: &[String],
+    plaintext: &[u8],
+) -> Result<String, String> {
+    let mut args = vec![OsStr::new("--encrypt"), OsStr::new("--armor")];
+    for recipient in recipients {
+        args.push(OsStr::new("--recipient"));
+        args.push(OsStr::new(recipient));
+    }
+    let output = run_age(secrets, &args, plaintext)?;
+    String::from_utf8(output).map_err(|_| "age produced non-UTF-8 armor".to_string())
+}
+
+fn run_age(secrets: &SecretsConfig, args: &[&OsStr], input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut child = Command::new(&secrets.command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("failed to run `{}`: {error}", secrets.command))?;
+
+    // a command that exits early closes stdin; its exit status explains why.
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(error) = stdin.write_all(input)
+        && error.kind() != ErrorKind::BrokenPipe
+    {
+        return Err(format!("failed writing to `{}`: {error}", secrets.command));
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|error| format!("failed waiting for `{}`: {error}", secrets.command))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if stderr.is_empty() {
+            format!("`{}` exited with {}", secrets.command, output.status)
+        } else {
+            format!("`{}` failed: {stderr}", secrets.command)
+        });
+    }
+    Ok(output.stdout)
+}
+
+/// Decrypts secrets on first use during config load, so configs without
+/// `${secret:...}` references never need age installed.
+#[derive(Debug)]
+pub struct SecretResolver<'a> {
+    secrets: &'a SecretsConfig,
+    decrypted: BTreeMap<String, String>,
+}
+
+impl<'a> SecretResolver<'a> {
+    /// Creates a resolver over a config's `secrets` block.
+    pub fn new(secrets: &'a SecretsConfig) -> Self {
+        Self {
+            secrets,
+            decrypted: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the plaintext of a named secret.
+    pub fn get(&mut self, name: &str) -> Result<String, ConfigError> {
+        if let Some(value) = self.decrypted.get(name) {
+            return Ok(value.clone());
+        }
+
+        let armored = self
+            .secrets
+            .values
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownSecret(name.to_string()))?;
+        let value = decrypt(self.secrets, armored).map_err(|reason| ConfigError::SecretFailed {
+            name: name.to_string(),
+            reason,
+        })?;
+        // age output for `echo` input ends with a newline nobody wants in a URL.
+        let value = value.trim_end_matches(['\r', '\n']).to_string();
+        self.decrypted.insert(name.to_string(), value.clone());
+        Ok(value)
+    }
+}
+
+/// Writes `armored` as `secrets.values.<name>` in config YAML source,
+/// replacing an existing entry or adding the `secrets`/`values` blocks when
+/// missing. Other content, including comments, is left as is.
+pub fn set_secret_in_source(source: &str, name: &str, armored: &str) -> String {
+    let mut lines = source.lines().map(str::to_string).collect::<Vec<_>>();
+    let secrets = find_key(&lines, 0, None, "secrets");
+    let values =
+        secrets.and_then(|(line, indent)| find_key(&lines, line + 1, Some(indent), "values"));
+
+    let (insert_at, entry_indent) = match (secrets, values) {
+        (Some(_), Some((values_line, values_indent))) => {
+            let entry_indent = values_indent + 2;
+            match find_key(&lines, values_line + 1, Some(values_indent), name) {
+                Some((entry_line, indent)) => {
+                    let end = block_end(&lines, entry_line, indent);
+                    lines.drain(entry_line..end);
+                    (entry_line, indent)
+                }
+                None => (values_line + 1, entry_indent),
+            }
+        }
+        (Some((secrets_line, secrets_indent)), None) => {
+            let values_indent = secrets_indent + 2;
+            lines.insert(
+                secrets_line + 1,
+                format!("{}values:", " ".repeat(values_indent)),
+            );
+            (secrets_line + 2, values_indent + 2)
+        }
+        (None, _) => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push("secrets:".to_string());
+            lines.push("  values:".to_string());
+            (lines.len(), 4)
+        }
+    };
+
+    let mut entry = vec![format!("{}{name}: |", " ".repeat(entry_indent))];
+    entry.extend(
+        armored
+            .lines()
+            .map(|line| format!("{}{line}", " ".repeat(entry_indent + 2))),
+    );
+    lines.splice(insert_at..insert_at, entry);
+
+    let mut output = lines.join("\n");
+    output.push('\n');
+    output
+}
+
+/// Finds `key:` inside the block that starts at `start` and is nested deeper
+/// than `parent_indent`, returning its line and indent.
+fn find_key(
+    lines: &[String],
+    start: usize,
+    parent_indent: Option<usize>,
+    key: &str,
+) -> Option<(usize, usize)> {
+    for (index, line) in lines.iter().enumerate().skip(start) {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let indent = line.len() - trimmed.len();
+        match parent_indent {
+            Some(parent) if indent <= parent => return None,
+            None if indent > 0 => continue,
+            _ => {}
+        }
+        if trimmed
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with(':'))
+        {
+            return Some((index, indent));
+        }
+    }
+    None
+}
+
+/// Returns the index just past the entry starting at `line`.
+fn block_end(lines: &[String], line: usize, indent: usize) -> usize {
+    lines
+        .iter()
+        .enumerate()
+        .skip(line + 1)
+        .find(|(_, text)| {
+            let trimmed = text.trim_start();
+            !trimmed.is_empty() && text.len() - trimmed.len() <= indent
+        })
+        .map_or(lines.len(), |(index, _)| index)
+}
@@ -0,0 +1,214 @@
+use crate::digest::DigestEntry;
+use crate::environment::Environment;
+use crate::event::CompletionEvent;
+use crate::history::recent_runs;
+use crate::runner::RunResult;
+use crate::state::StateStore;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `--session` value that names the session after the terminal it runs in.
+pub const AUTO_SESSION: &str = "auto";
+
+/// Threads whose first message is older than this are forgotten, so a
+/// session name reused next week starts a new conversation.
+const MAX_THREAD_AGE: TimeDelta = TimeDelta::days(7);
+
+/// State file remembering when each session was last ended.
+const ENDED_STATE: &str = "session-ends";
+
+/// Variables naming the terminal session, checked in order, with the prefix
+/// their value gets: macOS Terminal and iTerm2, Windows Terminal, tmux panes
+/// and GNU screen.
+const TERMINAL_SESSION_VARS: [(&str, &str); 4] = [
+    ("TERM_SESSION_ID", "term"),
+    ("WT_SESSION", "wt"),
+    ("TMUX_PANE", "tmux"),
+    ("STY", "screen"),
+];
+
+/// The session a run belongs to. `auto` names the terminal session, falling
+/// back to the invoking shell's process ID; `None` when neither is known.
+/// Any other name is used as given.
+pub fn resolve_session(requested: &str, env: &dyn Environment) -> Option<String> {
+    let requested = requested.trim();
+    if requested != AUTO_SESSION {
+        return Some(requested.to_string()).filter(|name| !name.is_empty());
+    }
+
+    TERMINAL_SESSION_VARS
+        .iter()
+        .find_map(|(name, prefix)| {
+            env.var(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .map(|value| format!("{prefix}-{value}"))
+        })
+        .or_else(shell_session)
+}
+
+#[cfg(unix)]
+fn shell_session() -> Option<String> {
+    Some(format!("shell-{}", std::os::unix::process::parent_id()))
+}
+
+#[cfg(not(unix))]
+fn shell_session() -> Option<String> {
+    None
+}
+
+/// The first message a session posted on a channel, which later runs in the
+/// session reply to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionThread {
+    /// ID of the first message: Slack's `ts` or Telegram's `message_id`.
+    pub message_id: String,
+
+    /// RFC3339 timestamp of when the message was posted.
+    pub posted_at: String,
+}
+
+/// The thread a session started on a channel, if it is recent enough to
+/// reply to.
+pub fn load_thread(
+    store: &StateStore,
+    channel_id: &str,
+    session: &str,
+    now: DateTime<Utc>,
+) -> Option<SessionThread> {
+    let state: BTreeMap<String, SessionThread> = store.load(&state_name(channel_id));
+    state
+        .get(session)
+        .filter(|thread| is_recent(thread, now))
+        .cloned()
+}
+
+/// Remembers the first message a session posted on a channel. A session
+/// that already has a thread keeps it.
+pub fn save_thread(
+    store: &StateStore,
+    channel_id: &str,
+    session: &str,
+    message_id: String,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let name = state_name(channel_id);
+    let _lock = store.lock(&name).map_err(|error| error.to_string())?;
+    let mut state: BTreeMap<String, SessionThread> = store.load(&name);
+    state.retain(|_, thread| is_recent(thread, now));
+    if state.contains_key(session) {
+        return Ok(());
+    }
+    state.insert(
+        session.to_string(),
+        SessionThread {
+            message_id,
+            posted_at: now.to_rfc3339(),
+        },
+    );
+    store.save(&name, &state)
+}
+
+fn is_recent(thread: &SessionThread, now: DateTime<Utc>) -> bool {
+    DateTime::parse_from_rfc3339(&thread.posted_at)
+        .is_ok_and(|posted_at| now - posted_at.with_timezone(&Utc) < MAX_THREAD_AGE)
+}
+
+fn state_name(channel_id: &str) -> String {
+    format!("sessions-{channel_id}")
+}
+
+/// Recorded runs in `session` since it was last ended, oldest first.
+pub fn session_runs(store: &StateStore, session: &str) -> Vec<CompletionEvent> {
+    let ended: BTreeMap<String, String> = store.load(ENDED_STATE);
+    let ended_at = ended
+        .get(session)
+        .and_then(|ended_at| DateTime::parse_from_rfc3339(ended_at).ok());
+    recent_runs(store)
+        .into_iter()
+        .filter(|run| run.session.as_deref() == Some(session))
+        .filter(|run| {
+            ended_at.is_none_or(|ended_at| {
+                DateTime::parse_from_rfc3339(&run.finished_at)
+                    .is_ok_and(|finished_at| finished_at > ended_at)
+            })
+        })
+        .collect()
+}
+
+/// One event summarising `runs` in `session`: it spans the first start to
+/// the last finish, fails with the first failure's exit code when any run
+/// failed, and lists every run in `session_runs`. `None` without runs.
+pub fn session_summary(
+    session: &str,
+    runs: &[CompletionEvent],
+    environment: &dyn Environment,
+) -> Option<CompletionEvent> {
+    let last = runs.last()?;
+    let now = environment.now();
+    let parse = |timestamp: &str| {
+        DateTime::parse_from_rfc3339(timestamp)
+            .map(|parsed| parsed.to_utc())
+            .unwrap_or(now)
+    };
+    let started_at = runs
+        .iter()
+        .map(|run| parse(&run.started_at))
+        .min()
+        .unwrap_or(now);
+    let finished_at = runs
+        .iter()
+        .map(|run| parse(&run.finished_at))
+        .max()
+        .unwrap_or(now);
+    let run = RunResult {
+        command: ["brb", "session", "end", session]
+            .map(str::to_string)
+            .to_vec(),
+        started_at,
+        finished_at,
+        duration: (finished_at - started_at).to_std().unwrap_or_default(),
+        exit_code: runs
+            .iter()
+            .map(|run| run.exit_code)
+            .find(|code| *code != 0)
+            .unwrap_or(0),
+        spawn_error: None,
+        output_tail: None,
+        cwd: Some(last.cwd.clone().into()),
+    };
+
+    let mut event = CompletionEvent::from_run_in(&run, environment);
+    event.project.clone_from(&last.project);
+    event.container.clone_from(&last.container);
+    event.session = Some(session.to_string());
+    event.session_runs = runs.iter().map(DigestEntry::from).collect();
+    Some(event)
+}
+
+/// Ends `session`: later runs under the same name start a new summary, and
+/// a new thread on each of `channel_ids`.
+pub fn end_session(
+    store: &StateStore,
+    session: &str,
+    channel_ids: &[String],
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    {
+        let _lock = store.lock(ENDED_STATE).map_err(|error| error.to_string())?;
+        let mut ended: BTreeMap<String, String> = store.load(ENDED_STATE);
+        ended.insert(session.to_string(), now.to_rfc3339());
+        store.save(ENDED_STATE, &ended)?;
+    }
+
+    for channel_id in channel_ids {
+        let name = state_name(channel_id);
+        let _lock = store.lock(&name).map_err(|error| error.to_string())?;
+        let mut state: BTreeMap<String, SessionThread> = store.load(&name);
+        if state.remove(session).is_some() {
+            store.save(&name, &state)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use crate::config::SnapshotConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long a version command may take before it is killed.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Tool versions and hardware recorded with a run, for comparing runs later.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentSnapshot {
+    /// First line of each tool's version output, by program name. Tools that
+    /// are missing, fail or print nothing are left out.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tools: BTreeMap<String, String>,
+
+    /// CPU model name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<String>,
+
+    /// Logical CPUs available to `brb`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_cores: Option<usize>,
+
+    /// Total physical memory in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_bytes: Option<u64>,
+}
+
+/// Records the configured tool versions and hardware, or returns `None` when
+/// the snapshot is disabled.
+pub fn sample_snapshot(config: &SnapshotConfig) -> Option<EnvironmentSnapshot> {
+    if config.tools.is_empty() && !config.hardware {
+        return None;
+    }
+
+    let mut snapshot = EnvironmentSnapshot {
+        tools: config
+            .tools
+            .iter()
+            .filter_map(|tool| {
+                let (program, args) = version_command(tool)?;
+                let version = tool_version(&program, &args)?;
+                Some((program, version))
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    if config.hardware {
+        snapshot.cpu = cpu_model();
+        snapshot.cpu_cores = std::thread::available_parallelism()
+            .ok()
+            .map(|cores| cores.get());
+        snapshot.memory_bytes = memory_bytes();
+    }
+
+    Some(snapshot)
+}
+
+/// Program and arguments for a `snapshot.tools` entry: a bare program name
+/// gets `--version`, anything longer is split on whitespace.
+pub fn version_command(tool: &str) -> Option<(String, Vec<String>)> {
+    let mut words = tool.split_whitespace().map(str::to_string);
+    let program = words.next()?;
+    let args = words.collect::<Vec<_>>();
+    if args.is_empty() {
+        return Some((program, vec!["--version".to_string()]));
+    }
+    Some((program, args))
+}
+
+/// The first non-empty line of a version command's output, preferring stdout
+/// since some tools (older Java, Python 2) print their version on stderr.
+pub fn version_line(stdout: &str, stderr: &str) -> Option<String> {
+    let first_line = |text: &str| {
+        text.lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+    };
+    first_line(stdout).or_else(|| first_line(stderr))
+}
+
+/// The CPU model from `/proc/cpuinfo` contents.
+pub fn parse_cpuinfo(cpuinfo: &str) -> Option<String> {
+    cpuinfo.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        matches!(key.trim(), "model name" | "Model" | "cpu model")
+            .then(|| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+/// Total memory in bytes from `/proc/meminfo` contents.
+pub fn parse_meminfo(meminfo: &str) -> Option<u64> {
+    let line = meminfo.lines().find(|line| line.starts_with("MemTotal:"))?;
+    let kilobytes = line
+        .trim_start_matches("MemTotal:")
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+fn tool_version(program: &str, args: &[String]) -> Option<String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < VERSION_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    version_line(
+        &String::from_utf8_lossy(&output.stdout),
+        &String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_model() -> Option<String> {
+    parse_cpuinfo(&std::fs::read_to_string("/proc/cpuinfo").ok()?)
+}
+
+#[cfg(target_os = "macos")]
+fn cpu_model() -> Option<String> {
+    sysctl("machdep.cpu.brand_string")
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn cpu_model() -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn memory_bytes() -> Option<u64> {
+    parse_meminfo(&std::fs::read_to_string("/proc/meminfo").ok()?)
+}
+
+#[cfg(target_os = "macos")]
+fn memory_bytes() -> Option<u64> {
+    sysctl("hw.memsize")?.parse().ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn memory_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+fn sysctl(name: &str) -> Option<String> {
+    let output = Command::new("sysctl").args(["-n", name]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    version_line(&String::from_utf8_lossy(&output.stdout), "")
+}
@@ -0,0 +1,78 @@
+use crate::config::{ConfigError, private_data_dir};
+use crate::lock::{LockError, RunLock, acquire_lock_in};
+use crate::perms::{create_private_dir, write_private};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Small JSON document store for state that outlives one `brb` run.
+#[derive(Debug, Clone)]
+pub struct StateStore {
+    dir: PathBuf,
+}
+
+impl StateStore {
+    /// Opens the store under brb's local data directory.
+    pub fn open() -> Result<Self, ConfigError> {
+        Ok(Self::at(private_data_dir()?.join("state")))
+    }
+
+    /// Opens a store rooted at an explicit directory.
+    ///
+    /// This helper is used by integration tests.
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the store's root directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Loads a document, returning the default value when it does not exist
+    /// or cannot be parsed.
+    pub fn load<T: DeserializeOwned + Default>(&self, name: &str) -> T {
+        fs::read_to_string(self.path(name))
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes a document atomically (write to a temp file, then rename).
+    pub fn save<T: Serialize>(&self, name: &str, value: &T) -> Result<(), String> {
+        let path = self.path(name);
+        create_private_dir(&self.dir)
+            .map_err(|error| format!("failed to create {}: {error}", self.dir.display()))?;
+
+        let json = serde_json::to_string_pretty(value)
+            .map_err(|error| format!("failed to encode state `{name}`: {error}"))?;
+        let temp = path.with_extension("json.tmp");
+        write_private(&temp, json)
+            .and_then(|()| fs::rename(&temp, &path))
+            .map_err(|error| format!("failed to write {}: {error}", path.display()))
+    }
+
+    /// Takes an exclusive lock guarding read-modify-write of a document.
+    pub fn lock(&self, name: &str) -> Result<RunLock, LockError> {
+        acquire_lock_in(&self.dir.join("locks"), &state_key(name), true)
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", state_key(name)))
+    }
+}
+
+/// Maps an arbitrary document name (e.g. containing a channel ID) to a safe
+/// file stem.
+fn state_key(name: &str) -> String {
+    name.chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
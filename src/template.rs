@@ -0,0 +1,433 @@
+use crate::channels::{desktop_sound, desktop_urgency};
+use crate::config::{Channel, ChannelConfig, Config, TemplateDef};
+use crate::event::CompletionEvent;
+use crate::format::{
+    WebhookPayload, custom_payload, desktop_text, fitted_payload, googlechat_message,
+    healthchecks_body, message_text, mqtt_payload, ntfy_message, pushover_message, select_fields,
+    slack_blocks, telegram_message, zulip_message,
+};
+use crate::pipeline::prepare;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Placeholders available inside templates.
+pub const PLACEHOLDERS: &[&str] = &[
+    "emoji",
+    "title",
+    "body",
+    "command",
+    "status",
+    "exit_code",
+    "duration",
+    "finished",
+    "host",
+    "cwd",
+    "project",
+    "run_id",
+];
+
+/// Maximum include nesting; validation rejects cycles before this matters.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Text(String),
+    Field(&'a str),
+    Include(&'a str),
+}
+
+/// Checks every template for unknown placeholders, missing includes and
+/// include cycles.
+pub fn validate_templates(templates: &BTreeMap<String, TemplateDef>) -> Result<(), String> {
+    let mut includes = BTreeMap::new();
+
+    for (name, template) in templates {
+        let mut included = BTreeSet::new();
+        for (part, source) in template.sources() {
+            for token in
+                tokenize(source).map_err(|error| format!("templates.{name}{part}: {error}"))?
+            {
+                match token {
+                    Token::Text(_) => {}
+                    Token::Field(field) if PLACEHOLDERS.contains(&field) => {}
+                    Token::Field(field) => {
+                        return Err(format!(
+                            "templates.{name}{part}: unknown placeholder `{{{field}}}` (expected one of {})",
+                            PLACEHOLDERS.join(", ")
+                        ));
+                    }
+                    Token::Include(other) => match templates.get(other) {
+                        None => {
+                            return Err(format!(
+                                "templates.{name}{part}: included template `{other}` is not defined"
+                            ));
+                        }
+                        Some(target) if target.include_source().is_none() => {
+                            return Err(format!(
+                                "templates.{name}{part}: included template `{other}` has no body"
+                            ));
+                        }
+                        Some(_) => {
+                            included.insert(other);
+                        }
+                    },
+                }
+            }
+        }
+        includes.insert(name.as_str(), included);
+    }
+
+    for name in includes.keys() {
+        let mut path = vec![*name];
+        find_cycle(&includes, &mut path)?;
+    }
+
+    Ok(())
+}
+
+/// Checks a template source used outside `templates`, such as a channel's
+/// topic, for unknown placeholders. Such sources cannot include templates.
+pub fn check_placeholders(source: &str) -> Result<(), String> {
+    for token in tokenize(source)? {
+        match token {
+            Token::Text(_) => {}
+            Token::Field(field) if PLACEHOLDERS.contains(&field) => {}
+            Token::Field(field) => {
+                return Err(format!(
+                    "an unknown placeholder `{{{field}}}` (expected one of {})",
+                    PLACEHOLDERS.join(", ")
+                ));
+            }
+            Token::Include(name) => {
+                return Err(format!(
+                    "an include `{{> {name}}}`, which only templates may use"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn find_cycle<'a>(
+    includes: &BTreeMap<&'a str, BTreeSet<&'a str>>,
+    path: &mut Vec<&'a str>,
+) -> Result<(), String> {
+    let current = path[path.len() - 1];
+    for next in includes.get(current).into_iter().flatten() {
+        if path.contains(next) {
+            path.push(next);
+            return Err(format!(
+                "templates include themselves: {}",
+                path.join(" -> ")
+            ));
+        }
+        path.push(next);
+        find_cycle(includes, path)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// Renders a template source, substituting placeholders from `values` and
+/// expanding `{> name}` includes. Invalid sources are returned unchanged.
+pub fn render(
+    templates: &BTreeMap<String, TemplateDef>,
+    source: &str,
+    values: &BTreeMap<&str, String>,
+) -> String {
+    render_at_depth(templates, source, values, 0)
+}
+
+fn render_at_depth(
+    templates: &BTreeMap<String, TemplateDef>,
+    source: &str,
+    values: &BTreeMap<&str, String>,
+    depth: usize,
+) -> String {
+    let Ok(tokens) = tokenize(source) else {
+        return source.to_string();
+    };
+
+    let mut output = String::new();
+    for token in tokens {
+        match token {
+            Token::Text(text) => output.push_str(&text),
+            Token::Field(field) => match values.get(field) {
+                Some(value) => output.push_str(value),
+                None => output.push_str(&format!("{{{field}}}")),
+            },
+            Token::Include(name) => {
+                if let Some(included) = templates.get(name).and_then(TemplateDef::include_source)
+                    && depth < MAX_INCLUDE_DEPTH
+                {
+                    output.push_str(&render_at_depth(templates, included, values, depth + 1));
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Renders what `target` (a channel ID or template name) would show for
+/// `event` after the channel's pipeline, plus the request body for webhook,
+/// Slack and Telegram channels, without sending it.
+pub fn preview(config: &Config, target: &str, event: &CompletionEvent) -> Result<String, String> {
+    let prepared = match config.channels.get(target) {
+        Some(channel) => match prepare(config, channel, event) {
+            Ok(prepared) => Some(prepared),
+            Err(reason) => return Ok(format!("not sent ({reason})\n")),
+        },
+        None if matches!(config.templates.get(target), Some(TemplateDef::Message(_))) => None,
+        None => {
+            return Err(format!(
+                "`{target}` is neither a channel nor a title/body template in config"
+            ));
+        }
+    };
+    // the pipeline's `template` and `fields` steps apply to the channel.
+    let (channel, event, template) = match &prepared {
+        Some(prepared) => (
+            Some(&*prepared.channel),
+            &*prepared.event,
+            prepared.channel.template.as_deref(),
+        ),
+        None => (None, event, Some(target)),
+    };
+    let kind = channel.map(|channel| &channel.kind);
+
+    let text = message_text(config, template, event);
+    let mut output = format!("title: {}\nbody:\n{}\n", text.title, text.body);
+    if let Some(ChannelConfig::Slack(slack)) = kind {
+        let blocks = slack_blocks(config, slack, template, event);
+        let json = serde_json::to_string_pretty(&blocks).map_err(|error| error.to_string())?;
+        output.push_str(&format!("\npayload:\n{json}\n"));
+    }
+    if let Some(ChannelConfig::GoogleChat(chat)) = kind {
+        let message = googlechat_message(config, chat, template, event);
+        let json = serde_json::to_string_pretty(&message).map_err(|error| error.to_string())?;
+        output.push_str(&format!("\npayload:\n{json}\n"));
+    }
+    if let Some(ChannelConfig::Telegram(telegram)) = kind {
+        let message = telegram_message(config, telegram, template, event);
+        let json = serde_json::to_string_pretty(&message).map_err(|error| error.to_string())?;
+        output.push_str(&format!("\npayload:\n{json}\n"));
+    }
+    if let Some(ChannelConfig::Pushover(pushover)) = kind {
+        // the user key and API token are added when sending.
+        let message = pushover_message(config, pushover, template, event);
+        let json = serde_json::to_string_pretty(&message).map_err(|error| error.to_string())?;
+        output.push_str(&format!("\npayload:\n{json}\n"));
+    }
+    if let Some(ChannelConfig::Zulip(zulip)) = kind {
+        // the bot's API key is added when sending.
+        let message = zulip_message(config, zulip, template, event);
+        let json = serde_json::to_string_pretty(&message).map_err(|error| error.to_string())?;
+        output.push_str(&format!("\npayload:\n{json}\n"));
+    }
+    if let Some(ChannelConfig::Healthchecks(healthchecks)) = kind {
+        let body = healthchecks_body(config, healthchecks, template, event);
+        output.push_str(&format!("\npayload:\n{body}\n"));
+    }
+    if let (Some(channel), Some(ChannelConfig::Mqtt(mqtt))) = (channel, kind) {
+        let mut body = serde_json::to_value(event).map_err(|error| error.to_string())?;
+        select_fields(&mut body, channel, &BTreeMap::new());
+        let json = serde_json::to_string_pretty(&body).map_err(|error| error.to_string())?;
+        output.push_str(&format!("\npayload ({}):\n{json}\n", mqtt.topic));
+    }
+    if let Some(ChannelConfig::Ntfy(ntfy)) = kind {
+        // auth headers stay out of the preview.
+        let (body, headers) = ntfy_message(config, ntfy, template, event);
+        output.push_str("\npayload:\n");
+        for (name, value) in headers {
+            output.push_str(&format!("{name}: {value}\n"));
+        }
+        output.push_str(&format!("\n{body}\n"));
+    }
+    if let (Some(channel), Some(ChannelConfig::Webhook(webhook))) = (channel, kind) {
+        if webhook.encrypt_to.is_empty() {
+            output.push_str("\npayload:\n");
+        } else {
+            output.push_str(&format!(
+                "\npayload (sent encrypted to {} recipient(s)):\n",
+                webhook.encrypt_to.len()
+            ));
+        }
+        let (payload, truncated) = fitted_payload(config, channel, webhook, event)?;
+        if truncated {
+            output.push_str("(truncated to fit max_payload)\n");
+        }
+        match payload {
+            WebhookPayload::Json(body) => {
+                let json =
+                    serde_json::to_string_pretty(&body).map_err(|error| error.to_string())?;
+                output.push_str(&json);
+                output.push('\n');
+            }
+            WebhookPayload::Text { body, headers } => {
+                for (name, value) in headers {
+                    output.push_str(&format!("{name}: {value}\n"));
+                }
+                output.push_str(&format!("\n{body}\n"));
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Renders the request body `channel_id` would send for `event`: JSON
+/// pretty-printed, or a text body after its headers. Credentials added when
+/// sending, captured values, running-message edits and `encrypt_to`
+/// sealing are left out.
+pub fn render_body(
+    config: &Config,
+    channel_id: &str,
+    event: &CompletionEvent,
+) -> Result<String, String> {
+    let Some(channel) = config.channels.get(channel_id) else {
+        return Err(format!("channel `{channel_id}` is not defined in config"));
+    };
+    let prepared = match prepare(config, channel, event) {
+        Ok(prepared) => prepared,
+        Err(reason) => return Ok(format!("not sent ({reason})\n")),
+    };
+    let (channel, event) = (&*prepared.channel, &*prepared.event);
+    let template = channel.template.as_deref();
+
+    let payload = match &channel.kind {
+        ChannelConfig::Webhook(webhook) => fitted_payload(config, channel, webhook, event)?.0,
+        ChannelConfig::Slack(slack) => {
+            WebhookPayload::Json(slack_blocks(config, slack, template, event))
+        }
+        ChannelConfig::GoogleChat(chat) => {
+            WebhookPayload::Json(googlechat_message(config, chat, template, event))
+        }
+        ChannelConfig::Telegram(telegram) => {
+            WebhookPayload::Json(telegram_message(config, telegram, template, event))
+        }
+        ChannelConfig::Pushover(pushover) => {
+            WebhookPayload::Json(pushover_message(config, pushover, template, event))
+        }
+        ChannelConfig::Ntfy(ntfy) => {
+            let (body, headers) = ntfy_message(config, ntfy, template, event);
+            WebhookPayload::Text { body, headers }
+        }
+        ChannelConfig::Zulip(zulip) => {
+            WebhookPayload::Json(zulip_message(config, zulip, template, event))
+        }
+        ChannelConfig::Healthchecks(healthchecks) => WebhookPayload::Text {
+            body: healthchecks_body(config, healthchecks, template, event),
+            headers: BTreeMap::new(),
+        },
+        ChannelConfig::Mqtt(_) => WebhookPayload::Json(mqtt_payload(channel, event)),
+        ChannelConfig::Custom(custom) => {
+            WebhookPayload::Json(custom_payload(channel, custom, event))
+        }
+        ChannelConfig::Desktop(_) => {
+            return Err(format!(
+                "`{channel_id}` is a desktop channel, which sends no body; use `brb channels preview {channel_id}`"
+            ));
+        }
+        ChannelConfig::Url(_) => {
+            return Err(format!("channel `{channel_id}` was not resolved"));
+        }
+    };
+
+    Ok(match payload {
+        WebhookPayload::Json(body) => {
+            let json = serde_json::to_string_pretty(&body).map_err(|error| error.to_string())?;
+            format!("{json}\n")
+        }
+        WebhookPayload::Text { body, headers } => {
+            let mut output = headers
+                .into_iter()
+                .map(|(name, value)| format!("{name}: {value}\n"))
+                .collect::<String>();
+            // a blank line ends the headers, when there are any.
+            if !output.is_empty() {
+                output.push('\n');
+            }
+            output.push_str(&format!("{body}\n"));
+            output
+        }
+    })
+}
+
+/// Renders the notification a desktop channel would show for `event`: its
+/// title, body and urgency.
+pub fn desktop_preview(
+    config: &Config,
+    channel_id: &str,
+    event: &CompletionEvent,
+) -> Result<String, String> {
+    let (channel, desktop) = match config.channels.get(channel_id) {
+        Some(
+            channel @ Channel {
+                kind: ChannelConfig::Desktop(desktop),
+                ..
+            },
+        ) => (channel, desktop),
+        Some(_) => {
+            return Err(format!(
+                "`{channel_id}` is not a desktop channel; use `brb template render {channel_id}`"
+            ));
+        }
+        None => return Err(format!("channel `{channel_id}` is not defined in config")),
+    };
+
+    let prepared = match prepare(config, channel, event) {
+        Ok(prepared) => prepared,
+        Err(reason) => return Ok(format!("not sent ({reason})\n")),
+    };
+    let event = &*prepared.event;
+    let text = desktop_text(config, desktop, prepared.channel.template.as_deref(), event);
+    let mut preview = format!(
+        "title: {}\nbody:\n{}\nurgency: {}\n",
+        text.title,
+        text.body,
+        desktop_urgency(config, event)
+    );
+    if let Some(sound) = desktop_sound(desktop, event) {
+        preview.push_str(&format!("sound: {sound}\n"));
+    }
+    Ok(preview)
+}
+
+/// Splits a template into literal text, `{placeholder}` fields and
+/// `{> name}` includes. `{{` and `}}` produce literal braces.
+fn tokenize(source: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = source;
+
+    while let Some(index) = rest.find(['{', '}']) {
+        text.push_str(&rest[..index]);
+        let tail = &rest[index..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            text.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        if tail.starts_with('}') {
+            return Err("unmatched `}` (use `}}` for a literal brace)".to_string());
+        }
+
+        let Some(end) = tail.find('}') else {
+            return Err("unterminated `{` (use `{{` for a literal brace)".to_string());
+        };
+        let inner = tail[1..end].trim();
+        if !text.is_empty() {
+            tokens.push(Token::Text(std::mem::take(&mut text)));
+        }
+        match inner.strip_prefix('>') {
+            Some(name) => tokens.push(Token::Include(name.trim())),
+            None => tokens.push(Token::Field(inner)),
+        }
+        rest = &tail[end + 1..];
+    }
+
+    text.push_str(rest);
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    Ok(tokens)
+}
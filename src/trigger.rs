@@ -0,0 +1,151 @@
+use crate::channels::{DeliveryResult, notify_selected};
+use crate::config::{Config, OutputTrigger, TriggerStream};
+use crate::event::CompletionEvent;
+use crate::redact::redact_text;
+use crate::runner::{LineWatcher, OutputStream};
+use chrono::{SecondsFormat, Utc};
+use regex::Regex;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// Status of events sent when a trigger fires.
+pub const TRIGGERED_STATUS: &str = "triggered";
+
+/// Watches one run's output for the configured `triggers`, notifying each
+/// trigger's channels the first time one of its lines matches, while the
+/// command keeps running.
+pub struct TriggerWatch {
+    config: Config,
+    event: Arc<OnceLock<CompletionEvent>>,
+    build_event: Arc<dyn Fn() -> CompletionEvent + Send + Sync>,
+    started: Instant,
+    patterns: Vec<Option<Regex>>,
+    fired: Mutex<Vec<bool>>,
+    deliveries: Mutex<Vec<JoinHandle<Vec<DeliveryResult>>>>,
+}
+
+impl TriggerWatch {
+    /// Starts watching for a run described by the event `build_event`
+    /// returns, whose timing and status are filled in when a trigger fires.
+    /// The event is only built, once, when the first trigger fires, so runs
+    /// that never match don't pay for sampling it. `None` without triggers.
+    pub fn new(
+        config: &Config,
+        build_event: impl Fn() -> CompletionEvent + Send + Sync + 'static,
+    ) -> Option<Arc<Self>> {
+        if config.triggers.is_empty() {
+            return None;
+        }
+        // patterns are checked at config load.
+        let patterns = config
+            .triggers
+            .iter()
+            .map(|trigger| Regex::new(&trigger.pattern).ok())
+            .collect();
+        Some(Arc::new(Self {
+            config: config.clone(),
+            event: Arc::new(OnceLock::new()),
+            build_event: Arc::new(build_event),
+            started: Instant::now(),
+            patterns,
+            fired: Mutex::new(vec![false; config.triggers.len()]),
+            deliveries: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// A line watcher for [`crate::runner::RunOptions::on_line`].
+    pub fn watcher(self: &Arc<Self>) -> LineWatcher {
+        let watch = Arc::clone(self);
+        LineWatcher::new(move |stream, line| watch.check(stream, line))
+    }
+
+    /// Checks one line of output, sending a notification in the background
+    /// for each trigger it fires for the first time.
+    pub fn check(&self, stream: OutputStream, line: &str) {
+        for (index, trigger) in self.config.triggers.iter().enumerate() {
+            let matched = self.patterns[index]
+                .as_ref()
+                .is_some_and(|pattern| pattern.is_match(line));
+            if !matched || !watches(trigger.stream, stream) {
+                continue;
+            }
+            let Ok(mut fired) = self.fired.lock() else {
+                return;
+            };
+            if std::mem::replace(&mut fired[index], true) {
+                continue;
+            }
+            drop(fired);
+
+            // the event is built on the delivery thread so output isn't held up.
+            let fire = self.fire(index, trigger, line);
+            let base = Arc::clone(&self.event);
+            let build_event = Arc::clone(&self.build_event);
+            let config = self.config.clone();
+            let channels = trigger.channels.clone();
+            let delivery = thread::spawn(move || {
+                let event = fire.apply(base.get_or_init(|| build_event()));
+                notify_selected(&config, &channels, &event)
+            });
+            if let Ok(mut deliveries) = self.deliveries.lock() {
+                deliveries.push(delivery);
+            }
+        }
+    }
+
+    /// Waits for the notifications fired triggers sent and returns how each
+    /// went.
+    pub fn finish(&self) -> Vec<DeliveryResult> {
+        let deliveries = self
+            .deliveries
+            .lock()
+            .map(|mut deliveries| std::mem::take(&mut *deliveries))
+            .unwrap_or_default();
+        deliveries
+            .into_iter()
+            .filter_map(|delivery| delivery.join().ok())
+            .flatten()
+            .collect()
+    }
+
+    fn fire(&self, index: usize, trigger: &OutputTrigger, line: &str) -> Fire {
+        Fire {
+            index,
+            finished_at: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            duration_ms: self.started.elapsed().as_millis(),
+            message: trigger.message.clone(),
+            output_match: redact_text(&self.config.redact, line.trim()),
+        }
+    }
+}
+
+/// What a trigger saw when it fired, applied to the run's event.
+struct Fire {
+    index: usize,
+    finished_at: String,
+    duration_ms: u128,
+    message: Option<String>,
+    output_match: String,
+}
+
+impl Fire {
+    fn apply(self, base: &CompletionEvent) -> CompletionEvent {
+        let mut event = base.clone();
+        event.status = TRIGGERED_STATUS.to_string();
+        event.finished_at = self.finished_at;
+        event.duration_ms = self.duration_ms;
+        event.exit_reason = self.message;
+        event.output_match = Some(self.output_match);
+        event.trigger = Some(self.index);
+        event
+    }
+}
+
+fn watches(watched: TriggerStream, stream: OutputStream) -> bool {
+    match watched {
+        TriggerStream::Any => true,
+        TriggerStream::Stdout => stream == OutputStream::Stdout,
+        TriggerStream::Stderr => stream == OutputStream::Stderr,
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+/// Raw config value for a duration or size; YAML may give either a string or
+/// a bare number.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Number(u64),
+    Text(String),
+}
+
+/// Parses a compact duration such as `500ms`, `90s`, `15m`, `1h30m` or `1d`.
+///
+/// Every number needs a unit and the total must be non-zero.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{value}` (expected e.g. 30s, 15m, 1h30m)");
+
+    let mut total_ms = 0u64;
+    for (amount, unit) in split_amounts(value).ok_or_else(invalid)? {
+        let unit_ms = match unit.as_str() {
+            "ms" => 1,
+            "s" => 1000,
+            "m" => 60 * 1000,
+            "h" => 60 * 60 * 1000,
+            "d" => 24 * 60 * 60 * 1000,
+            _ => return Err(invalid()),
+        };
+        total_ms = amount
+            .checked_mul(unit_ms)
+            .and_then(|ms| total_ms.checked_add(ms))
+            .ok_or_else(invalid)?;
+    }
+
+    if total_ms == 0 {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_millis(total_ms))
+}
+
+/// Parses a byte size such as `512`, `512kb`, `10mb`, `1gb` or `64kib`.
+///
+/// `kb`/`mb`/`gb` are decimal (1000-based); `kib`/`mib`/`gib` are binary.
+/// A bare number is bytes.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid size `{value}` (expected e.g. 512kb, 10mb, 1gb)");
+
+    let amounts = split_amounts(value).ok_or_else(invalid)?;
+    let [(amount, unit)] = amounts.as_slice() else {
+        return Err(invalid());
+    };
+
+    let multiplier: u64 = match unit.as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000 * 1000,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "kib" => 1024,
+        "mib" => 1024 * 1024,
+        "gib" => 1024 * 1024 * 1024,
+        _ => return Err(invalid()),
+    };
+
+    amount.checked_mul(multiplier).ok_or_else(invalid)
+}
+
+/// Splits `1h30m` into `[(1, "h"), (30, "m")]`, lowercasing units. A trailing
+/// number without a unit gets an empty unit. Returns `None` for empty input
+/// or a unit without a number.
+fn split_amounts(value: &str) -> Option<Vec<(u64, String)>> {
+    let mut amounts = Vec::new();
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits_end = rest
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount = rest[..digits_end].parse::<u64>().ok()?;
+        rest = &rest[digits_end..];
+
+        let unit_end = rest
+            .find(|ch: char| ch.is_ascii_digit())
+            .unwrap_or(rest.len());
+        amounts.push((amount, rest[..unit_end].trim().to_ascii_lowercase()));
+        rest = rest[unit_end..].trim_start();
+    }
+
+    Some(amounts)
+}
+
+/// Deserializes a human duration such as `10m`.
+pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = match RawValue::deserialize(deserializer)? {
+        RawValue::Number(number) => number.to_string(),
+        RawValue::Text(text) => text,
+    };
+    parse_duration(&value).map_err(serde::de::Error::custom)
+}
+
+/// Deserializes an optional human duration such as `10m`.
+pub fn deserialize_optional_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_duration")] Duration);
+
+    Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(duration)| duration))
+}
+
+/// Deserializes a byte size such as `512kb`; bare numbers are bytes.
+pub fn deserialize_size<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match RawValue::deserialize(deserializer)? {
+        RawValue::Number(bytes) => Ok(bytes),
+        RawValue::Text(text) => parse_size(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Deserializes an optional byte size such as `512kb`.
+pub fn deserialize_optional_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_size")] u64);
+
+    Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(size)| size))
+}
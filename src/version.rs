@@ -0,0 +1,35 @@
+use serde::Serialize;
+
+/// What this `brb` binary is and how it was built.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildInfo {
+    /// Crate version.
+    pub version: &'static str,
+
+    /// Abbreviated commit the binary was built from, or `unknown` outside a
+    /// git checkout.
+    pub git_sha: &'static str,
+
+    /// UTC build date (`YYYY-MM-DD`), or `SOURCE_DATE_EPOCH` when set.
+    pub build_date: &'static str,
+
+    /// Target triple, e.g. `x86_64-unknown-linux-gnu`.
+    pub target: &'static str,
+
+    /// Cargo features enabled at build time.
+    pub features: Vec<&'static str>,
+}
+
+/// Build details recorded by `build.rs`.
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("BRB_GIT_SHA"),
+        build_date: env!("BRB_BUILD_DATE"),
+        target: env!("BRB_TARGET"),
+        features: env!("BRB_FEATURES")
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .collect(),
+    }
+}
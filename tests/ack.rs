@@ -0,0 +1,146 @@
+use brb_cli::ack::{acknowledges_run, await_ntfy_ack, ntfy_ack_action, ntfy_ack_url};
+use brb_cli::channels::Interaction;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::dbus::Signal;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn load(channels: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!("version: 1\ndefault_channels: [alert]\nchannels:\n{channels}"),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+#[test]
+fn parses_ack_settings() {
+    let config = load(
+        "  alert:\n    type: desktop\n    ack:\n      timeout: 5m\n      escalate_to: [pager]\n  pager:\n    type: webhook\n    url: https://example.com/hook\n",
+    )
+    .unwrap();
+
+    let ack = config.channels["alert"].ack.as_ref().unwrap();
+    assert_eq!(ack.timeout, Duration::from_secs(300));
+    assert_eq!(ack.escalate_to, vec!["pager".to_string()]);
+}
+
+#[test]
+fn rejects_ack_on_channels_that_cannot_report_it() {
+    let error = load(
+        "  alert:\n    type: webhook\n    url: https://hooks.slack.com/services/x\n    format: slack\n    ack:\n      timeout: 1m\n",
+    )
+    .unwrap_err();
+    assert!(
+        error.contains("sets ack, which needs a desktop channel or the ntfy format"),
+        "{error}"
+    );
+}
+
+#[test]
+fn rejects_bad_escalation_targets() {
+    let error = load(
+        "  alert:\n    type: desktop\n    ack:\n      timeout: 1m\n      escalate_to: [pagr]\n  pager:\n    type: desktop\n",
+    )
+    .unwrap_err();
+    assert!(error.contains("escalates unacknowledged failures to `pagr`, which is not defined"));
+    assert!(error.contains("pager"), "{error}");
+
+    let error = load(
+        "  alert:\n    type: desktop\n    ack:\n      timeout: 1m\n      escalate_to: [alert]\n",
+    )
+    .unwrap_err();
+    assert!(error.contains("which is the channel itself"), "{error}");
+}
+
+#[test]
+fn ack_action_maps_to_acknowledged() {
+    let signal = Signal::ActionInvoked {
+        id: 1,
+        action: "ack".to_string(),
+    };
+    let interaction = Interaction::from_signal(Some(&signal));
+    assert_eq!(interaction, Interaction::Acknowledged);
+    assert!(interaction.acknowledges());
+    assert!(Interaction::Clicked.acknowledges());
+    assert!(!Interaction::Dismissed.acknowledges());
+    assert!(!Interaction::Unanswered.acknowledges());
+}
+
+#[test]
+fn ntfy_ack_topic_sits_next_to_the_channel_topic() {
+    assert_eq!(
+        ntfy_ack_url("https://ntfy.sh/builds?auth=abc"),
+        "https://ntfy.sh/builds-ack"
+    );
+    assert_eq!(
+        ntfy_ack_url("https://ntfy.sh/builds/"),
+        "https://ntfy.sh/builds-ack"
+    );
+    assert_eq!(
+        ntfy_ack_action("Acknowledge", "https://ntfy.sh/builds-ack", "run-1"),
+        "http, Acknowledge, https://ntfy.sh/builds-ack, method=POST, body=run-1, clear=true"
+    );
+}
+
+#[test]
+fn finds_the_run_in_a_poll() {
+    let poll = concat!(
+        "{\"event\":\"open\"}\n",
+        "{\"event\":\"message\",\"message\":\"other-run\"}\n",
+        "not json\n",
+        "{\"event\":\"message\",\"message\":\"run-1\\n\"}\n",
+    );
+    assert!(acknowledges_run(poll, "run-1"));
+    assert!(!acknowledges_run(poll, "run-2"));
+}
+
+#[test]
+fn stops_polling_once_acknowledged_or_timed_out() {
+    let mut polls = 0;
+    let acknowledged = await_ntfy_ack("run-1", 100, Duration::from_secs(60), |since| {
+        assert_eq!(since, 100);
+        polls += 1;
+        Some("{\"event\":\"message\",\"message\":\"run-1\"}".to_string())
+    });
+    assert!(acknowledged);
+    assert_eq!(polls, 1);
+
+    let acknowledged = await_ntfy_ack("run-1", 100, Duration::from_millis(50), |_| None);
+    assert!(!acknowledged);
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn other_channels_are_sent_to_before_waiting_for_an_ack() {
+    use brb_cli::channels::notify_selected;
+    use brb_cli::event::CompletionEvent;
+    use brb_cli::test_support::MockServer;
+
+    let server = MockServer::start(vec![]);
+    let config = load(&format!(
+        "  alert:\n    type: webhook\n    format: ntfy\n    url: {}\n    ack:\n      timeout: 1s\n  team:\n    type: webhook\n    url: {}\n",
+        server.url("/builds"),
+        server.url("/team"),
+    ))
+    .unwrap();
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 1;
+
+    let results = notify_selected(&config, &["alert".to_string(), "team".to_string()], &event);
+    assert_eq!(results[0].acknowledged, Some(false));
+    assert!(results[1].success, "{:?}", results[1].error);
+
+    let requests = server.requests();
+    let sent = requests
+        .iter()
+        .map(|request| (request.method.as_str(), request.path.as_str()))
+        .take(2)
+        .collect::<Vec<_>>();
+    assert_eq!(sent, [("POST", "/builds"), ("POST", "/team")]);
+    assert!(requests[2].path.starts_with("/builds-ack/json"));
+}
@@ -0,0 +1,80 @@
+use brb_cli::active::{extract_progress, register_run, running_commands};
+use regex::Regex;
+use std::fs;
+use tempfile::TempDir;
+
+fn command(args: &str) -> Vec<String> {
+    args.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn lists_runs_until_they_finish() {
+    let temp = TempDir::new().unwrap();
+
+    let build = register_run(temp.path(), &command("cargo build")).unwrap();
+    let test = register_run(temp.path(), &command("cargo test")).unwrap();
+
+    let running = running_commands(temp.path());
+    assert_eq!(running.len(), 2);
+    assert!(running.iter().all(|run| run.pid == std::process::id()));
+
+    let others = build.others();
+    assert_eq!(others.len(), 1);
+    assert_eq!(others[0].command, command("cargo test"));
+
+    drop(test);
+    assert!(build.others().is_empty());
+
+    drop(build);
+    assert!(running_commands(temp.path()).is_empty());
+    assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn cleans_up_entries_left_by_killed_runs() {
+    let temp = TempDir::new().unwrap();
+    // an entry whose lock nobody holds, as a killed brb leaves behind.
+    fs::write(
+        temp.path().join("stale.json"),
+        r#"{"pid":1,"command":["sleep","600"],"started_at":"2026-01-01T00:00:00.000Z"}"#,
+    )
+    .unwrap();
+
+    let run = register_run(temp.path(), &command("make")).unwrap();
+    assert!(run.others().is_empty());
+    assert!(!temp.path().join("stale.json").exists());
+    assert!(!temp.path().join("stale.lock").exists());
+}
+
+#[test]
+fn extracts_percentages_and_step_counts() {
+    let percent = Regex::new(r"(\d+(?:\.\d+)?)%").unwrap();
+    assert_eq!(
+        extract_progress(&percent, "Downloading 12% ... 72.5% done").as_deref(),
+        Some("72.5%")
+    );
+    assert_eq!(extract_progress(&percent, "used 250% of quota"), None);
+    assert_eq!(extract_progress(&percent, "compiling"), None);
+
+    let whole = Regex::new(r"\d+ ?%").unwrap();
+    assert_eq!(extract_progress(&whole, "at 40 %").as_deref(), Some("40%"));
+
+    let steps = Regex::new(r"\[(?P<done>\d+)/(?P<total>\d+)\]").unwrap();
+    assert_eq!(
+        extract_progress(&steps, "[12/40] Compiling serde").as_deref(),
+        Some("12/40")
+    );
+}
+
+#[test]
+fn status_shows_the_latest_progress() {
+    let temp = TempDir::new().unwrap();
+    let run = register_run(temp.path(), &command("make")).unwrap();
+    assert_eq!(running_commands(temp.path())[0].progress, None);
+
+    run.set_progress("72%").unwrap();
+
+    let running = running_commands(temp.path());
+    assert_eq!(running[0].progress.as_deref(), Some("72%"));
+    assert_eq!(running[0].command, command("make"));
+}
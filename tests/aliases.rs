@@ -0,0 +1,130 @@
+use brb_cli::alias::expand_alias;
+use brb_cli::cli::{Action, AliasesAction, RunArgs, parse_args};
+use brb_cli::config::{Config, NotifyOn, load_config_from_path};
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn load(aliases: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [desktop]\nchannels:\n  desktop:\n    type: desktop\n  phone:\n    type: webhook\n    url: https://ntfy.sh/me\naliases:\n{aliases}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn run(command: &[&str]) -> RunArgs {
+    RunArgs {
+        command: command.iter().map(|arg| arg.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn expands_alias_with_presets_and_extra_args() {
+    let config = load(
+        "  build:\n    command: [cargo, build, --release]\n    channels: [phone]\n    notify_on: failure\n    min_duration: 1m\n",
+    )
+    .unwrap();
+
+    let mut args = run(&["build", "--locked"]);
+    assert_eq!(expand_alias(&config, &mut args), Some("build".to_string()));
+    assert_eq!(args.command, ["cargo", "build", "--release", "--locked"]);
+    assert_eq!(args.channels, ["phone"]);
+    assert_eq!(args.notify_on, Some(NotifyOn::Failure));
+    assert_eq!(args.min_duration, Some(Duration::from_secs(60)));
+}
+
+#[test]
+fn command_line_wins_over_alias_presets() {
+    let config = load(
+        "  build:\n    command: [cargo, build]\n    channels: [phone]\n    notify_on: failure\n",
+    )
+    .unwrap();
+
+    let mut args = RunArgs {
+        channels: vec!["desktop".to_string()],
+        notify_on: Some(NotifyOn::Always),
+        ..run(&["build"])
+    };
+    expand_alias(&config, &mut args);
+    assert_eq!(args.channels, ["desktop"]);
+    assert_eq!(args.notify_on, Some(NotifyOn::Always));
+
+    let mut args = RunArgs {
+        all_channels: true,
+        ..run(&["build"])
+    };
+    expand_alias(&config, &mut args);
+    assert!(args.channels.is_empty());
+}
+
+#[test]
+fn separator_and_unknown_names_bypass_aliases() {
+    let config = load("  build:\n    command: [cargo, build]\n").unwrap();
+
+    let mut args = RunArgs {
+        separated: true,
+        ..run(&["build"])
+    };
+    assert_eq!(expand_alias(&config, &mut args), None);
+    assert_eq!(args.command, ["build"]);
+
+    let mut args = run(&["make", "build"]);
+    assert_eq!(expand_alias(&config, &mut args), None);
+    assert_eq!(args.command, ["make", "build"]);
+}
+
+#[test]
+fn validates_aliases() {
+    let error = load("  channels:\n    command: [ls]\n").unwrap_err();
+    assert!(
+        error.contains("alias `channels` clashes with the `brb channels` subcommand"),
+        "{error}"
+    );
+
+    let error = load("  build:\n    command: []\n").unwrap_err();
+    assert!(error.contains("must have a non-empty command"), "{error}");
+
+    let error = load("  build:\n    command: [make]\n    channels: [phnoe]\n").unwrap_err();
+    assert!(
+        error.contains("uses channel `phnoe`, which is not defined")
+            && error.ends_with("did you mean `phone`?"),
+        "{error}"
+    );
+}
+
+#[test]
+fn parses_aliases_subcommand() {
+    assert_eq!(
+        parse_args(vec!["aliases".into()]).unwrap(),
+        Action::Aliases(AliasesAction::List)
+    );
+    assert_eq!(
+        parse_args(vec!["aliases".into(), "list".into()]).unwrap(),
+        Action::Aliases(AliasesAction::List)
+    );
+}
+
+#[test]
+fn resolves_alias_directory_and_env() {
+    let config = load(
+        "  build:\n    command: [make]\n    cwd: project\n    env:\n      PROFILE: release\n      OS_NAME: ${os}\n  abs:\n    command: [make]\n    cwd: /srv/app\n",
+    )
+    .unwrap();
+
+    let build = &config.aliases["build"];
+    let cwd = build.cwd.as_ref().unwrap();
+    assert!(cwd.is_absolute() && cwd.ends_with("project"), "{cwd:?}");
+    assert_eq!(build.env["PROFILE"], "release");
+    assert_eq!(build.env["OS_NAME"], std::env::consts::OS);
+    assert_eq!(
+        config.aliases["abs"].cwd.as_deref(),
+        Some(std::path::Path::new("/srv/app"))
+    );
+}
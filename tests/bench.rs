@@ -0,0 +1,87 @@
+use brb_cli::bench::{BenchSummary, combined_run};
+use brb_cli::config::Config;
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::notification_body;
+use brb_cli::runner::RunResult;
+use chrono::{TimeDelta, Utc};
+use std::time::Duration;
+
+fn runs(durations_ms: &[u64], exit_codes: &[i32]) -> Vec<RunResult> {
+    let mut started_at = Utc::now();
+    durations_ms
+        .iter()
+        .zip(exit_codes)
+        .map(|(duration_ms, exit_code)| {
+            let finished_at = started_at + TimeDelta::milliseconds(*duration_ms as i64);
+            let run = RunResult {
+                command: vec!["make".to_string()],
+                started_at,
+                finished_at,
+                duration: Duration::from_millis(*duration_ms),
+                exit_code: *exit_code,
+                spawn_error: None,
+                output_tail: Some(format!("exit {exit_code}")),
+                cwd: None,
+            };
+            started_at = finished_at;
+            run
+        })
+        .collect()
+}
+
+#[test]
+fn summarises_durations_and_failures() {
+    let durations = (1..=20).map(|second| second * 1000).collect::<Vec<_>>();
+    let mut exit_codes = vec![0; 20];
+    exit_codes[3] = 2;
+
+    let summary = BenchSummary::from_runs(&runs(&durations, &exit_codes)).unwrap();
+    assert_eq!(
+        summary,
+        BenchSummary {
+            runs: 20,
+            failures: 1,
+            min_ms: 1000,
+            mean_ms: 10500,
+            p95_ms: 19000,
+            max_ms: 20000,
+        }
+    );
+
+    let single = BenchSummary::from_runs(&runs(&[700], &[0])).unwrap();
+    assert_eq!((single.p95_ms, single.mean_ms), (700, 700));
+    assert_eq!(BenchSummary::from_runs(&[]), None);
+}
+
+#[test]
+fn combined_run_spans_every_run_and_keeps_the_first_failure() {
+    let finished = runs(&[1000, 2000, 3000], &[0, 7, 1]);
+    let run = combined_run(&finished).unwrap();
+    assert_eq!(run.started_at, finished[0].started_at);
+    assert_eq!(run.finished_at, finished[2].finished_at);
+    assert_eq!(run.duration, Duration::from_secs(6));
+    assert_eq!(run.exit_code, 7);
+    assert_eq!(run.output_tail.as_deref(), Some("exit 7"));
+
+    let run = combined_run(&runs(&[1000, 1000], &[0, 0])).unwrap();
+    assert_eq!(run.exit_code, 0);
+    assert!(combined_run(&[]).is_none());
+}
+
+#[test]
+fn bench_statistics_appear_in_the_body_and_payload() {
+    let mut event = CompletionEvent::test_event();
+    event.bench = BenchSummary::from_runs(&runs(&[1000, 3000], &[0, 1]));
+
+    let body = notification_body(&Config::default(), &event);
+    assert!(body.contains("\n2 runs: min 1.00s, mean 2.00s, p95 3.00s, max 3.00s, 1 failed"));
+
+    let payload = serde_json::to_value(&event).unwrap();
+    assert_eq!(payload["bench"]["p95_ms"], 3000);
+    assert!(
+        serde_json::to_value(CompletionEvent::test_event())
+            .unwrap()
+            .get("bench")
+            .is_none()
+    );
+}
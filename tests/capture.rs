@@ -0,0 +1,101 @@
+use brb_cli::capture::{
+    apply_captures, capture_pointer, extract_captures, load_captures, save_captures,
+};
+use brb_cli::state::StateStore;
+use chrono::{TimeDelta, Utc};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tempfile::TempDir;
+
+fn map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn converts_capture_paths_to_json_pointers() {
+    assert_eq!(capture_pointer("$.ts").as_deref(), Some("/ts"));
+    assert_eq!(
+        capture_pointer("$.message.id").as_deref(),
+        Some("/message/id")
+    );
+    assert_eq!(
+        capture_pointer("$.items[0].id").as_deref(),
+        Some("/items/0/id")
+    );
+    assert_eq!(capture_pointer("$['a/b']").as_deref(), Some("/a~1b"));
+    assert_eq!(
+        capture_pointer("/message/id").as_deref(),
+        Some("/message/id")
+    );
+    assert_eq!(capture_pointer("$").as_deref(), Some(""));
+    assert_eq!(capture_pointer("ts"), None);
+    assert_eq!(capture_pointer("$..ts"), None);
+    assert_eq!(capture_pointer("$.items[0"), None);
+}
+
+#[test]
+fn extracts_values_from_a_response() {
+    let capture = map(&[
+        ("ts", "$.ts"),
+        ("channel", "/channel"),
+        ("count", "$.meta.count"),
+        ("missing", "$.nope"),
+        ("empty", "$.empty"),
+    ]);
+    let response =
+        r#"{"ok":true,"ts":"1700000000.000100","channel":"C1","meta":{"count":3},"empty":null}"#;
+
+    let captured = extract_captures(&capture, response);
+
+    assert_eq!(
+        captured,
+        map(&[
+            ("channel", "C1"),
+            ("count", "3"),
+            ("ts", "1700000000.000100")
+        ])
+    );
+    assert!(extract_captures(&capture, "ok").is_empty());
+}
+
+#[test]
+fn keeps_captures_per_command() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let now = Utc::now();
+
+    save_captures(&store, "slack", "abc", map(&[("ts", "1")]), now).unwrap();
+    save_captures(&store, "slack", "def", map(&[("ts", "2")]), now).unwrap();
+    save_captures(
+        &store,
+        "slack",
+        "abc",
+        map(&[("ts", "3")]),
+        now + TimeDelta::seconds(1),
+    )
+    .unwrap();
+
+    assert_eq!(load_captures(&store, "slack", "abc"), map(&[("ts", "3")]));
+    assert_eq!(load_captures(&store, "slack", "def"), map(&[("ts", "2")]));
+    assert!(load_captures(&store, "other", "abc").is_empty());
+}
+
+#[test]
+fn fills_extra_fields_from_captures() {
+    let extra = map(&[
+        ("thread_ts", "{captured.ts}"),
+        ("team", "infra"),
+        ("ref", "msg-{captured.id}"),
+    ]);
+    let mut body = json!({"text": "done", "thread_ts": "{captured.ts}", "team": "infra", "ref": "msg-{captured.id}"});
+
+    apply_captures(&mut body, &extra, &map(&[("ts", "1.2")]));
+
+    assert_eq!(
+        body,
+        json!({"text": "done", "thread_ts": "1.2", "team": "infra"})
+    );
+}
@@ -0,0 +1,77 @@
+use brb_cli::cargo::{brb_args, find_manifest, package_name, project_name};
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn reads_the_package_name() {
+    let manifest = "[workspace]\nmembers = [\"cli\"]\n\n[package]\nversion = \"1.0.0\"\nname = \"myservice\" # the service\n\n[dependencies]\nname = \"other\"\n";
+    assert_eq!(package_name(manifest).as_deref(), Some("myservice"));
+    assert_eq!(
+        package_name("[package]\nname = 'single'\n").as_deref(),
+        Some("single")
+    );
+    assert_eq!(package_name("[workspace]\nmembers = []\n"), None);
+}
+
+#[test]
+fn finds_the_nearest_manifest_or_the_one_named() {
+    let temp = TempDir::new().unwrap();
+    let nested = temp.path().join("src/bin");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(
+        temp.path().join("Cargo.toml"),
+        "[package]\nname = \"myservice\"\n",
+    )
+    .unwrap();
+    let workspace = temp.path().join("tools");
+    fs::create_dir_all(&workspace).unwrap();
+    fs::write(workspace.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+
+    let manifest = find_manifest(&args(&["test"]), &nested).unwrap();
+    assert_eq!(manifest, temp.path().join("Cargo.toml"));
+    assert_eq!(project_name(&manifest).as_deref(), Some("myservice"));
+
+    let manifest = find_manifest(
+        &args(&["build", "--manifest-path", "tools/Cargo.toml"]),
+        temp.path(),
+    )
+    .unwrap();
+    // a virtual workspace is named after its directory.
+    assert_eq!(project_name(&manifest).as_deref(), Some("tools"));
+
+    let manifest = find_manifest(
+        &args(&["run", "--", "--manifest-path=elsewhere"]),
+        &workspace,
+    );
+    assert_eq!(manifest, Some(workspace.join("Cargo.toml")));
+}
+
+#[test]
+fn wraps_cargo_with_the_project_and_min_duration() {
+    assert_eq!(
+        brb_args(
+            &args(&["test", "--release"]),
+            Some("myservice"),
+            Some(Duration::from_secs(30))
+        ),
+        args(&[
+            "--project",
+            "myservice",
+            "--min-duration",
+            "30s",
+            "--",
+            "cargo",
+            "test",
+            "--release"
+        ])
+    );
+    assert_eq!(
+        brb_args(&args(&["check"]), None, None),
+        args(&["--", "cargo", "check"])
+    );
+}
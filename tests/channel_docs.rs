@@ -0,0 +1,187 @@
+use brb_cli::config::load_config_from_path;
+use brb_cli::event::CompletionEvent;
+use brb_cli::reference::{
+    CHANNEL_REFERENCES, COMMON_FIELDS, FieldReference, channel_reference, render_reference,
+};
+use brb_cli::template::desktop_preview;
+use std::fs;
+use tempfile::TempDir;
+
+fn field(type_name: &str, name: &str) -> FieldReference {
+    *channel_reference(type_name)
+        .unwrap()
+        .fields
+        .iter()
+        .find(|field| field.name == name)
+        .unwrap_or_else(|| panic!("{type_name}.{name} is not documented"))
+}
+
+#[test]
+fn fields_are_read_from_the_config_types() {
+    let url = field("webhook", "url");
+    assert_eq!((url.kind, url.required), ("string or list of string", true));
+    assert_eq!(field("webhook", "max_payload").kind, "size");
+    assert_eq!(field("telegram", "chat_id").kind, "string or integer");
+    assert_eq!(
+        field("telegram", "api_url").default,
+        Some("https://api.telegram.org")
+    );
+    assert_eq!(field("mqtt", "qos").default, Some("0"));
+    assert_eq!(field("desktop", "max_message_length").default, Some("1000"));
+
+    let when_dnd = field("desktop", "when_dnd");
+    assert_eq!(when_dnd.default, Some("notify"));
+    assert!(
+        when_dnd
+            .description
+            .ends_with(" One of `notify`, `skip`, `queue` or `escalate`."),
+        "{}",
+        when_dnd.description
+    );
+
+    // flattened and type-specific fields are not common options.
+    let common = COMMON_FIELDS
+        .iter()
+        .map(|field| field.name)
+        .collect::<Vec<_>>();
+    assert_eq!(common.first(), Some(&"enabled"));
+    assert!(!common.contains(&"kind"), "{common:?}");
+    assert_eq!(CHANNEL_REFERENCES.first().unwrap().type_name, "desktop");
+    assert!(channel_reference("googlechat").is_ok());
+}
+
+#[test]
+fn examples_are_valid_config() {
+    for reference in CHANNEL_REFERENCES {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config.yml");
+        fs::write(
+            &path,
+            format!(
+                "version: 1\ndefault_channels: [example]\nchannels:\n  example:\n{}",
+                reference.example
+            ),
+        )
+        .unwrap();
+        if let Err(error) = load_config_from_path(&path) {
+            panic!("type: {}: {error}", reference.type_name);
+        }
+    }
+}
+
+/// The channel `example` declares, as loaded config prints it with `{:?}`,
+/// and the desktop notification it shows for a failure with a long note.
+fn loaded_channel(example: &str) -> (String, Option<String>) {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!("version: 1\ndefault_channels: [example]\nchannels:\n  example:\n{example}"),
+    )
+    .unwrap();
+    let config = match load_config_from_path(&path) {
+        Ok(config) => config,
+        Err(error) => panic!("{error}\n{example}"),
+    };
+
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 1;
+    event.status = "failure".to_string();
+    event.notes = vec!["x".repeat(5000)];
+    let preview = desktop_preview(&config, "example", &event).ok();
+    (format!("{:?}", config.channels["example"]), preview)
+}
+
+/// `example` without the top-level channel key `field` or anything nested
+/// under it.
+fn without_field(example: &str, field: &str) -> String {
+    let mut inside = false;
+    let mut kept = String::new();
+    for line in example.lines() {
+        if line.starts_with(&format!("    {field}:")) {
+            inside = true;
+        } else if inside && !line.starts_with("     ") {
+            inside = false;
+        }
+        if !inside {
+            kept.push_str(line);
+            kept.push('\n');
+        }
+    }
+    kept
+}
+
+/// Whether loading a channel with `field` set to its documented default gives
+/// the same channel as leaving it out.
+fn default_matches(example: &str, field: &FieldReference) -> bool {
+    let Some(default) = field.default else {
+        return true;
+    };
+    let base = without_field(example, field.name);
+    let value = if field.kind.starts_with("string") {
+        serde_json::to_string(default).unwrap()
+    } else {
+        default.to_string()
+    };
+    let (unset, unset_preview) = loaded_channel(&base);
+    let (set, set_preview) = loaded_channel(&format!("{base}    {}: {value}\n", field.name));
+    // a few limits stay unset in config and are applied when sending.
+    if unset.contains(&format!(" {}: None", field.name)) && unset_preview.is_some() {
+        return unset_preview == set_preview;
+    }
+    unset == set
+}
+
+#[test]
+fn documented_defaults_match_config() {
+    let mut wrong = Vec::new();
+    for reference in CHANNEL_REFERENCES {
+        for field in reference.fields {
+            if !default_matches(reference.example, field) {
+                wrong.push(format!("{}.{}", reference.type_name, field.name));
+            }
+        }
+    }
+    let desktop = channel_reference("desktop").unwrap();
+    for field in COMMON_FIELDS {
+        if !default_matches(desktop.example, field) {
+            wrong.push(field.name.to_string());
+        }
+    }
+    assert!(wrong.is_empty(), "documented defaults differ: {wrong:?}");
+}
+
+#[test]
+fn renders_fields_defaults_and_the_example() {
+    let output = render_reference(channel_reference("slack").unwrap());
+
+    assert!(
+        output.starts_with("type: slack\nSlack message laid out with Block Kit"),
+        "{output}"
+    );
+    assert!(
+        output.contains("  url (string, required)\n      Incoming webhook URL.\n"),
+        "{output}"
+    );
+    assert!(
+        output.contains("  attach_lines (integer, default 20)\n"),
+        "{output}"
+    );
+    assert!(
+        output.contains("  notify_on (string, default always)\n"),
+        "{output}"
+    );
+    assert!(
+        output.ends_with(
+            "Example:\n  channels:\n    slack:\n      type: slack\n      url: https://hooks.slack.com/services/T000/B000/XXXX\n      attach_output: true\n"
+        ),
+        "{output}"
+    );
+}
+
+#[test]
+fn unknown_types_suggest_the_closest() {
+    let error = channel_reference("slak").unwrap_err();
+    assert!(error.starts_with("unknown channel type `slak`"), "{error}");
+    assert!(error.ends_with("; did you mean `slack`?"), "{error}");
+}
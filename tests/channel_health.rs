@@ -0,0 +1,132 @@
+use brb_cli::channels::{DeliveryError, DeliveryErrorKind, DeliveryResult};
+use brb_cli::config::load_config_from_path;
+use brb_cli::health::{channel_health, channel_overview, record_deliveries};
+use brb_cli::state::StateStore;
+use std::collections::BTreeMap;
+use std::fs;
+use tempfile::TempDir;
+
+fn delivered(channel_id: &str) -> DeliveryResult {
+    DeliveryResult {
+        channel_id: channel_id.to_string(),
+        success: true,
+        error: None,
+        latency_ms: Some(80),
+        attempts: 1,
+        queued: false,
+        skipped: None,
+        interaction: None,
+        acknowledged: None,
+        captured: BTreeMap::new(),
+        targets: Vec::new(),
+    }
+}
+
+fn failed(channel_id: &str, message: &str) -> DeliveryResult {
+    DeliveryResult {
+        success: false,
+        error: Some(DeliveryError {
+            kind: DeliveryErrorKind::Http { status: 500 },
+            message: message.to_string(),
+        }),
+        ..delivered(channel_id)
+    }
+}
+
+#[test]
+fn keeps_the_last_success_and_error_per_channel() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+
+    record_deliveries(
+        &store,
+        &[
+            delivered("desktop"),
+            failed("ci", "webhook returned HTTP 500"),
+        ],
+        "2026-10-16T09:00:00Z",
+    )
+    .unwrap();
+    record_deliveries(
+        &store,
+        &[
+            delivered("ci"),
+            DeliveryResult {
+                skipped: Some("do not disturb".to_string()),
+                ..delivered("desktop")
+            },
+        ],
+        "2026-10-16T10:00:00Z",
+    )
+    .unwrap();
+
+    let health = channel_health(&store);
+    assert_eq!(
+        health["desktop"].last_success.as_deref(),
+        Some("2026-10-16T09:00:00Z")
+    );
+    assert_eq!(health["desktop"].last_error, None);
+    assert_eq!(
+        health["ci"].last_success.as_deref(),
+        Some("2026-10-16T10:00:00Z")
+    );
+    let error = health["ci"].last_error.as_ref().unwrap();
+    assert_eq!(error.at, "2026-10-16T09:00:00Z");
+    assert_eq!(error.message, "webhook returned HTTP 500");
+}
+
+#[test]
+fn overview_lists_every_channel_with_its_policy_and_health() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        "version: 1\ndefault_channels: [ci]\nchannels:\n  ci:\n    type: webhook\n    url: https://example.com/hook\n    notify_on: failure\n  spare:\n    type: webhook\n    url: https://example.com/spare\n    enabled: false\n",
+    )
+    .unwrap();
+    let config = load_config_from_path(&path).unwrap();
+    let store = StateStore::at(temp.path().join("state"));
+    record_deliveries(&store, &[failed("ci", "timed out")], "2026-10-16T09:00:00Z").unwrap();
+
+    let channels = channel_overview(&config, channel_health(&store));
+
+    assert_eq!(channels.len(), 2);
+    assert!(channels[0].default);
+    assert!(channels[0].enabled);
+    assert_eq!(channels[0].notify_on, "failure");
+    assert!(channels[0].failing());
+    assert!(!channels[1].default);
+    assert!(!channels[1].enabled);
+    assert!(!channels[1].failing());
+    let json = serde_json::to_value(&channels).unwrap();
+    assert_eq!(json[0]["type"], "webhook");
+    assert_eq!(json[0]["last_error"]["message"], "timed out");
+    assert_eq!(json[1]["last_success"], serde_json::Value::Null);
+    assert_eq!(json[1]["enabled"], false);
+}
+
+#[test]
+fn orders_deliveries_within_the_same_second() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        "version: 1\ndefault_channels: [ci]\nchannels:\n  ci:\n    type: webhook\n    url: https://example.com/hook\n",
+    )
+    .unwrap();
+    let config = load_config_from_path(&path).unwrap();
+    let store = StateStore::at(temp.path().join("state"));
+
+    record_deliveries(
+        &store,
+        &[failed("ci", "timed out")],
+        "2026-10-16T09:00:00.250000Z",
+    )
+    .unwrap();
+    record_deliveries(&store, &[delivered("ci")], "2026-10-16T09:00:00.750000Z").unwrap();
+    assert!(!channel_overview(&config, channel_health(&store))[0].failing());
+
+    // a record from before sub-second timestamps still orders by instant.
+    record_deliveries(&store, &[failed("ci", "timed out")], "2026-10-16T09:00:01Z").unwrap();
+    assert!(channel_overview(&config, channel_health(&store))[0].failing());
+}
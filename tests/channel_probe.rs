@@ -0,0 +1,154 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::config::{Config, MqttChannel, WebhookChannel, WebhookFormat};
+use brb_cli::probe::{AuthStatus, check_webhook_auth, probe_broker, probe_webhook};
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::net::TcpListener;
+
+fn webhook(url: String) -> WebhookChannel {
+    WebhookChannel {
+        url: vec![url],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn probe_reports_http_status_and_stage_latency() {
+    // the TCP stage connects without sending anything before the HEAD request.
+    let server = MockServer::start(vec![MockResponse::status(405)]);
+
+    let result = probe_webhook("hook", &webhook(server.url("/hook?token=secret")));
+
+    assert!(result.reachable());
+    assert_eq!(result.http_status, Some(405));
+    assert_eq!(server.requests()[0].method, "HEAD");
+    assert!(result.dns_ms.is_some() && result.connect_ms.is_some() && result.head_ms.is_some());
+    let line = result.to_string();
+    assert!(line.starts_with("hook: reachable, HTTP 405 (dns "));
+    assert!(!line.contains("secret"));
+}
+
+#[test]
+fn probe_stops_at_failed_connect() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let result = probe_webhook("hook", &webhook(format!("http://127.0.0.1:{port}/")));
+    assert!(!result.reachable());
+    assert!(result.connect_ms.is_none() && result.head_ms.is_none());
+    assert!(result.to_string().starts_with(&format!(
+        "hook: unreachable: tcp connect to port {port} failed"
+    )));
+}
+
+fn mqtt(broker: String) -> MqttChannel {
+    MqttChannel {
+        broker,
+        topic: "brb/events".to_string(),
+        qos: 0,
+        retain: false,
+        username: None,
+        password: None,
+        client_id: None,
+        retries: 0,
+    }
+}
+
+#[test]
+fn mqtt_probe_connects_to_the_broker_without_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let result = probe_broker("home", &mqtt(format!("mqtt://127.0.0.1:{port}")));
+    assert!(result.reachable());
+    assert!(result.dns_ms.is_some() && result.connect_ms.is_some());
+    assert!(result.head_ms.is_none() && result.http_status.is_none());
+    assert!(result.to_string().starts_with("home: reachable (dns "));
+
+    drop(listener);
+    let result = probe_broker("home", &mqtt(format!("mqtt://127.0.0.1:{port}")));
+    assert!(!result.reachable());
+    assert!(result.to_string().starts_with(&format!(
+        "home: unreachable: tcp connect to port {port} failed"
+    )));
+
+    let result = probe_broker("home", &mqtt("http://127.0.0.1/".to_string()));
+    assert!(!result.reachable());
+    assert!(
+        result
+            .to_string()
+            .contains("invalid broker: unsupported scheme")
+    );
+}
+
+fn chat(format: WebhookFormat, url: String) -> WebhookChannel {
+    WebhookChannel {
+        format,
+        ..webhook(url)
+    }
+}
+
+#[test]
+fn slack_credentials_are_checked_with_an_empty_post() {
+    let server = MockServer::start(vec![
+        MockResponse::status(400).body("invalid_payload"),
+        MockResponse::status(403).body("invalid_token"),
+    ]);
+    let slack = chat(
+        WebhookFormat::Slack,
+        server.url("/services/T000/B000/secret"),
+    );
+
+    let result = check_webhook_auth(&Config::default(), "slack", &slack);
+    assert_eq!(result.status, AuthStatus::Accepted);
+    assert_eq!(
+        result.to_string(),
+        "slack: credentials accepted (HTTP 400 invalid_payload)"
+    );
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.text(), "{}");
+
+    let result = check_webhook_auth(&Config::default(), "slack", &slack);
+    assert_eq!(result.status, AuthStatus::Rejected);
+    assert!(!result.ok());
+    assert!(!result.to_string().contains("secret"));
+}
+
+#[test]
+fn discord_and_ntfy_credentials_are_checked_without_posting() {
+    let server = MockServer::start(vec![
+        MockResponse::status(200).body(r#"{"id":"1","type":1}"#),
+        MockResponse::status(401).body(r#"{"code":40101}"#),
+    ]);
+
+    let discord = chat(WebhookFormat::Discord, server.url("/api/webhooks/1/token"));
+    let result = check_webhook_auth(&Config::default(), "discord", &discord);
+    assert_eq!(result.status, AuthStatus::Accepted);
+    assert_eq!(server.requests()[0].method, "GET");
+
+    let mut ntfy = chat(WebhookFormat::Ntfy, server.url("/builds/"));
+    ntfy.headers
+        .insert("Authorization".to_string(), "Bearer tk_wrong".to_string());
+    let result = check_webhook_auth(&Config::default(), "ntfy", &ntfy);
+    assert_eq!(result.status, AuthStatus::Rejected);
+    let request = &server.requests()[1];
+    assert_eq!(
+        (request.method.as_str(), request.path.as_str()),
+        ("GET", "/builds/auth")
+    );
+    assert_eq!(request.header("authorization"), Some("Bearer tk_wrong"));
+}
+
+#[test]
+fn formats_without_a_credential_check_are_skipped() {
+    let teams = chat(
+        WebhookFormat::Teams,
+        "https://example.webhook.office.com/webhookb2/x".to_string(),
+    );
+
+    let result = check_webhook_auth(&Config::default(), "teams", &teams);
+    assert_eq!(result.status, AuthStatus::Skipped);
+    assert!(result.ok());
+}
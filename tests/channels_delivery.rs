@@ -1,21 +1,30 @@
-use brb_cli::channels::notify_selected;
-use brb_cli::config::{ChannelConfig, Config, CustomChannel, DesktopChannel, WebhookChannel};
+use brb_cli::channels::{
+    DeliveryErrorKind, channels_for_event, custom_invocation, desktop_sound, find_executable,
+    notify_selected, notify_send_args, parse_retry_after, preflight_problems, tiered_channels,
+    with_exit_code_channels,
+};
+use brb_cli::config::{
+    Channel, ChannelConfig, Config, CustomChannel, DesktopChannel, DurationTier, ExitCodeRule,
+    HttpConfig, Interpreter, NotifyOn, Oversize, Priority, SoundRule, WebhookChannel,
+};
 use brb_cli::event::CompletionEvent;
+use brb_cli::test_support::{MockResponse, MockServer};
 use std::collections::BTreeMap;
 
 fn config_with_channel(channel_id: &str, channel: ChannelConfig) -> Config {
     let mut channels = BTreeMap::new();
-    channels.insert(channel_id.to_string(), channel);
+    channels.insert(channel_id.to_string(), channel.into());
     Config {
         version: 1,
         default_channels: vec![channel_id.to_string()],
         channels,
+        ..Default::default()
     }
 }
 
 #[test]
 fn missing_selected_channel_reports_failure() {
-    let config = config_with_channel("desktop", ChannelConfig::Desktop(DesktopChannel {}));
+    let config = config_with_channel("desktop", ChannelConfig::Desktop(DesktopChannel::default()));
     let event = CompletionEvent::test_event();
     let selected = vec!["missing".to_string()];
 
@@ -26,8 +35,8 @@ fn missing_selected_channel_reports_failure() {
     assert!(
         results[0]
             .error
-            .as_deref()
-            .unwrap_or_default()
+            .as_ref()
+            .map_or("", |error| error.message.as_str())
             .contains("channel not found")
     );
 }
@@ -37,9 +46,9 @@ fn invalid_webhook_method_fails_fast() {
     let config = config_with_channel(
         "bad-webhook",
         ChannelConfig::Webhook(WebhookChannel {
-            url: "https://example.com/hook".to_string(),
+            url: vec!["https://example.com/hook".to_string()],
             method: "NOT A METHOD".to_string(),
-            headers: BTreeMap::new(),
+            ..Default::default()
         }),
     );
     let event = CompletionEvent::test_event();
@@ -51,8 +60,8 @@ fn invalid_webhook_method_fails_fast() {
     assert!(
         results[0]
             .error
-            .as_deref()
-            .unwrap_or_default()
+            .as_ref()
+            .map_or("", |error| error.message.as_str())
             .contains("invalid HTTP method")
     );
 }
@@ -65,7 +74,7 @@ fn custom_channel_success_path() {
         ChannelConfig::Custom(CustomChannel {
             exec: "sh".to_string(),
             args: vec!["-c".to_string(), "cat >/dev/null; exit 0".to_string()],
-            env: BTreeMap::new(),
+            ..Default::default()
         }),
     );
     let event = CompletionEvent::test_event();
@@ -87,7 +96,7 @@ fn custom_channel_failure_redacts_token_like_values() {
                 "-c".to_string(),
                 "cat >/dev/null; echo 'token=abc123' 1>&2; exit 1".to_string(),
             ],
-            env: BTreeMap::new(),
+            ..Default::default()
         }),
     );
     let event = CompletionEvent::test_event();
@@ -96,7 +105,554 @@ fn custom_channel_failure_redacts_token_like_values() {
     let results = notify_selected(&config, &selected, &event);
     assert_eq!(results.len(), 1);
     assert!(!results[0].success);
-    let message = results[0].error.as_deref().unwrap_or_default();
+    let message = results[0]
+        .error
+        .as_ref()
+        .map_or("", |error| error.message.as_str());
     assert!(message.contains("[REDACTED]"));
     assert!(!message.contains("abc123"));
 }
+
+#[test]
+fn notify_on_policy_filters_channels() {
+    let mut config =
+        config_with_channel("desktop", ChannelConfig::Desktop(DesktopChannel::default()));
+    config.channels.insert(
+        "failures".to_string(),
+        Channel {
+            notify_on: NotifyOn::Failure,
+            ..Channel::from(ChannelConfig::Desktop(DesktopChannel::default()))
+        },
+    );
+    config.channels.insert(
+        "changes".to_string(),
+        Channel {
+            notify_on: NotifyOn::Change,
+            ..Channel::from(ChannelConfig::Desktop(DesktopChannel::default()))
+        },
+    );
+    let event = CompletionEvent::test_event();
+    let selected = vec![
+        "desktop".to_string(),
+        "failures".to_string(),
+        "changes".to_string(),
+    ];
+
+    let first = channels_for_event(&config, &selected, &event);
+    assert_eq!(first, vec!["desktop".to_string(), "changes".to_string()]);
+
+    let mut event = event;
+    event.previous_status = Some("success".to_string());
+    let repeated = channels_for_event(&config, &selected, &event);
+    assert_eq!(repeated, vec!["desktop".to_string()]);
+}
+
+#[test]
+fn min_duration_skips_quick_runs() {
+    let mut config =
+        config_with_channel("desktop", ChannelConfig::Desktop(DesktopChannel::default()));
+    config.min_duration = Some(std::time::Duration::from_secs(30));
+    let mut event = CompletionEvent::test_event();
+    let selected = vec!["desktop".to_string()];
+
+    event.duration_ms = 29_999;
+    assert!(channels_for_event(&config, &selected, &event).is_empty());
+
+    event.duration_ms = 30_000;
+    assert_eq!(channels_for_event(&config, &selected, &event), selected);
+}
+
+#[test]
+fn longer_runs_pick_later_tiers() {
+    let mut config =
+        config_with_channel("desktop", ChannelConfig::Desktop(DesktopChannel::default()));
+    let tier = |minutes: u64, channels: &[&str]| DurationTier {
+        min_duration: std::time::Duration::from_secs(minutes * 60),
+        channels: channels.iter().map(|id| id.to_string()).collect(),
+    };
+    // listed out of order on purpose: the longest reached duration wins.
+    config.tiers = vec![
+        tier(60, &["desktop", "slack", "telegram"]),
+        tier(5, &["desktop", "slack"]),
+    ];
+    let mut event = CompletionEvent::test_event();
+
+    event.duration_ms = 4 * 60 * 1000;
+    assert_eq!(tiered_channels(&config, &event), ["desktop"]);
+
+    event.duration_ms = 5 * 60 * 1000;
+    assert_eq!(tiered_channels(&config, &event), ["desktop", "slack"]);
+
+    event.duration_ms = 3 * 60 * 60 * 1000;
+    assert_eq!(
+        tiered_channels(&config, &event),
+        ["desktop", "slack", "telegram"]
+    );
+}
+
+#[test]
+fn exit_code_rules_add_channels() {
+    let mut config =
+        config_with_channel("desktop", ChannelConfig::Desktop(DesktopChannel::default()));
+    config.exit_codes.insert(
+        137,
+        ExitCodeRule {
+            message: Some("killed (likely OOM)".to_string()),
+            channels: vec!["infra".to_string(), "desktop".to_string()],
+        },
+    );
+    let selected = vec!["desktop".to_string()];
+    let mut event = CompletionEvent::test_event();
+
+    event.exit_code = 1;
+    assert_eq!(
+        with_exit_code_channels(&config, &selected, &event),
+        selected
+    );
+
+    event.exit_code = 137;
+    assert_eq!(
+        with_exit_code_channels(&config, &selected, &event),
+        ["desktop", "infra"]
+    );
+}
+
+#[test]
+fn disabled_channels_are_skipped_wherever_they_are_selected() {
+    let server = MockServer::start(vec![]);
+    let mut config = config_with_channel(
+        "pager",
+        ChannelConfig::Webhook(WebhookChannel {
+            url: vec![server.url("/hook")],
+            ..Default::default()
+        }),
+    );
+    config.channels.get_mut("pager").unwrap().enabled = false;
+    config.exit_codes.insert(
+        137,
+        ExitCodeRule {
+            message: None,
+            channels: vec!["pager".to_string()],
+        },
+    );
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 137;
+
+    let selected = with_exit_code_channels(&config, &config.default_channels, &event);
+    let results = notify_selected(&config, &selected, &event);
+
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].delivered());
+    assert_eq!(results[0].skipped.as_deref(), Some("disabled"));
+    assert!(server.requests().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn custom_channel_receives_extra_fields() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let output = temp.path().join("payload.json");
+    let mut extra = BTreeMap::new();
+    extra.insert("team".to_string(), "platform".to_string());
+    let config = config_with_channel(
+        "custom-extra",
+        ChannelConfig::Custom(CustomChannel {
+            exec: "sh".to_string(),
+            args: vec!["-c".to_string(), format!("cat > '{}'", output.display())],
+            extra,
+            ..Default::default()
+        }),
+    );
+    let event = CompletionEvent::test_event();
+    let selected = vec!["custom-extra".to_string()];
+
+    let results = notify_selected(&config, &selected, &event);
+    assert!(results[0].success);
+    let payload: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(payload["team"], "platform");
+    assert_eq!(payload["tool"], "brb");
+}
+
+#[test]
+fn webhook_retries_then_reports_failure() {
+    let config = config_with_channel(
+        "unreachable",
+        ChannelConfig::Webhook(WebhookChannel {
+            url: vec!["http://127.0.0.1:9/hook".to_string()],
+            retries: 1,
+            ..Default::default()
+        }),
+    );
+    let event = CompletionEvent::test_event();
+    let selected = vec!["unreachable".to_string()];
+
+    let started = std::time::Instant::now();
+    let results = notify_selected(&config, &selected, &event);
+    assert!(!results[0].success);
+    assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+    let error = results[0].error.as_ref().unwrap();
+    assert_eq!(error.kind, DeliveryErrorKind::Network);
+    assert_eq!(error.message, "webhook request failed");
+    assert_eq!(results[0].attempts, 2);
+    assert!(results[0].latency_ms.is_some_and(|ms| ms >= 1000));
+}
+
+#[test]
+fn webhook_inherits_http_defaults_with_channel_overrides() {
+    let server = MockServer::start(vec![]);
+    let mut config = config_with_channel(
+        "relay",
+        ChannelConfig::Webhook(WebhookChannel {
+            url: vec![server.url("/hook")],
+            headers: BTreeMap::from([("authorization".to_string(), "Bearer own".to_string())]),
+            ..Default::default()
+        }),
+    );
+    config.http = HttpConfig {
+        headers: BTreeMap::from([
+            ("Authorization".to_string(), "Bearer shared".to_string()),
+            ("X-Team".to_string(), "infra".to_string()),
+        ]),
+        user_agent: Some("ci-bot/1".to_string()),
+        timeout: Some(std::time::Duration::from_secs(5)),
+        proxy: None,
+    };
+
+    let results = notify_selected(
+        &config,
+        &["relay".to_string()],
+        &CompletionEvent::test_event(),
+    );
+    let request = &server.requests()[0];
+
+    assert!(results[0].success, "{:?}", results[0].error);
+    assert_eq!(request.header("x-team"), Some("infra"));
+    assert_eq!(request.header("user-agent"), Some("ci-bot/1"));
+    assert_eq!(request.header("authorization"), Some("Bearer own"));
+}
+
+#[test]
+fn oversize_webhook_bodies_can_be_gzipped() {
+    let server = MockServer::start(vec![]);
+    let config = config_with_channel(
+        "relay",
+        ChannelConfig::Webhook(WebhookChannel {
+            url: vec![server.url("/hook")],
+            max_payload: Some(64),
+            oversize: Oversize::Gzip,
+            ..Default::default()
+        }),
+    );
+
+    let results = notify_selected(
+        &config,
+        &["relay".to_string()],
+        &CompletionEvent::test_event(),
+    );
+    let request = &server.requests()[0];
+
+    assert!(results[0].success, "{:?}", results[0].error);
+    assert_eq!(request.header("content-encoding"), Some("gzip"));
+    assert_eq!(request.header("content-type"), Some("application/json"));
+    assert_eq!(request.body[..2], [0x1f, 0x8b]);
+}
+
+#[test]
+fn parses_retry_after_seconds_and_dates() {
+    let now = chrono::DateTime::parse_from_rfc3339("2026-02-22T12:00:00Z")
+        .unwrap()
+        .to_utc();
+
+    assert_eq!(
+        parse_retry_after(" 7 ", now),
+        Some(std::time::Duration::from_secs(7))
+    );
+    assert_eq!(
+        parse_retry_after("Sun, 22 Feb 2026 12:00:30 GMT", now),
+        Some(std::time::Duration::from_secs(30))
+    );
+    assert_eq!(
+        parse_retry_after("Sun, 22 Feb 2026 11:00:00 GMT", now),
+        Some(std::time::Duration::ZERO)
+    );
+    assert_eq!(parse_retry_after("soon", now), None);
+}
+
+#[test]
+fn rate_limited_webhooks_wait_for_retry_after() {
+    let server = MockServer::start(vec![
+        MockResponse::status(429).header("Retry-After", "1"),
+        MockResponse::status(204),
+    ]);
+    // no retries configured: the Retry-After still earns one.
+    let config = config_with_channel(
+        "chat",
+        ChannelConfig::Webhook(WebhookChannel {
+            url: vec![server.url("/hook")],
+            ..Default::default()
+        }),
+    );
+
+    let started = std::time::Instant::now();
+    let results = notify_selected(
+        &config,
+        &["chat".to_string()],
+        &CompletionEvent::test_event(),
+    );
+
+    assert!(results[0].success, "{:?}", results[0].error);
+    assert_eq!(results[0].attempts, 2);
+    assert_eq!(server.requests().len(), 2);
+    assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+}
+
+#[test]
+fn preflight_reports_missing_custom_notifiers() {
+    let mut config = config_with_channel(
+        "script",
+        ChannelConfig::Custom(CustomChannel {
+            exec: "sh".to_string(),
+            ..Default::default()
+        }),
+    );
+    assert!(preflight_problems(&config).is_empty());
+
+    config.channels.insert(
+        "missing".to_string(),
+        ChannelConfig::Custom(CustomChannel {
+            exec: "brb-notifier-that-does-not-exist".to_string(),
+            ..Default::default()
+        })
+        .into(),
+    );
+    assert_eq!(
+        preflight_problems(&config),
+        vec![
+            "channel `missing`: custom notifier `brb-notifier-that-does-not-exist` was not found or is not executable"
+                .to_string()
+        ]
+    );
+    assert!(find_executable("/definitely/not/here").is_none());
+}
+
+#[test]
+fn custom_interpreter_wraps_the_script() {
+    let mut custom = CustomChannel {
+        exec: "notify.ps1".to_string(),
+        args: vec!["--quiet".to_string()],
+        interpreter: Some(Interpreter::Pwsh),
+        ..Default::default()
+    };
+    assert_eq!(
+        custom_invocation(&custom),
+        (
+            "pwsh".to_string(),
+            [
+                "-NoProfile",
+                "-NonInteractive",
+                "-ExecutionPolicy",
+                "Bypass",
+                "-File",
+                "notify.ps1",
+                "--quiet"
+            ]
+            .map(String::from)
+            .to_vec()
+        )
+    );
+
+    custom.interpreter = Some(Interpreter::Cmd);
+    custom.exec = "notify.cmd".to_string();
+    assert_eq!(
+        custom_invocation(&custom).1,
+        vec![
+            "/C".to_string(),
+            "notify.cmd".to_string(),
+            "--quiet".to_string()
+        ]
+    );
+
+    assert_eq!(
+        Interpreter::for_script("C:/hooks/Notify.BAT"),
+        Some(Interpreter::Cmd)
+    );
+    assert_eq!(
+        Interpreter::for_script("notify.ps1"),
+        Some(Interpreter::Powershell)
+    );
+    assert_eq!(Interpreter::for_script("notify"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn custom_channel_runs_script_through_interpreter() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let output = temp.path().join("seen.txt");
+    // not executable: only the interpreter needs to be.
+    let script = temp.path().join("notify.sh");
+    std::fs::write(&script, format!("cat > '{}'\n", output.display())).unwrap();
+    let mut config = config_with_channel(
+        "script",
+        ChannelConfig::Custom(CustomChannel {
+            exec: script.display().to_string(),
+            interpreter: Some(Interpreter::Sh),
+            ..Default::default()
+        }),
+    );
+    assert!(preflight_problems(&config).is_empty());
+
+    let results = notify_selected(
+        &config,
+        &["script".to_string()],
+        &CompletionEvent::test_event(),
+    );
+    assert!(results[0].success, "{:?}", results[0].error);
+    assert!(
+        std::fs::read_to_string(&output)
+            .unwrap()
+            .contains("\"tool\":\"brb\"")
+    );
+
+    config.channels.insert(
+        "missing".to_string(),
+        ChannelConfig::Custom(CustomChannel {
+            exec: temp.path().join("gone.sh").display().to_string(),
+            interpreter: Some(Interpreter::Sh),
+            ..Default::default()
+        })
+        .into(),
+    );
+    assert_eq!(preflight_problems(&config).len(), 1);
+    assert!(preflight_problems(&config)[0].contains("script"));
+}
+
+#[cfg(unix)]
+#[test]
+fn custom_channel_receives_env_files_and_removes_them() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let output = temp.path().join("seen.txt");
+    let mut env_files = BTreeMap::new();
+    env_files.insert("TOKEN_FILE".to_string(), "s3cret".to_string());
+    let config = config_with_channel(
+        "custom-files",
+        ChannelConfig::Custom(CustomChannel {
+            exec: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                format!(
+                    "cat >/dev/null; echo \"$TOKEN_FILE\" > '{0}'; cat \"$TOKEN_FILE\" >> '{0}'",
+                    output.display()
+                ),
+            ],
+            env_files,
+            ..Default::default()
+        }),
+    );
+    let event = CompletionEvent::test_event();
+    let selected = vec!["custom-files".to_string()];
+
+    let results = notify_selected(&config, &selected, &event);
+    assert!(results[0].success, "{:?}", results[0].error);
+    let seen = std::fs::read_to_string(&output).unwrap();
+    let (path, value) = seen.split_once('\n').unwrap();
+    assert_eq!(value, "s3cret");
+    assert!(!std::path::Path::new(path).exists());
+}
+
+#[test]
+fn desktop_grouping_and_success_dismissal_hints() {
+    let mut config = Config::default();
+    let desktop = DesktopChannel {
+        group: true,
+        dismiss_success_after: Some(std::time::Duration::from_secs(5)),
+        ..Default::default()
+    };
+    let mut event = CompletionEvent::test_event();
+
+    let args = notify_send_args(&config, &desktop, &event);
+    assert_eq!(args[0], "--urgency=normal");
+    assert!(args.contains(&format!(
+        "--hint=string:x-dunst-stack-tag:brb:{}",
+        event.fingerprint
+    )));
+    assert!(args.contains(&"--expire-time=5000".to_string()));
+    assert!(args.contains(&"--transient".to_string()));
+
+    event.exit_code = 1;
+    let args = notify_send_args(&config, &desktop, &event);
+    assert!(args.contains(&"--expire-time=0".to_string()));
+    assert!(!args.contains(&"--transient".to_string()));
+
+    let args = notify_send_args(&config, &DesktopChannel::default(), &event);
+    assert_eq!(args, ["--urgency=normal"]);
+
+    config.appearance.failure.priority = Some(Priority::High);
+    let args = notify_send_args(&config, &DesktopChannel::default(), &event);
+    assert_eq!(args, ["--urgency=critical"]);
+}
+
+#[test]
+fn desktop_sounds_follow_status_and_duration() {
+    let rule = |status: Option<&str>, min_duration: Option<u64>, sound: &str| SoundRule {
+        status: status.map(str::to_string),
+        min_duration: min_duration.map(std::time::Duration::from_secs),
+        sound: sound.to_string(),
+    };
+    let desktop = DesktopChannel {
+        sounds: vec![
+            rule(Some("failure"), Some(600), "alarm-clock-elapsed"),
+            rule(Some("failure"), None, "dialog-warning"),
+            rule(None, Some(600), "complete"),
+        ],
+        ..Default::default()
+    };
+    let mut event = CompletionEvent::test_event();
+
+    event.duration_ms = 1_000;
+    assert_eq!(desktop_sound(&desktop, &event), None);
+    event.duration_ms = 900_000;
+    assert_eq!(desktop_sound(&desktop, &event), Some("complete"));
+
+    event.status = "failure".to_string();
+    event.exit_code = 1;
+    assert_eq!(desktop_sound(&desktop, &event), Some("alarm-clock-elapsed"));
+    event.duration_ms = 1_000;
+    assert_eq!(desktop_sound(&desktop, &event), Some("dialog-warning"));
+
+    let args = notify_send_args(&Config::default(), &desktop, &event);
+    assert!(args.contains(&"--hint=string:sound-name:dialog-warning".to_string()));
+}
+
+#[cfg(unix)]
+#[test]
+fn suppressed_channels_fire_only_when_the_other_failed() {
+    let custom = |script: &str| {
+        ChannelConfig::Custom(CustomChannel {
+            exec: "sh".to_string(),
+            args: vec!["-c".to_string(), format!("cat >/dev/null; {script}")],
+            ..Default::default()
+        })
+    };
+    let mut config = config_with_channel("desktop", custom("exit 0"));
+    config.channels.insert(
+        "phone".to_string(),
+        Channel {
+            suppress_if_delivered: vec!["desktop".to_string()],
+            ..Channel::from(custom("exit 0"))
+        },
+    );
+    let event = CompletionEvent::test_event();
+    // the phone is listed first but still waits for the desktop result.
+    let selected = vec!["phone".to_string(), "desktop".to_string()];
+
+    let results = notify_selected(&config, &selected, &event);
+    assert_eq!(results[0].channel_id, "desktop");
+    assert!(results[0].delivered());
+    assert_eq!(results[1].skipped.as_deref(), Some("delivered on desktop"));
+
+    config.channels.get_mut("desktop").unwrap().kind = custom("exit 1");
+    let results = notify_selected(&config, &selected, &event);
+    assert!(!results[0].success);
+    assert!(results[1].delivered());
+}
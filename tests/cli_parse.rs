@@ -1,4 +1,12 @@
-use brb_cli::cli::{Action, ChannelsAction, ConfigAction, RunArgs, parse_args};
+use brb_cli::cli::{
+    Action, ChannelsAction, ConfigAction, HistoryAction, ImportAction, LockMode, PreviewStatus,
+    RunArgs, SecretAction, SessionAction, TemplateAction, apply_env_overrides, parse_args,
+    parse_invocation,
+};
+use brb_cli::config::NotifyOn;
+use brb_cli::output::ColorChoice;
+use brb_cli::runner::Interactivity;
+use std::time::Duration;
 
 #[test]
 fn parse_default_run_command() {
@@ -7,7 +15,8 @@ fn parse_default_run_command() {
         parsed,
         Action::Run(RunArgs {
             channels: vec![],
-            command: vec!["pnpm".into(), "test".into()]
+            command: vec!["pnpm".into(), "test".into()],
+            ..Default::default()
         })
     );
 }
@@ -26,7 +35,170 @@ fn parse_channel_flags() {
         parsed,
         Action::Run(RunArgs {
             channels: vec!["desktop".into(), "ci-webhook".into()],
-            command: vec!["cargo".into(), "test".into()]
+            command: vec!["cargo".into(), "test".into()],
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn parse_channel_shorthand() {
+    let parsed = parse_args(vec![
+        "@slack".into(),
+        "--every".into(),
+        "5m".into(),
+        "@desktop".into(),
+        "--".into(),
+        "echo".into(),
+        "@home".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            channels: vec!["slack".into(), "desktop".into()],
+            every: Some(Duration::from_secs(300)),
+            command: vec!["echo".into(), "@home".into()],
+            separated: true,
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn channel_shorthand_stops_at_command() {
+    let parsed = parse_args(vec!["ssh".into(), "@host".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            command: vec!["ssh".into(), "@host".into()],
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn parse_all_channels_flag() {
+    let parsed = parse_args(vec!["--all-channels".into(), "./migrate.sh".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            all_channels: true,
+            command: vec!["./migrate.sh".into()],
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn all_channels_conflicts_with_channel() {
+    let parsed = parse_args(vec![
+        "--all-channels".into(),
+        "--channel".into(),
+        "desktop".into(),
+        "true".into(),
+    ]);
+    assert!(parsed.is_err());
+}
+
+#[test]
+fn parse_channels_list_json() {
+    let parsed = parse_args(vec!["channels".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::List { json: false })
+    );
+
+    let parsed = parse_args(vec!["channels".into(), "list".into(), "--json".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::List { json: true })
+    );
+}
+
+#[test]
+fn parse_import_subcommands() {
+    let parsed = parse_args(vec![
+        "import".into(),
+        "ntfy-url".into(),
+        "https://ntfy.sh/builds".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Import(ImportAction::NtfyUrl {
+            url: "https://ntfy.sh/builds".to_string()
+        })
+    );
+
+    let parsed = parse_args(vec![
+        "import".into(),
+        "apprise".into(),
+        "apprise.yml".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Import(ImportAction::Apprise {
+            path: "apprise.yml".into()
+        })
+    );
+}
+
+#[test]
+fn parse_project_flag() {
+    let parsed = parse_args(vec![
+        "--project".into(),
+        "myservice".into(),
+        "--".into(),
+        "cargo".into(),
+        "test".into(),
+    ])
+    .unwrap();
+    let Action::Run(args) = parsed else {
+        panic!("expected a run");
+    };
+    assert_eq!(args.project.as_deref(), Some("myservice"));
+    assert_eq!(args.command, vec!["cargo", "test"]);
+}
+
+#[test]
+fn parse_session_flag() {
+    let parsed = parse_args(
+        ["--session", "auto", "--", "make", "deploy"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .unwrap();
+    let Action::Run(args) = parsed else {
+        panic!("expected a run");
+    };
+    assert_eq!(args.session.as_deref(), Some("auto"));
+    assert_eq!(args.command, vec!["make", "deploy"]);
+}
+
+#[test]
+fn parse_session_end() {
+    let parsed = parse_args(["session", "end"].map(String::from).to_vec()).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Session(SessionAction::End {
+            name: None,
+            channels: vec![]
+        })
+    );
+
+    let parsed = parse_args(
+        ["session", "end", "release", "--channel", "slack"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Session(SessionAction::End {
+            name: Some("release".to_string()),
+            channels: vec!["slack".to_string()]
         })
     );
 }
@@ -34,7 +206,31 @@ fn parse_channel_flags() {
 #[test]
 fn parse_channels_subcommand() {
     let parsed = parse_args(vec!["channels".into(), "validate".into()]).unwrap();
-    assert_eq!(parsed, Action::Channels(ChannelsAction::Validate));
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::Validate {
+            permissive: false,
+            probe: false,
+            auth: false
+        })
+    );
+
+    let parsed = parse_args(vec![
+        "channels".into(),
+        "validate".into(),
+        "--permissive".into(),
+        "--probe".into(),
+        "--auth".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::Validate {
+            permissive: true,
+            probe: true,
+            auth: true
+        })
+    );
 }
 
 #[test]
@@ -51,7 +247,9 @@ fn parse_double_dash_separator() {
         parsed,
         Action::Run(RunArgs {
             channels: vec!["desktop".into()],
-            command: vec!["echo".into(), "hello".into()]
+            command: vec!["echo".into(), "hello".into()],
+            separated: true,
+            ..Default::default()
         })
     );
 }
@@ -86,8 +284,558 @@ fn parse_config_defaults_to_path() {
     assert_eq!(parsed, Action::Config(ConfigAction::Path));
 }
 
+#[test]
+fn parse_status_subcommand() {
+    let parsed = parse_args(vec!["status".into()]).unwrap();
+    assert_eq!(parsed, Action::Status);
+}
+
+#[test]
+fn parse_doctor_subcommand() {
+    let parsed = parse_args(vec!["doctor".into()]).unwrap();
+    assert_eq!(parsed, Action::Doctor);
+}
+
 #[test]
 fn parse_config_path_subcommand() {
     let parsed = parse_args(vec!["config".into(), "path".into()]).unwrap();
     assert_eq!(parsed, Action::Config(ConfigAction::Path));
 }
+
+#[test]
+fn parse_repeat_flags() {
+    let parsed = parse_args(vec![
+        "--every".into(),
+        "1h30m".into(),
+        "--max-runs".into(),
+        "3".into(),
+        "curl".into(),
+        "-f".into(),
+        "https://example.com".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            every: Some(Duration::from_secs(90 * 60)),
+            max_runs: Some(3),
+            command: vec!["curl".into(), "-f".into(), "https://example.com".into()],
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn parse_invalid_interval_is_error() {
+    let error = parse_args(vec!["--every".into(), "soon".into(), "true".into()])
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("invalid duration"));
+}
+
+#[test]
+fn parse_max_runs_requires_every() {
+    assert!(parse_args(vec!["--max-runs".into(), "2".into(), "true".into()]).is_err());
+}
+
+#[test]
+fn parse_lock_flags() {
+    let parsed = parse_args(vec![
+        "--lock".into(),
+        "deploy".into(),
+        "--lock-mode".into(),
+        "fail".into(),
+        "./deploy.sh".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            lock: Some("deploy".into()),
+            lock_mode: LockMode::Fail,
+            command: vec!["./deploy.sh".into()],
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn parse_interactivity_flags() {
+    let parsed = parse_args(vec!["--force-pty".into(), "ssh".into(), "host".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            interactivity: Interactivity::Pty,
+            command: vec!["ssh".into(), "host".into()],
+            ..Default::default()
+        })
+    );
+
+    let parsed = parse_args(vec![
+        "run".into(),
+        "--assume-non-interactive".into(),
+        "--".into(),
+        "docker".into(),
+        "run".into(),
+        "-it".into(),
+        "alpine".into(),
+    ])
+    .unwrap();
+    assert!(matches!(
+        parsed,
+        Action::Run(RunArgs {
+            interactivity: Interactivity::NonInteractive,
+            ..
+        })
+    ));
+
+    assert!(
+        parse_args(vec![
+            "--force-pty".into(),
+            "--assume-non-interactive".into(),
+            "ssh".into(),
+        ])
+        .is_err()
+    );
+}
+
+#[test]
+fn parse_color_flag_anywhere_before_the_command() {
+    let invocation = parse_invocation(vec!["make".into()]).unwrap();
+    assert_eq!(invocation.color, ColorChoice::Auto);
+
+    let invocation = parse_invocation(vec![
+        "--color".into(),
+        "never".into(),
+        "make".into(),
+        "--color".into(),
+        "always".into(),
+    ])
+    .unwrap();
+    assert_eq!(invocation.color, ColorChoice::Never);
+    assert_eq!(
+        invocation.action,
+        Action::Run(RunArgs {
+            command: vec!["make".into(), "--color".into(), "always".into()],
+            ..Default::default()
+        })
+    );
+
+    let invocation = parse_invocation(vec![
+        "history".into(),
+        "list".into(),
+        "--color=always".into(),
+    ])
+    .unwrap();
+    assert_eq!(invocation.color, ColorChoice::Always);
+    assert_eq!(
+        invocation.action,
+        Action::History(HistoryAction::List {
+            fingerprint: None,
+            session: None
+        })
+    );
+
+    let invocation = parse_invocation(vec![
+        "run".into(),
+        "--color".into(),
+        "never".into(),
+        "--".into(),
+        "ls".into(),
+    ])
+    .unwrap();
+    assert_eq!(invocation.color, ColorChoice::Never);
+
+    assert!(parse_invocation(vec!["--color".into(), "sometimes".into(), "ls".into()]).is_err());
+}
+
+#[test]
+fn parse_template_render() {
+    let parsed = parse_args(vec![
+        "template".into(),
+        "render".into(),
+        "slack".into(),
+        "--exit-code".into(),
+        "2".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Template(TemplateAction::Render {
+            target: "slack".into(),
+            event: None,
+            exit_code: Some(2),
+        })
+    );
+}
+
+#[test]
+fn parse_secret_subcommands() {
+    let parsed = parse_args(vec!["secret".into(), "edit".into(), "slack-url".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Secret(SecretAction::Edit {
+            name: "slack-url".into()
+        })
+    );
+
+    assert!(parse_args(vec!["secret".into(), "decrypt".into()]).is_err());
+}
+
+#[test]
+fn parse_run_option_overrides() {
+    let parsed = parse_args(vec![
+        "--min-duration".into(),
+        "2m".into(),
+        "--notify-on=failure".into(),
+        "make".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            min_duration: Some(Duration::from_secs(120)),
+            notify_on: Some(NotifyOn::Failure),
+            command: vec!["make".into()],
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn env_overrides_fill_unset_options() {
+    let env = |name: &str| match name {
+        "BRB_CHANNELS" => Some("slack, desktop".to_string()),
+        "BRB_MIN_DURATION" => Some("45s".to_string()),
+        "BRB_NOTIFY_ON" => Some("change".to_string()),
+        "BRB_SESSION" => Some(" release ".to_string()),
+        _ => None,
+    };
+
+    let mut args = RunArgs::default();
+    apply_env_overrides(&mut args, env).unwrap();
+    assert_eq!(
+        args.channels,
+        vec!["slack".to_string(), "desktop".to_string()]
+    );
+    assert_eq!(args.min_duration, Some(Duration::from_secs(45)));
+    assert_eq!(args.notify_on, Some(NotifyOn::Change));
+    assert_eq!(args.session.as_deref(), Some("release"));
+
+    let mut args = RunArgs {
+        channels: vec!["phone".into()],
+        min_duration: Some(Duration::from_secs(5)),
+        notify_on: Some(NotifyOn::Always),
+        ..Default::default()
+    };
+    apply_env_overrides(&mut args, env).unwrap();
+    assert_eq!(args.channels, vec!["phone".to_string()]);
+    assert_eq!(args.min_duration, Some(Duration::from_secs(5)));
+    assert_eq!(args.notify_on, Some(NotifyOn::Always));
+
+    let mut args = RunArgs {
+        all_channels: true,
+        ..Default::default()
+    };
+    apply_env_overrides(&mut args, env).unwrap();
+    assert!(args.channels.is_empty());
+}
+
+#[test]
+fn invalid_env_override_names_the_variable() {
+    let mut args = RunArgs::default();
+    let error = apply_env_overrides(&mut args, |name| {
+        (name == "BRB_NOTIFY_ON").then(|| "sometimes".to_string())
+    })
+    .unwrap_err();
+    assert!(error.to_string().starts_with("invalid BRB_NOTIFY_ON: "));
+}
+
+#[test]
+fn parse_run_subcommand() {
+    let parsed = parse_args(vec![
+        "run".into(),
+        "@slack".into(),
+        "--every".into(),
+        "5m".into(),
+        "--".into(),
+        "cargo".into(),
+        "test".into(),
+        "--release".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            channels: vec!["slack".into()],
+            every: Some(Duration::from_secs(300)),
+            command: vec!["cargo".into(), "test".into(), "--release".into()],
+            separated: true,
+            ..Default::default()
+        })
+    );
+
+    assert!(matches!(
+        parse_args(vec!["run".into()]),
+        Err(brb_cli::cli::CliError::MissingCommand)
+    ));
+}
+
+#[test]
+fn parse_bench_subcommand() {
+    let parsed = parse_args(vec![
+        "bench".into(),
+        "-n".into(),
+        "5".into(),
+        "@slack".into(),
+        "--report".into(),
+        "bench.json".into(),
+        "--".into(),
+        "cargo".into(),
+        "build".into(),
+    ])
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Bench {
+            runs: 5,
+            args: RunArgs {
+                channels: vec!["slack".into()],
+                report: Some("bench.json".into()),
+                command: vec!["cargo".into(), "build".into()],
+                separated: true,
+                ..Default::default()
+            },
+        }
+    );
+
+    let parsed = parse_args(vec!["bench".into(), "make".into()]).unwrap();
+    assert!(matches!(parsed, Action::Bench { runs: 10, .. }));
+
+    assert!(parse_args(vec!["bench".into(), "-n".into(), "0".into(), "make".into()]).is_err());
+    assert!(matches!(
+        parse_args(vec![
+            "bench".into(),
+            "--every".into(),
+            "1m".into(),
+            "make".into()
+        ]),
+        Err(brb_cli::cli::CliError::BenchWithEvery)
+    ));
+}
+
+#[test]
+fn parse_command_named_run_after_separator() {
+    let parsed = parse_args(vec!["--".into(), "run".into(), "--fast".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Run(RunArgs {
+            command: vec!["run".into(), "--fast".into()],
+            separated: true,
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn parse_version_json() {
+    assert_eq!(
+        parse_args(vec!["--version".into()]).unwrap(),
+        Action::Version { json: false }
+    );
+    assert_eq!(
+        parse_args(vec!["-V".into(), "--json".into()]).unwrap(),
+        Action::Version { json: true }
+    );
+    assert!(parse_args(vec!["--version".into(), "--yaml".into()]).is_err());
+}
+
+#[test]
+fn parse_config_migrate_path_subcommand() {
+    let parsed = parse_args(vec!["config".into(), "migrate-path".into()]).unwrap();
+    assert_eq!(parsed, Action::Config(ConfigAction::MigratePath));
+}
+
+#[test]
+fn parse_channels_preview() {
+    let parsed = parse_args(
+        [
+            "channels",
+            "preview",
+            "desktop",
+            "--status",
+            "failure",
+            "--duration",
+            "95m",
+        ]
+        .map(String::from)
+        .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::Preview {
+            channel_id: "desktop".to_string(),
+            status: PreviewStatus::Failure,
+            duration: Some(Duration::from_secs(95 * 60)),
+        })
+    );
+
+    let parsed = parse_args(
+        ["channels", "preview", "desktop"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::Preview {
+            channel_id: "desktop".to_string(),
+            status: PreviewStatus::Success,
+            duration: None,
+        })
+    );
+    assert!(
+        parse_args(
+            ["channels", "preview", "desktop", "--status", "flaky"]
+                .map(String::from)
+                .to_vec()
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn parse_channels_render() {
+    let parsed = parse_args(
+        ["channels", "render", "slack", "--status", "failure"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::Render {
+            channel_id: "slack".to_string(),
+            status: PreviewStatus::Failure,
+        })
+    );
+}
+
+#[test]
+fn parse_channels_docs() {
+    let parsed = parse_args(["channels", "docs", "ntfy"].map(String::from).to_vec()).unwrap();
+    assert_eq!(
+        parsed,
+        Action::Channels(ChannelsAction::Docs {
+            channel_type: "ntfy".to_string(),
+        })
+    );
+    assert!(parse_args(["channels", "docs"].map(String::from).to_vec()).is_err());
+}
+
+#[test]
+fn parse_history_subcommands() {
+    let parsed = parse_args(vec!["history".into()]).unwrap();
+    assert_eq!(
+        parsed,
+        Action::History(HistoryAction::List {
+            fingerprint: None,
+            session: None
+        })
+    );
+
+    let parsed = parse_args(
+        ["history", "list", "--fingerprint", "9f3c2a1b0d4e5f60"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::History(HistoryAction::List {
+            fingerprint: Some("9f3c2a1b0d4e5f60".to_string()),
+            session: None
+        })
+    );
+
+    let parsed = parse_args(
+        ["history", "list", "--session", "release"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::History(HistoryAction::List {
+            fingerprint: None,
+            session: Some("release".to_string())
+        })
+    );
+
+    let parsed = parse_args(
+        [
+            "history",
+            "resend",
+            "a39daccb",
+            "--channel",
+            "slack",
+            "--channel",
+            "ci",
+        ]
+        .map(String::from)
+        .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::History(HistoryAction::Resend {
+            run_id: "a39daccb".to_string(),
+            channels: vec!["slack".to_string(), "ci".to_string()],
+        })
+    );
+
+    let parsed = parse_args(["history", "show", "a39d"].map(String::from).to_vec()).unwrap();
+    assert_eq!(
+        parsed,
+        Action::History(HistoryAction::Show {
+            run_id: "a39d".to_string()
+        })
+    );
+}
+
+#[test]
+fn parse_annotate() {
+    let parsed = parse_args(
+        ["annotate", "a39daccb", "--note", "root cause: OOM"]
+            .map(String::from)
+            .to_vec(),
+    )
+    .unwrap();
+    assert_eq!(
+        parsed,
+        Action::Annotate {
+            run_id: "a39daccb".to_string(),
+            note: "root cause: OOM".to_string(),
+            post: false,
+            channels: vec![],
+        }
+    );
+
+    // naming a channel posts the note there.
+    let parsed = parse_args(
+        [
+            "annotate",
+            "a39daccb",
+            "--note",
+            "fixed",
+            "--channel",
+            "slack",
+        ]
+        .map(String::from)
+        .to_vec(),
+    )
+    .unwrap();
+    assert!(matches!(parsed, Action::Annotate { post: true, .. }));
+    assert!(parse_args(["annotate", "a39daccb"].map(String::from).to_vec()).is_err());
+}
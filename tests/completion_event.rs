@@ -0,0 +1,89 @@
+use brb_cli::environment::FixedEnvironment;
+use brb_cli::event::{CompletionEvent, new_run_id};
+use brb_cli::format::idempotency_key;
+use brb_cli::runner::{RunOptions, run_command_in};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+fn fixed_environment() -> FixedEnvironment {
+    FixedEnvironment {
+        now: chrono::DateTime::parse_from_rfc3339("2026-02-22T12:00:00Z")
+            .unwrap()
+            .to_utc(),
+        hostname: "build-01".to_string(),
+        current_dir: Some(PathBuf::from("/work/api")),
+        vars: BTreeMap::new(),
+        run_id: "0b8f5b1e-5a4c-4d1e-9a57-3f2a1c9e7d10".to_string(),
+    }
+}
+
+#[test]
+fn run_ids_are_random_v4_uuids() {
+    let first = new_run_id();
+    let second = new_run_id();
+
+    assert_ne!(first, second);
+    let groups = first.split('-').map(str::len).collect::<Vec<_>>();
+    assert_eq!(groups, vec![8, 4, 4, 4, 12]);
+    assert_eq!(&first[14..15], "4");
+    assert!(matches!(&first[19..20], "8" | "9" | "a" | "b"));
+}
+
+#[test]
+fn idempotency_key_is_stable_per_run_and_channel() {
+    let event = CompletionEvent::test_event();
+
+    assert_eq!(
+        idempotency_key(&event, "ci-webhook"),
+        idempotency_key(&event, "ci-webhook")
+    );
+    assert_ne!(
+        idempotency_key(&event, "ci-webhook"),
+        idempotency_key(&event, "team-chat")
+    );
+    assert!(idempotency_key(&event, "ci-webhook").starts_with(&event.run_id));
+}
+
+#[test]
+fn posted_notes_get_their_own_idempotency_key() {
+    let mut event = CompletionEvent::test_event();
+    let original = idempotency_key(&event, "ci-webhook");
+
+    event.notes.push("root cause: OOM".to_string());
+    let first_note = idempotency_key(&event, "ci-webhook");
+    event.notes.push("fixed in #42".to_string());
+
+    assert_ne!(first_note, original);
+    assert_ne!(idempotency_key(&event, "ci-webhook"), first_note);
+    assert!(first_note.starts_with(&original));
+}
+
+#[test]
+fn events_from_a_fixed_environment_are_reproducible() {
+    let environment = fixed_environment();
+
+    let first = serde_json::to_value(CompletionEvent::test_event_in(&environment)).unwrap();
+    let second = serde_json::to_value(CompletionEvent::test_event_in(&environment)).unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(first["run_id"], "0b8f5b1e-5a4c-4d1e-9a57-3f2a1c9e7d10");
+    assert_eq!(first["host"], "build-01");
+    assert_eq!(first["cwd"], "/work/api");
+    assert_eq!(first["started_at"], "2026-02-22T12:00:00.000Z");
+    assert_eq!(first["finished_at"], "2026-02-22T12:00:00.000Z");
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_take_their_timestamps_from_the_environment() {
+    let environment = fixed_environment();
+    let command = vec!["true".to_string()];
+
+    let run = run_command_in(&command, &RunOptions::default(), &environment);
+    let event = CompletionEvent::from_run_in(&run, &environment);
+
+    assert_eq!(run.started_at, environment.now);
+    assert_eq!(run.finished_at, environment.now);
+    assert_eq!(event.exit_code, 0);
+    assert_eq!(event.cwd, "/work/api");
+}
@@ -1,4 +1,5 @@
 use brb_cli::config::{ChannelConfig, load_config_from_path};
+use brb_cli::event::host_name;
 use std::fs;
 use tempfile::TempDir;
 
@@ -21,11 +22,11 @@ channels:
 
     let config = load_config_from_path(&config_path).unwrap();
     let channel = config.channels.get("ci-webhook").unwrap();
-    let ChannelConfig::Webhook(webhook) = channel else {
+    let ChannelConfig::Webhook(webhook) = &channel.kind else {
         panic!("expected webhook channel");
     };
-    assert!(webhook.url.starts_with("https://example.com/hook?token="));
-    assert!(!webhook.url.contains("${env:PATH}"));
+    assert!(webhook.url[0].starts_with("https://example.com/hook?token="));
+    assert!(!webhook.url[0].contains("${env:PATH}"));
 }
 
 #[test]
@@ -69,3 +70,38 @@ channels:
     let error = load_config_from_path(&config_path).unwrap_err().to_string();
     assert!(error.contains("invalid environment interpolation expression"));
 }
+
+#[test]
+fn interpolates_builtin_variables() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.yml");
+    fs::write(
+        &config_path,
+        r#"
+version: 1
+default_channels: [ci-webhook]
+channels:
+  ci-webhook:
+    type: webhook
+    url: https://example.com/hook/${os}
+    extra:
+      machine: ${hostname}
+      owner: ${user}@${hostname}
+      literal: ${other}
+"#,
+    )
+    .unwrap();
+
+    let config = load_config_from_path(&config_path).unwrap();
+    let ChannelConfig::Webhook(webhook) = &config.channels["ci-webhook"].kind else {
+        panic!("expected webhook channel");
+    };
+    assert_eq!(
+        webhook.url[0],
+        format!("https://example.com/hook/{}", std::env::consts::OS)
+    );
+    assert_eq!(webhook.extra["machine"], host_name());
+    assert!(webhook.extra["owner"].ends_with(&format!("@{}", host_name())));
+    assert!(!webhook.extra["owner"].contains("${user}"));
+    assert_eq!(webhook.extra["literal"], "${other}");
+}
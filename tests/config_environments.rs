@@ -0,0 +1,162 @@
+use brb_cli::config::{
+    ChannelConfig, LoadOptions, NotifyOn, RunEnvironment, load_config_from_path_with,
+};
+use brb_cli::environment::FixedEnvironment;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"version: 1
+default_channels: [desktop]
+channels:
+  desktop: { type: desktop }
+  team: { type: webhook, url: "https://example.com/hook" }
+environments:
+  ci:
+    default_channels: [team]
+    channels:
+      team:
+        notify_on: failure
+        format: slack
+  interactive:
+    min_duration: 30s
+"#;
+
+fn load(raw: &str, environment: RunEnvironment) -> Result<brb_cli::config::Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(&path, raw).unwrap();
+    let options = LoadOptions {
+        environment: Some(environment),
+        ..LoadOptions::default()
+    };
+    load_config_from_path_with(&path, options)
+        .map(|loaded| loaded.config)
+        .map_err(|error| error.to_string())
+}
+
+fn environment(vars: &[(&str, &str)]) -> FixedEnvironment {
+    FixedEnvironment {
+        now: chrono::Utc::now(),
+        hostname: "build-01".to_string(),
+        current_dir: Some(PathBuf::from("/work")),
+        vars: vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        run_id: "run".to_string(),
+    }
+}
+
+#[test]
+fn the_matching_block_overrides_the_rest() {
+    let ci = load(CONFIG, RunEnvironment::Ci).unwrap();
+    assert_eq!(ci.default_channels, ["team"]);
+    let team = &ci.channels["team"];
+    assert_eq!(team.notify_on, NotifyOn::Failure);
+    let ChannelConfig::Webhook(webhook) = &team.kind else {
+        panic!("expected webhook channel");
+    };
+    assert_eq!(webhook.url, ["https://example.com/hook"]);
+    assert!(ci.min_duration.is_none());
+
+    let interactive = load(CONFIG, RunEnvironment::Interactive).unwrap();
+    assert_eq!(interactive.default_channels, ["desktop"]);
+    assert!(interactive.min_duration.is_some());
+    assert_eq!(interactive.channels["team"].notify_on, NotifyOn::Always);
+
+    let unattended = load(CONFIG, RunEnvironment::Unattended).unwrap();
+    assert_eq!(unattended.default_channels, ["desktop"]);
+    assert!(unattended.min_duration.is_none());
+}
+
+#[test]
+fn unknown_environments_are_rejected() {
+    let error = load(
+        "version: 1\ndefault_channels: [desktop]\nchannels:\n  desktop: { type: desktop }\nenvironments:\n  interactiv:\n    min_duration: 30s\n",
+        RunEnvironment::Interactive,
+    )
+    .unwrap_err();
+    assert!(
+        error.contains(
+            "unknown environment `interactiv` (expected one of ci, interactive, unattended)"
+        ),
+        "{error}"
+    );
+    assert!(error.contains("line 6"), "{error}");
+}
+
+#[test]
+fn every_block_is_checked_whichever_applies() {
+    let raw = CONFIG.replace("        format: slack\n", "        fromat: slack\n");
+    let error = load(&raw, RunEnvironment::Unattended).unwrap_err();
+    assert!(error.contains("with environments.ci: "), "{error}");
+    assert!(error.contains("unknown field `fromat`"), "{error}");
+    assert!(error.contains("did you mean `format`?"), "{error}");
+}
+
+#[test]
+fn errors_outside_the_blocks_keep_their_position() {
+    let raw = CONFIG.replace(
+        "default_channels: [desktop]\n",
+        "default_channel: [desktop]\n",
+    );
+    let error = load(&raw, RunEnvironment::Ci).unwrap_err();
+    assert!(error.contains("unknown field `default_channel`"), "{error}");
+    assert!(error.contains("line 2, column 1"), "{error}");
+    assert!(!error.contains("environments"), "{error}");
+}
+
+#[test]
+fn every_block_is_validated_whichever_applies() {
+    let raw = CONFIG.replace(
+        "    default_channels: [team]\n",
+        "    default_channels: [tema]\n",
+    );
+    for environment in [RunEnvironment::Interactive, RunEnvironment::Ci] {
+        let error = load(&raw, environment).unwrap_err();
+        assert!(
+            error.contains("with environments.ci: default channel `tema`"),
+            "{error}"
+        );
+        assert!(error.contains("line 8, column 24"), "{error}");
+        assert!(error.contains("did you mean `team`?"), "{error}");
+    }
+
+    let raw = CONFIG.replace(
+        "default_channels: [desktop]\n",
+        "default_channels: [dekstop]\n",
+    );
+    let error = load(&raw, RunEnvironment::Ci).unwrap_err();
+    assert!(error.contains("default channel `dekstop`"), "{error}");
+    assert!(error.contains("line 2"), "{error}");
+    assert!(!error.contains("environments"), "{error}");
+}
+
+#[test]
+fn detects_ci_then_a_terminal() {
+    let detect = |vars: &[(&str, &str)], terminal| {
+        RunEnvironment::detect_in(&environment(vars), terminal).unwrap()
+    };
+
+    assert_eq!(detect(&[("CI", "true")], true), RunEnvironment::Ci);
+    assert_eq!(detect(&[("GITLAB_CI", "true")], false), RunEnvironment::Ci);
+    assert_eq!(
+        detect(&[("CI", "false")], true),
+        RunEnvironment::Interactive
+    );
+    assert_eq!(detect(&[], false), RunEnvironment::Unattended);
+    assert_eq!(
+        detect(&[("CI", "true"), ("BRB_ENVIRONMENT", "interactive")], false),
+        RunEnvironment::Interactive
+    );
+
+    let error = RunEnvironment::detect_in(&environment(&[("BRB_ENVIRONMENT", "laptop")]), true)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("BRB_ENVIRONMENT is `laptop` (expected one of ci, interactive, unattended)"),
+        "{error}"
+    );
+}
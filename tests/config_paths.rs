@@ -0,0 +1,73 @@
+use brb_cli::config::{MigrateStatus, migrate_config, xdg_dir};
+use std::ffi::OsString;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn xdg_dir_uses_absolute_values_only() {
+    let fallback = Path::new("/home/me/.config");
+    let absolute = TempDir::new().unwrap();
+
+    assert_eq!(
+        xdg_dir(Some(absolute.path().as_os_str().to_owned()), fallback),
+        absolute.path()
+    );
+    assert_eq!(xdg_dir(None, fallback), fallback);
+    assert_eq!(xdg_dir(Some(OsString::new()), fallback), fallback);
+    assert_eq!(xdg_dir(Some("relative/config".into()), fallback), fallback);
+}
+
+#[test]
+fn migrates_the_first_legacy_config_found() {
+    let temp = TempDir::new().unwrap();
+    let current = temp.path().join("xdg").join("brb").join("config.yml");
+    let missing = temp.path().join("missing").join("config.yml");
+    let legacy = temp.path().join("legacy").join("config.yml");
+    fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+    fs::write(&legacy, "version: 1\n").unwrap();
+
+    let status = migrate_config(&current, &[missing, legacy.clone()]).unwrap();
+
+    assert_eq!(
+        status,
+        MigrateStatus::Moved {
+            from: legacy.clone(),
+            to: current.clone()
+        }
+    );
+    assert_eq!(fs::read_to_string(&current).unwrap(), "version: 1\n");
+    assert!(!legacy.exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&current).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn never_overwrites_an_existing_config() {
+    let temp = TempDir::new().unwrap();
+    let current = temp.path().join("config.yml");
+    let legacy = temp.path().join("config.yaml");
+    fs::write(&current, "current").unwrap();
+    fs::write(&legacy, "legacy").unwrap();
+
+    let status = migrate_config(&current, std::slice::from_ref(&legacy)).unwrap();
+
+    assert_eq!(status, MigrateStatus::AlreadyCurrent(current.clone()));
+    assert_eq!(fs::read_to_string(&current).unwrap(), "current");
+    assert!(legacy.exists());
+}
+
+#[test]
+fn reports_when_there_is_nothing_to_migrate() {
+    let temp = TempDir::new().unwrap();
+    let current = temp.path().join("config.yml");
+
+    let status = migrate_config(&current, &[temp.path().join("old.yml")]).unwrap();
+
+    assert_eq!(status, MigrateStatus::NothingFound);
+    assert!(!current.exists());
+}
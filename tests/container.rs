@@ -0,0 +1,150 @@
+use brb_cli::container::{ContainerInfo, detect_container_in};
+use brb_cli::environment::FixedEnvironment;
+use brb_cli::event::CompletionEvent;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const ID: &str = "2f6a1c9b12aa4d0e8c3b5f7a9e1d2c4b6a8f0e2d4c6b8a0f2e4d6c8b0a2f4e6d";
+
+fn environment(vars: &[(&str, &str)]) -> FixedEnvironment {
+    FixedEnvironment {
+        now: chrono::Utc::now(),
+        hostname: "2f6a1c9b12aa".to_string(),
+        current_dir: None,
+        vars: vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        run_id: String::new(),
+    }
+}
+
+fn write(root: &Path, path: &str, contents: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+#[test]
+fn plain_hosts_have_no_container() {
+    let root = TempDir::new().unwrap();
+    write(
+        root.path(),
+        "proc/self/cgroup",
+        "0::/user.slice/session-2.scope\n",
+    );
+    assert_eq!(detect_container_in(&environment(&[]), root.path()), None);
+}
+
+#[test]
+fn detects_docker_and_its_id() {
+    let root = TempDir::new().unwrap();
+    write(root.path(), ".dockerenv", "");
+    write(root.path(), "proc/self/cgroup", "0::/\n");
+    write(
+        root.path(),
+        "proc/self/mountinfo",
+        &format!(
+            "612 540 0:52 / / rw - overlay overlay rw,upperdir=/var/lib/docker/overlay2/{}/diff\n\
+             629 612 254:1 /var/lib/docker/containers/{ID}/hostname /etc/hostname rw - ext4\n",
+            "9".repeat(64)
+        ),
+    );
+
+    let container = detect_container_in(&environment(&[]), root.path()).unwrap();
+    assert_eq!(container.runtime, "docker");
+    assert_eq!(container.id.as_deref(), Some("2f6a1c9b12aa"));
+
+    let root = TempDir::new().unwrap();
+    write(
+        root.path(),
+        "proc/self/cgroup",
+        &format!("12:pids:/docker/{ID}\n"),
+    );
+    let container = detect_container_in(&environment(&[]), root.path()).unwrap();
+    assert_eq!(container.runtime, "docker");
+    assert_eq!(container.id.as_deref(), Some("2f6a1c9b12aa"));
+}
+
+#[test]
+fn detects_podman_from_containerenv() {
+    let root = TempDir::new().unwrap();
+    write(
+        root.path(),
+        "run/.containerenv",
+        &format!(
+            "engine=\"podman-5.2.0\"\nname=\"nightly-etl\"\nid=\"{ID}\"\nimage=\"localhost/etl:latest\"\n"
+        ),
+    );
+
+    let container = detect_container_in(&environment(&[]), root.path()).unwrap();
+    assert_eq!(
+        container,
+        ContainerInfo {
+            runtime: "podman".to_string(),
+            id: Some("2f6a1c9b12aa".to_string()),
+            name: Some("nightly-etl".to_string()),
+            image: Some("localhost/etl:latest".to_string()),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn detects_kubernetes_pods() {
+    let root = TempDir::new().unwrap();
+    write(
+        root.path(),
+        "var/run/secrets/kubernetes.io/serviceaccount/namespace",
+        "staging\n",
+    );
+
+    let container = detect_container_in(
+        &environment(&[
+            ("KUBERNETES_SERVICE_HOST", "10.0.0.1"),
+            ("HOSTNAME", "migrate-7d9f5-x2k4q"),
+            ("NODE_NAME", "node-3"),
+        ]),
+        root.path(),
+    )
+    .unwrap();
+    assert_eq!(container.runtime, "kubernetes");
+    assert_eq!(container.pod.as_deref(), Some("migrate-7d9f5-x2k4q"));
+    assert_eq!(container.namespace.as_deref(), Some("staging"));
+    assert_eq!(container.node.as_deref(), Some("node-3"));
+}
+
+#[test]
+fn host_labels_name_the_container() {
+    let mut event = CompletionEvent::test_event();
+    event.host = "2f6a1c9b12aa".to_string();
+    assert_eq!(event.host_label(), "2f6a1c9b12aa");
+
+    event.container = Some(ContainerInfo {
+        runtime: "docker".to_string(),
+        id: Some("2f6a1c9b12aa".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(event.host_label(), "2f6a1c9b12aa (docker container)");
+
+    event.container = Some(ContainerInfo {
+        runtime: "podman".to_string(),
+        id: Some("2f6a1c9b12aa".to_string()),
+        name: Some("nightly-etl".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(
+        event.host_label(),
+        "nightly-etl (podman container 2f6a1c9b12aa)"
+    );
+
+    event.container = Some(ContainerInfo {
+        runtime: "kubernetes".to_string(),
+        pod: Some("migrate-7d9f5-x2k4q".to_string()),
+        namespace: Some("staging".to_string()),
+        ..Default::default()
+    });
+    assert_eq!(event.host_label(), "migrate-7d9f5-x2k4q (pod in staging)");
+}
@@ -0,0 +1,141 @@
+use brb_cli::channels::{Interaction, desktop_notification};
+use brb_cli::config::{ChannelConfig, Config, DesktopChannel, load_config_from_path};
+use brb_cli::dbus::Signal;
+use brb_cli::event::CompletionEvent;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[cfg(all(target_os = "linux", feature = "desktop"))]
+#[test]
+fn parses_notification_signals() {
+    use brb_cli::dbus::{NOTIFICATIONS, OBJECT_PATH, parse_signal};
+    use zbus::Message;
+
+    let signal = |interface: &str, member: &str, body: &(u32, &str)| {
+        Message::signal(OBJECT_PATH, interface, member)
+            .unwrap()
+            .build(body)
+            .unwrap()
+    };
+
+    assert_eq!(
+        parse_signal(&signal(NOTIFICATIONS, "ActionInvoked", &(42, "rerun"))),
+        Some(Signal::ActionInvoked {
+            id: 42,
+            action: "rerun".to_string()
+        })
+    );
+    let closed = Message::signal(OBJECT_PATH, NOTIFICATIONS, "NotificationClosed")
+        .unwrap()
+        .build(&(42u32, 2u32))
+        .unwrap();
+    assert_eq!(
+        parse_signal(&closed),
+        Some(Signal::Closed { id: 42, reason: 2 })
+    );
+    assert_eq!(
+        parse_signal(&signal(NOTIFICATIONS, "ActivationToken", &(42, "token"))),
+        None
+    );
+    assert_eq!(
+        parse_signal(&signal(
+            "org.example.Other",
+            "ActionInvoked",
+            &(42, "rerun")
+        )),
+        None
+    );
+}
+
+#[test]
+fn maps_signals_to_interactions() {
+    let action = |action: &str| Signal::ActionInvoked {
+        id: 1,
+        action: action.to_string(),
+    };
+    let closed = |reason| Signal::Closed { id: 1, reason };
+
+    assert_eq!(Interaction::from_signal(None), Interaction::Unanswered);
+    assert_eq!(
+        Interaction::from_signal(Some(&action("default"))),
+        Interaction::Clicked
+    );
+    assert_eq!(
+        Interaction::from_signal(Some(&action("rerun"))),
+        Interaction::Rerun
+    );
+    assert_eq!(
+        Interaction::from_signal(Some(&closed(1))),
+        Interaction::Expired
+    );
+    assert_eq!(
+        Interaction::from_signal(Some(&closed(2))),
+        Interaction::Dismissed
+    );
+    assert_eq!(
+        Interaction::from_signal(Some(&closed(3))),
+        Interaction::Closed
+    );
+}
+
+#[test]
+fn desktop_notification_mirrors_channel_settings() {
+    let config = Config::default();
+    let desktop = DesktopChannel {
+        group: true,
+        dismiss_success_after: Some(Duration::from_secs(5)),
+        wait: Some(Duration::from_secs(60)),
+        rerun: true,
+        ..DesktopChannel::default()
+    };
+    let event = CompletionEvent::test_event();
+
+    let notification =
+        desktop_notification(&config, &desktop, &event, "title".into(), "body".into());
+    assert_eq!(notification.urgency, 1);
+    assert_eq!(notification.expire_timeout, 5000);
+    assert!(notification.transient);
+    assert_eq!(
+        notification.actions,
+        vec![
+            ("default".to_string(), "brb".to_string()),
+            ("rerun".to_string(), "Re-run".to_string()),
+        ]
+    );
+    assert_eq!(notification.hints.len(), 2);
+
+    let mut failed = event.clone();
+    failed.exit_code = 1;
+    let notification =
+        desktop_notification(&config, &desktop, &failed, "title".into(), "body".into());
+    assert_eq!(notification.urgency, 1);
+    assert_eq!(notification.expire_timeout, 0);
+    assert!(!notification.transient);
+}
+
+#[test]
+fn rerun_requires_wait() {
+    let load = |desktop: &str| {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("config.yml");
+        fs::write(
+            &path,
+            format!(
+                "version: 1\ndefault_channels: [desktop]\nchannels:\n  desktop:\n    type: desktop\n{desktop}"
+            ),
+        )
+        .unwrap();
+        load_config_from_path(&path).map_err(|error| error.to_string())
+    };
+
+    let config = load("    wait: 2m\n    rerun: true\n").unwrap();
+    let ChannelConfig::Desktop(desktop) = &config.channels["desktop"].kind else {
+        panic!("expected desktop channel");
+    };
+    assert_eq!(desktop.wait, Some(Duration::from_secs(120)));
+    assert!(desktop.rerun);
+
+    let error = load("    rerun: true\n").unwrap_err();
+    assert!(error.contains("sets rerun without wait"), "{error}");
+}
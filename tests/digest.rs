@@ -0,0 +1,92 @@
+use brb_cli::config::Config;
+use brb_cli::digest::{DigestDecision, apply_digest};
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::notification_body;
+use brb_cli::state::StateStore;
+use chrono::{TimeDelta, Utc};
+use std::time::Duration;
+use tempfile::TempDir;
+
+const WINDOW: Duration = Duration::from_secs(600);
+
+fn event(run_id: &str, exit_code: i32) -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.run_id = run_id.to_string();
+    event.exit_code = exit_code;
+    event.status = if exit_code == 0 { "success" } else { "failure" }.to_string();
+    event
+}
+
+#[test]
+fn first_completion_is_sent_immediately() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+
+    let decision = apply_digest(&store, "slack", WINDOW, &event("a", 0), Utc::now()).unwrap();
+    let DigestDecision::Send(sent) = decision else {
+        panic!("expected first completion to be sent");
+    };
+    assert_eq!(sent.run_id, "a");
+    assert!(sent.digest.is_empty());
+}
+
+#[test]
+fn completions_inside_window_are_held_then_flushed() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let now = Utc::now();
+
+    apply_digest(&store, "slack", WINDOW, &event("a", 0), now).unwrap();
+    let held_b = apply_digest(&store, "slack", WINDOW, &event("b", 0), now).unwrap();
+    let held_c = apply_digest(&store, "slack", WINDOW, &event("c", 0), now).unwrap();
+    assert!(matches!(held_b, DigestDecision::Hold));
+    assert!(matches!(held_c, DigestDecision::Hold));
+
+    let later = now + TimeDelta::minutes(11);
+    let DigestDecision::Send(sent) =
+        apply_digest(&store, "slack", WINDOW, &event("d", 0), later).unwrap()
+    else {
+        panic!("expected completion after the window to be sent");
+    };
+    assert_eq!(sent.run_id, "d");
+    let held = sent.digest.iter().map(|entry| entry.run_id.as_str());
+    assert_eq!(held.collect::<Vec<_>>(), ["b", "c"]);
+
+    let json = serde_json::to_value(&*sent).unwrap();
+    assert_eq!(json["digest"][0]["status"], "success");
+
+    let body = notification_body(&Config::default(), &sent);
+    assert!(body.contains("- ok brb channels test"));
+}
+
+#[test]
+fn failures_inside_window_are_sent_with_held_completions() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let now = Utc::now();
+
+    apply_digest(&store, "slack", WINDOW, &event("a", 0), now).unwrap();
+    apply_digest(&store, "slack", WINDOW, &event("b", 0), now).unwrap();
+    let DigestDecision::Send(sent) =
+        apply_digest(&store, "slack", WINDOW, &event("c", 1), now).unwrap()
+    else {
+        panic!("expected failure inside the window to be sent");
+    };
+    assert_eq!(sent.run_id, "c");
+    let held = sent.digest.iter().map(|entry| entry.run_id.as_str());
+    assert_eq!(held.collect::<Vec<_>>(), ["b"]);
+
+    let after = apply_digest(&store, "slack", WINDOW, &event("d", 0), now).unwrap();
+    assert!(matches!(after, DigestDecision::Hold));
+}
+
+#[test]
+fn digest_state_is_per_channel() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let now = Utc::now();
+
+    apply_digest(&store, "slack", WINDOW, &event("a", 0), now).unwrap();
+    let other = apply_digest(&store, "discord", WINDOW, &event("b", 0), now).unwrap();
+    assert!(matches!(other, DigestDecision::Send(_)));
+}
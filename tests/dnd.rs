@@ -0,0 +1,68 @@
+use brb_cli::config::{WhenDnd, load_config_from_path};
+use brb_cli::dnd::{queue_for_dnd, release_dnd_queue};
+use brb_cli::event::CompletionEvent;
+use brb_cli::state::StateStore;
+use std::fs;
+use tempfile::TempDir;
+
+fn event(run_id: &str) -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.run_id = run_id.to_string();
+    event
+}
+
+#[test]
+fn queued_completions_are_released_once() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+
+    queue_for_dnd(&store, "desktop", &event("a")).unwrap();
+    queue_for_dnd(&store, "desktop", &event("b")).unwrap();
+
+    let released = release_dnd_queue(&store, "desktop", &event("c")).unwrap();
+    assert_eq!(released.run_id, "c");
+    let run_ids = released
+        .digest
+        .iter()
+        .map(|entry| entry.run_id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(run_ids, ["a", "b"]);
+
+    let released = release_dnd_queue(&store, "desktop", &event("d")).unwrap();
+    assert!(released.digest.is_empty());
+}
+
+fn load(desktop: &str) -> Result<brb_cli::config::Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [desktop]\nchannels:\n  desktop:\n    type: desktop\n{desktop}  phone:\n    type: webhook\n    url: https://ntfy.sh/me\n"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+#[test]
+fn validates_escalation_targets() {
+    let config = load("    when_dnd: escalate\n    escalate_to: [phone]\n").unwrap();
+    let brb_cli::config::ChannelConfig::Desktop(desktop) = &config.channels["desktop"].kind else {
+        panic!("expected desktop channel");
+    };
+    assert_eq!(desktop.when_dnd, WhenDnd::Escalate);
+
+    let error = load("    when_dnd: escalate\n").unwrap_err();
+    assert!(error.contains("without escalate_to"), "{error}");
+
+    let error = load("    when_dnd: escalate\n    escalate_to: [phnoe]\n").unwrap_err();
+    assert!(
+        error.contains("escalates to `phnoe`, which is not defined")
+            && error.ends_with("did you mean `phone`?"),
+        "{error}"
+    );
+
+    let error = load("    when_dnd: escalate\n    escalate_to: [desktop]\n").unwrap_err();
+    assert!(error.contains("which is a desktop channel"), "{error}");
+}
@@ -0,0 +1,72 @@
+use brb_cli::event::CompletionEvent;
+use brb_cli::fingerprint::{fingerprint, normalized_command};
+
+fn command(args: &str) -> Vec<String> {
+    args.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn separates_commands_by_their_arguments() {
+    assert_ne!(
+        fingerprint(&command("cargo test -p foo")),
+        fingerprint(&command("cargo test -p bar"))
+    );
+    assert_ne!(
+        fingerprint(&command("cargo test")),
+        fingerprint(&command("cargo build"))
+    );
+}
+
+#[test]
+fn ignores_transient_flags() {
+    let base = fingerprint(&command("cargo test -p foo"));
+    for variant in [
+        "cargo test -p foo -v",
+        "cargo test -p foo --jobs 8",
+        "cargo test -j8 -p foo",
+        "cargo --color=always test -p foo",
+        "cargo --color never test -p foo --quiet",
+    ] {
+        assert_eq!(fingerprint(&command(variant)), base, "{variant}");
+    }
+}
+
+#[test]
+fn keeps_values_that_are_not_transient() {
+    // `-j` without a number is left alone, as is `--color` before a real argument.
+    assert_eq!(
+        normalized_command(&command("make -j target")),
+        ["make", "target"]
+    );
+    assert_eq!(
+        normalized_command(&command("ls --color src")),
+        ["ls", "src"]
+    );
+}
+
+#[test]
+fn uses_the_program_file_name() {
+    assert_eq!(
+        fingerprint(&command("/usr/bin/cargo test")),
+        fingerprint(&command("cargo test"))
+    );
+}
+
+#[test]
+fn strips_secret_values() {
+    assert_eq!(
+        fingerprint(&command("./deploy.sh --token abc")),
+        fingerprint(&command("./deploy.sh --token xyz"))
+    );
+    assert!(!normalized_command(&command("./deploy.sh --token abc")).contains(&"abc".to_string()));
+}
+
+#[test]
+fn events_carry_the_fingerprint() {
+    let event = CompletionEvent::test_event();
+    assert_eq!(event.fingerprint, fingerprint(&event.command));
+    assert_eq!(event.fingerprint.len(), 16);
+
+    let payload = serde_json::to_value(&event).unwrap();
+    assert_eq!(payload["fingerprint"], event.fingerprint);
+}
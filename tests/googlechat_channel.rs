@@ -0,0 +1,94 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::probe::{AuthStatus, check_channels_auth};
+use brb_cli::test_support::{MockResponse, MockServer};
+use serde_json::Value;
+use std::fs;
+use tempfile::TempDir;
+
+fn load(channel: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [chat]\nchannels:\n  chat:\n    type: googlechat\n{channel}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 2;
+    event.status = "failure".to_string();
+    event.command = ["grep", "-c", "<main>"].map(String::from).to_vec();
+    event.output_tail = Some("error: a < b".to_string());
+    event
+}
+
+fn send(server: &MockServer, event: &CompletionEvent) -> (String, Value) {
+    let config = load(&format!(
+        "    url: {}\n    attach_output: true\n",
+        server.url("/v1/spaces/AAAA/messages?key=k&token=t")
+    ))
+    .unwrap();
+    let result = notify_selected(&config, &["chat".to_string()], event).remove(0);
+    assert!(result.success, "{:?}", result.error);
+    let request = server.requests().pop().unwrap();
+    (request.path, serde_json::from_slice(&request.body).unwrap())
+}
+
+#[test]
+fn posts_a_card_with_escaped_text() {
+    let server = MockServer::start(vec![MockResponse::status(200).body("{}")]);
+
+    let (path, body) = send(&server, &failed_event());
+
+    assert_eq!(path, "/v1/spaces/AAAA/messages?key=k&token=t");
+    let card = &body["cardsV2"][0]["card"];
+    assert!(
+        card["header"]["title"]
+            .as_str()
+            .unwrap()
+            .contains("failed (exit 2)"),
+        "{card}"
+    );
+    let widgets = &card["sections"][0]["widgets"];
+    assert_eq!(widgets[0]["decoratedText"]["topLabel"], "Command");
+    assert_eq!(widgets[0]["decoratedText"]["text"], "grep -c &lt;main&gt;");
+    assert_eq!(widgets[2]["decoratedText"]["text"], "2");
+    assert_eq!(
+        card["sections"][1]["widgets"][0]["textParagraph"]["text"],
+        "error: a &lt; b"
+    );
+    assert!(body.get("thread").is_none(), "{body}");
+}
+
+#[test]
+fn sessions_reply_in_one_thread() {
+    let server = MockServer::start(vec![MockResponse::status(200).body("{}")]);
+    let mut event = failed_event();
+    event.session = Some("release".to_string());
+
+    let (path, body) = send(&server, &event);
+
+    assert_eq!(
+        path,
+        "/v1/spaces/AAAA/messages?key=k&token=t&messageReplyOption=REPLY_MESSAGE_FALLBACK_TO_NEW_THREAD"
+    );
+    assert_eq!(body["thread"]["threadKey"], "brb-release");
+}
+
+#[test]
+fn validates_the_url_and_skips_the_auth_check() {
+    let error = load("    url: ftp://chat.googleapis.com/v1/spaces/AAAA/messages\n").unwrap_err();
+    assert!(error.contains("an unsupported url scheme `ftp`"), "{error}");
+
+    let config = load("    url: https://chat.googleapis.com/v1/spaces/AAAA/messages\n").unwrap();
+    assert_eq!(check_channels_auth(&config)[0].status, AuthStatus::Skipped);
+}
@@ -0,0 +1,113 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::{notify_selected, ping_start};
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::probe::{AuthStatus, check_channels_auth};
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::fs;
+use tempfile::TempDir;
+
+const UUID: &str = "5b1e0c2a-7d4f-4a8e-9c3b-2f6d8e1a4b70";
+
+fn load(check: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [cron]\nchannels:\n  cron:\n    type: healthchecks\n    check: \"{check}\"\n    attach_output: true\n"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn channels() -> Vec<String> {
+    vec!["cron".to_string()]
+}
+
+#[test]
+fn pings_start_and_success_with_the_run_id() {
+    let server = MockServer::start(vec![
+        MockResponse::status(200).body("OK"),
+        MockResponse::status(200).body("OK"),
+    ]);
+    let config = load(&server.url(&format!("/{UUID}/"))).unwrap();
+    let mut event = CompletionEvent::test_event();
+    event.output_tail = Some("12 rows synced\n".to_string());
+
+    assert!(ping_start(&config, &channels(), &event.run_id).is_empty());
+    let result = notify_selected(&config, &channels(), &event).remove(0);
+    assert!(result.success, "{:?}", result.error);
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].path,
+        format!("/{UUID}/start?rid={}", event.run_id)
+    );
+    assert!(requests[0].body.is_empty());
+    assert_eq!(requests[1].path, format!("/{UUID}?rid={}", event.run_id));
+    let body = String::from_utf8(requests[1].body.clone()).unwrap();
+    assert!(body.contains("Exit code: 0\nDuration: "), "{body}");
+    assert!(body.ends_with("output:\n12 rows synced"), "{body}");
+}
+
+#[test]
+fn failures_and_triggers_use_their_endpoints() {
+    let server = MockServer::start(vec![
+        MockResponse::status(200).body("OK"),
+        MockResponse::status(200).body("OK"),
+    ]);
+    let config = load(&server.url(&format!("/{UUID}"))).unwrap();
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 3;
+    event.status = "failure".to_string();
+
+    notify_selected(&config, &channels(), &event);
+    event.status = "triggered".to_string();
+    notify_selected(&config, &channels(), &event);
+
+    let requests = server.requests();
+    assert_eq!(
+        requests[0].path,
+        format!("/{UUID}/fail?rid={}", event.run_id)
+    );
+    let body = String::from_utf8(requests[0].body.clone()).unwrap();
+    assert!(body.contains("Exit code: 3"), "{body}");
+    assert_eq!(
+        requests[1].path,
+        format!("/{UUID}/log?rid={}", event.run_id)
+    );
+}
+
+#[test]
+fn unknown_checks_fail_the_start_ping() {
+    let server = MockServer::start(vec![MockResponse::status(404).body("not found")]);
+    let config = load(&server.url(&format!("/{UUID}"))).unwrap();
+
+    let problems = ping_start(&config, &channels(), "run");
+
+    assert_eq!(
+        problems,
+        ["could not ping the start of the run on `cron`: webhook returned HTTP 404"]
+    );
+}
+
+#[test]
+fn checks_are_uuids_or_ping_urls() {
+    let config = load(UUID).unwrap();
+    assert_eq!(check_channels_auth(&config)[0].status, AuthStatus::Skipped);
+    load("https://hc-ping.com/pingkey/nightly-backup").unwrap();
+
+    let error = load("nightly-backup").unwrap_err();
+    assert!(
+        error.contains("channel `cron` has a check that is neither a UUID nor a ping URL"),
+        "{error}"
+    );
+    let error = load("ftp://hc-ping.com/abc").unwrap_err();
+    assert!(
+        error.contains("an unsupported check url scheme `ftp`"),
+        "{error}"
+    );
+}
@@ -0,0 +1,68 @@
+use brb_cli::event::CompletionEvent;
+use brb_cli::history::{HISTORY_LIMIT, annotate_run, find_run, recent_runs, record_run};
+use brb_cli::state::StateStore;
+use tempfile::TempDir;
+
+fn event(run_id: &str) -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.run_id = run_id.to_string();
+    event
+}
+
+#[test]
+fn keeps_the_most_recent_runs() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+
+    for index in 0..HISTORY_LIMIT + 2 {
+        record_run(&store, &event(&format!("run-{index}"))).unwrap();
+    }
+
+    let runs = recent_runs(&store);
+    assert_eq!(runs.len(), HISTORY_LIMIT);
+    assert_eq!(runs[0].run_id, "run-2");
+    assert_eq!(
+        runs.last().unwrap().run_id,
+        format!("run-{}", HISTORY_LIMIT + 1)
+    );
+}
+
+#[test]
+fn finds_runs_by_id_or_unique_prefix() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let mut recorded = event("a39daccb-1b09");
+    recorded.exit_code = 3;
+    record_run(&store, &recorded).unwrap();
+    record_run(&store, &event("a39dbbbb-0000")).unwrap();
+    record_run(&store, &event("a39d")).unwrap();
+
+    assert_eq!(find_run(&store, "a39daccb-1b09").unwrap().exit_code, 3);
+    assert_eq!(find_run(&store, "a39dac").unwrap().exit_code, 3);
+    // an exact ID wins over being a prefix of others.
+    assert_eq!(find_run(&store, "a39d").unwrap().run_id, "a39d");
+
+    let error = find_run(&store, "a39").unwrap_err();
+    assert!(error.contains("matches more than one run"));
+    let error = find_run(&store, "ffff").unwrap_err();
+    assert_eq!(error, "no run `ffff` in history");
+    assert!(find_run(&store, "").is_err());
+}
+
+#[test]
+fn notes_are_kept_with_the_run() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    record_run(&store, &event("a39daccb")).unwrap();
+    record_run(&store, &event("c045169f")).unwrap();
+
+    annotate_run(&store, "a39d", "root cause: OOM").unwrap();
+    let annotated = annotate_run(&store, "a39daccb", "fixed").unwrap();
+
+    assert_eq!(annotated.notes, ["root cause: OOM", "fixed"]);
+    assert_eq!(find_run(&store, "a39daccb").unwrap().notes, annotated.notes);
+    assert!(find_run(&store, "c045169f").unwrap().notes.is_empty());
+    let runs = recent_runs(&store);
+    assert_eq!(runs[1].run_id, "c045169f", "order is unchanged");
+    assert!(annotate_run(&store, "ffff", "lost").is_err());
+}
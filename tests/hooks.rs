@@ -0,0 +1,90 @@
+use brb_cli::config::{ConfigError, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::hooks::run_hooks;
+use std::fs;
+use tempfile::TempDir;
+
+#[cfg(unix)]
+#[test]
+fn hooks_run_in_event_directory_with_event_on_stdin() {
+    let temp = TempDir::new().unwrap();
+    let mut event = CompletionEvent::test_event();
+    event.cwd = temp.path().display().to_string();
+
+    let hooks = [
+        "cat > event.json".to_string(),
+        "echo second >> order.txt".to_string(),
+    ];
+    let problems = run_hooks(&hooks, &event);
+
+    assert!(problems.is_empty(), "{problems:?}");
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(temp.path().join("event.json")).unwrap()).unwrap();
+    assert_eq!(written["run_id"], event.run_id.as_str());
+    assert!(temp.path().join("order.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn failing_hooks_are_reported_and_later_hooks_still_run() {
+    let temp = TempDir::new().unwrap();
+    let mut event = CompletionEvent::test_event();
+    event.cwd = temp.path().display().to_string();
+
+    let hooks = ["exit 3".to_string(), "touch ran".to_string()];
+    let problems = run_hooks(&hooks, &event);
+
+    assert_eq!(problems, ["hook `exit 3` exited with status 3"]);
+    assert!(temp.path().join("ran").exists());
+}
+
+#[test]
+fn selects_hooks_by_status_and_rejects_blank_commands() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.yml");
+    fs::write(
+        &config_path,
+        r#"
+version: 1
+default_channels: [desktop]
+channels:
+  desktop:
+    type: desktop
+hooks:
+  on_success: [./celebrate.sh]
+  on_failure: [tput bel, open build.log]
+"#,
+    )
+    .unwrap();
+    let config = load_config_from_path(&config_path).unwrap();
+    assert_eq!(config.hooks.for_status("success"), ["./celebrate.sh"]);
+    assert_eq!(
+        config.hooks.for_status("failure"),
+        ["tput bel", "open build.log"]
+    );
+
+    fs::write(
+        &config_path,
+        r#"
+version: 1
+default_channels: [desktop]
+channels:
+  desktop:
+    type: desktop
+aliases:
+  build:
+    command: [cargo, build]
+    hooks:
+      on_failure: [" "]
+"#,
+    )
+    .unwrap();
+    let error = load_config_from_path(&config_path).unwrap_err();
+    let ConfigError::Invalid(diagnostic) = &error else {
+        panic!("expected a diagnostic, got {error}");
+    };
+    assert_eq!(
+        diagnostic.field.as_deref(),
+        Some("aliases.build.hooks.on_failure")
+    );
+}
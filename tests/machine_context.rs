@@ -0,0 +1,54 @@
+use brb_cli::config::ContextConfig;
+use brb_cli::context::{MachineContext, context_warnings, sample_context};
+
+#[test]
+fn disabled_context_is_not_sampled() {
+    assert!(sample_context(&ContextConfig::default()).is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn samples_free_disk_space() {
+    let context = sample_context(&ContextConfig {
+        disk: true,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(context.disk_free_bytes.is_some());
+    assert!(context.network_reachable.is_none());
+}
+
+#[test]
+fn warns_about_low_disk_battery_and_network() {
+    let context = MachineContext {
+        on_battery: Some(true),
+        battery_percent: Some(7),
+        disk_free_bytes: Some(200 * 1024 * 1024),
+        network_reachable: Some(false),
+        warnings: vec![],
+    };
+
+    let warnings = context_warnings(&ContextConfig::default(), &context);
+    assert_eq!(
+        warnings,
+        vec![
+            "low disk space (200 MB free)".to_string(),
+            "low battery (7%)".to_string(),
+            "network unreachable".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn low_disk_threshold_is_configurable() {
+    let context = MachineContext {
+        disk_free_bytes: Some(200 * 1024 * 1024),
+        ..Default::default()
+    };
+    let config = ContextConfig {
+        low_disk: Some(100 * 1000 * 1000),
+        ..Default::default()
+    };
+
+    assert!(context_warnings(&config, &context).is_empty());
+}
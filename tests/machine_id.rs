@@ -0,0 +1,51 @@
+use brb_cli::config::load_config_from_path;
+use brb_cli::event::CompletionEvent;
+use brb_cli::machine_id::load_or_create;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn machine_id_is_generated_once_and_reused() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("machine-id");
+
+    let first = load_or_create(&path).unwrap();
+    assert_eq!(first.len(), 36);
+    assert_eq!(fs::read_to_string(&path).unwrap().trim(), first);
+    assert_eq!(load_or_create(&path).unwrap(), first);
+}
+
+#[test]
+fn invalid_machine_id_is_replaced() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("machine-id");
+    fs::write(&path, "not-an-id\n").unwrap();
+
+    let id = load_or_create(&path).unwrap();
+    assert_ne!(id, "not-an-id");
+    assert_eq!(load_or_create(&path).unwrap(), id);
+}
+
+#[test]
+fn machine_id_is_serialized_only_when_set() {
+    let mut event = CompletionEvent::test_event();
+    let json = serde_json::to_value(&event).unwrap();
+    assert!(json.get("machine_id").is_none());
+
+    event.machine_id = Some("0b6f3a52-6c1e-4f7a-9d2e-5a8c1b7e4f90".to_string());
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["machine_id"], "0b6f3a52-6c1e-4f7a-9d2e-5a8c1b7e4f90");
+}
+
+#[test]
+fn machine_id_is_on_by_default_and_can_be_disabled() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yaml");
+    let base = "version: 1\ndefault_channels: [local]\nchannels:\n  local:\n    type: desktop\n";
+
+    fs::write(&path, base).unwrap();
+    assert!(load_config_from_path(&path).unwrap().machine_id);
+
+    fs::write(&path, format!("{base}machine_id: false\n")).unwrap();
+    assert!(!load_config_from_path(&path).unwrap().machine_id);
+}
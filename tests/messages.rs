@@ -0,0 +1,147 @@
+use brb_cli::config::{Config, DurationStyle};
+use brb_cli::environment::FixedEnvironment;
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::{notification_body, notification_title};
+use brb_cli::messages::Locale;
+use std::collections::BTreeMap;
+
+#[test]
+fn parses_posix_and_bcp47_locale_tags() {
+    assert_eq!(Locale::parse("de_DE.UTF-8"), Some(Locale::De));
+    assert_eq!(Locale::parse("fr-CA"), Some(Locale::Fr));
+    assert_eq!(Locale::parse("ES"), Some(Locale::Es));
+    assert_eq!(Locale::parse("C.UTF-8"), None);
+}
+
+#[test]
+fn configured_locale_wins_over_environment() {
+    assert_eq!(Locale::resolve(Some("de")), Locale::De);
+}
+
+#[test]
+fn locale_falls_back_through_environment_variables() {
+    let environment = FixedEnvironment {
+        now: chrono::Utc::now(),
+        hostname: String::new(),
+        current_dir: None,
+        vars: BTreeMap::from([
+            ("LC_ALL".to_string(), String::new()),
+            ("LANG".to_string(), "fr_FR.UTF-8".to_string()),
+        ]),
+        run_id: String::new(),
+    };
+
+    assert_eq!(Locale::resolve_in(None, &environment), Locale::Fr);
+    assert_eq!(Locale::resolve_in(Some("es"), &environment), Locale::Es);
+}
+
+#[test]
+fn renders_notifications_in_configured_locale() {
+    let mut config = Config::default();
+    config.display.locale = Some("de".to_string());
+    config.appearance.failure.emoji = Some(String::new());
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 3;
+
+    assert_eq!(
+        notification_title(&config, &event),
+        "brb: fehlgeschlagen (Exit-Code 3)"
+    );
+    assert!(notification_body(&config, &event).contains("beendet"));
+
+    event.notes.push("Speicher voll".to_string());
+    assert!(notification_body(&config, &event).ends_with("\nNotiz: Speicher voll"));
+}
+
+#[test]
+fn exit_reasons_replace_the_failure_wording() {
+    let mut config = Config::default();
+    config.appearance.failure.emoji = Some(String::new());
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 137;
+    event.exit_reason = Some("killed (likely OOM)".to_string());
+
+    assert_eq!(
+        notification_title(&config, &event),
+        "brb: killed (likely OOM) (exit 137)"
+    );
+
+    config.display.locale = Some("de".to_string());
+    assert_eq!(
+        notification_title(&config, &event),
+        "brb: killed (likely OOM) (Exit-Code 137)"
+    );
+}
+
+#[test]
+fn titles_name_the_project_instead_of_brb() {
+    let mut config = Config::default();
+    config.appearance.success.emoji = Some(String::new());
+    let mut event = CompletionEvent::test_event();
+    event.project = Some("myservice".to_string());
+
+    assert_eq!(notification_title(&config, &event), "myservice: success");
+}
+
+#[test]
+fn mentions_other_runs_in_progress() {
+    let mut config = Config::default();
+    let mut event = CompletionEvent::test_event();
+
+    event.other_runs = Some(0);
+    assert!(!notification_body(&config, &event).contains("in progress"));
+
+    event.other_runs = Some(1);
+    assert!(notification_body(&config, &event).ends_with("\n1 other brb run still in progress"));
+
+    event.other_runs = Some(2);
+    config.display.locale = Some("es".to_string());
+    assert!(notification_body(&config, &event).ends_with("\n2 ejecuciones de brb más en curso"));
+}
+
+#[test]
+fn formats_numbers_with_locale_separators() {
+    assert_eq!(Locale::En.messages().number(1234567.891, 2), "1,234,567.89");
+    assert_eq!(Locale::De.messages().number(1234567.891, 2), "1.234.567,89");
+    assert_eq!(Locale::Fr.messages().number(-1234.5, 1), "-1\u{202f}234,5");
+    assert_eq!(Locale::Es.messages().number(999.0, 0), "999");
+}
+
+#[test]
+fn formats_durations_in_each_style() {
+    let en = Locale::En.messages();
+    assert_eq!(en.duration(3250, DurationStyle::Seconds), "3.25s");
+    assert_eq!(en.duration(3_900_000, DurationStyle::Seconds), "3,900.00s");
+    assert_eq!(en.duration(3250, DurationStyle::Compact), "3.25s");
+    assert_eq!(en.duration(125_000, DurationStyle::Compact), "2m 5s");
+    assert_eq!(en.duration(3_900_000, DurationStyle::Compact), "1h 5m");
+    assert_eq!(en.duration(7_200_000, DurationStyle::Compact), "2h");
+    assert_eq!(en.duration(1000, DurationStyle::Long), "1 second");
+    assert_eq!(en.duration(3250, DurationStyle::Long), "3.25 seconds");
+    assert_eq!(
+        en.duration(61_000, DurationStyle::Long),
+        "1 minute 1 second"
+    );
+    assert_eq!(
+        en.duration(3_900_000, DurationStyle::Long),
+        "1 hour 5 minutes"
+    );
+
+    let de = Locale::De.messages();
+    assert_eq!(de.duration(3250, DurationStyle::Seconds), "3,25 s");
+    assert_eq!(
+        de.duration(3_900_000, DurationStyle::Long),
+        "1 Stunde 5 Minuten"
+    );
+}
+
+#[test]
+fn renders_durations_in_configured_style() {
+    let mut config = Config::default();
+    config.display.locale = Some("fr".to_string());
+    config.display.durations = DurationStyle::Long;
+    let mut event = CompletionEvent::test_event();
+    event.duration_ms = 125_000;
+
+    assert!(notification_body(&config, &event).contains("(2 minutes 5 secondes, terminé"));
+}
@@ -0,0 +1,63 @@
+use brb_cli::ncprefs::{
+    AppSetting, SCRIPT_EDITOR, TERMINAL_NOTIFIER, parse_app_setting, setting_problem,
+};
+
+const APPS: &str = r#"(
+        {
+        "auth" = 7;
+        "bundle-id" = "com.apple.ScriptEditor2";
+        "content_visibility" = 0;
+        flags = 8396816;
+        grouping = 0;
+        path = "/System/Applications/Utilities/Script Editor.app";
+        "src" = (
+            0
+        );
+    },
+        {
+        "bundle-id" = "fr.julienxx.oss.terminal-notifier";
+        flags = 41951246;
+        path = "/opt/homebrew/Cellar/terminal-notifier/2.0.0/terminal-notifier.app";
+    },
+        {
+        "bundle-id" = "com.example.quiet";
+        flags = 33562630;
+        path = "/Applications/Quiet {beta}.app";
+    }
+)"#;
+
+#[test]
+fn reads_each_apps_notification_setting() {
+    assert_eq!(
+        parse_app_setting(APPS, SCRIPT_EDITOR),
+        Some(AppSetting::Disabled)
+    );
+    assert_eq!(
+        parse_app_setting(APPS, TERMINAL_NOTIFIER),
+        Some(AppSetting::Shown)
+    );
+    assert_eq!(
+        parse_app_setting(APPS, "com.example.quiet"),
+        Some(AppSetting::Silent)
+    );
+    assert_eq!(parse_app_setting(APPS, "com.example.missing"), None);
+    assert_eq!(parse_app_setting("", SCRIPT_EDITOR), None);
+}
+
+#[test]
+fn problems_say_where_to_fix_them() {
+    assert_eq!(setting_problem(AppSetting::Shown, "Script Editor"), None);
+
+    let problem = setting_problem(AppSetting::Disabled, "Script Editor").unwrap();
+    assert!(
+        problem.contains("turned off for Script Editor"),
+        "{problem}"
+    );
+    assert!(
+        problem.contains("System Settings → Notifications → Script Editor"),
+        "{problem}"
+    );
+
+    let problem = setting_problem(AppSetting::Silent, "terminal-notifier").unwrap();
+    assert!(problem.contains("alert style None"), "{problem}");
+}
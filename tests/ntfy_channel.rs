@@ -0,0 +1,173 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::probe::{AuthStatus, check_channels_auth};
+use brb_cli::template::preview;
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::fs;
+use tempfile::TempDir;
+
+fn load(channel: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [phone]\nchannels:\n  phone:\n    type: ntfy\n{channel}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 2;
+    event.status = "failure".to_string();
+    event
+}
+
+#[test]
+fn publishes_with_status_priority_and_tags() {
+    let server = MockServer::start(vec![]);
+    let mut config = load(&format!(
+        "    server: {}\n    topic: builds\n",
+        server.url("")
+    ))
+    .unwrap();
+    config.appearance.failure.emoji = Some("❌".to_string());
+
+    let failure = notify_selected(&config, &["phone".to_string()], &failed_event()).remove(0);
+    let success = notify_selected(
+        &config,
+        &["phone".to_string()],
+        &CompletionEvent::test_event(),
+    )
+    .remove(0);
+
+    assert!(failure.success, "{:?}", failure.error);
+    assert!(success.success, "{:?}", success.error);
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/builds");
+    assert_eq!(requests[0].header("priority"), Some("high"));
+    assert_eq!(requests[0].header("tags"), Some("brb,failure"));
+    // the emoji in the title travels as an RFC 2047 encoded word.
+    let title = requests[0].header("title").unwrap();
+    assert!(title.starts_with("=?UTF-8?B?"), "{title}");
+    assert!(requests[0].header("authorization").is_none());
+    assert!(
+        requests[0].text().starts_with("brb channels test ("),
+        "{}",
+        requests[0].text()
+    );
+
+    assert_eq!(requests[1].header("priority"), Some("default"));
+    assert_eq!(requests[1].header("tags"), Some("brb,success"));
+}
+
+#[test]
+fn status_overrides_and_auth_are_sent() {
+    let server = MockServer::start(vec![]);
+    let config = load(&format!(
+        "    server: {}\n    topic: builds\n    token: tk_abc123\n    failure:\n      priority: urgent\n      tags: [rotating_light, ci]\n",
+        server.url("")
+    ))
+    .unwrap();
+
+    let result = notify_selected(&config, &["phone".to_string()], &failed_event()).remove(0);
+
+    assert!(result.success, "{:?}", result.error);
+    let requests = server.requests();
+    assert_eq!(requests[0].header("priority"), Some("urgent"));
+    assert_eq!(requests[0].header("tags"), Some("rotating_light,ci"));
+    assert_eq!(
+        requests[0].header("authorization"),
+        Some("Bearer tk_abc123")
+    );
+}
+
+#[test]
+fn basic_auth_uses_username_and_password() {
+    let server = MockServer::start(vec![]);
+    let config = load(&format!(
+        "    server: {}\n    topic: builds\n    username: phil\n    password: mypass\n",
+        server.url("")
+    ))
+    .unwrap();
+
+    notify_selected(&config, &["phone".to_string()], &failed_event());
+
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Basic cGhpbDpteXBhc3M=")
+    );
+}
+
+#[test]
+fn retries_and_checks_credentials_with_the_auth_endpoint() {
+    let server = MockServer::start(vec![
+        MockResponse::status(503),
+        MockResponse::status(200),
+        MockResponse::status(403).body(r#"{"error":"forbidden"}"#),
+    ]);
+    let config = load(&format!(
+        "    server: {}\n    topic: builds\n    token: tk_abc123\n    retries: 1\n",
+        server.url("/")
+    ))
+    .unwrap();
+
+    let result = notify_selected(&config, &["phone".to_string()], &failed_event()).remove(0);
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(result.attempts, 2);
+
+    assert_eq!(check_channels_auth(&config)[0].status, AuthStatus::Rejected);
+    let requests = server.requests();
+    assert_eq!(requests[2].method, "GET");
+    assert_eq!(requests[2].path, "/builds/auth");
+    assert_eq!(
+        requests[2].header("authorization"),
+        Some("Bearer tk_abc123")
+    );
+}
+
+#[test]
+fn validates_the_topic_and_auth() {
+    let error = load("    topic: \"\"\n").unwrap_err();
+    assert!(
+        error.contains("channel `phone` has an empty topic"),
+        "{error}"
+    );
+
+    let error = load("    topic: my builds\n").unwrap_err();
+    assert!(error.contains("which ntfy does not accept"), "{error}");
+
+    let error = load("    topic: builds\n    token: tk_1\n    username: phil\n").unwrap_err();
+    assert!(error.contains("both a token and a username"), "{error}");
+    assert!(!error.contains("tk_1"), "{error}");
+
+    let error = load("    topic: builds\n    username: phil\n").unwrap_err();
+    assert!(error.contains("a username but no password"), "{error}");
+
+    let error = load("    topic: builds\n    server: ftp://ntfy.example\n").unwrap_err();
+    assert!(error.contains("unsupported server scheme `ftp`"), "{error}");
+
+    let error = load("    topic: builds\n    success:\n      tags: [\"a,b\"]\n").unwrap_err();
+    assert!(error.contains("success.tags"), "{error}");
+
+    let config = load("    topic: builds\n").unwrap();
+    assert_eq!(config.channels["phone"].type_name(), "ntfy");
+}
+
+#[test]
+fn previews_headers_without_credentials() {
+    let config = load("    topic: builds\n    token: tk_abc123\n").unwrap();
+
+    let output = preview(&config, "phone", &failed_event()).unwrap();
+
+    assert!(output.contains("Priority: high"), "{output}");
+    assert!(output.contains("Tags: brb,failure"), "{output}");
+    assert!(!output.contains("tk_abc123"), "{output}");
+}
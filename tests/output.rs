@@ -0,0 +1,86 @@
+use brb_cli::output::{ColorChoice, Style, color_enabled};
+use std::collections::BTreeMap;
+
+fn env(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+    let vars = pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<BTreeMap<_, _>>();
+    move |name| vars.get(name).cloned()
+}
+
+#[test]
+fn auto_colours_terminals_only() {
+    assert!(color_enabled(ColorChoice::Auto, true, env(&[])));
+    assert!(!color_enabled(ColorChoice::Auto, false, env(&[])));
+    assert!(!color_enabled(
+        ColorChoice::Auto,
+        true,
+        env(&[("TERM", "dumb")])
+    ));
+}
+
+#[test]
+fn auto_follows_no_color_and_clicolor() {
+    assert!(!color_enabled(
+        ColorChoice::Auto,
+        true,
+        env(&[("NO_COLOR", "1")])
+    ));
+    // an empty NO_COLOR does not count as set.
+    assert!(color_enabled(
+        ColorChoice::Auto,
+        true,
+        env(&[("NO_COLOR", "")])
+    ));
+    assert!(!color_enabled(
+        ColorChoice::Auto,
+        true,
+        env(&[("CLICOLOR", "0")])
+    ));
+    assert!(color_enabled(
+        ColorChoice::Auto,
+        false,
+        env(&[("CLICOLOR_FORCE", "1")])
+    ));
+    assert!(!color_enabled(
+        ColorChoice::Auto,
+        false,
+        env(&[("CLICOLOR_FORCE", "0")])
+    ));
+    assert!(!color_enabled(
+        ColorChoice::Auto,
+        false,
+        env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")])
+    ));
+}
+
+#[test]
+fn explicit_choice_wins_over_environment() {
+    assert!(color_enabled(
+        ColorChoice::Always,
+        false,
+        env(&[("NO_COLOR", "1")])
+    ));
+    assert!(!color_enabled(
+        ColorChoice::Never,
+        true,
+        env(&[("CLICOLOR_FORCE", "1")])
+    ));
+}
+
+#[test]
+fn styles_only_when_enabled() {
+    let plain = Style::new(false);
+    assert_eq!(plain.failure("command failed"), "command failed");
+    assert_eq!(plain.status("success", "success"), "success");
+
+    let colored = Style::new(true);
+    assert_eq!(
+        colored.failure("command failed"),
+        "\x1b[31mcommand failed\x1b[0m"
+    );
+    assert_eq!(colored.status("success", "ok"), "\x1b[32mok\x1b[0m");
+    assert_eq!(colored.status("running", "running"), "running");
+    assert_eq!(colored.heading(""), "");
+}
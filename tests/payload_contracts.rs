@@ -0,0 +1,140 @@
+//! Golden-file contract tests for the bodies each preset sends. After an
+//! intended format change, rewrite the files with
+//! `BRB_UPDATE_GOLDEN=1 cargo test --test payload_contracts` and review the
+//! diff.
+
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::environment::FixedEnvironment;
+use brb_cli::event::{CompletionEvent, status_for};
+use brb_cli::template::render_body;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+const CONFIG: &str = r#"
+version: 1
+default_channels: [generic]
+display:
+  locale: en
+  timezone: UTC
+channels:
+  generic: { type: webhook, url: "https://example.com/hook" }
+  slack-webhook: { type: webhook, url: "https://example.com/hook", format: slack }
+  discord: { type: webhook, url: "https://example.com/hook", format: discord }
+  teams: { type: webhook, url: "https://example.com/hook", format: teams }
+  ntfy-webhook: { type: webhook, url: "https://ntfy.sh/builds", format: ntfy }
+  cloudevents: { type: webhook, url: "https://example.com/hook", format: cloudevents }
+  slack: { type: slack, url: "https://hooks.slack.com/services/T000/B000/XXXX" }
+  googlechat: { type: googlechat, url: "https://chat.googleapis.com/v1/spaces/AAAA/messages" }
+  telegram: { type: telegram, bot_token: "123456:ABC-DEF1234ghIkl", chat_id: "-1001234" }
+  pushover:
+    type: pushover
+    user_key: uQiRzpo4DXghDmr9QzzfQu27cmVRsG
+    api_token: azGDORePK8gMaC0QOYAMyEEuzJnyUi
+  ntfy: { type: ntfy, topic: builds }
+  zulip:
+    type: zulip
+    site: https://example.zulipchat.com
+    bot_email: brb-bot@example.zulipchat.com
+    api_key: gjA04ZYcqXKalvYMA8OeXSfzUOLrtbZv
+    stream: builds
+  healthchecks: { type: healthchecks, check: 5b1e0c2a-7d4f-4a8e-9c3b-2f6d8e1a4b70 }
+"#;
+
+const CHANNELS: [(&str, &str); 13] = [
+    ("generic", "json"),
+    ("slack-webhook", "json"),
+    ("discord", "json"),
+    ("teams", "json"),
+    ("ntfy-webhook", "txt"),
+    ("cloudevents", "json"),
+    ("slack", "json"),
+    ("googlechat", "json"),
+    ("telegram", "json"),
+    ("pushover", "json"),
+    ("ntfy", "txt"),
+    ("zulip", "json"),
+    ("healthchecks", "txt"),
+];
+
+fn config() -> Config {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(&path, CONFIG).unwrap();
+    load_config_from_path(&path).unwrap()
+}
+
+fn sample_event(exit_code: i32) -> CompletionEvent {
+    let environment = FixedEnvironment {
+        now: chrono::DateTime::parse_from_rfc3339("2026-02-22T12:00:00Z")
+            .unwrap()
+            .to_utc(),
+        hostname: "build-01".to_string(),
+        current_dir: Some(PathBuf::from("/work/api")),
+        vars: BTreeMap::new(),
+        run_id: "0b8f5b1e-5a4c-4d1e-9a57-3f2a1c9e7d10".to_string(),
+    };
+    let mut event = CompletionEvent::test_event_in(&environment);
+    event.command = ["cargo", "test", "--workspace"].map(String::from).to_vec();
+    event.fingerprint = "1f0c5e7a9b2d4c68".to_string();
+    event.started_at = "2026-02-22T11:56:48.000Z".to_string();
+    event.duration_ms = 192_000;
+    event.exit_code = exit_code;
+    event.status = status_for(exit_code).to_string();
+    event
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+#[test]
+fn preset_bodies_match_golden_files() {
+    let config = config();
+    let update = std::env::var_os("BRB_UPDATE_GOLDEN").is_some();
+    let mut mismatches = Vec::new();
+
+    for (status, exit_code) in [("success", 0), ("failure", 2)] {
+        let event = sample_event(exit_code);
+        for (channel_id, extension) in CHANNELS {
+            let name = format!("{channel_id}-{status}.{extension}");
+            let rendered = render_body(&config, channel_id, &event).unwrap();
+            let path = golden_path(&name);
+            if update {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, &rendered).unwrap();
+                continue;
+            }
+            let expected = fs::read_to_string(&path).unwrap_or_default();
+            if rendered != expected {
+                mismatches.push(format!("{name}:\n{rendered}"));
+            }
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "bodies differ from tests/golden (rerun with BRB_UPDATE_GOLDEN=1 if intended):\n\n{}",
+        mismatches.join("\n")
+    );
+}
+
+#[test]
+fn bodyless_channels_are_refused() {
+    let mut config = config();
+    config.channels.insert(
+        "desktop".to_string(),
+        brb_cli::config::ChannelConfig::Desktop(Default::default()).into(),
+    );
+
+    let error = render_body(&config, "desktop", &sample_event(0)).unwrap_err();
+    assert!(
+        error.contains("use `brb channels preview desktop`"),
+        "{error}"
+    );
+    let error = render_body(&config, "missing", &sample_event(0)).unwrap_err();
+    assert_eq!(error, "channel `missing` is not defined in config");
+}
@@ -0,0 +1,101 @@
+use brb_cli::doctor::literal_credentials;
+#[cfg(unix)]
+use brb_cli::doctor::permission_problems;
+#[cfg(unix)]
+use brb_cli::state::StateStore;
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+#[cfg(unix)]
+use std::path::Path;
+#[cfg(unix)]
+use tempfile::TempDir;
+
+#[cfg(unix)]
+fn mode(path: &Path) -> u32 {
+    fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[cfg(unix)]
+#[test]
+fn state_store_keeps_its_files_private() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path().join("state"));
+    store.save("digest:slack", &vec!["held"]).unwrap();
+
+    assert_eq!(mode(store.dir()), 0o700);
+    let file = fs::read_dir(store.dir())
+        .unwrap()
+        .filter_map(Result::ok)
+        .find(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .unwrap();
+    assert_eq!(mode(&file.path()), 0o600);
+}
+
+#[test]
+fn finds_credentials_written_out_in_config() {
+    let fields = literal_credentials(
+        r#"
+version: 1
+default_channels: [slack]
+secrets:
+  recipients: [age1example]
+channels:
+  slack:
+    type: webhook
+    url: https://hooks.slack.com/services/T000/B000/XXXX
+  relay:
+    type: webhook
+    url: https://relay.example.com/hook
+    headers:
+      Authorization: Bearer abc123
+      X-Team: infra
+  safe:
+    type: webhook
+    url: ${env:RELAY_URL}
+    headers:
+      Authorization: Bearer ${secret:relay-token}
+  script:
+    type: custom
+    exec: ./notify.sh
+    env:
+      GITHUB_TOKEN: ghp_abc
+"#,
+    );
+
+    assert_eq!(
+        fields,
+        [
+            "channels.slack.url",
+            "channels.relay.headers.Authorization",
+            "channels.script.env.GITHUB_TOKEN",
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn doctor_reports_readable_credentials_and_open_directories() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.yml");
+    let data_dir = temp.path().join("data");
+    fs::write(
+        &config_path,
+        "channels:\n  phone:\n    type: webhook\n    url: https://ntfy.sh/alerts?token=abc\n",
+    )
+    .unwrap();
+    fs::set_permissions(&config_path, fs::Permissions::from_mode(0o644)).unwrap();
+    fs::create_dir_all(&data_dir).unwrap();
+    fs::set_permissions(&data_dir, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let problems = permission_problems(&config_path, &data_dir);
+    assert_eq!(problems.len(), 2, "{problems:?}");
+    assert!(problems[0].contains("channels.phone.url"));
+    assert!(problems[0].contains("chmod 600"));
+    assert!(problems[1].contains("chmod 700"));
+
+    fs::set_permissions(&config_path, fs::Permissions::from_mode(0o600)).unwrap();
+    fs::set_permissions(&data_dir, fs::Permissions::from_mode(0o700)).unwrap();
+    assert!(permission_problems(&config_path, &data_dir).is_empty());
+}
@@ -0,0 +1,259 @@
+use brb_cli::config::{Config, ConfigError, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::pipeline::run_pipeline;
+use std::borrow::Cow;
+use std::fs;
+use tempfile::TempDir;
+
+fn load(yaml: &str) -> Result<Config, ConfigError> {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.yml");
+    fs::write(&config_path, yaml).unwrap();
+    load_config_from_path(&config_path)
+}
+
+fn failed_deploy() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.command = vec![
+        "./deploy.sh".to_string(),
+        "--token".to_string(),
+        "abc".to_string(),
+    ];
+    event.status = "failure".to_string();
+    event.exit_code = 1;
+    event.duration_ms = 120_000;
+    event.output_tail = Some("pushed with ghp_secret1\n".to_string());
+    event
+}
+
+#[test]
+fn applies_steps_in_order() {
+    let config = load(
+        r#"
+version: 1
+default_channels: [ops]
+channels:
+  ops:
+    type: desktop
+    pipeline:
+      - filter: { status: failure, min_duration: 1m, command: deploy }
+      - redact: { patterns: ["ghp_[a-z0-9]+"] }
+      - enrich: { team: infra }
+"#,
+    )
+    .unwrap();
+
+    let event = failed_deploy();
+    let channel = &config.channels["ops"];
+    let processed = run_pipeline(&channel.pipeline, channel, &event)
+        .unwrap()
+        .event;
+
+    assert_eq!(processed.command, ["./deploy.sh", "--token", "[REDACTED]"]);
+    assert_eq!(
+        processed.output_tail.as_deref(),
+        Some("pushed with [REDACTED]\n")
+    );
+    assert_eq!(processed.labels["team"], "infra");
+    let payload = serde_json::to_value(&*processed).unwrap();
+    assert_eq!(payload["labels"]["team"], "infra");
+}
+
+#[test]
+fn filters_drop_events_and_empty_pipelines_borrow() {
+    let config = load(
+        r#"
+version: 1
+default_channels: [ops]
+channels:
+  ops:
+    type: desktop
+    pipeline:
+      - filter: { status: success }
+  quiet:
+    type: desktop
+"#,
+    )
+    .unwrap();
+    let event = failed_deploy();
+
+    let ops = &config.channels["ops"];
+    let dropped = run_pipeline(&ops.pipeline, ops, &event).unwrap_err();
+    assert!(dropped.contains("status is not success"));
+
+    let quiet = &config.channels["quiet"];
+    let passed = run_pipeline(&quiet.pipeline, quiet, &event).unwrap();
+    assert!(matches!(passed.event, Cow::Borrowed(_)));
+    assert!(matches!(passed.channel, Cow::Borrowed(_)));
+    assert!(
+        serde_json::to_value(&*passed.event)
+            .unwrap()
+            .get("labels")
+            .is_none()
+    );
+}
+
+#[test]
+fn filters_on_fingerprint() {
+    let event = failed_deploy();
+    let config = load(&format!(
+        r#"
+version: 1
+default_channels: [deploys]
+channels:
+  deploys:
+    type: desktop
+    pipeline:
+      - filter: {{ fingerprint: "{}" }}
+  others:
+    type: desktop
+    pipeline:
+      - filter: {{ fingerprint: "0000000000000000" }}
+"#,
+        event.fingerprint
+    ))
+    .unwrap();
+
+    let deploys = &config.channels["deploys"];
+    assert!(run_pipeline(&deploys.pipeline, deploys, &event).is_ok());
+    let others = &config.channels["others"];
+    let dropped = run_pipeline(&others.pipeline, others, &event).unwrap_err();
+    assert!(dropped.contains("fingerprint did not match"));
+}
+
+#[test]
+fn validates_pipeline_steps() {
+    let error = load(
+        r#"
+version: 1
+default_channels: [ops]
+channels:
+  ops:
+    type: desktop
+    pipeline:
+      - filter: { status: failed }
+"#,
+    )
+    .unwrap_err();
+    let ConfigError::Invalid(diagnostic) = &error else {
+        panic!("expected a diagnostic, got {error}");
+    };
+    assert_eq!(
+        diagnostic.field.as_deref(),
+        Some("channels.ops.pipeline[0].status")
+    );
+    assert_eq!(diagnostic.value.as_deref(), Some("failed"));
+
+    let error = load(
+        r#"
+version: 1
+default_channels: [ops]
+channels:
+  ops:
+    type: desktop
+    pipeline:
+      - redact: { patterns: ["("] }
+"#,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("invalid regex `(`"));
+
+    let error = load(
+        r#"
+version: 1
+default_channels: [ops]
+templates:
+  short:
+    title: "{status}"
+channels:
+  ops:
+    type: desktop
+    pipeline:
+      - template: shrt
+"#,
+    )
+    .unwrap_err();
+    let ConfigError::Invalid(diagnostic) = &error else {
+        panic!("expected a diagnostic, got {error}");
+    };
+    assert_eq!(
+        diagnostic.field.as_deref(),
+        Some("channels.ops.pipeline[0].template")
+    );
+    assert!(
+        error.to_string().contains("did you mean `short`?"),
+        "{error}"
+    );
+
+    let error = load(
+        r#"
+version: 1
+default_channels: [ops]
+channels:
+  ops:
+    type: desktop
+    pipeline:
+      - fields: { include: [stauts] }
+"#,
+    )
+    .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("include has unknown event field `stauts`"),
+        "{error}"
+    );
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn channel_options_run_as_steps_before_the_pipeline() {
+    let config = load(
+        r#"
+version: 1
+default_channels: [ops]
+redact:
+  patterns: ["ghp_[a-z0-9]+"]
+templates:
+  short:
+    title: "{status}"
+  long:
+    title: "{status} after {duration}"
+channels:
+  ops:
+    type: webhook
+    url: https://example.com/hooks/brb
+    template: short
+    include_fields: [status, command]
+    pipeline:
+      - notify_on: failure
+      - template: long
+      - fields: { include: [status] }
+"#,
+    )
+    .unwrap();
+    let channel = &config.channels["ops"];
+    let event = failed_deploy();
+
+    let prepared = brb_cli::pipeline::prepare(&config, channel, &event).unwrap();
+    assert_eq!(
+        prepared.event.output_tail.as_deref(),
+        Some("pushed with [REDACTED]\n"),
+        "the top-level redact is the first step"
+    );
+    assert_eq!(prepared.channel.template.as_deref(), Some("long"));
+    assert_eq!(
+        prepared.channel.include_fields,
+        Some(vec!["status".to_string()])
+    );
+    let body: serde_json::Value =
+        serde_json::from_str(&brb_cli::template::render_body(&config, "ops", &event).unwrap())
+            .unwrap();
+    assert_eq!(body, serde_json::json!({ "status": "failure" }));
+
+    let mut event = event;
+    event.status = "success".to_string();
+    event.exit_code = 0;
+    let dropped = brb_cli::pipeline::prepare(&config, channel, &event).unwrap_err();
+    assert_eq!(dropped, "filtered: notify_on failure");
+}
@@ -0,0 +1,334 @@
+use brb_cli::capture::{load_captures, run_key, save_captures};
+#[cfg(feature = "webhook")]
+use brb_cli::channels::{notify_selected, post_progress};
+use brb_cli::config::{ChannelConfig, Config, WebhookFormat, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::progress_payload;
+use brb_cli::progress::{
+    apply_progress, apply_thread_reply, capture_message, capture_telegram_message, is_follow_up,
+    message_id, progress_edit_target, progress_post_url,
+};
+use brb_cli::state::StateStore;
+#[cfg(feature = "webhook")]
+use brb_cli::test_support::{MockResponse, MockServer};
+use chrono::Utc;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use tempfile::TempDir;
+
+fn load(channel: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!("version: 1\ndefault_channels: [chat]\nchannels:\n  chat:\n    type: webhook\n    progress: true\n{channel}"),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn slack_message() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("channel".to_string(), "C1".to_string()),
+        ("ts".to_string(), "42".to_string()),
+    ])
+}
+
+#[test]
+fn captures_posted_messages_from_responses() {
+    let slack = capture_message(
+        WebhookFormat::Slack,
+        r#"{"ok":true,"channel":"C1","ts":"1700000000.000100"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        message_id(WebhookFormat::Slack, &slack),
+        Some("1700000000.000100")
+    );
+    assert_eq!(slack["channel"], "C1");
+
+    let error = capture_message(
+        WebhookFormat::Slack,
+        r#"{"ok":false,"error":"channel_not_found"}"#,
+    )
+    .unwrap_err();
+    assert_eq!(error, "slack returned `channel_not_found`");
+
+    let discord = capture_message(WebhookFormat::Discord, r#"{"id":"99"}"#).unwrap();
+    assert_eq!(message_id(WebhookFormat::Discord, &discord), Some("99"));
+
+    let error = capture_message(WebhookFormat::Discord, "").unwrap_err();
+    assert_eq!(error, "response has no message id");
+
+    let telegram =
+        capture_telegram_message(r#"{"ok":true,"result":{"message_id":7,"chat":{"id":1}}}"#)
+            .unwrap();
+    assert_eq!(telegram["message_id"], "7");
+}
+
+#[test]
+fn edits_go_to_each_platforms_edit_endpoint() {
+    let discord = "https://discord.com/api/webhooks/1/abc";
+    assert_eq!(
+        progress_post_url(WebhookFormat::Discord, discord),
+        "https://discord.com/api/webhooks/1/abc?wait=true"
+    );
+    assert_eq!(
+        progress_post_url(WebhookFormat::Discord, &format!("{discord}?thread_id=7")),
+        "https://discord.com/api/webhooks/1/abc?thread_id=7&wait=true"
+    );
+    assert_eq!(
+        progress_edit_target(
+            WebhookFormat::Discord,
+            &format!("{discord}?thread_id=7"),
+            "42"
+        ),
+        (
+            "PATCH",
+            "https://discord.com/api/webhooks/1/abc/messages/42?thread_id=7".to_string()
+        )
+    );
+    assert_eq!(
+        progress_edit_target(
+            WebhookFormat::Slack,
+            "https://slack.com/api/chat.postMessage",
+            "42"
+        ),
+        ("POST", "https://slack.com/api/chat.update".to_string())
+    );
+
+    let mut body = json!({"text": "done", "channel": "#builds"});
+    apply_progress(&mut body, &slack_message());
+    assert_eq!(body, json!({"text": "done", "channel": "C1", "ts": "42"}));
+
+    let mut body = json!({"text": "matched", "channel": "#builds"});
+    apply_thread_reply(&mut body, &slack_message());
+    assert_eq!(
+        body,
+        json!({"text": "matched", "channel": "C1", "thread_ts": "42"})
+    );
+}
+
+#[test]
+fn triggers_and_notes_follow_up_a_run() {
+    let mut event = CompletionEvent::test_event();
+    assert!(!is_follow_up(&event));
+    event.trigger = Some(0);
+    assert!(is_follow_up(&event));
+    event.trigger = None;
+    // a resent run keeps its notes but finishes the run again.
+    event.notes = vec!["flaky".to_string()];
+    assert!(!is_follow_up(&event));
+    event.posted_note = Some("flaky".to_string());
+    assert!(is_follow_up(&event));
+}
+
+#[test]
+fn posted_messages_are_kept_with_captures_under_the_run() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let now = Utc::now();
+    let command = BTreeMap::from([("thread".to_string(), "17".to_string())]);
+
+    save_captures(&store, "chat", "fingerprint", command.clone(), now).unwrap();
+    save_captures(&store, "chat", &run_key("run-1"), slack_message(), now).unwrap();
+
+    assert_eq!(
+        load_captures(&store, "chat", &run_key("run-1")),
+        slack_message()
+    );
+    assert_eq!(load_captures(&store, "chat", "fingerprint"), command);
+    assert!(load_captures(&store, "chat", &run_key("run-2")).is_empty());
+}
+
+#[test]
+fn running_messages_match_the_channel_format() {
+    let config = load(
+        "    url: https://slack.com/api/chat.postMessage\n    format: slack\n    extra:\n      channel: C1\n      thread_ts: \"{captured.thread}\"\n    capture:\n      thread: $.ts\n",
+    )
+    .unwrap();
+    let ChannelConfig::Webhook(webhook) = &config.channels["chat"].kind else {
+        panic!("expected a webhook");
+    };
+    let mut event = CompletionEvent::test_event();
+    event.command = vec!["cargo".to_string(), "build".to_string()];
+    event.host = "build-01".to_string();
+    let captured = BTreeMap::from([("thread".to_string(), "17".to_string())]);
+
+    let body = progress_payload(&config, webhook, &event, &captured);
+    assert_eq!(body["text"], "brb: running…: cargo build");
+    assert_eq!(body["channel"], "C1");
+    assert_eq!(body["thread_ts"], "17");
+    assert_eq!(body["attachments"][0]["footer"], "build-01");
+
+    event.project = Some("api".to_string());
+    let body = progress_payload(&config, webhook, &event, &BTreeMap::new());
+    assert_eq!(body["text"], "api: running…: cargo build");
+}
+
+#[test]
+fn validates_progress_channels() {
+    load("    url: https://discord.com/api/webhooks/1/abc\n    format: discord\n").unwrap();
+
+    let error =
+        load("    url: https://hooks.slack.com/services/x\n    format: slack\n").unwrap_err();
+    assert!(error.contains("needs the Slack Web API url"), "{error}");
+
+    let error = load("    url: https://ntfy.sh/builds\n    format: ntfy\n").unwrap_err();
+    assert!(
+        error.contains("progress only applies to format: slack or discord"),
+        "{error}"
+    );
+
+    let error = load(
+        "    url: [https://discord.com/api/webhooks/1/a, https://discord.com/api/webhooks/2/b]\n    format: discord\n",
+    )
+    .unwrap_err();
+    assert!(error.contains("progress needs a single url"), "{error}");
+}
+
+#[cfg(feature = "webhook")]
+fn ok(body: &str) -> MockResponse {
+    MockResponse::status(200).body(body)
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn slack_follow_ups_reply_in_the_running_messages_thread() {
+    let server = MockServer::start(vec![
+        ok(r#"{"ok":true,"channel":"C1","ts":"1.5"}"#),
+        ok(r#"{"ok":true,"channel":"C1","ts":"1.6"}"#),
+        ok(r#"{"ok":true,"channel":"C1","ts":"1.5"}"#),
+    ]);
+    let config = load(&format!(
+        "    url: {}\n    format: slack\n    extra:\n      channel: C1\n",
+        server.url("/api/chat.postMessage")
+    ))
+    .unwrap();
+    let selected = ["chat".to_string()];
+    let mut event = CompletionEvent::test_event();
+    event.run_id = brb_cli::event::new_run_id();
+
+    assert!(post_progress(&config, &selected, &|| event.clone()).is_empty());
+    let mut fired = event.clone();
+    fired.trigger = Some(0);
+    assert!(notify_selected(&config, &selected, &fired)[0].success);
+    assert!(notify_selected(&config, &selected, &event)[0].success);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].json().get("thread_ts"), None);
+    assert_eq!(requests[1].path, "/api/chat.postMessage");
+    assert_eq!(requests[1].json()["thread_ts"], "1.5");
+    assert_eq!(requests[2].path, "/api/chat.update");
+    assert_eq!(requests[2].json()["ts"], "1.5");
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn telegram_follow_ups_reply_to_the_running_message() {
+    let server = MockServer::start(vec![
+        ok(r#"{"ok":true,"result":{"message_id":7}}"#),
+        ok(r#"{"ok":true,"result":{"message_id":8}}"#),
+        ok(r#"{"ok":true,"result":{"message_id":7}}"#),
+    ]);
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [phone]\nchannels:\n  phone:\n    type: telegram\n    bot_token: 1:abc\n    chat_id: 42\n    api_url: {}\n    progress: true\n",
+            server.url("")
+        ),
+    )
+    .unwrap();
+    let config = load_config_from_path(&path).unwrap();
+    let selected = ["phone".to_string()];
+    let mut event = CompletionEvent::test_event();
+    event.run_id = brb_cli::event::new_run_id();
+
+    assert!(post_progress(&config, &selected, &|| event.clone()).is_empty());
+    let mut noted = event.clone();
+    noted.notes = vec!["flaky network".to_string()];
+    noted.posted_note = Some("flaky network".to_string());
+    assert!(notify_selected(&config, &selected, &noted)[0].success);
+    assert!(notify_selected(&config, &selected, &event)[0].success);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[0].path, "/bot1:abc/sendMessage");
+    assert!(
+        requests[0].json()["text"]
+            .as_str()
+            .unwrap()
+            .contains("brb: running…"),
+    );
+    assert_eq!(requests[1].path, "/bot1:abc/sendMessage");
+    assert_eq!(requests[1].json()["text"], "note: flaky network");
+    assert_eq!(requests[1].json()["reply_parameters"]["message_id"], 7);
+    assert_eq!(requests[2].path, "/bot1:abc/editMessageText");
+    assert_eq!(requests[2].json()["message_id"], 7);
+}
+
+#[cfg(feature = "webhook")]
+#[test]
+fn posted_notes_reply_to_the_completion_on_their_own() {
+    let server = MockServer::start(vec![
+        ok(r#"{"ok":true,"channel":"C1","ts":"1.5"}"#),
+        ok(r#"{"ok":true,"result":{"message_id":7}}"#),
+        ok(r#"{"ok":true,"channel":"C1","ts":"1.6"}"#),
+        ok(r#"{"ok":true,"result":{"message_id":8}}"#),
+    ]);
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [chat]\nchannels:\n  chat:\n    type: webhook\n    url: {}\n    format: slack\n    extra:\n      channel: C1\n  phone:\n    type: telegram\n    bot_token: 1:abc\n    chat_id: 42\n    api_url: {}\n  hook:\n    type: webhook\n    url: {}\n",
+            server.url("/api/chat.postMessage"),
+            server.url(""),
+            server.url("/hook"),
+        ),
+    )
+    .unwrap();
+    let config = load_config_from_path(&path).unwrap();
+    let selected = ["chat", "phone", "hook"].map(String::from);
+    let mut event = CompletionEvent::test_event();
+    event.run_id = brb_cli::event::new_run_id();
+
+    // a note before anything was posted has nothing to reply to.
+    let mut noted = event.clone();
+    noted.notes = vec!["root cause: OOM".to_string()];
+    noted.posted_note = Some("root cause: OOM".to_string());
+    let early = notify_selected(&config, &selected[..2], &noted);
+    assert_eq!(
+        early[0].skipped.as_deref(),
+        Some("no message of the run to reply to")
+    );
+
+    assert!(
+        notify_selected(&config, &selected[..2], &event)
+            .iter()
+            .all(|result| result.success)
+    );
+    let results = notify_selected(&config, &selected, &noted);
+    assert!(results[0].delivered() && results[1].delivered());
+    assert_eq!(
+        results[2].skipped.as_deref(),
+        Some("cannot reply to the run's message")
+    );
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 4);
+    let slack = requests[2].json();
+    assert_eq!(slack["text"], "note: root cause: OOM");
+    assert_eq!(
+        (&slack["channel"], &slack["thread_ts"]),
+        (&json!("C1"), &json!("1.5"))
+    );
+    assert_eq!(slack.get("attachments"), None);
+    assert_eq!(requests[3].path, "/bot1:abc/sendMessage");
+    assert_eq!(requests[3].json()["text"], "note: root cause: OOM");
+    assert_eq!(requests[3].json()["reply_parameters"]["message_id"], 7);
+}
@@ -0,0 +1,55 @@
+use brb_cli::project::detect_project;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn names_the_nearest_manifest_package() {
+    let temp = TempDir::new().unwrap();
+    let repo = temp.path().join("monorepo");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    fs::create_dir_all(repo.join("crates/api/src")).unwrap();
+    fs::create_dir_all(repo.join("web/src")).unwrap();
+    fs::write(
+        repo.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        repo.join("crates/api/Cargo.toml"),
+        "[package]\nname = \"myservice\"\n",
+    )
+    .unwrap();
+    fs::write(
+        repo.join("web/package.json"),
+        r#"{ "name": "@acme/dashboard", "private": true }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        detect_project(&repo.join("crates/api/src")).as_deref(),
+        Some("myservice")
+    );
+    assert_eq!(
+        detect_project(&repo.join("web/src")).as_deref(),
+        Some("@acme/dashboard")
+    );
+}
+
+#[test]
+fn falls_back_to_the_repository_then_the_directory() {
+    let temp = TempDir::new().unwrap();
+    let repo = temp.path().join("infra");
+    fs::create_dir_all(repo.join(".git")).unwrap();
+    fs::create_dir_all(repo.join("scripts")).unwrap();
+    // a virtual workspace names nothing, so the repository does.
+    fs::write(repo.join("Cargo.toml"), "[workspace]\n").unwrap();
+
+    assert_eq!(
+        detect_project(&repo.join("scripts")).as_deref(),
+        Some("infra")
+    );
+
+    let loose = temp.path().join("scratch");
+    fs::create_dir_all(&loose).unwrap();
+    assert_eq!(detect_project(&loose).as_deref(), Some("scratch"));
+}
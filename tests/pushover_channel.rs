@@ -0,0 +1,143 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::probe::{AuthStatus, check_channels_auth};
+use brb_cli::template::preview;
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::fs;
+use tempfile::TempDir;
+
+const USER: &str = "uQiRzpo4DXghDmr9QzzfQu27cmVRsG";
+const TOKEN: &str = "azGDORePK8gMaC0QOYAMyEEuzJnyUi";
+
+fn load(channel: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [phone]\nchannels:\n  phone:\n    type: pushover\n{channel}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn keys(server: &MockServer) -> String {
+    format!(
+        "    user_key: {USER}\n    api_token: {TOKEN}\n    api_url: {}\n",
+        server.url("")
+    )
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 2;
+    event.status = "failure".to_string();
+    event
+}
+
+#[test]
+fn sends_messages_with_priority_from_the_exit_code() {
+    let server = MockServer::start(vec![]);
+    let config = load(&format!("{}    device: pixel\n", keys(&server))).unwrap();
+
+    let failure = notify_selected(&config, &["phone".to_string()], &failed_event()).remove(0);
+    let success = notify_selected(
+        &config,
+        &["phone".to_string()],
+        &CompletionEvent::test_event(),
+    )
+    .remove(0);
+
+    assert!(failure.success, "{:?}", failure.error);
+    assert!(success.success, "{:?}", success.error);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/1/messages.json");
+    let body = requests[0].json();
+    assert_eq!(body["token"], TOKEN);
+    assert_eq!(body["user"], USER);
+    assert_eq!(body["device"], "pixel");
+    assert_eq!(body["priority"], 1);
+    assert_eq!(body["title"], "brb: failed (exit 2)");
+    assert!(body["timestamp"].is_i64(), "{body}");
+    assert_eq!(requests[1].json()["priority"], 0);
+}
+
+#[test]
+fn urgent_failures_are_emergency_messages() {
+    let server = MockServer::start(vec![]);
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [phone]\nappearance:\n  failure:\n    priority: urgent\nchannels:\n  phone:\n    type: pushover\n{}",
+            keys(&server)
+        ),
+    )
+    .unwrap();
+    let config = load_config_from_path(&path).unwrap();
+
+    notify_selected(&config, &["phone".to_string()], &failed_event());
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["priority"], 2);
+    assert_eq!(body["retry"], 60);
+    assert_eq!(body["expire"], 3600);
+}
+
+#[test]
+fn retries_and_checks_the_keys() {
+    let server = MockServer::start(vec![
+        MockResponse::status(500),
+        MockResponse::status(200).body(r#"{"status":1}"#),
+        MockResponse::status(400).body(r#"{"status":0,"errors":["user key is invalid"]}"#),
+    ]);
+    let config = load(&format!("{}    retries: 1\n", keys(&server))).unwrap();
+
+    let result = notify_selected(&config, &["phone".to_string()], &failed_event()).remove(0);
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(result.attempts, 2);
+
+    let auth = check_channels_auth(&config).remove(0);
+    assert_eq!(auth.status, AuthStatus::Rejected);
+    let requests = server.requests();
+    assert_eq!(requests[2].path, "/1/users/validate.json");
+    assert_eq!(requests[2].json()["user"], USER);
+}
+
+#[test]
+fn validates_the_keys_without_echoing_them() {
+    let error = load(&format!("    user_key: \"\"\n    api_token: {TOKEN}\n")).unwrap_err();
+    assert!(
+        error.contains("channel `phone` has an empty user_key"),
+        "{error}"
+    );
+
+    let error = load(&format!(
+        "    user_key: {USER}\n    api_token: short-token\n"
+    ))
+    .unwrap_err();
+    assert!(
+        error.contains("an invalid api_token (expected 30 letters and digits)"),
+        "{error}"
+    );
+    assert!(!error.contains("short-token"), "{error}");
+
+    let error = load(&format!("    user_key: {USER}\n")).unwrap_err();
+    assert!(error.contains("missing field `api_token`"), "{error}");
+}
+
+#[test]
+fn previews_the_message_without_keys() {
+    let config = load(&format!("    user_key: {USER}\n    api_token: {TOKEN}\n")).unwrap();
+
+    let output = preview(&config, "phone", &failed_event()).unwrap();
+
+    assert!(output.contains("\"priority\": 1"), "{output}");
+    assert!(!output.contains(USER), "{output}");
+    assert!(!output.contains(TOKEN), "{output}");
+}
@@ -0,0 +1,82 @@
+use brb_cli::config::{RedactConfig, load_config_from_path};
+use brb_cli::redact::redact_command;
+use std::fs;
+use tempfile::TempDir;
+
+fn argv(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn default_flags_mask_following_and_joined_values() {
+    let redacted = redact_command(
+        &RedactConfig::default(),
+        &argv(&[
+            "deploy",
+            "--token",
+            "abc",
+            "--PASSWORD=hunter2",
+            "--verbose",
+        ]),
+    );
+
+    assert_eq!(
+        redacted,
+        argv(&[
+            "deploy",
+            "--token",
+            "[REDACTED]",
+            "--PASSWORD=[REDACTED]",
+            "--verbose"
+        ])
+    );
+}
+
+#[test]
+fn configured_flags_replace_defaults_and_patterns_apply() {
+    let config = RedactConfig {
+        flags: Some(vec!["-p".to_string()]),
+        patterns: vec!["ghp_[A-Za-z0-9]+".to_string()],
+    };
+
+    let redacted = redact_command(
+        &config,
+        &argv(&[
+            "mysql",
+            "-p",
+            "pw",
+            "--token",
+            "kept",
+            "https://ghp_abc123@github.com",
+        ]),
+    );
+
+    assert_eq!(
+        redacted,
+        argv(&[
+            "mysql",
+            "-p",
+            "[REDACTED]",
+            "--token",
+            "kept",
+            "https://[REDACTED]@github.com"
+        ])
+    );
+}
+
+#[test]
+fn invalid_pattern_fails_load() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        "version: 1\ndefault_channels: [desktop]\nchannels:\n  desktop:\n    type: desktop\nredact:\n  patterns: [\"(unclosed\"]\n",
+    )
+    .unwrap();
+
+    let error = load_config_from_path(&path).unwrap_err().to_string();
+    assert!(
+        error.starts_with("invalid config: redact.patterns[0] `(unclosed` is not a valid regex"),
+        "{error}"
+    );
+}
@@ -0,0 +1,33 @@
+use brb_cli::lock::{LockError, acquire_lock_in};
+use tempfile::TempDir;
+
+#[test]
+fn uncontended_lock_reports_no_wait() {
+    let temp = TempDir::new().unwrap();
+
+    let lock = acquire_lock_in(temp.path(), "deploy", false).unwrap();
+    assert_eq!(lock.info.name, "deploy");
+    assert!(!lock.info.contended);
+    assert_eq!(lock.info.waited_ms, 0);
+}
+
+#[test]
+fn held_lock_fails_fast() {
+    let temp = TempDir::new().unwrap();
+    let held = acquire_lock_in(temp.path(), "deploy", false).unwrap();
+
+    let error = acquire_lock_in(temp.path(), "deploy", false).unwrap_err();
+    assert!(matches!(error, LockError::Busy { .. }));
+    assert!(error.to_string().contains("held by another brb run"));
+
+    drop(held);
+    assert!(acquire_lock_in(temp.path(), "deploy", false).is_ok());
+}
+
+#[test]
+fn rejects_path_like_lock_names() {
+    let temp = TempDir::new().unwrap();
+
+    let error = acquire_lock_in(temp.path(), "../deploy", false).unwrap_err();
+    assert!(matches!(error, LockError::InvalidName(_)));
+}
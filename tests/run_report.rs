@@ -0,0 +1,64 @@
+use brb_cli::channels::{DeliveryError, DeliveryErrorKind, DeliveryResult, Interaction};
+use brb_cli::event::CompletionEvent;
+use brb_cli::report::RunReport;
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[test]
+fn writes_runs_and_delivery_results_as_json() {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("report.json");
+    let event = CompletionEvent::test_event();
+    let deliveries = vec![
+        DeliveryResult {
+            channel_id: "desktop".to_string(),
+            success: true,
+            error: None,
+            latency_ms: Some(120),
+            attempts: 1,
+            queued: false,
+            skipped: None,
+            interaction: Some(Interaction::Dismissed),
+            acknowledged: None,
+            captured: BTreeMap::new(),
+            targets: Vec::new(),
+        },
+        DeliveryResult {
+            channel_id: "ci-webhook".to_string(),
+            success: false,
+            error: Some(DeliveryError {
+                kind: DeliveryErrorKind::Http { status: 500 },
+                message: "webhook returned HTTP 500".to_string(),
+            }),
+            latency_ms: Some(120),
+            attempts: 1,
+            queued: false,
+            skipped: None,
+            interaction: None,
+            acknowledged: None,
+            captured: BTreeMap::new(),
+            targets: Vec::new(),
+        },
+    ];
+
+    let mut report = RunReport::default();
+    report.push(&event, &deliveries, Duration::from_millis(245));
+    report.write(&path).unwrap();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json["tool"], "brb");
+    assert_eq!(json["exit_code"], 0);
+    assert_eq!(json["runs"][0]["event"]["status"], "success");
+    assert_eq!(json["runs"][0]["deliveries"][1]["channel_id"], "ci-webhook");
+    assert_eq!(json["runs"][0]["deliveries"][1]["success"], false);
+    assert_eq!(
+        json["runs"][0]["deliveries"][1]["error"],
+        serde_json::json!({"kind": "http", "status": 500, "message": "webhook returned HTTP 500"})
+    );
+    assert_eq!(json["runs"][0]["deliveries"][1]["latency_ms"], 120);
+    assert_eq!(json["runs"][0]["deliveries"][0]["interaction"], "dismissed");
+    assert_eq!(json["runs"][0]["delivery_ms"], 245);
+}
@@ -0,0 +1,224 @@
+use brb_cli::runner::{
+    CaptureMode, Interactivity, LineWatcher, OutputStream, RunOptions, capture_mode,
+    likely_interactive, pty_command, run_command_with,
+};
+use std::sync::{Arc, Mutex};
+
+#[cfg(unix)]
+#[test]
+fn captures_last_lines_of_stdout_and_stderr() {
+    let command = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "printf 'one\\ntwo\\n'; sleep 0.2; echo boom >&2; exit 3".to_string(),
+    ];
+
+    let run = run_command_with(
+        &command,
+        &RunOptions {
+            capture_lines: 2,
+            ..Default::default()
+        },
+    );
+    assert_eq!(run.exit_code, 3);
+    let tail = run.output_tail.unwrap();
+    assert_eq!(tail.lines().count(), 2);
+    assert!(tail.contains("boom"));
+    assert_eq!(tail, "two\nboom");
+}
+
+#[cfg(unix)]
+#[test]
+fn watchers_see_each_line_as_it_is_written() {
+    let command = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "printf 'one\\r\\n10%%\\r20%%\\n'; echo two >&2; printf three".to_string(),
+    ];
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&lines);
+
+    run_command_with(
+        &command,
+        &RunOptions {
+            capture_lines: 1,
+            on_line: vec![LineWatcher::new(move |stream, line| {
+                seen.lock().unwrap().push((stream, line.to_string()));
+            })],
+            ..Default::default()
+        },
+    );
+
+    let mut lines = lines.lock().unwrap().clone();
+    lines.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(
+        lines,
+        [
+            (OutputStream::Stdout, "10%".to_string()),
+            (OutputStream::Stdout, "20%".to_string()),
+            (OutputStream::Stdout, "one".to_string()),
+            (OutputStream::Stdout, "three".to_string()),
+            (OutputStream::Stderr, "two".to_string()),
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn inherited_stdio_captures_nothing() {
+    let command = vec!["true".to_string()];
+
+    let run = run_command_with(&command, &RunOptions::default());
+    assert_eq!(run.exit_code, 0);
+    assert!(run.output_tail.is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_in_given_directory_with_extra_env() {
+    let temp = tempfile::TempDir::new().unwrap();
+    let command = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "echo \"$BRB_TEST_VALUE\" > seen.txt".to_string(),
+    ];
+    let options = RunOptions {
+        cwd: Some(temp.path().to_path_buf()),
+        env: [("BRB_TEST_VALUE".to_string(), "hello".to_string())].into(),
+        ..Default::default()
+    };
+
+    let run = run_command_with(&command, &options);
+    assert_eq!(run.exit_code, 0);
+    assert_eq!(
+        std::fs::read_to_string(temp.path().join("seen.txt")).unwrap(),
+        "hello\n"
+    );
+
+    let event = brb_cli::event::CompletionEvent::from_run(&run);
+    assert_eq!(event.cwd, temp.path().display().to_string());
+}
+
+#[test]
+fn missing_directory_is_a_spawn_error() {
+    let options = RunOptions {
+        cwd: Some("/definitely/not/here".into()),
+        ..Default::default()
+    };
+
+    let run = run_command_with(&["true".to_string()], &options);
+    assert_eq!(run.exit_code, 127);
+    assert!(
+        run.spawn_error
+            .unwrap()
+            .starts_with("failed to start `true` in /definitely/not/here:")
+    );
+}
+
+fn argv(words: &[&str]) -> Vec<String> {
+    words.iter().map(|word| word.to_string()).collect()
+}
+
+#[test]
+fn recognises_likely_interactive_commands() {
+    assert!(likely_interactive(&argv(&["ssh", "host"])));
+    assert!(likely_interactive(&argv(&["/usr/bin/vim", "notes.md"])));
+    assert!(likely_interactive(&argv(&[
+        "docker", "run", "-it", "alpine"
+    ])));
+    assert!(likely_interactive(&argv(&[
+        "kubectl", "exec", "--stdin", "pod"
+    ])));
+    assert!(likely_interactive(&argv(&[
+        "podman",
+        "container",
+        "exec",
+        "--tty=true",
+        "web",
+        "sh"
+    ])));
+    assert!(!likely_interactive(&argv(&["docker", "build", "."])));
+    assert!(!likely_interactive(&argv(&[
+        "docker", "build", "-t", "x", "."
+    ])));
+    assert!(!likely_interactive(&argv(&[
+        "podman",
+        "push",
+        "-t",
+        "registry/x"
+    ])));
+    assert!(!likely_interactive(&argv(&[
+        "kubectl", "exec", "pod", "--", "ls", "-t"
+    ])));
+    assert!(!likely_interactive(&argv(&["cargo", "test"])));
+    assert!(!likely_interactive(&[]));
+}
+
+#[test]
+fn interactive_commands_keep_the_terminal_only_when_stdin_is_one() {
+    let ssh = argv(&["ssh", "host"]);
+    let capturing = RunOptions {
+        capture_lines: 10,
+        ..Default::default()
+    };
+
+    assert_eq!(capture_mode(&ssh, &capturing, true), CaptureMode::Inherit);
+    assert_eq!(capture_mode(&ssh, &capturing, false), CaptureMode::Pipe);
+    assert_eq!(
+        capture_mode(&argv(&["make"]), &capturing, true),
+        CaptureMode::Pipe
+    );
+
+    let forced = RunOptions {
+        interactivity: Interactivity::NonInteractive,
+        ..capturing.clone()
+    };
+    assert_eq!(capture_mode(&ssh, &forced, true), CaptureMode::Pipe);
+
+    // nothing to capture means nothing to decide.
+    assert_eq!(
+        capture_mode(&ssh, &RunOptions::default(), false),
+        CaptureMode::Inherit
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn pty_command_quotes_arguments_for_script() {
+    let Some(script) = pty_command(&argv(&["echo", "it's here", "$HOME"])) else {
+        return;
+    };
+    assert_eq!(
+        script,
+        argv(&[
+            "script",
+            "-q",
+            "-e",
+            "-f",
+            "-c",
+            r"echo 'it'\''s here' '$HOME'",
+            "/dev/null"
+        ])
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn forced_pty_captures_output_through_a_terminal() {
+    let command = argv(&["sh", "-c", "test -t 1 && echo on-a-tty; exit 4"]);
+    if pty_command(&command).is_none() {
+        return;
+    }
+
+    let run = run_command_with(
+        &command,
+        &RunOptions {
+            capture_lines: 5,
+            interactivity: Interactivity::Pty,
+            ..Default::default()
+        },
+    );
+    assert_eq!(run.exit_code, 4);
+    assert_eq!(run.output_tail.as_deref(), Some("on-a-tty"));
+    assert_eq!(run.command, command);
+}
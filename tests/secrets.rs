@@ -0,0 +1,265 @@
+use brb_cli::config::{
+    ChannelConfig, ConfigError, LoadOptions, load_config_from_path, load_config_from_path_with,
+};
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::{WebhookPayload, seal_payload, webhook_payload};
+use brb_cli::secrets::{decrypt, encrypt, secrets_from_source, set_secret_in_source};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+const ARMOR: &str =
+    "-----BEGIN AGE ENCRYPTED FILE-----\nc2VjcmV0LXRva2Vu\n-----END AGE ENCRYPTED FILE-----\n";
+
+/// Writes a stand-in for `age` that "encrypts" with base64 and requires the
+/// identity file passed after `--identity`, if any, to exist.
+fn fake_age(dir: &Path) -> String {
+    let path = dir.join("fake-age");
+    fs::write(
+        &path,
+        r#"#!/bin/sh
+mode=$1
+identity=
+while [ $# -gt 0 ]; do
+  [ "$1" = --identity ] && identity=$2
+  shift
+done
+if [ -n "$identity" ] && [ ! -f "$identity" ]; then
+  echo "no identity at $identity" >&2
+  exit 1
+fi
+case "$mode" in
+  --decrypt) grep -v -- '-----' | base64 -d ;;
+  --encrypt)
+    echo "-----BEGIN AGE ENCRYPTED FILE-----"
+    base64
+    echo "-----END AGE ENCRYPTED FILE-----"
+    ;;
+esac
+"#,
+    )
+    .unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    path.display().to_string()
+}
+
+fn config_yaml(dir: &Path, command: &str, url: &str) -> String {
+    let identity = dir.join("identity.txt");
+    fs::write(&identity, "AGE-SECRET-KEY-TEST\n").unwrap();
+    let secrets = set_secret_in_source("", "token", ARMOR);
+    format!(
+        r#"version: 1
+default_channels: [hook]
+channels:
+  hook:
+    type: webhook
+    url: {url}
+{secrets}  command: {command}
+  identity: {}
+"#,
+        identity.display()
+    )
+}
+
+fn load(dir: &Path, yaml: &str) -> Result<brb_cli::config::Config, ConfigError> {
+    let path = dir.join("config.yml");
+    fs::write(&path, yaml).unwrap();
+    load_config_from_path(&path)
+}
+
+#[test]
+fn secret_references_are_decrypted_at_load() {
+    let temp = TempDir::new().unwrap();
+    let command = fake_age(temp.path());
+    let yaml = config_yaml(
+        temp.path(),
+        &command,
+        "https://example.com/hook?token=${secret:token}",
+    );
+
+    let config = load(temp.path(), &yaml).unwrap();
+    let ChannelConfig::Webhook(webhook) = &config.channels["hook"].kind else {
+        panic!("expected webhook channel");
+    };
+    assert_eq!(webhook.url, ["https://example.com/hook?token=secret-token"]);
+}
+
+#[test]
+fn unreferenced_secrets_do_not_need_age() {
+    let temp = TempDir::new().unwrap();
+    let yaml = config_yaml(temp.path(), "/nonexistent/age", "https://example.com/hook");
+
+    assert!(load(temp.path(), &yaml).is_ok());
+}
+
+#[test]
+fn unknown_secret_fails_load() {
+    let temp = TempDir::new().unwrap();
+    let command = fake_age(temp.path());
+    let yaml = config_yaml(
+        temp.path(),
+        &command,
+        "https://example.com/hook?token=${secret:missing}",
+    );
+
+    let error = load(temp.path(), &yaml).unwrap_err();
+    assert!(matches!(error, ConfigError::UnknownSecret(name) if name == "missing"));
+}
+
+#[test]
+fn decrypt_failure_names_the_secret() {
+    let temp = TempDir::new().unwrap();
+    let command = fake_age(temp.path());
+    let yaml = config_yaml(
+        temp.path(),
+        &command,
+        "https://example.com/hook?token=${secret:token}",
+    );
+    fs::remove_file(temp.path().join("identity.txt")).unwrap();
+
+    let error = load(temp.path(), &yaml).unwrap_err().to_string();
+    assert!(
+        error.starts_with("failed to decrypt secret `token`"),
+        "{error}"
+    );
+    assert!(error.contains("no identity"), "{error}");
+}
+
+#[test]
+fn encrypt_and_decrypt_round_trip_through_the_command() {
+    let temp = TempDir::new().unwrap();
+    let command = fake_age(temp.path());
+    let yaml = config_yaml(temp.path(), &command, "https://example.com/hook");
+    let secrets = secrets_from_source(&yaml).unwrap();
+
+    let armored = encrypt(&secrets, "hunter2").unwrap();
+    assert!(armored.starts_with("-----BEGIN AGE ENCRYPTED FILE-----"));
+    assert_eq!(decrypt(&secrets, &armored).unwrap(), "hunter2");
+}
+
+#[test]
+fn set_secret_adds_block_when_missing() {
+    let source = "version: 1 # keep me\nchannels: {}\n";
+
+    let updated = set_secret_in_source(source, "token", ARMOR);
+    assert!(
+        updated.starts_with(
+            "version: 1 # keep me\nchannels: {}\n\nsecrets:\n  values:\n    token: |\n"
+        )
+    );
+    let secrets = secrets_from_source(&updated).unwrap();
+    assert_eq!(secrets.values["token"], ARMOR);
+    assert_eq!(secrets.command, "age");
+}
+
+#[test]
+fn set_secret_replaces_existing_entry_and_keeps_others() {
+    let source = set_secret_in_source("version: 1\n", "token", ARMOR);
+    let source = set_secret_in_source(&source, "other", "OTHER\n");
+    let replacement =
+        "-----BEGIN AGE ENCRYPTED FILE-----\nbmV3\n-----END AGE ENCRYPTED FILE-----\n";
+
+    let updated = set_secret_in_source(&source, "token", replacement);
+    let secrets = secrets_from_source(&updated).unwrap();
+    assert_eq!(secrets.values["token"], replacement);
+    assert_eq!(secrets.values["other"], "OTHER\n");
+    assert_eq!(secrets.values.len(), 2);
+}
+
+#[test]
+fn set_secret_adds_values_under_existing_block() {
+    let source = "secrets:\n  command: rage\nchannels:\n  hook:\n    headers:\n      secrets: x\n";
+
+    let updated = set_secret_in_source(source, "token", ARMOR);
+    let secrets = secrets_from_source(&updated).unwrap();
+    assert_eq!(secrets.command, "rage");
+    assert_eq!(secrets.values["token"], ARMOR);
+    assert!(updated.ends_with("      secrets: x\n"));
+}
+
+#[test]
+fn set_secret_keeps_anchors_merge_keys_and_comments() {
+    let source = r#"version: 1
+default_channels: [ci]
+# shared by every webhook
+x-hook: &hook
+  type: webhook
+  headers: { X-Team: builds }
+channels:
+  ci:
+    <<: *hook # inherits type and headers
+    url: https://ci.example.com/hook
+"#;
+
+    let updated = set_secret_in_source(source, "token", ARMOR);
+    assert!(updated.starts_with(source));
+    let secrets = secrets_from_source(&updated).unwrap();
+    assert_eq!(secrets.values["token"], ARMOR);
+}
+
+#[test]
+fn secrets_in_custom_args_are_warned_about() {
+    let temp = TempDir::new().unwrap();
+    let command = fake_age(temp.path());
+    let yaml = config_yaml(temp.path(), &command, "https://example.com/hook").replace(
+        "channels:\n",
+        "channels:\n  notify:\n    type: custom\n    exec: notify\n    args: [\"--token=${secret:token}\"]\n    env_files:\n      TOKEN_FILE: ${secret:token}\n",
+    );
+    let path = temp.path().join("config.yml");
+    fs::write(&path, yaml).unwrap();
+
+    let loaded = load_config_from_path_with(&path, LoadOptions::default()).unwrap();
+    assert_eq!(loaded.warnings.len(), 1, "{:?}", loaded.warnings);
+    assert!(loaded.warnings[0].starts_with("`channels.notify.args` interpolates a secret"));
+    let ChannelConfig::Custom(custom) = &loaded.config.channels["notify"].kind else {
+        panic!("expected custom channel");
+    };
+    assert_eq!(custom.env_files["TOKEN_FILE"], "secret-token");
+}
+
+#[test]
+fn encrypted_webhook_payloads_are_sealed_in_an_envelope() {
+    let temp = TempDir::new().unwrap();
+    let command = fake_age(temp.path());
+    let yaml = config_yaml(temp.path(), &command, "https://example.com/hook").replace(
+        "    url: https://example.com/hook\n",
+        "    url: https://example.com/hook\n    encrypt_to: [age1examplerecipient]\n",
+    );
+    let config = load(temp.path(), &yaml).unwrap();
+    let ChannelConfig::Webhook(webhook) = &config.channels["hook"].kind else {
+        panic!("expected webhook channel");
+    };
+    let event = CompletionEvent::test_event();
+
+    let payload = webhook_payload(&config, webhook, None, &event);
+    let WebhookPayload::Json(envelope) = seal_payload(&config, webhook, &event, payload).unwrap()
+    else {
+        panic!("expected JSON envelope");
+    };
+    assert_eq!(envelope["envelope"], "age");
+    assert_eq!(envelope["run_id"], event.run_id.as_str());
+    assert_eq!(envelope["content_type"], "application/json");
+
+    let plaintext = decrypt(&config.secrets, envelope["ciphertext"].as_str().unwrap()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&plaintext).unwrap();
+    assert_eq!(body["run_id"], event.run_id.as_str());
+}
+
+#[test]
+fn rejects_invalid_encryption_recipients() {
+    let temp = TempDir::new().unwrap();
+    let yaml = config_yaml(temp.path(), "age", "https://example.com/hook").replace(
+        "    url: https://example.com/hook\n",
+        "    url: https://example.com/hook\n    encrypt_to: [not-a-key]\n",
+    );
+
+    let error = load(temp.path(), &yaml).unwrap_err().to_string();
+    assert!(
+        error.contains("invalid age recipient `not-a-key`"),
+        "{error}"
+    );
+}
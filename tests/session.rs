@@ -0,0 +1,174 @@
+use brb_cli::config::Config;
+use brb_cli::environment::FixedEnvironment;
+use brb_cli::event::{CompletionEvent, status_for};
+use brb_cli::format::notification_body;
+use brb_cli::history::record_run;
+use brb_cli::session::{
+    end_session, load_thread, resolve_session, save_thread, session_runs, session_summary,
+};
+use brb_cli::state::StateStore;
+use chrono::{TimeDelta, Utc};
+use std::collections::BTreeMap;
+use tempfile::TempDir;
+
+fn environment(vars: &[(&str, &str)]) -> FixedEnvironment {
+    FixedEnvironment {
+        now: Utc::now(),
+        hostname: "laptop".to_string(),
+        current_dir: None,
+        vars: vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+        run_id: String::new(),
+    }
+}
+
+#[test]
+fn named_sessions_are_used_as_given() {
+    let env = environment(&[("TMUX_PANE", "%3")]);
+    assert_eq!(
+        resolve_session(" release ", &env).as_deref(),
+        Some("release")
+    );
+    assert_eq!(resolve_session("  ", &env), None);
+}
+
+#[test]
+fn auto_names_the_terminal_session() {
+    let env = environment(&[("TMUX_PANE", "%3"), ("STY", "4242.pts-1.laptop")]);
+    assert_eq!(resolve_session("auto", &env).as_deref(), Some("tmux-%3"));
+
+    let env = environment(&[("TERM_SESSION_ID", "w0t1p0:6B29FC40"), ("TMUX_PANE", "%3")]);
+    assert_eq!(
+        resolve_session("auto", &env).as_deref(),
+        Some("term-w0t1p0:6B29FC40")
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn auto_falls_back_to_the_shell() {
+    let session = resolve_session("auto", &environment(&[])).unwrap();
+    assert_eq!(
+        session,
+        format!("shell-{}", std::os::unix::process::parent_id())
+    );
+}
+
+#[test]
+fn sessions_keep_their_first_message() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let now = Utc::now();
+
+    assert_eq!(load_thread(&store, "slack", "release", now), None);
+    save_thread(
+        &store,
+        "slack",
+        "release",
+        "1700000000.000100".to_string(),
+        now,
+    )
+    .unwrap();
+    save_thread(
+        &store,
+        "slack",
+        "release",
+        "1700000042.000200".to_string(),
+        now,
+    )
+    .unwrap();
+
+    let thread = load_thread(&store, "slack", "release", now).unwrap();
+    assert_eq!(thread.message_id, "1700000000.000100");
+    assert_eq!(load_thread(&store, "telegram", "release", now), None);
+    assert_eq!(load_thread(&store, "slack", "nightly", now), None);
+}
+
+#[test]
+fn old_threads_are_forgotten() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let then = Utc::now() - TimeDelta::days(8);
+
+    save_thread(&store, "slack", "release", "1".to_string(), then).unwrap();
+    assert_eq!(load_thread(&store, "slack", "release", Utc::now()), None);
+
+    save_thread(&store, "slack", "release", "2".to_string(), Utc::now()).unwrap();
+    let thread = load_thread(&store, "slack", "release", Utc::now()).unwrap();
+    assert_eq!(thread.message_id, "2");
+}
+
+fn run(command: &str, exit_code: i32, session: Option<&str>, finished_at: &str) -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.command = command.split(' ').map(str::to_string).collect();
+    event.exit_code = exit_code;
+    event.status = status_for(exit_code).to_string();
+    event.session = session.map(str::to_string);
+    event.started_at = "2026-02-22T12:00:00Z".to_string();
+    event.finished_at = finished_at.to_string();
+    event.duration_ms = 1_000;
+    event
+}
+
+#[test]
+fn summaries_list_each_run_in_the_session() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    for event in [
+        run("cargo build", 0, Some("release"), "2026-02-22T12:01:00Z"),
+        run("cargo test", 0, None, "2026-02-22T12:02:00Z"),
+        run("cargo test", 101, Some("release"), "2026-02-22T12:03:00Z"),
+        run("./deploy.sh", 0, Some("release"), "2026-02-22T12:04:00Z"),
+    ] {
+        record_run(&store, &event).unwrap();
+    }
+
+    let runs = session_runs(&store, "release");
+    let summary = session_summary("release", &runs, &environment(&[])).unwrap();
+    assert_eq!(summary.command, ["brb", "session", "end", "release"]);
+    assert_eq!(summary.session.as_deref(), Some("release"));
+    assert_eq!(summary.status, "failure");
+    assert_eq!(summary.exit_code, 101);
+    assert_eq!(summary.duration_ms, 240_000);
+    assert_eq!(summary.session_runs.len(), 3);
+    assert_eq!(summary.session_runs[1].command, ["cargo", "test"]);
+
+    let config = Config::default();
+    let body = notification_body(&config, &summary);
+    assert!(
+        body.ends_with(
+            "\nsession release (3):\n- ok cargo build (1.00s)\n- FAIL cargo test (1.00s)\n- ok ./deploy.sh (1.00s)"
+        ),
+        "{body}"
+    );
+}
+
+#[test]
+fn ended_sessions_start_over() {
+    let temp = TempDir::new().unwrap();
+    let store = StateStore::at(temp.path());
+    let now = Utc::now();
+    record_run(
+        &store,
+        &run("cargo build", 0, Some("release"), &now.to_rfc3339()),
+    )
+    .unwrap();
+    save_thread(&store, "slack", "release", "1".to_string(), now).unwrap();
+
+    end_session(
+        &store,
+        "release",
+        &["slack".to_string()],
+        now + TimeDelta::seconds(1),
+    )
+    .unwrap();
+    assert!(session_runs(&store, "release").is_empty());
+    assert_eq!(load_thread(&store, "slack", "release", now), None);
+    assert!(session_summary("release", &[], &environment(&[])).is_none());
+
+    let later = (now + TimeDelta::seconds(2)).to_rfc3339();
+    record_run(&store, &run("cargo test", 0, Some("release"), &later)).unwrap();
+    assert_eq!(session_runs(&store, "release").len(), 1);
+}
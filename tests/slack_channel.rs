@@ -0,0 +1,114 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::template::preview;
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::fs;
+use tempfile::TempDir;
+
+fn load(channel: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [slack]\nchannels:\n  slack:\n    type: slack\n{channel}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 2;
+    event.status = "failure".to_string();
+    event.command = vec!["cargo".to_string(), "test".to_string()];
+    event.host = "build-01".to_string();
+    event.duration_ms = 65_000;
+    event.output_tail = Some("test result: FAILED".to_string());
+    event
+}
+
+#[test]
+fn sends_block_kit_messages() {
+    let server = MockServer::start(vec![]);
+    let config = load(&format!(
+        "    url: {}\n    attach_output: true\n",
+        server.url("/services/T/B/x")
+    ))
+    .unwrap();
+
+    let result = notify_selected(&config, &["slack".to_string()], &failed_event()).remove(0);
+
+    assert!(result.success, "{:?}", result.error);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/services/T/B/x");
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+    let body = requests[0].json();
+    assert_eq!(body["text"], "brb: failed (exit 2): cargo test");
+    let attachment = &body["attachments"][0];
+    assert_eq!(attachment["color"], "#e01e5a");
+    let blocks = attachment["blocks"].as_array().unwrap();
+    assert_eq!(blocks[0]["type"], "header");
+    assert_eq!(blocks[0]["text"]["text"], "brb: failed (exit 2)");
+    let fields = blocks[1]["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["text"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [
+            "*Command*\n`cargo test`",
+            "*Duration*\n65.00s",
+            "*Host*\nbuild-01",
+            "*Exit code*\n2",
+        ]
+    );
+    assert_eq!(
+        blocks[2]["text"]["text"],
+        "*output*\n```test result: FAILED```"
+    );
+    assert_eq!(blocks.last().unwrap()["type"], "context");
+}
+
+#[test]
+fn retries_server_errors() {
+    let server = MockServer::start(vec![MockResponse::status(503)]);
+    let config = load(&format!(
+        "    url: {}\n    retries: 1\n",
+        server.url("/hook")
+    ))
+    .unwrap();
+
+    let result = notify_selected(&config, &["slack".to_string()], &failed_event()).remove(0);
+
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(result.attempts, 2);
+}
+
+#[test]
+fn rejects_invalid_urls() {
+    let error = load("    url: not a url\n").unwrap_err();
+    assert!(
+        error.contains("channel `slack` has an invalid url"),
+        "{error}"
+    );
+
+    let error = load("    url: https://hooks.slack.com/x\n    format: slack\n").unwrap_err();
+    assert!(error.contains("unknown field `format`"), "{error}");
+}
+
+#[test]
+fn previews_the_blocks() {
+    let config = load("    url: https://hooks.slack.com/services/T/B/x\n").unwrap();
+
+    let output = preview(&config, "slack", &failed_event()).unwrap();
+
+    assert!(output.contains("payload:\n{"), "{output}");
+    assert!(output.contains("\"type\": \"header\""), "{output}");
+}
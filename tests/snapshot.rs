@@ -0,0 +1,67 @@
+use brb_cli::config::SnapshotConfig;
+use brb_cli::snapshot::{
+    parse_cpuinfo, parse_meminfo, sample_snapshot, version_command, version_line,
+};
+
+#[test]
+fn disabled_snapshot_is_not_sampled() {
+    assert!(sample_snapshot(&SnapshotConfig::default()).is_none());
+}
+
+#[test]
+fn bare_tools_are_asked_for_their_version() {
+    assert_eq!(
+        version_command("rustc"),
+        Some(("rustc".to_string(), vec!["--version".to_string()]))
+    );
+    assert_eq!(
+        version_command(" python3  -V "),
+        Some(("python3".to_string(), vec!["-V".to_string()]))
+    );
+    assert_eq!(version_command("  "), None);
+}
+
+#[test]
+fn version_line_prefers_stdout() {
+    assert_eq!(
+        version_line("\nnode v20.11.0\nextra\n", "warning"),
+        Some("node v20.11.0".to_string())
+    );
+    assert_eq!(
+        version_line("", "openjdk version \"1.8.0\"\n"),
+        Some("openjdk version \"1.8.0\"".to_string())
+    );
+    assert_eq!(version_line(" \n", ""), None);
+}
+
+#[test]
+fn parses_cpu_model_and_total_memory() {
+    let cpuinfo = "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel\t\t: 85\nmodel name\t: Intel(R) Xeon(R) Gold 6148\n";
+    assert_eq!(
+        parse_cpuinfo(cpuinfo),
+        Some("Intel(R) Xeon(R) Gold 6148".to_string())
+    );
+    assert_eq!(parse_cpuinfo("processor\t: 0\n"), None);
+
+    let meminfo = "MemTotal:       16314384 kB\nMemFree:         1024000 kB\n";
+    assert_eq!(parse_meminfo(meminfo), Some(16314384 * 1024));
+    assert_eq!(parse_meminfo("MemFree: 1 kB\n"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn records_versions_of_tools_that_run() {
+    let snapshot = sample_snapshot(&SnapshotConfig {
+        tools: vec![
+            "echo brb 1.2.3".to_string(),
+            "false".to_string(),
+            "brb-no-such-tool".to_string(),
+        ],
+        hardware: true,
+    })
+    .unwrap();
+
+    assert_eq!(snapshot.tools.len(), 1);
+    assert_eq!(snapshot.tools["echo"], "brb 1.2.3");
+    assert!(snapshot.cpu_cores.is_some_and(|cores| cores > 0));
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::probe::{AuthStatus, check_channels_auth};
+use brb_cli::template::preview;
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::fs;
+use tempfile::TempDir;
+
+const TOKEN: &str = "123456:ABC-DEF1234ghIkl";
+
+fn load(channel: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [phone]\nchannels:\n  phone:\n    type: telegram\n{channel}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 2;
+    event.status = "failure".to_string();
+    event.command = vec!["grep".to_string(), "<main>".to_string()];
+    event
+}
+
+#[test]
+fn sends_html_messages_through_the_bot_api() {
+    let server = MockServer::start(vec![]);
+    let config = load(&format!(
+        "    bot_token: {TOKEN}\n    chat_id: -1001234\n    api_url: {}\n",
+        server.url("")
+    ))
+    .unwrap();
+
+    let result = notify_selected(&config, &["phone".to_string()], &failed_event()).remove(0);
+
+    assert!(result.success, "{:?}", result.error);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, format!("/bot{TOKEN}/sendMessage"));
+    let body = requests[0].json();
+    assert_eq!(body["chat_id"], "-1001234");
+    assert_eq!(body["parse_mode"], "HTML");
+    let text = body["text"].as_str().unwrap();
+    assert!(
+        text.starts_with("<b>brb: failed (exit 2)</b>\ngrep &lt;main&gt; ("),
+        "{text}"
+    );
+}
+
+#[test]
+fn failures_never_show_the_token() {
+    let server = MockServer::start(vec![MockResponse::status(503), MockResponse::status(503)]);
+    let config = load(&format!(
+        "    bot_token: {TOKEN}\n    chat_id: \"@builds\"\n    api_url: {}\n    retries: 1\n",
+        server.url("")
+    ))
+    .unwrap();
+
+    let result = notify_selected(&config, &["phone".to_string()], &failed_event()).remove(0);
+
+    assert!(!result.success);
+    assert_eq!(result.attempts, 2);
+    let error = result.error.unwrap().message;
+    assert!(!error.contains(TOKEN), "{error}");
+}
+
+#[test]
+fn checks_the_token_with_get_me() {
+    let server = MockServer::start(vec![
+        MockResponse::status(200).body(r#"{"ok":true,"result":{"username":"brb_bot"}}"#),
+        MockResponse::status(401).body(r#"{"ok":false,"description":"Unauthorized"}"#),
+    ]);
+    let config = load(&format!(
+        "    bot_token: {TOKEN}\n    chat_id: 42\n    api_url: {}\n",
+        server.url("")
+    ))
+    .unwrap();
+
+    assert_eq!(check_channels_auth(&config)[0].status, AuthStatus::Accepted);
+    assert_eq!(check_channels_auth(&config)[0].status, AuthStatus::Rejected);
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, format!("/bot{TOKEN}/getMe"));
+}
+
+#[test]
+fn validates_the_token_and_chat() {
+    let error = load("    bot_token: \"\"\n    chat_id: 42\n").unwrap_err();
+    assert!(
+        error.contains("channel `phone` has an empty bot_token"),
+        "{error}"
+    );
+
+    let error = load("    bot_token: not-a-token\n    chat_id: 42\n").unwrap_err();
+    assert!(error.contains("BotFather's"), "{error}");
+    assert!(!error.contains("not-a-token"), "{error}");
+
+    let error = load(&format!("    bot_token: {TOKEN}\n    chat_id: \"\"\n")).unwrap_err();
+    assert!(error.contains("an empty chat_id"), "{error}");
+
+    let error = load(&format!("    bot_token: {TOKEN}\n")).unwrap_err();
+    assert!(error.contains("missing field `chat_id`"), "{error}");
+}
+
+#[test]
+fn previews_the_message() {
+    let config = load(&format!("    bot_token: {TOKEN}\n    chat_id: 42\n")).unwrap();
+
+    let output = preview(&config, "phone", &failed_event()).unwrap();
+
+    assert!(output.contains("\"parse_mode\": \"HTML\""), "{output}");
+    assert!(!output.contains(TOKEN), "{output}");
+}
@@ -0,0 +1,221 @@
+use brb_cli::config::{Config, Priority, WebhookChannel, WebhookFormat, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::{WebhookPayload, message_text, webhook_payload};
+use brb_cli::template::{desktop_preview, preview};
+use serde_json::json;
+use std::fs;
+use tempfile::TempDir;
+
+fn load(yaml: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.yml");
+    fs::write(&config_path, yaml).unwrap();
+    load_config_from_path(&config_path).map_err(|error| error.to_string())
+}
+
+fn with_templates(templates: &str) -> String {
+    format!(
+        r#"
+version: 1
+default_channels: [desktop]
+display:
+  locale: en
+channels:
+  desktop:
+    type: desktop
+    template: short
+templates:
+{templates}
+"#
+    )
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 3;
+    event.status = "failure".to_string();
+    event.host = "ci-1".to_string();
+    event
+}
+
+#[test]
+fn renders_placeholders_and_includes() {
+    let config = load(&with_templates(
+        r#"  where: "on {host}"
+  short:
+    title: "{emoji} {status}"
+    body: "{command} exited {exit_code} {> where} {{literal}}""#,
+    ))
+    .unwrap();
+
+    let text = message_text(&config, Some("short"), &failed_event());
+    assert_eq!(text.title, "❌ failure");
+    assert_eq!(text.body, "brb channels test exited 3 on ci-1 {literal}");
+}
+
+#[test]
+fn missing_parts_keep_builtin_wording() {
+    let config = load(&with_templates(
+        r#"  short:
+    body: "{title}!""#,
+    ))
+    .unwrap();
+
+    let text = message_text(&config, Some("short"), &failed_event());
+    assert_eq!(text.title, "brb: failed (exit 3)");
+    assert_eq!(text.body, "brb: failed (exit 3)!");
+}
+
+#[test]
+fn webhook_presets_use_channel_template() {
+    let config = load(&with_templates(
+        r#"  short:
+    title: "{command} broke""#,
+    ))
+    .unwrap();
+    let webhook = WebhookChannel {
+        url: vec!["https://example.com".to_string()],
+        format: WebhookFormat::Ntfy,
+        ..Default::default()
+    };
+
+    let WebhookPayload::Text { headers, .. } =
+        webhook_payload(&config, &webhook, Some("short"), &failed_event())
+    else {
+        panic!("expected text payload");
+    };
+    assert_eq!(headers["Title"], "brb channels test broke");
+}
+
+#[test]
+fn rejects_unknown_placeholders() {
+    let error = load(&with_templates(
+        r#"  short:
+    title: "{stauts}""#,
+    ))
+    .unwrap_err();
+    assert!(error.contains("templates.short.title: unknown placeholder `{stauts}`"));
+}
+
+#[test]
+fn rejects_missing_and_cyclic_includes() {
+    let error = load(&with_templates(
+        r#"  short:
+    body: "{> nope}""#,
+    ))
+    .unwrap_err();
+    assert!(error.contains("included template `nope` is not defined"));
+
+    let error = load(&with_templates(
+        r#"  a: "{> b}"
+  b: "{> a}"
+  short:
+    body: "{> a}""#,
+    ))
+    .unwrap_err();
+    assert!(error.contains("templates include themselves: a -> b -> a"));
+}
+
+#[test]
+fn channel_template_must_be_a_message_template() {
+    let error = load(&with_templates(r#"  short: "just a snippet""#)).unwrap_err();
+    assert!(error.contains("channel `desktop` uses template `short`"));
+}
+
+#[test]
+fn preview_renders_channel_text_and_webhook_payload() {
+    let yaml = with_templates(
+        r#"  short:
+    title: "{status} on {host}""#,
+    )
+    .replace(
+        "    template: short\n",
+        "    template: short\n  hook:\n    type: webhook\n    url: https://example.com\n    format: discord\n    template: short\n",
+    );
+    let config = load(&yaml).unwrap();
+
+    let event = CompletionEvent::test_event()
+        .with_overrides(&json!({ "exit_code": 4, "host": "build-7" }))
+        .unwrap();
+    assert_eq!(event.status, "failure");
+
+    let output = preview(&config, "hook", &event).unwrap();
+    assert!(output.starts_with("title: failure on build-7\n"));
+    assert!(output.contains("\"content\": \"failure on build-7\""));
+
+    let output = preview(&config, "short", &event).unwrap();
+    assert!(!output.contains("payload:"));
+
+    let error = preview(&config, "nope", &event).unwrap_err();
+    assert!(error.contains("neither a channel nor a title/body template"));
+}
+
+#[test]
+fn desktop_preview_shows_title_body_and_urgency() {
+    let yaml = with_templates(
+        r#"  short:
+    title: "{status} after {duration}""#,
+    )
+    .replace(
+        "    template: short\n",
+        "    template: short\n  hook:\n    type: webhook\n    url: https://example.com\n",
+    );
+    let mut config = load(&yaml).unwrap();
+    let mut event = failed_event();
+    event.duration_ms = 95 * 60 * 1000;
+
+    let output = desktop_preview(&config, "desktop", &event).unwrap();
+    assert!(output.starts_with("title: failure after 5,700.00s\nbody:\n"));
+    assert!(output.ends_with("urgency: normal\n"));
+
+    config.appearance.failure.priority = Some(Priority::High);
+    let output = desktop_preview(&config, "desktop", &event).unwrap();
+    assert!(output.ends_with("urgency: critical\n"));
+
+    let output = desktop_preview(&config, "desktop", &CompletionEvent::test_event()).unwrap();
+    assert!(output.ends_with("urgency: normal\n"));
+
+    assert!(!output.contains("sound:"));
+
+    let error = desktop_preview(&config, "hook", &event).unwrap_err();
+    assert!(error.contains("`hook` is not a desktop channel"));
+}
+
+#[test]
+fn desktop_preview_cuts_long_bodies() {
+    let config = load(
+        "version: 1\ndefault_channels: [desktop]\nchannels:\n  desktop:\n    type: desktop\n    max_message_length: 60\n",
+    )
+    .unwrap();
+    let mut event = failed_event();
+    event.notes = vec!["x".repeat(500)];
+
+    let output = desktop_preview(&config, "desktop", &event).unwrap();
+    let body = output
+        .split_once("body:\n")
+        .and_then(|(_, rest)| rest.split_once("\nurgency:"))
+        .map(|(body, _)| body)
+        .unwrap();
+    assert_eq!(body.chars().count(), 60);
+    assert!(body.ends_with("… [truncated]"));
+}
+
+#[test]
+fn renders_the_project_name() {
+    let config = load(&with_templates(
+        r#"  short:
+    title: "{emoji} {project}: {command}""#,
+    ))
+    .unwrap();
+
+    let mut event = CompletionEvent::test_event();
+    assert_eq!(
+        message_text(&config, Some("short"), &event).title,
+        "✅ brb: brb channels test"
+    );
+    event.project = Some("myservice".to_string());
+    assert_eq!(
+        message_text(&config, Some("short"), &event).title,
+        "✅ myservice: brb channels test"
+    );
+}
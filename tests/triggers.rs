@@ -0,0 +1,159 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::{notification_body, notification_title};
+use brb_cli::runner::OutputStream;
+use brb_cli::test_support::MockServer;
+use brb_cli::trigger::TriggerWatch;
+use serde_json::Value;
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tempfile::TempDir;
+
+fn load(url: &str, triggers: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [infra]\nchannels:\n  infra: {{ type: webhook, url: \"{url}\" }}\ntriggers:\n{triggers}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+const OOM: &str = "  - pattern: OutOfMemoryError\n    stream: stderr\n    channels: [infra]\n";
+
+#[test]
+fn fires_once_on_the_watched_stream() {
+    let server = MockServer::start(vec![]);
+    let config = load(&server.url("/hook"), OOM).unwrap();
+    let watch = TriggerWatch::new(&config, CompletionEvent::test_event).unwrap();
+
+    watch.check(OutputStream::Stdout, "grep OutOfMemoryError app.log");
+    watch.check(OutputStream::Stderr, "starting workers");
+    watch.check(
+        OutputStream::Stderr,
+        "java.lang.OutOfMemoryError: Java heap space",
+    );
+    watch.check(OutputStream::Stderr, "java.lang.OutOfMemoryError: again");
+    let results = watch.finish();
+
+    assert_eq!(results.len(), 1);
+    assert!(results[0].success, "{:?}", results[0].error);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["status"], "triggered");
+    assert_eq!(
+        body["output_match"],
+        "java.lang.OutOfMemoryError: Java heap space"
+    );
+}
+
+#[test]
+fn builds_the_event_only_when_a_trigger_first_fires() {
+    let server = MockServer::start(vec![]);
+    let config = load(
+        &server.url("/hook"),
+        "  - pattern: OOM\n    channels: [infra]\n  - pattern: panicked\n    channels: [infra]\n",
+    )
+    .unwrap();
+    let builds = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&builds);
+    let watch = TriggerWatch::new(&config, move || {
+        counted.fetch_add(1, Ordering::SeqCst);
+        CompletionEvent::test_event()
+    })
+    .unwrap();
+
+    watch.check(OutputStream::Stdout, "compiling");
+    assert_eq!(builds.load(Ordering::SeqCst), 0);
+
+    watch.check(OutputStream::Stderr, "OOM killer invoked");
+    watch.check(OutputStream::Stderr, "thread 'main' panicked");
+    assert_eq!(watch.finish().len(), 2);
+    assert_eq!(builds.load(Ordering::SeqCst), 1);
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+fn triggers_and_the_completion_have_their_own_idempotency_keys() {
+    let server = MockServer::start(vec![]);
+    let config = load(&server.url("/hook"), OOM).unwrap();
+    let event = CompletionEvent::test_event();
+    let base = event.clone();
+    let watch = TriggerWatch::new(&config, move || base.clone()).unwrap();
+
+    watch.check(OutputStream::Stderr, "java.lang.OutOfMemoryError");
+    assert!(watch.finish()[0].success);
+    let results = notify_selected(&config, &["infra".to_string()], &event);
+    assert!(results[0].success, "{:?}", results[0].error);
+
+    let requests = server.requests();
+    let keys = requests
+        .iter()
+        .map(|request| request.header("idempotency-key").unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            format!("{}:infra:trigger-0", event.run_id),
+            format!("{}:infra", event.run_id),
+        ]
+    );
+}
+
+#[test]
+fn triggered_events_say_the_command_is_still_running() {
+    let config = load(
+        "https://example.com/hook",
+        "  - pattern: panicked\n    channels: [infra]\n    message: worker panicked\n",
+    )
+    .unwrap();
+    let mut event = CompletionEvent::test_event();
+    event.output_match = Some("thread 'main' panicked at src/main.rs:3:5".to_string());
+
+    assert_eq!(
+        notification_title(&config, &event),
+        "brb: output matched (running…)"
+    );
+    let body = notification_body(&config, &event);
+    assert!(
+        body.ends_with("running…)\noutput matched: thread 'main' panicked at src/main.rs:3:5"),
+        "{body}"
+    );
+
+    event.exit_reason = Some("worker panicked".to_string());
+    assert_eq!(
+        notification_title(&config, &event),
+        "brb: worker panicked (running…)"
+    );
+}
+
+#[test]
+fn rejects_bad_patterns_and_unknown_channels() {
+    let error = load(
+        "https://example.com/hook",
+        "  - pattern: \"(unclosed\"\n    channels: [infra]\n",
+    )
+    .unwrap_err();
+    assert!(
+        error.contains("triggers[0] pattern `(unclosed` is not a valid regex"),
+        "{error}"
+    );
+
+    let error = load(
+        "https://example.com/hook",
+        "  - pattern: OOM\n    channels: [infar]\n",
+    )
+    .unwrap_err();
+    assert!(
+        error.contains("triggers[0] uses channel `infar`, which is not defined in channels"),
+        "{error}"
+    );
+}
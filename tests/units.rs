@@ -0,0 +1,42 @@
+use brb_cli::units::{parse_duration, parse_size};
+use std::time::Duration;
+
+#[test]
+fn parses_compound_durations() {
+    assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+    assert_eq!(parse_duration("1h 30m").unwrap(), Duration::from_secs(5400));
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("2D").unwrap(), Duration::from_secs(172_800));
+}
+
+#[test]
+fn rejects_invalid_durations_consistently() {
+    for value in ["", "90", "0s", "1.5h", "m", "10 minutes"] {
+        let error = parse_duration(value).unwrap_err();
+        assert_eq!(
+            error,
+            format!("invalid duration `{value}` (expected e.g. 30s, 15m, 1h30m)")
+        );
+    }
+}
+
+#[test]
+fn parses_decimal_and_binary_sizes() {
+    assert_eq!(parse_size("512").unwrap(), 512);
+    assert_eq!(parse_size("512kb").unwrap(), 512_000);
+    assert_eq!(parse_size("10MB").unwrap(), 10_000_000);
+    assert_eq!(parse_size("64KiB").unwrap(), 65_536);
+    assert_eq!(parse_size("1 gib").unwrap(), 1_073_741_824);
+}
+
+#[test]
+fn rejects_invalid_sizes_consistently() {
+    for value in ["", "kb", "1.5mb", "1mb2kb", "10 bytes"] {
+        let error = parse_size(value).unwrap_err();
+        assert_eq!(
+            error,
+            format!("invalid size `{value}` (expected e.g. 512kb, 10mb, 1gb)")
+        );
+    }
+}
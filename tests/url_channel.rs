@@ -0,0 +1,87 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{ChannelConfig, Config, WebhookFormat, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::test_support::MockServer;
+use std::fs;
+use tempfile::TempDir;
+
+fn load(target: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [quick]\nchannels:\n  quick:\n    type: url\n    notify_on: failure\n    target: \"{target}\"\n"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+#[test]
+fn delivers_through_the_channel_the_target_describes() {
+    let server = MockServer::start(vec![]);
+    let config = load(&format!("json://127.0.0.1:{}/hooks/brb", server.port())).unwrap();
+
+    let result = notify_selected(
+        &config,
+        &["quick".to_string()],
+        &CompletionEvent::test_event(),
+    )
+    .remove(0);
+
+    assert!(result.success, "{:?}", result.error);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/hooks/brb");
+    assert_eq!(requests[0].json()["status"], "success");
+}
+
+#[test]
+fn resolves_shoutrrr_and_apprise_forms() {
+    let config = load("discord://abc-token@1234").unwrap();
+    let channel = &config.channels["quick"];
+    assert_eq!(channel.type_name(), "webhook");
+    let ChannelConfig::Webhook(webhook) = &channel.kind else {
+        panic!("expected a webhook channel");
+    };
+    assert_eq!(webhook.format, WebhookFormat::Discord);
+    assert_eq!(
+        webhook.url,
+        ["https://discord.com/api/webhooks/1234/abc-token"]
+    );
+
+    let config = load("discord://1234/abc-token").unwrap();
+    let ChannelConfig::Webhook(webhook) = &config.channels["quick"].kind else {
+        panic!("expected a webhook channel");
+    };
+    assert_eq!(
+        webhook.url,
+        ["https://discord.com/api/webhooks/1234/abc-token"]
+    );
+
+    let config = load("ntfy://builds").unwrap();
+    let ChannelConfig::Ntfy(ntfy) = &config.channels["quick"].kind else {
+        panic!("expected an ntfy channel");
+    };
+    assert_eq!(ntfy.server, "https://ntfy.sh");
+    assert_eq!(ntfy.topic, "builds");
+}
+
+#[test]
+fn rejects_unusable_targets_without_echoing_them() {
+    let error = load("gotify://host/s3cret-token").unwrap_err();
+    assert!(
+        error.contains("channel `quick` has an unusable target (unsupported"),
+        "{error}"
+    );
+    assert!(!error.contains("s3cret-token"), "{error}");
+
+    let error = load("tgram://123:abc/1/2").unwrap_err();
+    assert!(error.contains("use one channel per chat"), "{error}");
+
+    // the translated channel is validated like one written out in full.
+    let error = load("pover://short@token").unwrap_err();
+    assert!(error.contains("an invalid user_key"), "{error}");
+}
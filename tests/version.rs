@@ -0,0 +1,16 @@
+use brb_cli::version::build_info;
+
+#[test]
+fn build_info_describes_this_build() {
+    let info = build_info();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert!(!info.git_sha.is_empty());
+    assert!(!info.target.is_empty());
+
+    let date = info.build_date.as_bytes();
+    assert_eq!(date.len(), 10);
+    assert!(date[4] == b'-' && date[7] == b'-');
+
+    let json = serde_json::to_value(&info).unwrap();
+    assert!(json["features"].is_array());
+}
@@ -0,0 +1,342 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::{DeliveryErrorKind, DeliveryResult, notify_selected};
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Loads a config whose only channel, `hook`, is a webhook with the given
+/// settings, plus any other top-level YAML in `rest`.
+fn config(channel: &str, rest: &str) -> Config {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    let channel = channel
+        .lines()
+        .map(|line| format!("    {line}\n"))
+        .collect::<String>();
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [hook]\n{rest}\nchannels:\n  hook:\n    type: webhook\n{channel}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).unwrap()
+}
+
+fn deliver(config: &Config, event: &CompletionEvent) -> DeliveryResult {
+    notify_selected(config, &["hook".to_string()], event).remove(0)
+}
+
+#[test]
+fn sends_the_event_with_method_and_headers() {
+    let server = MockServer::start(vec![]);
+    let config = config(
+        &format!(
+            "url: {}\nmethod: PUT\nheaders:\n  X-Token: abc\nextra:\n  team: infra",
+            server.url("/events?source=brb")
+        ),
+        "",
+    );
+    let event = CompletionEvent::test_event();
+
+    let result = deliver(&config, &event);
+
+    assert!(result.success, "{:?}", result.error);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!(request.method, "PUT");
+    assert_eq!(request.path, "/events?source=brb");
+    assert_eq!(request.header("X-Token"), Some("abc"));
+    assert_eq!(request.header("content-type"), Some("application/json"));
+    let expected_key = format!("{}:hook", event.run_id);
+    assert_eq!(
+        request.header("idempotency-key"),
+        Some(expected_key.as_str())
+    );
+    let body = request.json();
+    assert_eq!(body["run_id"], event.run_id);
+    assert_eq!(body["team"], "infra");
+}
+
+#[test]
+fn sends_cloudevents_with_their_content_type() {
+    let server = MockServer::start(vec![]);
+    let config = config(
+        &format!(
+            "url: {}\nformat: cloudevents\nextra:\n  team: infra",
+            server.url("/events")
+        ),
+        "",
+    );
+    let event = CompletionEvent::test_event();
+
+    let result = deliver(&config, &event);
+
+    assert!(result.success, "{:?}", result.error);
+    let request = &server.requests()[0];
+    assert_eq!(
+        request.header("content-type"),
+        Some("application/cloudevents+json")
+    );
+    let body = request.json();
+    assert_eq!(body["specversion"], "1.0");
+    assert_eq!(body["id"], event.run_id);
+    assert_eq!(body["team"], "infra");
+    assert_eq!(body["data"]["run_id"], event.run_id);
+}
+
+#[test]
+fn renders_chat_formats_through_templates() {
+    let server = MockServer::start(vec![]);
+    let config = config(
+        &format!(
+            "url: {}\nformat: slack\ntemplate: short",
+            server.url("/slack")
+        ),
+        "templates:\n  short:\n    title: \"{status} on {host}\"",
+    );
+    let mut event = CompletionEvent::test_event();
+    event.host = "build-01".to_string();
+
+    let result = deliver(&config, &event);
+
+    assert!(result.success, "{:?}", result.error);
+    let body = server.requests()[0].json();
+    assert_eq!(body["attachments"][0]["title"], "success on build-01");
+    assert_eq!(body["attachments"][0]["footer"], "build-01");
+}
+
+#[test]
+fn retries_server_errors_with_the_same_request() {
+    let server = MockServer::start(vec![MockResponse::status(502), MockResponse::status(200)]);
+    let config = config(&format!("url: {}\nretries: 1", server.url("/hook")), "");
+
+    let result = deliver(&config, &CompletionEvent::test_event());
+
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(result.attempts, 2);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0], requests[1]);
+}
+
+#[test]
+fn client_errors_are_not_retried() {
+    let server = MockServer::start(vec![MockResponse::status(400).body("bad payload")]);
+    let config = config(&format!("url: {}\nretries: 3", server.url("/hook")), "");
+
+    let result = deliver(&config, &CompletionEvent::test_event());
+
+    assert!(!result.success);
+    assert_eq!(
+        result.error.unwrap().kind,
+        DeliveryErrorKind::Http { status: 400 }
+    );
+    assert_eq!(result.attempts, 1);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn slow_receivers_time_out() {
+    let server = MockServer::start(vec![
+        MockResponse::status(204).delay(Duration::from_secs(1)),
+    ]);
+    let config = config(
+        &format!("url: {}", server.url("/hook")),
+        "http:\n  timeout: 200ms",
+    );
+
+    let result = deliver(&config, &CompletionEvent::test_event());
+
+    assert!(!result.success);
+    assert_eq!(result.error.unwrap().kind, DeliveryErrorKind::Timeout);
+    assert_eq!(result.attempts, 1);
+}
+
+#[test]
+fn fans_out_to_every_url() {
+    let first = MockServer::start(vec![]);
+    let second = MockServer::start(vec![MockResponse::status(500)]);
+    let config = config(
+        &format!(
+            "urls:\n  - {}\n  - {}",
+            first.url("/hook"),
+            second.url("/hook")
+        ),
+        "",
+    );
+    let event = CompletionEvent::test_event();
+
+    let result = deliver(&config, &event);
+
+    assert!(!result.success);
+    let error = result.error.unwrap();
+    assert_eq!(error.kind, DeliveryErrorKind::Http { status: 500 });
+    assert!(error.message.starts_with("1 of 2 webhook urls failed"));
+    assert_eq!(result.targets.len(), 2);
+    assert!(result.targets[0].success);
+    assert_eq!(result.targets[1].host, "127.0.0.1");
+    assert!(!result.targets[1].success);
+    assert_eq!(result.attempts, 2);
+    assert_eq!(
+        first.requests()[0].body,
+        second.requests()[0].body,
+        "every url gets the same payload"
+    );
+}
+
+#[test]
+fn url_accepts_a_list() {
+    let first = MockServer::start(vec![]);
+    let second = MockServer::start(vec![]);
+    let config = config(
+        &format!("url: [{}, {}]", first.url("/a"), second.url("/b")),
+        "",
+    );
+
+    let result = deliver(&config, &CompletionEvent::test_event());
+
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(first.requests()[0].path, "/a");
+    assert_eq!(second.requests()[0].path, "/b");
+}
+
+#[test]
+fn ntfy_failures_wait_for_an_acknowledgement() {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 1;
+    let server = MockServer::start(vec![
+        MockResponse::status(200),
+        MockResponse::status(200).body(&format!(
+            "{{\"event\":\"message\",\"message\":\"{}\"}}\n",
+            event.run_id
+        )),
+    ]);
+    let config = config(
+        &format!(
+            "url: {}\nformat: ntfy\nack:\n  timeout: 30s",
+            server.url("/builds")
+        ),
+        "",
+    );
+
+    let result = deliver(&config, &event);
+
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(result.acknowledged, Some(true));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    let action = requests[0].header("actions").unwrap();
+    assert!(action.starts_with("http, Acknowledge, http://"), "{action}");
+    assert!(action.contains("/builds-ack, method=POST"), "{action}");
+    assert!(
+        action.contains(&format!("body={}", event.run_id)),
+        "{action}"
+    );
+    assert!(
+        requests[1]
+            .path
+            .starts_with("/builds-ack/json?poll=1&since=")
+    );
+}
+
+#[test]
+fn unacknowledged_failures_escalate() {
+    let ntfy = MockServer::start(vec![]);
+    let pager = MockServer::start(vec![]);
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [hook]\nchannels:\n  hook:\n    type: webhook\n    url: {}\n    format: ntfy\n    ack:\n      timeout: 1ms\n      escalate_to: [pager]\n  pager:\n    type: webhook\n    url: {}\n",
+            ntfy.url("/builds"),
+            pager.url("/page")
+        ),
+    )
+    .unwrap();
+    let config = load_config_from_path(&path).unwrap();
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 1;
+
+    let results = notify_selected(&config, &["hook".to_string()], &event);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].acknowledged, Some(false));
+    assert_eq!(results[1].channel_id, "pager");
+    assert!(results[1].success, "{:?}", results[1].error);
+    assert_eq!(pager.requests().len(), 1);
+
+    event.exit_code = 0;
+    let results = notify_selected(&config, &["hook".to_string()], &event);
+    assert_eq!(results.len(), 1, "successes need no acknowledgement");
+    assert_eq!(results[0].acknowledged, None);
+    assert!(ntfy.requests()[2].header("actions").is_none());
+}
+
+/// Loads a config with the webhook channels `primary`, `backup` and `pager`
+/// pointing at the given URLs and an escalation chain of `backup` then
+/// `pager`.
+fn escalation_config(primary: &str, backup: &str, pager: &str) -> Config {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [primary]\nescalation:\n  - channels: [backup]\n  - channels: [pager]\nchannels:\n  primary:\n    type: webhook\n    url: {primary}\n  backup:\n    type: webhook\n    url: {backup}\n  pager:\n    type: webhook\n    url: {pager}\n"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).unwrap()
+}
+
+#[test]
+fn failed_deliveries_walk_the_escalation_chain() {
+    let primary = MockServer::start(vec![MockResponse::status(500)]);
+    let backup = MockServer::start(vec![MockResponse::status(500)]);
+    let pager = MockServer::start(vec![]);
+    let config = escalation_config(
+        &primary.url("/hook"),
+        &backup.url("/hook"),
+        &pager.url("/hook"),
+    );
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 1;
+
+    let results = notify_selected(&config, &["primary".to_string()], &event);
+
+    let channels = results
+        .iter()
+        .map(|result| result.channel_id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(channels, ["primary", "backup", "pager"]);
+    assert!(results[2].success, "{:?}", results[2].error);
+}
+
+#[test]
+fn escalation_stops_once_a_step_delivers() {
+    let primary = MockServer::start(vec![MockResponse::status(500)]);
+    let backup = MockServer::start(vec![]);
+    let pager = MockServer::start(vec![]);
+    let config = escalation_config(
+        &primary.url("/hook"),
+        &backup.url("/hook"),
+        &pager.url("/hook"),
+    );
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 1;
+
+    let results = notify_selected(&config, &["primary".to_string()], &event);
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[1].channel_id, "backup");
+    assert!(pager.requests().is_empty());
+
+    event.exit_code = 0;
+    let results = notify_selected(&config, &["primary".to_string()], &event);
+    assert_eq!(results.len(), 1, "successes are never escalated");
+}
@@ -0,0 +1,421 @@
+use brb_cli::config::{
+    Channel, ChannelConfig, Config, DisplayTimezone, Oversize, Priority, StatusAppearance,
+    WebhookChannel, WebhookFormat,
+};
+use brb_cli::event::CompletionEvent;
+use brb_cli::format::{
+    TRUNCATION_MARKER, WebhookPayload, channel_payload, elide_command, fitted_payload,
+    notification_body, payload_size, render_timestamp, webhook_payload,
+};
+
+fn english_config() -> Config {
+    let mut config = Config::default();
+    config.display.locale = Some("en".to_string());
+    config
+}
+
+fn webhook(format: WebhookFormat) -> WebhookChannel {
+    WebhookChannel {
+        url: vec!["https://example.com/hook".to_string()],
+        format,
+        ..Default::default()
+    }
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.status = "failure".to_string();
+    event.exit_code = 2;
+    event
+}
+
+#[test]
+fn generic_format_sends_raw_event() {
+    let WebhookPayload::Json(body) = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Generic),
+        None,
+        &failed_event(),
+    ) else {
+        panic!("expected JSON payload");
+    };
+    assert_eq!(body["tool"], "brb");
+    assert_eq!(body["exit_code"], 2);
+}
+
+#[test]
+fn slack_format_uses_text_and_colored_attachment() {
+    let WebhookPayload::Json(body) = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Slack),
+        None,
+        &failed_event(),
+    ) else {
+        panic!("expected JSON payload");
+    };
+    assert!(
+        body["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("brb: failed (exit 2)")
+    );
+    assert_eq!(body["attachments"][0]["color"], "#e01e5a");
+}
+
+#[test]
+fn discord_and_teams_formats_have_required_fields() {
+    let WebhookPayload::Json(discord) = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Discord),
+        None,
+        &failed_event(),
+    ) else {
+        panic!("expected JSON payload");
+    };
+    assert!(discord["content"].is_string());
+    assert_eq!(discord["embeds"][0]["color"], 0xe01e5a);
+
+    let WebhookPayload::Json(teams) = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Teams),
+        None,
+        &failed_event(),
+    ) else {
+        panic!("expected JSON payload");
+    };
+    assert_eq!(teams["@type"], "MessageCard");
+    assert_eq!(teams["themeColor"], "E01E5A");
+}
+
+#[test]
+fn ntfy_format_sends_text_with_headers() {
+    let WebhookPayload::Text { body, headers } = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Ntfy),
+        None,
+        &failed_event(),
+    ) else {
+        panic!("expected text payload");
+    };
+    assert!(body.contains("brb channels test"));
+    assert_eq!(headers["Title"], "brb: failed (exit 2)");
+    assert_eq!(headers["Priority"], "high");
+    assert_eq!(headers["Tags"], "x");
+}
+
+#[test]
+fn chat_formats_attach_output_tail_on_failure() {
+    let mut channel = webhook(WebhookFormat::Discord);
+    channel.attach_output = true;
+    let mut event = failed_event();
+    event.output_tail = Some("error[E0308]: mismatched types".to_string());
+
+    let WebhookPayload::Json(body) = webhook_payload(&english_config(), &channel, None, &event)
+    else {
+        panic!("expected JSON payload");
+    };
+    let description = body["embeds"][0]["description"].as_str().unwrap();
+    assert!(description.contains("error[E0308]: mismatched types"));
+
+    event.exit_code = 0;
+    let WebhookPayload::Json(body) = webhook_payload(&english_config(), &channel, None, &event)
+    else {
+        panic!("expected JSON payload");
+    };
+    let description = body["embeds"][0]["description"].as_str().unwrap();
+    assert!(!description.contains("mismatched types"));
+}
+
+#[test]
+fn chat_formats_cut_messages_to_platform_limits() {
+    let mut event = failed_event();
+    event.notes = vec!["ü".repeat(3000); 4];
+
+    let WebhookPayload::Json(discord) = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Discord),
+        None,
+        &event,
+    ) else {
+        panic!("expected JSON payload");
+    };
+    let description = discord["embeds"][0]["description"].as_str().unwrap();
+    assert_eq!(description.chars().count(), 4096);
+    assert!(description.ends_with(&format!("{TRUNCATION_MARKER}\n```")));
+    assert!(description.starts_with("```\nbrb channels test"));
+
+    // ntfy counts bytes, and `ü` takes two.
+    let WebhookPayload::Text { body, .. } = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Ntfy),
+        None,
+        &event,
+    ) else {
+        panic!("expected text payload");
+    };
+    assert!(body.len() <= 4096);
+    assert!(body.ends_with(TRUNCATION_MARKER));
+
+    let WebhookPayload::Json(slack) = webhook_payload(
+        &english_config(),
+        &webhook(WebhookFormat::Slack),
+        None,
+        &event,
+    ) else {
+        panic!("expected JSON payload");
+    };
+    let text = slack["attachments"][0]["text"].as_str().unwrap();
+    assert!(!text.contains(TRUNCATION_MARKER));
+}
+
+#[test]
+fn max_message_length_overrides_the_platform_limit() {
+    let mut channel = webhook(WebhookFormat::Slack);
+    channel.max_message_length = Some(120);
+    channel.attach_output = true;
+    let mut event = failed_event();
+    event.notes = vec!["disk full on /var".to_string(); 20];
+    event.output_tail = Some("No space left on device".to_string());
+
+    let WebhookPayload::Json(slack) = webhook_payload(&english_config(), &channel, None, &event)
+    else {
+        panic!("expected JSON payload");
+    };
+    let text = slack["attachments"][0]["text"].as_str().unwrap();
+    assert_eq!(text.chars().count(), 120);
+    assert!(text.contains(&format!("{TRUNCATION_MARKER}```")));
+    assert!(text.ends_with("```No space left on device```"));
+}
+
+#[test]
+fn appearance_overrides_apply_to_presets() {
+    let mut config = english_config();
+    config.appearance.failure = StatusAppearance {
+        emoji: Some("🔥".to_string()),
+        color: Some("#ff8800".to_string()),
+        priority: Some(Priority::Urgent),
+    };
+
+    let WebhookPayload::Json(slack) = webhook_payload(
+        &config,
+        &webhook(WebhookFormat::Slack),
+        None,
+        &failed_event(),
+    ) else {
+        panic!("expected JSON payload");
+    };
+    assert!(
+        slack["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("🔥 brb: failed")
+    );
+    assert_eq!(slack["attachments"][0]["color"], "#ff8800");
+
+    let WebhookPayload::Text { headers, .. } = webhook_payload(
+        &config,
+        &webhook(WebhookFormat::Ntfy),
+        None,
+        &failed_event(),
+    ) else {
+        panic!("expected text payload");
+    };
+    assert_eq!(headers["Priority"], "urgent");
+}
+
+#[test]
+fn elides_middle_arguments_of_long_commands() {
+    let command: Vec<String> = ["npm", "run", "build", "--", "--mode", "production"]
+        .into_iter()
+        .map(String::from)
+        .chain((0..40).map(|index| format!("packages/module-{index}/src/index.ts")))
+        .chain(["--watch".to_string()])
+        .collect();
+
+    let elided = elide_command(&command, 80);
+    assert!(elided.chars().count() <= 80);
+    assert!(elided.starts_with("npm run build "));
+    assert!(elided.ends_with(" --watch"));
+    assert!(elided.contains(" … "));
+
+    let short = ["cargo", "test"].map(String::from);
+    assert_eq!(elide_command(&short, 80), "cargo test");
+
+    // a single huge argument is cut in the middle of the text.
+    let huge = vec!["node".to_string(), "x".repeat(500)];
+    let elided = elide_command(&huge, 80);
+    assert_eq!(elided.chars().count(), 80);
+    assert!(elided.starts_with("node xxx") && elided.contains('…'));
+}
+
+#[test]
+fn rendered_messages_shorten_commands_but_payloads_keep_them() {
+    let mut config = english_config();
+    config.display.max_command_length = 40;
+    let mut event = failed_event();
+    event.command = std::iter::once("npm".to_string())
+        .chain((0..200).map(|index| format!("--flag-{index}")))
+        .collect();
+
+    let body = notification_body(&config, &event);
+    assert!(body.starts_with("npm --flag-0 "));
+    assert!(body.contains(" … "));
+    assert!(!body.contains("--flag-100 "));
+
+    let WebhookPayload::Json(payload) =
+        webhook_payload(&config, &webhook(WebhookFormat::Generic), None, &event)
+    else {
+        panic!("expected JSON payload");
+    };
+    assert_eq!(payload["command"].as_array().unwrap().len(), 201);
+}
+
+#[test]
+fn renders_timestamps_in_configured_timezone_and_format() {
+    let mut config = english_config();
+    config.display.timezone = DisplayTimezone::try_from("+02:00".to_string()).unwrap();
+    config.display.timestamp_format = "%Y-%m-%d %H:%M".to_string();
+    assert_eq!(
+        render_timestamp(&config, "2026-02-22T23:30:00.000Z"),
+        "2026-02-23 01:30"
+    );
+
+    config.display.timezone = DisplayTimezone::Utc;
+    let mut event = failed_event();
+    event.finished_at = "2026-02-22T03:14:00.000Z".to_string();
+    assert!(notification_body(&config, &event).contains("finished 2026-02-22 03:14"));
+}
+
+#[test]
+fn channel_field_selection_trims_generic_payload() {
+    let mut hook = webhook(WebhookFormat::Generic);
+    hook.extra
+        .insert("team".to_string(), "platform".to_string());
+    let mut channel = Channel::from(ChannelConfig::Webhook(hook.clone()));
+    channel.include_fields = Some(vec!["status".to_string(), "duration_ms".to_string()]);
+
+    let WebhookPayload::Json(body) =
+        channel_payload(&english_config(), &channel, &hook, &failed_event())
+    else {
+        panic!("expected JSON payload");
+    };
+    let mut keys = body
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, ["duration_ms", "status", "team"]);
+
+    channel.include_fields = None;
+    channel.exclude_fields = vec!["cwd".to_string(), "host".to_string()];
+    let WebhookPayload::Json(body) =
+        channel_payload(&english_config(), &channel, &hook, &failed_event())
+    else {
+        panic!("expected JSON payload");
+    };
+    assert!(body.get("cwd").is_none() && body.get("host").is_none());
+    assert_eq!(body["exit_code"], 2);
+}
+
+#[test]
+fn field_map_renames_generic_payload_keys() {
+    let mut hook = webhook(WebhookFormat::Generic);
+    hook.field_map
+        .insert("exit_code".to_string(), "code".to_string());
+    hook.field_map
+        .insert("duration_ms".to_string(), "elapsed".to_string());
+    hook.field_map.insert("cwd".to_string(), "dir".to_string());
+    let mut channel = Channel::from(ChannelConfig::Webhook(hook.clone()));
+    channel.exclude_fields = vec!["cwd".to_string()];
+
+    let WebhookPayload::Json(body) =
+        channel_payload(&english_config(), &channel, &hook, &failed_event())
+    else {
+        panic!("expected JSON payload");
+    };
+    assert_eq!(body["code"], 2);
+    assert!(body["elapsed"].is_u64());
+    assert!(body.get("exit_code").is_none() && body.get("duration_ms").is_none());
+    // excluded fields stay excluded under their new name too.
+    assert!(body.get("dir").is_none() && body.get("cwd").is_none());
+    assert_eq!(body["status"], "failure");
+}
+
+#[test]
+fn cloudevents_format_carries_the_selected_fields_as_data() {
+    let mut hook = webhook(WebhookFormat::CloudEvents);
+    hook.extra
+        .insert("team".to_string(), "platform".to_string());
+    hook.field_map
+        .insert("exit_code".to_string(), "code".to_string());
+    let mut channel = Channel::from(ChannelConfig::Webhook(hook.clone()));
+    channel.include_fields = Some(vec!["status".to_string(), "exit_code".to_string()]);
+    let mut event = failed_event();
+    event.notes = vec!["flaky".to_string()];
+
+    let WebhookPayload::Json(body) = channel_payload(&english_config(), &channel, &hook, &event)
+    else {
+        panic!("expected JSON payload");
+    };
+    assert_eq!(body["specversion"], "1.0");
+    assert_eq!(body["type"], "com.github.mcmanussliam.brb.run.completed");
+    assert_eq!(body["source"], format!("brb://{}", event.host));
+    assert_eq!(body["id"], format!("{}:note-1", event.run_id));
+    assert_eq!(body["time"], event.finished_at);
+    // `extra` keys are extension attributes beside `data`.
+    assert_eq!(body["team"], "platform");
+    assert_eq!(
+        body["data"],
+        serde_json::json!({ "status": "failure", "code": 2 })
+    );
+}
+
+#[test]
+fn channel_field_selection_leaves_presets_alone() {
+    let hook = webhook(WebhookFormat::Slack);
+    let mut channel = Channel::from(ChannelConfig::Webhook(hook.clone()));
+    channel.include_fields = Some(vec!["status".to_string()]);
+
+    let WebhookPayload::Json(body) =
+        channel_payload(&english_config(), &channel, &hook, &failed_event())
+    else {
+        panic!("expected JSON payload");
+    };
+    assert!(body.get("attachments").is_some());
+}
+
+#[test]
+fn oversize_payloads_are_truncated_and_marked() {
+    let config = english_config();
+    let mut webhook = webhook(WebhookFormat::Slack);
+    webhook.attach_output = true;
+    webhook.max_payload = Some(900);
+    let channel = Channel::from(ChannelConfig::Webhook(webhook.clone()));
+    let mut event = failed_event();
+    event.output_tail = Some(format!("{}the last line", "x".repeat(1200)));
+
+    let (payload, truncated) = fitted_payload(&config, &channel, &webhook, &event).unwrap();
+    assert!(truncated);
+    assert!(payload_size(&payload) <= 900);
+    let WebhookPayload::Json(body) = payload else {
+        panic!("expected JSON payload");
+    };
+    assert_eq!(body["truncated"], true);
+    assert!(body.to_string().contains("the last line"));
+
+    event.output_tail = None;
+    let (_, truncated) = fitted_payload(&config, &channel, &webhook, &event).unwrap();
+    assert!(!truncated);
+
+    webhook.max_payload = Some(10);
+    let channel = Channel::from(ChannelConfig::Webhook(webhook.clone()));
+    let error = fitted_payload(&config, &channel, &webhook, &event).unwrap_err();
+    assert!(error.contains("after truncation"));
+
+    webhook.oversize = Oversize::Gzip;
+    let channel = Channel::from(ChannelConfig::Webhook(webhook.clone()));
+    let (_, truncated) = fitted_payload(&config, &channel, &webhook, &event).unwrap();
+    assert!(!truncated);
+}
@@ -0,0 +1,149 @@
+#![cfg(feature = "webhook")]
+
+use brb_cli::channels::notify_selected;
+use brb_cli::config::{Config, load_config_from_path};
+use brb_cli::event::CompletionEvent;
+use brb_cli::probe::{AuthStatus, check_channels_auth};
+use brb_cli::template::preview;
+use brb_cli::test_support::{MockResponse, MockServer};
+use std::collections::BTreeMap;
+use std::fs;
+use tempfile::TempDir;
+
+const API_KEY: &str = "gjA04ZYcqXKalvYMA8OeXSfzUOLrtbZv";
+
+fn load(channel: &str) -> Result<Config, String> {
+    let temp = TempDir::new().unwrap();
+    let path = temp.path().join("config.yml");
+    fs::write(
+        &path,
+        format!(
+            "version: 1\ndefault_channels: [chat]\nchannels:\n  chat:\n    type: zulip\n{channel}"
+        ),
+    )
+    .unwrap();
+    load_config_from_path(&path).map_err(|error| error.to_string())
+}
+
+fn channel(site: &str, rest: &str) -> String {
+    format!(
+        "    site: {site}\n    bot_email: brb-bot@example.zulipchat.com\n    api_key: {API_KEY}\n    stream: builds\n{rest}"
+    )
+}
+
+fn failed_event() -> CompletionEvent {
+    let mut event = CompletionEvent::test_event();
+    event.exit_code = 2;
+    event.status = "failure".to_string();
+    event.command = ["grep", "-c", "```"].map(String::from).to_vec();
+    event.project = Some("api".to_string());
+    event
+}
+
+/// Decodes a form-encoded request body.
+fn form(body: &[u8]) -> BTreeMap<String, String> {
+    let url =
+        reqwest::Url::parse(&format!("http://form/?{}", String::from_utf8_lossy(body))).unwrap();
+    url.query_pairs().into_owned().collect()
+}
+
+#[test]
+fn posts_markdown_to_the_project_topic() {
+    let server = MockServer::start(vec![
+        MockResponse::status(200).body(r#"{"result":"success","msg":"","id":42}"#),
+    ]);
+    let config = load(&channel(&server.url("/"), "")).unwrap();
+
+    let result = notify_selected(&config, &["chat".to_string()], &failed_event()).remove(0);
+
+    assert!(result.success, "{:?}", result.error);
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/api/v1/messages");
+    assert!(
+        requests[0]
+            .header("authorization")
+            .is_some_and(|value| value.starts_with("Basic ")),
+    );
+    let form = form(&requests[0].body);
+    assert_eq!(form["type"], "stream");
+    assert_eq!(form["to"], "builds");
+    assert_eq!(form["topic"], "api");
+    let content = &form["content"];
+    assert!(
+        content.starts_with("**api: failed (exit 2)**\n````\ngrep -c ``` ("),
+        "{content}"
+    );
+    assert!(content.ends_with("\n````"), "{content}");
+}
+
+#[test]
+fn topics_take_placeholders() {
+    let config = load(&channel(
+        "https://example.zulipchat.com",
+        "    topic: \"{project} on {host}\"\n",
+    ))
+    .unwrap();
+    let mut event = failed_event();
+    event.host = "build-01".to_string();
+
+    let output = preview(&config, "chat", &event).unwrap();
+
+    assert!(
+        output.contains("\"topic\": \"api on build-01\""),
+        "{output}"
+    );
+    assert!(!output.contains(API_KEY), "{output}");
+
+    let error = load(&channel(
+        "https://example.zulipchat.com",
+        "    topic: \"{branch}\"\n",
+    ))
+    .unwrap_err();
+    assert!(
+        error.contains("channel `chat` has a topic with an unknown placeholder `{branch}`"),
+        "{error}"
+    );
+}
+
+#[test]
+fn checks_the_bot_with_users_me() {
+    let server = MockServer::start(vec![
+        MockResponse::status(200).body(r#"{"result":"success","is_bot":true}"#),
+        MockResponse::status(401).body(r#"{"result":"error","code":"UNAUTHORIZED"}"#),
+    ]);
+    let config = load(&channel(&server.url(""), "")).unwrap();
+
+    assert_eq!(check_channels_auth(&config)[0].status, AuthStatus::Accepted);
+    assert_eq!(check_channels_auth(&config)[0].status, AuthStatus::Rejected);
+    let requests = server.requests();
+    assert_eq!(requests[0].method, "GET");
+    assert_eq!(requests[0].path, "/api/v1/users/me");
+}
+
+#[test]
+fn validates_the_site_bot_and_stream() {
+    let error = load(&channel("ftp://example.zulipchat.com", "")).unwrap_err();
+    assert!(
+        error.contains("an unsupported site scheme `ftp`"),
+        "{error}"
+    );
+
+    let error = load(
+        "    site: https://example.zulipchat.com\n    bot_email: brb-bot\n    api_key: k\n    stream: builds\n",
+    )
+    .unwrap_err();
+    assert!(error.contains("is not an email address"), "{error}");
+
+    let error = load(
+        "    site: https://example.zulipchat.com\n    bot_email: brb-bot@example.zulipchat.com\n    api_key: \" \"\n    stream: builds\n",
+    )
+    .unwrap_err();
+    assert!(error.contains("an empty api_key"), "{error}");
+
+    let error = load(&channel(
+        "https://example.zulipchat.com",
+        "    topic: \"\"\n",
+    ))
+    .unwrap_err();
+    assert!(error.contains("an empty topic"), "{error}");
+}